@@ -50,11 +50,16 @@
     command::{EditCommand, FocusCommand},
     directory::Directory,
     meta,
-    syntax::{highlight::reset_highlight_configs, Syntax},
+    syntax::{
+        fold::reset_fold_queries, highlight::reset_highlight_configs,
+        indent::reset_indent_queries, symbol::reset_tags_queries,
+        textobject::reset_textobject_queries, Syntax,
+    },
 };
 use lapce_rpc::{
     core::{CoreMessage, CoreNotification},
     file::PathObject,
+    proxy::RemotePromptKind,
     RpcMessage,
 };
 use lsp_types::{CompletionItemKind, MessageType, ShowMessageParams};
@@ -96,6 +101,7 @@
     },
     panel::{position::PanelContainerPosition, view::panel_container_view},
     plugin::{plugin_info_view, PluginData},
+    proxy,
     settings::{settings_view, theme_color_settings_view},
     status::status,
     text_input::TextInputBuilder,
@@ -301,8 +307,9 @@ fn create_windows(
 
         // Split user input into known existing directors and
         // file paths that exist or not
-        let (dirs, files): (Vec<&PathObject>, Vec<&PathObject>) =
-            paths.iter().partition(|p| p.is_dir);
+        let (dirs, files): (Vec<&PathObject>, Vec<&PathObject>) = paths
+            .iter()
+            .partition(|p| p.is_dir || LapceWorkspace::is_workspace_file(&p.path));
 
         if !dirs.is_empty() {
             // There were directories specified, so we'll load those as windows
@@ -329,17 +336,32 @@ fn create_windows(
                 #[cfg(not(windows))]
                 let workspace_type = LapceWorkspaceType::Local;
 
+                let workspace = if dir.is_dir {
+                    LapceWorkspace {
+                        kind: workspace_type,
+                        path: Some(dir.path.to_owned()),
+                        ..Default::default()
+                    }
+                } else {
+                    match LapceWorkspace::from_file(&dir.path) {
+                        Ok(workspace) => workspace,
+                        Err(err) => {
+                            tracing::error!(
+                                "Failed to read workspace file {:?}: {err}",
+                                dir.path
+                            );
+                            continue;
+                        }
+                    }
+                };
+
                 let info = WindowInfo {
                     size,
                     pos,
                     maximised: false,
                     tabs: TabsInfo {
                         active_tab: 0,
-                        workspaces: vec![LapceWorkspace {
-                            kind: workspace_type,
-                            path: Some(dir.path.to_owned()),
-                            last_open: 0,
-                        }],
+                        workspaces: vec![workspace],
                     },
                 };
 
@@ -2502,11 +2524,16 @@ fn palette_item(
         PaletteItemContent::Line { .. }
         | PaletteItemContent::Workspace { .. }
         | PaletteItemContent::SshHost { .. }
+        | PaletteItemContent::CommandHost { .. }
         | PaletteItemContent::Language { .. }
         | PaletteItemContent::LineEnding { .. }
+        | PaletteItemContent::Encoding { .. }
         | PaletteItemContent::ColorTheme { .. }
         | PaletteItemContent::SCMReference { .. }
         | PaletteItemContent::TerminalProfile { .. }
+        | PaletteItemContent::KeyboardMacro { .. }
+        | PaletteItemContent::PasteHistory { .. }
+        | PaletteItemContent::ExCommand { .. }
         | PaletteItemContent::IconTheme { .. } => {
             let text = item.filter_text;
             let indices = item.indices;
@@ -2550,11 +2577,19 @@ fn palette_input(window_tab_data: Rc<WindowTabData>) -> impl View {
     let config = window_tab_data.common.config;
     let focus = window_tab_data.common.focus;
     let is_focused = move || focus.get() == Focus::Palette;
+    let remote_prompt = window_tab_data.palette.remote_prompt;
 
     let input = TextInputBuilder::new()
         .is_focused(is_focused)
         .build_editor(editor)
-        .placeholder(move || window_tab_data.palette.placeholder_text().to_owned())
+        .placeholder(move || window_tab_data.palette.placeholder_text())
+        .masked(move || {
+            remote_prompt.with(|prompt| {
+                prompt
+                    .as_ref()
+                    .is_some_and(|prompt| prompt.kind == RemotePromptKind::Secret)
+            })
+        })
         .style(|s| s.width_full());
 
     container(container(input).style(move |s| {
@@ -3312,6 +3347,7 @@ fn workspace_title(workspace: &LapceWorkspace) -> Option<String> {
     Some(match &workspace.kind {
         LapceWorkspaceType::Local => format!("{dir}"),
         LapceWorkspaceType::RemoteSSH(remote) => format!("{dir} [{remote}]"),
+        LapceWorkspaceType::RemoteCommand(remote) => format!("{dir} [{remote}]"),
         #[cfg(windows)]
         LapceWorkspaceType::RemoteWSL(remote) => format!("{dir} [{remote}]"),
     })
@@ -3657,6 +3693,16 @@ fn window(window_data: WindowData) -> impl View {
 }
 
 pub fn launch() {
+    // Run by ssh to ask for a password or the like, in place of a terminal
+    if let Ok(socket) = std::env::var(proxy::ASKPASS_ENV) {
+        let prompt = std::env::args().nth(1).unwrap_or_default();
+        if let Err(err) = proxy::askpass(&socket, &prompt) {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let cli = Cli::parse();
 
     if !cli.wait {
@@ -3848,6 +3894,10 @@ pub fn launch() {
                     "grammar or query got updated, reset highlight configs"
                 );
                 reset_highlight_configs();
+                reset_textobject_queries();
+                reset_fold_queries();
+                reset_tags_queries();
+                reset_indent_queries();
                 for (_, window) in app_data.windows.get_untracked() {
                     for (_, tab) in window.window_tabs.get_untracked() {
                         for (_, doc) in tab.main_split.docs.get_untracked() {
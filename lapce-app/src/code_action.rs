@@ -166,8 +166,7 @@ pub fn previous_page(&self) {
 
     pub fn show(
         &mut self,
-        plugin_id: PluginId,
-        code_actions: im::Vector<CodeActionOrCommand>,
+        code_actions: im::Vector<(PluginId, CodeActionOrCommand)>,
         offset: usize,
         mouse_click: bool,
     ) {
@@ -178,7 +177,7 @@ pub fn show(
         self.request_id += 1;
         self.items = code_actions
             .into_iter()
-            .map(|code_action| ScoredCodeActionItem {
+            .map(|(plugin_id, code_action)| ScoredCodeActionItem {
                 item: code_action,
                 plugin_id,
                 score: 0,
@@ -16,7 +16,7 @@
     proxy::ProxyStatus,
     terminal::{TermId, TerminalProfile},
 };
-use lsp_types::{CodeActionOrCommand, Position, WorkspaceEdit};
+use lsp_types::{CodeActionOrCommand, Position, ShowMessageParams, WorkspaceEdit};
 use serde_json::Value;
 use strum::{EnumMessage, IntoEnumIterator};
 use strum_macros::{Display, EnumIter, EnumMessage, EnumString, IntoStaticStr};
@@ -24,7 +24,7 @@
 use crate::{
     alert::AlertButton,
     debug::RunDebugMode,
-    doc::Doc,
+    doc::{Doc, DocHistory},
     editor::location::EditorLocation,
     editor_tab::EditorTabChild,
     id::EditorTabId,
@@ -179,6 +179,14 @@ pub enum LapceWorkbenchCommand {
     #[strum(message = "Close Folder")]
     CloseFolder,
 
+    #[strum(serialize = "open_workspace_file")]
+    #[strum(message = "Open Workspace from File")]
+    OpenWorkspaceFile,
+
+    #[strum(serialize = "add_folder_to_workspace")]
+    #[strum(message = "Add Folder to Workspace")]
+    AddFolderToWorkspace,
+
     #[strum(serialize = "open_file")]
     #[strum(message = "Open File")]
     OpenFile,
@@ -195,6 +203,10 @@ pub enum LapceWorkbenchCommand {
     #[strum(message = "Go to Implementation")]
     GoToImplementation,
 
+    #[strum(serialize = "show_color_presentations")]
+    #[strum(message = "Show Color Presentations")]
+    ShowColorPresentations,
+
     #[strum(serialize = "reveal_in_panel")]
     #[strum(message = "Reveal in Panel")]
     RevealInPanel,
@@ -353,6 +365,10 @@ pub enum LapceWorkbenchCommand {
     #[strum(message = "Connect to SSH Host")]
     ConnectSshHost,
 
+    #[strum(serialize = "connect_command_host")]
+    #[strum(message = "Connect to Container or Sandbox")]
+    ConnectCommandHost,
+
     #[cfg(windows)]
     #[strum(serialize = "connect_wsl_host")]
     #[strum(message = "Connect to WSL Host")]
@@ -466,6 +482,14 @@ pub enum LapceWorkbenchCommand {
     #[strum(serialize = "toggle_search_focus")]
     ToggleSearchFocus,
 
+    #[strum(message = "Toggle Ports Focus")]
+    #[strum(serialize = "toggle_ports_focus")]
+    TogglePortsFocus,
+
+    #[strum(message = "Toggle Timeline Focus")]
+    #[strum(serialize = "toggle_timeline_focus")]
+    ToggleTimelineFocus,
+
     // Visual toggle commands
     #[strum(serialize = "toggle_terminal_visual")]
     ToggleTerminalVisual,
@@ -488,12 +512,22 @@ pub enum LapceWorkbenchCommand {
     #[strum(serialize = "toggle_search_visual")]
     ToggleSearchVisual,
 
+    #[strum(serialize = "toggle_ports_visual")]
+    TogglePortsVisual,
+
+    #[strum(serialize = "toggle_timeline_visual")]
+    ToggleTimelineVisual,
+
     #[strum(serialize = "focus_editor")]
     FocusEditor,
 
     #[strum(serialize = "focus_terminal")]
     FocusTerminal,
 
+    #[strum(message = "Restore the Version of the File Being Compared")]
+    #[strum(serialize = "restore_file_version")]
+    RestoreFileVersion,
+
     #[strum(message = "Source Control: Init")]
     #[strum(serialize = "source_control_init")]
     SourceControlInit,
@@ -532,6 +566,26 @@ pub enum LapceWorkbenchCommand {
     #[strum(message = "Change current file line ending")]
     ChangeFileLineEnding,
 
+    #[strum(serialize = "toggle_read_only")]
+    #[strum(message = "Toggle Read-Only for Current File")]
+    ToggleReadOnly,
+
+    #[strum(serialize = "reopen_with_encoding")]
+    #[strum(message = "Reopen current file with encoding")]
+    ReopenWithEncoding,
+
+    #[strum(serialize = "save_with_encoding")]
+    #[strum(message = "Save current file with encoding")]
+    SaveWithEncoding,
+
+    #[strum(serialize = "hex_goto_offset")]
+    #[strum(message = "Hex Editor: Go to Offset")]
+    HexGotoOffset,
+
+    #[strum(serialize = "hex_find_bytes")]
+    #[strum(message = "Hex Editor: Find Bytes")]
+    HexFindBytes,
+
     #[strum(serialize = "next_editor_tab")]
     #[strum(message = "Next Editor Tab")]
     NextEditorTab,
@@ -589,6 +643,134 @@ pub enum LapceWorkbenchCommand {
     #[strum(serialize = "diff_files")]
     DiffFiles,
 
+    #[strum(serialize = "toggle_macro_recording")]
+    #[strum(message = "Toggle Keyboard Macro Recording")]
+    ToggleMacroRecording,
+
+    #[strum(serialize = "start_macro_recording")]
+    #[strum(message = "Start Recording Keyboard Macro")]
+    StartMacroRecording,
+
+    #[strum(serialize = "stop_macro_recording")]
+    #[strum(message = "Stop Recording Keyboard Macro")]
+    StopMacroRecording,
+
+    #[strum(serialize = "replay_macro")]
+    #[strum(message = "Replay Keyboard Macro")]
+    ReplayMacro,
+
+    #[strum(serialize = "replay_last_macro")]
+    #[strum(message = "Replay Last Keyboard Macro")]
+    ReplayLastMacro,
+
+    #[strum(serialize = "palette.keyboard_macros")]
+    #[strum(message = "List Keyboard Macros")]
+    PaletteKeyboardMacros,
+
+    #[strum(serialize = "select_register")]
+    #[strum(message = "Select Register for Next Yank or Paste")]
+    SelectRegister,
+
+    #[strum(serialize = "palette.paste_history")]
+    #[strum(message = "Paste from History")]
+    PalettePasteHistory,
+
+    #[strum(serialize = "palette.ex_command")]
+    #[strum(message = "Open Command Line")]
+    PaletteExCommand,
+
+    #[strum(serialize = "repeat_last_change")]
+    #[strum(message = "Repeat Last Change")]
+    RepeatLastChange,
+
+    #[strum(serialize = "select_inside_text_object")]
+    #[strum(message = "Select Inside Text Object")]
+    SelectInsideTextObject,
+
+    #[strum(serialize = "select_around_text_object")]
+    #[strum(message = "Select Around Text Object")]
+    SelectAroundTextObject,
+
+    #[strum(serialize = "change_inside_text_object")]
+    #[strum(message = "Change Inside Text Object")]
+    ChangeInsideTextObject,
+
+    #[strum(serialize = "change_around_text_object")]
+    #[strum(message = "Change Around Text Object")]
+    ChangeAroundTextObject,
+
+    #[strum(serialize = "next_function")]
+    #[strum(message = "Go to Next Function")]
+    NextFunction,
+
+    #[strum(serialize = "previous_function")]
+    #[strum(message = "Go to Previous Function")]
+    PreviousFunction,
+
+    #[strum(serialize = "next_class")]
+    #[strum(message = "Go to Next Class")]
+    NextClass,
+
+    #[strum(serialize = "previous_class")]
+    #[strum(message = "Go to Previous Class")]
+    PreviousClass,
+
+    #[strum(serialize = "surround")]
+    #[strum(message = "Surround with the Pending Operator")]
+    Surround,
+
+    #[strum(serialize = "surround_add")]
+    #[strum(message = "Surround Selection")]
+    SurroundAdd,
+
+    #[strum(serialize = "surround_change")]
+    #[strum(message = "Change Surrounding Pair")]
+    SurroundChange,
+
+    #[strum(serialize = "surround_delete")]
+    #[strum(message = "Delete Surrounding Pair")]
+    SurroundDelete,
+
+    #[strum(serialize = "fold_all")]
+    #[strum(message = "Fold All")]
+    FoldAll,
+
+    #[strum(serialize = "unfold_all")]
+    #[strum(message = "Unfold All")]
+    UnfoldAll,
+
+    #[strum(serialize = "fold_level_1")]
+    #[strum(message = "Fold Level 1")]
+    FoldLevel1,
+
+    #[strum(serialize = "fold_level_2")]
+    #[strum(message = "Fold Level 2")]
+    FoldLevel2,
+
+    #[strum(serialize = "fold_level_3")]
+    #[strum(message = "Fold Level 3")]
+    FoldLevel3,
+
+    #[strum(serialize = "fold_level_4")]
+    #[strum(message = "Fold Level 4")]
+    FoldLevel4,
+
+    #[strum(serialize = "fold_level_5")]
+    #[strum(message = "Fold Level 5")]
+    FoldLevel5,
+
+    #[strum(serialize = "fold_level_6")]
+    #[strum(message = "Fold Level 6")]
+    FoldLevel6,
+
+    #[strum(serialize = "fold_level_7")]
+    #[strum(message = "Fold Level 7")]
+    FoldLevel7,
+
+    #[strum(serialize = "fold_comments")]
+    #[strum(message = "Fold All Block Comments")]
+    FoldComments,
+
     #[strum(serialize = "quit")]
     #[strum(message = "Quit Editor")]
     Quit,
@@ -619,6 +801,13 @@ pub enum InternalCommand {
         path: PathBuf,
     },
     ReloadFileExplorer,
+    /// Open another root folder in the workspace
+    AddWorkspaceFolder {
+        path: PathBuf,
+    },
+    RemoveWorkspaceFolder {
+        path: PathBuf,
+    },
     /// Test whether a file/directory can be created at that path
     TestPathCreation {
         new_path: PathBuf,
@@ -635,6 +824,18 @@ pub enum InternalCommand {
         source: PathBuf,
         path: PathBuf,
     },
+    /// Copy files and directories into the directory `to`
+    CopyPaths {
+        paths: Vec<PathBuf>,
+        to: PathBuf,
+    },
+    /// Move files and directories into the directory `to`
+    MovePaths {
+        paths: Vec<PathBuf>,
+        to: PathBuf,
+    },
+    /// Undo the last copy or move of the file explorer
+    UndoFileOperation,
     GoToLocation {
         location: EditorLocation,
     },
@@ -690,8 +891,7 @@ pub enum InternalCommand {
     ShowCodeActions {
         offset: usize,
         mouse_click: bool,
-        plugin_id: PluginId,
-        code_actions: im::Vector<CodeActionOrCommand>,
+        code_actions: im::Vector<(PluginId, CodeActionOrCommand)>,
     },
     RunCodeAction {
         plugin_id: PluginId,
@@ -758,6 +958,10 @@ pub enum InternalCommand {
         buttons: Vec<AlertButton>,
     },
     HideAlert,
+    ShowMessage {
+        title: String,
+        message: ShowMessageParams,
+    },
     SaveScratchDoc {
         doc: Rc<Doc>,
     },
@@ -767,6 +971,12 @@ pub enum InternalCommand {
     UpdateProxyStatus {
         status: ProxyStatus,
     },
+    /// Answer a prompt from the connection to the remote, with `None` when
+    /// it's cancelled
+    AnswerRemotePrompt {
+        id: u64,
+        answer: Option<String>,
+    },
     DapFrameScopes {
         dap_id: DapId,
         frame_id: usize,
@@ -779,6 +989,24 @@ pub enum InternalCommand {
         left_path: PathBuf,
         right_path: PathBuf,
     },
+    /// Compare an old version of a file with its current content
+    OpenHistoryDiff {
+        history: DocHistory,
+    },
+    /// Replace the content of a file with an old version of it
+    RestoreHistory {
+        history: DocHistory,
+    },
+    /// A version of the file was saved in the local history
+    LocalHistoryChanged {
+        path: PathBuf,
+    },
+    ReplayKeyboardMacro {
+        register: char,
+    },
+    RunExCommand {
+        command: String,
+    },
     ExecuteProcess {
         program: String,
         arguments: Vec<String>,
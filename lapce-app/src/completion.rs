@@ -116,7 +116,11 @@ pub fn receive(
                 indices: Vec::new(),
             })
             .collect();
-        self.input_items.insert(input.to_string(), items);
+        // Several language servers may answer the same request, so merge their items.
+        self.input_items
+            .entry(input.to_string())
+            .or_default()
+            .append(items);
         self.filter_items();
     }
 
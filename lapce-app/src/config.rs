@@ -7,7 +7,7 @@
 use ::core::slice;
 use floem::peniko::Color;
 use itertools::Itertools;
-use lapce_core::directory::Directory;
+use lapce_core::{directory::Directory, language::set_user_languages};
 use lapce_proxy::plugin::wasi::find_all_volts;
 use lapce_rpc::plugin::VoltID;
 use lsp_types::{CompletionItemKind, SymbolKind};
@@ -174,6 +174,11 @@ pub fn load(
 
         lapce_config.terminal.get_indexed_colors();
 
+        set_user_languages(
+            &lapce_config.editor.languages,
+            &lapce_config.editor.file_associations,
+        );
+
         lapce_config
     }
 
@@ -224,7 +229,8 @@ fn merge_config(
                         .unwrap_or_else(|_| config.clone());
                 }
             }
-            LapceWorkspaceType::RemoteSSH(_) => {}
+            LapceWorkspaceType::RemoteSSH(_)
+            | LapceWorkspaceType::RemoteCommand(_) => {}
             #[cfg(windows)]
             LapceWorkspaceType::RemoteWSL(_) => {}
         }
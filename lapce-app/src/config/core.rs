@@ -1,6 +1,11 @@
+use std::{collections::HashMap, time::Duration};
+
+use lapce_rpc::file::FileNestingRule;
 use serde::{Deserialize, Serialize};
 use structdesc::FieldNames;
 
+use crate::local_history::LocalHistoryLimits;
+
 #[derive(FieldNames, Debug, Clone, Deserialize, Serialize, Default)]
 #[serde(rename_all = "kebab-case")]
 pub struct CoreConfig {
@@ -18,8 +23,109 @@ pub struct CoreConfig {
         desc = "Only allow double-click to open files in the file explorer"
     )]
     pub file_explorer_double_click: bool,
+    #[field_names(
+        desc = "Show folders that only contain a single folder in one row in the file explorer, like `src/foo/bar`"
+    )]
+    pub file_explorer_compact_folders: bool,
+    #[field_names(
+        desc = "Nest related files under another file in the file explorer, following the file explorer nesting patterns"
+    )]
+    pub file_explorer_nesting: bool,
     #[field_names(
         desc = "Enable auto-reload for the plugin when its configuration changes."
     )]
     pub auto_reload_plugin: bool,
+    #[field_names(
+        desc = "Forward the ports that programs in the terminal of a remote SSH workspace say they listen on."
+    )]
+    pub auto_forward_ports: bool,
+    #[field_names(
+        desc = "The most saved versions of a file kept in the local history. Set to 0 to keep none."
+    )]
+    pub local_history_max_entries: usize,
+    #[field_names(
+        desc = "How many days saved versions of files are kept in the local history"
+    )]
+    pub local_history_max_age_days: u64,
+    #[field_names(
+        desc = "The size in KiB above which saved versions of a file aren't kept in the local history"
+    )]
+    pub local_history_max_file_size: usize,
+    #[field_names(
+        desc = "The size in MiB that the local history of a workspace is kept under, by dropping the oldest saved versions"
+    )]
+    pub local_history_max_total_size: usize,
+    #[field_names(skip)]
+    pub remote_commands: HashMap<String, RemoteCommand>,
+    #[field_names(skip)]
+    #[serde(default)]
+    pub ssh_hosts: HashMap<String, SshHostConfig>,
+    /// The files nested under a file, by the name of that file, as a comma
+    /// separated list
+    #[field_names(skip)]
+    #[serde(default)]
+    pub file_explorer_nesting_patterns: HashMap<String, String>,
+}
+
+impl CoreConfig {
+    pub fn local_history_limits(&self) -> LocalHistoryLimits {
+        LocalHistoryLimits {
+            max_entries: self.local_history_max_entries,
+            max_age: Duration::from_secs(
+                self.local_history_max_age_days * 24 * 60 * 60,
+            ),
+            max_file_size: self.local_history_max_file_size * 1024,
+            max_total_size: self.local_history_max_total_size * 1024 * 1024,
+        }
+    }
+
+    /// The rules for nesting files in the file explorer, which are empty when
+    /// nesting is off.
+    pub fn file_explorer_nesting_rules(&self) -> Vec<FileNestingRule> {
+        if !self.file_explorer_nesting {
+            return Vec::new();
+        }
+        let mut rules = self
+            .file_explorer_nesting_patterns
+            .iter()
+            .map(|(parent, children)| FileNestingRule {
+                parent: parent.clone(),
+                children: children
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|child| !child.is_empty())
+                    .map(ToString::to_string)
+                    .collect(),
+            })
+            .collect::<Vec<_>>();
+        rules.sort_by(|a, b| a.parent.cmp(&b.parent));
+        rules
+    }
+}
+
+/// How to connect to an SSH host, on top of what `~/.ssh/config` says about
+/// it.
+#[derive(Debug, Clone, Deserialize, Serialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct SshHostConfig {
+    /// The hosts to connect through first, like `ProxyJump`
+    #[serde(default)]
+    pub proxy_jump: Option<String>,
+    /// The private key to authenticate with, like `IdentityFile`
+    #[serde(default)]
+    pub identity_file: Option<String>,
+}
+
+/// How to reach a remote host, like a container or a sandbox, by running a
+/// command on this machine.
+#[derive(Debug, Clone, Deserialize, Serialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct RemoteCommand {
+    /// The command that runs the arguments after it on the host, with
+    /// `{name}` standing for the name of the host
+    pub command: Vec<String>,
+    /// The command that copies the file `{local}` to `{remote}` on the host.
+    /// Files are written through `command` when it's empty.
+    #[serde(default)]
+    pub upload: Vec<String>,
 }
@@ -1,4 +1,7 @@
+use std::collections::HashMap;
+
 use floem::views::editor::text::RenderWhitespace;
+use lapce_core::language::LanguageDefinition;
 use serde::{Deserialize, Serialize};
 use structdesc::FieldNames;
 
@@ -157,6 +160,10 @@ pub struct EditorConfig {
         desc = "Set the inlay hint font size. If less than 5 or greater than editor font size, it uses the editor font size."
     )]
     pub inlay_hint_font_size: usize,
+    #[field_names(
+        desc = "If a color swatch should be displayed in front of color literals"
+    )]
+    pub enable_color_decorators: bool,
     #[field_names(desc = "If diagnostics should be displayed inline")]
     pub enable_error_lens: bool,
 
@@ -240,13 +247,33 @@ pub struct EditorConfig {
     pub bracket_pair_colorization: bool,
     #[field_names(desc = "Bracket colorization Limit")]
     pub bracket_colorization_limit: u64,
+    #[field_names(
+        desc = "Files larger than this many megabytes open read-only, without syntax highlighting or language servers"
+    )]
+    pub large_file_size: u64,
     #[field_names(
         desc = "Glob patterns for excluding files and folders (in file explorer)"
     )]
     pub files_exclude: String,
+    /// Language id -> the plugins whose language servers answer requests with a
+    /// single answer, such as formatting and rename, in order of preference
+    #[field_names(skip)]
+    pub language_server_priority: HashMap<String, Vec<String>>,
+    /// Glob -> the name of the language of the files it matches, ahead of the
+    /// languages' own extensions
+    #[field_names(skip)]
+    pub file_associations: HashMap<String, String>,
+    /// Name -> languages that aren't built in
+    #[field_names(skip)]
+    pub languages: HashMap<String, LanguageDefinition>,
 }
 
 impl EditorConfig {
+    /// The size in bytes above which files are opened as large files.
+    pub fn large_file_size(&self) -> u64 {
+        self.large_file_size.saturating_mul(1024 * 1024)
+    }
+
     pub fn font_size(&self) -> usize {
         self.font_size.clamp(6, 32)
     }
@@ -32,6 +32,9 @@ impl LapceIcons {
     pub const UNSAVED: &'static str = "unsaved";
     pub const WARNING: &'static str = "warning";
     pub const TERMINAL: &'static str = "terminal";
+    pub const PORTS: &'static str = "ports";
+    pub const TIMELINE: &'static str = "timeline";
+    pub const TIMELINE_RESTORE: &'static str = "timeline.restore";
     pub const SETTINGS: &'static str = "settings";
     pub const LIGHTBULB: &'static str = "lightbulb";
     pub const EXTENSIONS: &'static str = "extensions";
@@ -14,6 +14,7 @@
 use crate::{
     app::{AppData, AppInfo},
     doc::DocInfo,
+    keypress::macros::KeyboardMacros,
     panel::{data::PanelOrder, kind::PanelKind},
     window::{WindowData, WindowInfo},
     window_tab::WindowTabData,
@@ -27,6 +28,8 @@
 const PANEL_ORDERS: &str = "panel_orders";
 const DISABLED_VOLTS: &str = "disabled_volts";
 const RECENT_WORKSPACES: &str = "recent_workspaces";
+const KEYBOARD_MACROS: &str = "keyboard_macros";
+const EX_COMMAND_HISTORY: &str = "ex_command_history";
 
 pub enum SaveEvent {
     App(AppInfo),
@@ -36,6 +39,8 @@ pub enum SaveEvent {
     DisabledVolts(Vec<VoltID>),
     WorkspaceDisabledVolts(Arc<LapceWorkspace>, Vec<VoltID>),
     PanelOrder(PanelOrder),
+    KeyboardMacros(KeyboardMacros),
+    ExCommandHistory(Arc<LapceWorkspace>, Vec<String>),
 }
 
 #[derive(Clone)]
@@ -110,6 +115,20 @@ pub fn new() -> Result<Self> {
                                 tracing::error!("{:?}", err);
                             }
                         }
+                        SaveEvent::KeyboardMacros(macros) => {
+                            if let Err(err) =
+                                local_db.insert_keyboard_macros(&macros)
+                            {
+                                tracing::error!("{:?}", err);
+                            }
+                        }
+                        SaveEvent::ExCommandHistory(workspace, history) => {
+                            if let Err(err) = local_db
+                                .insert_ex_command_history(&workspace, &history)
+                            {
+                                tracing::error!("{:?}", err);
+                            }
+                        }
                     }
                 }
             })
@@ -196,7 +215,11 @@ fn insert_recent_workspace(&self, workspace: LapceWorkspace) -> Result<()> {
 
         let mut exits = false;
         for w in workspaces.iter_mut() {
-            if w.path == workspace.path && w.kind == workspace.kind {
+            if w.path == workspace.path
+                && w.file == workspace.file
+                && w.kind == workspace.kind
+            {
+                w.folders.clone_from(&workspace.folders);
                 w.last_open = std::time::SystemTime::now()
                     .duration_since(std::time::UNIX_EPOCH)
                     .unwrap()
@@ -393,6 +416,61 @@ fn insert_panel_orders(&self, order: &PanelOrder) -> Result<()> {
         Ok(())
     }
 
+    pub fn get_keyboard_macros(&self) -> Result<KeyboardMacros> {
+        let macros = std::fs::read_to_string(self.folder.join(KEYBOARD_MACROS))?;
+        let macros: KeyboardMacros = serde_json::from_str(&macros)?;
+        Ok(macros)
+    }
+
+    pub fn save_keyboard_macros(&self, macros: KeyboardMacros) {
+        if let Err(err) = self.save_tx.send(SaveEvent::KeyboardMacros(macros)) {
+            tracing::error!("{:?}", err);
+        }
+    }
+
+    fn insert_keyboard_macros(&self, macros: &KeyboardMacros) -> Result<()> {
+        let macros = serde_json::to_string_pretty(macros)?;
+        std::fs::write(self.folder.join(KEYBOARD_MACROS), macros)?;
+        Ok(())
+    }
+
+    pub fn get_ex_command_history(
+        &self,
+        workspace: &LapceWorkspace,
+    ) -> Result<Vec<String>> {
+        let folder = self.workspace_folder.join(workspace_folder_name(workspace));
+        let history = std::fs::read_to_string(folder.join(EX_COMMAND_HISTORY))?;
+        let history: Vec<String> = serde_json::from_str(&history)?;
+        Ok(history)
+    }
+
+    pub fn save_ex_command_history(
+        &self,
+        workspace: Arc<LapceWorkspace>,
+        history: Vec<String>,
+    ) {
+        if let Err(err) = self
+            .save_tx
+            .send(SaveEvent::ExCommandHistory(workspace, history))
+        {
+            tracing::error!("{:?}", err);
+        }
+    }
+
+    fn insert_ex_command_history(
+        &self,
+        workspace: &LapceWorkspace,
+        history: &[String],
+    ) -> Result<()> {
+        let folder = self.workspace_folder.join(workspace_folder_name(workspace));
+        if let Err(err) = std::fs::create_dir_all(&folder) {
+            tracing::error!("{:?}", err);
+        }
+        let history = serde_json::to_string_pretty(history)?;
+        std::fs::write(folder.join(EX_COMMAND_HISTORY), history)?;
+        Ok(())
+    }
+
     pub fn save_doc_position(
         &self,
         workspace: &LapceWorkspace,
@@ -439,13 +517,13 @@ pub fn get_doc_info(
     }
 }
 
-fn workspace_folder_name(workspace: &LapceWorkspace) -> String {
+pub(crate) fn workspace_folder_name(workspace: &LapceWorkspace) -> String {
     url::form_urlencoded::Serializer::new(String::new())
         .append_key_only(&workspace.to_string())
         .finish()
 }
 
-fn doc_path_name(path: &Path) -> String {
+pub(crate) fn doc_path_name(path: &Path) -> String {
     let mut hasher = Sha256::new();
     hasher.update(path.to_string_lossy().as_bytes());
     format!("{:x}", hasher.finalize())
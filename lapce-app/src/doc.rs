@@ -37,13 +37,14 @@
 use lapce_core::{
     buffer::{
         diff::{rope_diff, DiffLines},
-        rope_text::RopeText,
-        Buffer, InvalLines,
+        rope_text::{RopeText, RopeTextRef},
+        Buffer, InsertsValueIter, InvalLines,
     },
     char_buffer::CharBuffer,
     command::EditCommand,
-    cursor::{Cursor, CursorAffinity},
+    cursor::{Cursor, CursorAffinity, CursorMode},
     editor::{Action, EditConf, EditType},
+    hex,
     indent::IndentStyle,
     language::LapceLanguage,
     line_ending::LineEnding,
@@ -52,7 +53,14 @@
     rope_text_pos::RopeTextPosition,
     selection::{InsertDrift, Selection},
     style::line_styles,
-    syntax::{edit::SyntaxEdit, BracketParser, Syntax},
+    surround,
+    syntax::{
+        edit::SyntaxEdit,
+        fold::indent_folds,
+        indent::{indent_string, indent_width},
+        textobject::TextObject,
+        BracketParser, Syntax,
+    },
     word::{get_char_property, CharClassification, WordCursor},
 };
 use lapce_rpc::{
@@ -63,11 +71,12 @@
 };
 use lapce_xi_rope::{
     spans::{Spans, SpansBuilder},
-    Interval, Rope, RopeDelta, Transformer,
+    DeltaElement, Interval, Rope, RopeDelta, Transformer,
 };
 use lsp_types::{
-    CodeActionOrCommand, CodeLens, Diagnostic, DiagnosticSeverity,
-    DocumentSymbolResponse, InlayHint, InlayHintLabel, TextEdit,
+    CodeActionOrCommand, CodeLens, ColorInformation, Diagnostic, DiagnosticSeverity,
+    DocumentLink, DocumentSymbol, DocumentSymbolResponse, InlayHint, InlayHintLabel,
+    MessageType, ShowMessageParams, TextEdit,
 };
 use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
@@ -77,13 +86,14 @@
     config::{color::LapceColor, LapceConfig},
     editor::{
         compute_screen_lines,
-        gutter::FoldingRanges,
+        gutter::{FoldingRange, FoldingRanges},
         location::{EditorLocation, EditorPosition},
         EditorData,
     },
     find::{Find, FindProgress, FindResult},
     history::DocumentHistory,
     keypress::KeyPressFocus,
+    local_history::{format_time, LocalHistory},
     main_split::Editors,
     panel::{
         document_symbol::{SymbolData, SymbolInformationItemData},
@@ -106,12 +116,39 @@ pub struct EditorDiagnostic {
     pub diagnostic: Diagnostic,
 }
 
-#[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct DocHistory {
     pub path: PathBuf,
     pub version: String,
 }
 
+impl DocHistory {
+    /// The version of the file saved in the local history at `time`
+    pub fn local(path: PathBuf, time: u64) -> Self {
+        Self {
+            path,
+            version: format!("local:{time}"),
+        }
+    }
+
+    /// When the version was saved, if it's from the local history.
+    pub fn local_time(&self) -> Option<u64> {
+        self.version.strip_prefix("local:")?.parse().ok()
+    }
+
+    /// The version as shown to the user, like the time it was saved or the
+    /// short id of the commit.
+    pub fn label(&self) -> String {
+        if let Some(time) = self.local_time() {
+            format_time(time as i64)
+        } else if self.version == "head" {
+            self.version.clone()
+        } else {
+            self.version.chars().take(8).collect()
+        }
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum DocContent {
     /// A file at some location. This can be a remote path.
@@ -122,6 +159,8 @@ pub enum DocContent {
     History(DocHistory),
     /// A new file which doesn't exist in the file system
     Scratch { id: BufferId, name: String },
+    /// A binary file, shown and edited as a hex dump of its bytes
+    Hex { path: PathBuf, read_only: bool },
 }
 
 impl DocContent {
@@ -139,19 +178,74 @@ pub fn read_only(&self) -> bool {
             DocContent::Local => false,
             DocContent::History(_) => true,
             DocContent::Scratch { .. } => false,
+            DocContent::Hex { read_only, .. } => *read_only,
         }
     }
 
+    pub fn is_hex(&self) -> bool {
+        matches!(self, DocContent::Hex { .. })
+    }
+
     pub fn path(&self) -> Option<&PathBuf> {
         match self {
             DocContent::File { path, .. } => Some(path),
             DocContent::Local => None,
             DocContent::History(_) => None,
             DocContent::Scratch { .. } => None,
+            DocContent::Hex { path, .. } => Some(path),
         }
     }
 }
 
+/// Load the content of an old version of a file, from the local history or
+/// from source control, and pass it to `f`.
+pub fn load_history_content(
+    scope: Scope,
+    common: &CommonData,
+    history: &DocHistory,
+    f: impl FnOnce(String) + 'static,
+) {
+    if let Some(time) = history.local_time() {
+        let path = history.path.clone();
+        let workspace = common.workspace.clone();
+        let send = create_ext_action(scope, move |content: Option<String>| {
+            if let Some(content) = content {
+                f(content);
+            }
+        });
+        std::thread::Builder::new()
+            .name("LocalHistory".to_owned())
+            .spawn(move || {
+                send(
+                    LocalHistory::new(&workspace)
+                        .and_then(|history| history.read(&path, time).ok()),
+                );
+            })
+            .unwrap();
+        return;
+    }
+
+    let send = create_ext_action(scope, move |result| {
+        if let Ok(ProxyResponse::BufferHeadResponse { content, .. }) = result {
+            f(content);
+        }
+    });
+    let path = history.path.clone();
+    if history.version == "head" {
+        common.proxy.get_buffer_head(path, move |result| {
+            send(result);
+        });
+    } else {
+        common.proxy.git_file_at_commit(
+            path,
+            history.version.clone(),
+            move |result| {
+                send(result);
+            },
+        );
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct DocInfo {
     pub workspace: LapceWorkspace,
@@ -160,9 +254,9 @@ pub struct DocInfo {
     pub cursor_offset: usize,
 }
 
-/// (Offset -> (Plugin the code actions are from, Code Actions))
+/// (Offset -> Code Actions along with the plugin each of them is from)
 pub type CodeActions =
-    im::HashMap<usize, (PluginId, im::Vector<CodeActionOrCommand>)>;
+    im::HashMap<usize, im::Vector<(PluginId, CodeActionOrCommand)>>;
 
 pub type AllCodeLens = im::HashMap<usize, (PluginId, usize, im::Vector<CodeLens>)>;
 
@@ -174,11 +268,22 @@ pub struct Doc {
     pub cache_rev: RwSignal<u64>,
     /// Whether the buffer's content has been loaded/initialized into the buffer.
     pub loaded: RwSignal<bool>,
+    /// The encoding the file is read and written in, for documents of files.
+    pub encoding: RwSignal<Option<String>>,
+    /// Whether the file is too large, or binary, for syntax highlighting and
+    /// language servers, which are then left off.
+    pub large: RwSignal<bool>,
     pub buffer: RwSignal<Buffer>,
     pub syntax: RwSignal<Syntax>,
     semantic_styles: RwSignal<Option<Spans<Style>>>,
     /// Inlay hints for the document
     pub inlay_hints: RwSignal<Option<Spans<InlayHint>>>,
+    /// Links in the document, as reported by the LSP
+    pub document_links: RwSignal<Option<Spans<DocumentLink>>>,
+    /// Color literals in the document, as reported by the LSP
+    pub document_colors: RwSignal<Option<Spans<ColorInformation>>>,
+    /// Ranges which are edited together, such as the name of an opening and closing tag
+    pub linked_editing_ranges: RwSignal<Option<Vec<(usize, usize)>>>,
     /// Current completion lens text, if any.
     /// This will be displayed even on views that are not focused.
     pub completion_lens: RwSignal<Option<String>>,
@@ -243,6 +348,9 @@ pub fn new(
             ))),
             semantic_styles: cx.create_rw_signal(None),
             inlay_hints: cx.create_rw_signal(None),
+            document_links: cx.create_rw_signal(None),
+            document_colors: cx.create_rw_signal(None),
+            linked_editing_ranges: cx.create_rw_signal(None),
             diagnostics,
             completion_lens: cx.create_rw_signal(None),
             completion_pos: cx.create_rw_signal((0, 0)),
@@ -254,6 +362,8 @@ pub fn new(
                 read_only: false,
             }),
             loaded: cx.create_rw_signal(false),
+            encoding: cx.create_rw_signal(None),
+            large: cx.create_rw_signal(false),
             histories: cx.create_rw_signal(im::HashMap::new()),
             head_changes: cx.create_rw_signal(im::Vector::new()),
             sticky_headers: Rc::new(RefCell::new(HashMap::new())),
@@ -293,6 +403,9 @@ pub fn new_content(
             ))),
             semantic_styles: cx.create_rw_signal(None),
             inlay_hints: cx.create_rw_signal(None),
+            document_links: cx.create_rw_signal(None),
+            document_colors: cx.create_rw_signal(None),
+            linked_editing_ranges: cx.create_rw_signal(None),
             diagnostics: DiagnosticData {
                 expanded: cx.create_rw_signal(true),
                 diagnostics: cx.create_rw_signal(im::Vector::new()),
@@ -308,6 +421,8 @@ pub fn new_content(
             head_changes: cx.create_rw_signal(im::Vector::new()),
             sticky_headers: Rc::new(RefCell::new(HashMap::new())),
             loaded: cx.create_rw_signal(true),
+            encoding: cx.create_rw_signal(None),
+            large: cx.create_rw_signal(false),
             find_result: FindResult::new(cx),
             code_actions: cx.create_rw_signal(im::HashMap::new()),
             preedit: PreeditData::new(cx),
@@ -344,6 +459,9 @@ pub fn new_history(
             ))),
             semantic_styles: cx.create_rw_signal(None),
             inlay_hints: cx.create_rw_signal(None),
+            document_links: cx.create_rw_signal(None),
+            document_colors: cx.create_rw_signal(None),
+            linked_editing_ranges: cx.create_rw_signal(None),
             diagnostics: DiagnosticData {
                 expanded: cx.create_rw_signal(true),
                 diagnostics: cx.create_rw_signal(im::Vector::new()),
@@ -357,6 +475,8 @@ pub fn new_history(
             content: cx.create_rw_signal(content),
             sticky_headers: Rc::new(RefCell::new(HashMap::new())),
             loaded: cx.create_rw_signal(true),
+            encoding: cx.create_rw_signal(None),
+            large: cx.create_rw_signal(false),
             histories: cx.create_rw_signal(im::HashMap::new()),
             head_changes: cx.create_rw_signal(im::Vector::new()),
             code_actions: cx.create_rw_signal(im::HashMap::new()),
@@ -446,6 +566,11 @@ pub fn loaded(&self) -> bool {
     //// Initialize the content with some text, this marks the document as loaded.
     pub fn init_content(&self, content: Rope) {
         batch(|| {
+            if self.large.get_untracked() {
+                self.syntax.set(Syntax::plaintext());
+            } else {
+                self.detect_language(&content);
+            }
             self.syntax.with_untracked(|syntax| {
                 self.buffer.update(|buffer| {
                     buffer.init_content(content);
@@ -456,12 +581,26 @@ pub fn init_content(&self, content: Rope) {
             });
             self.loaded.set(true);
             self.on_update(None);
-            self.init_parser();
+            if !self.large.get_untracked() {
+                self.init_parser();
+            }
             self.init_diagnostics();
             self.retrieve_head();
         });
     }
 
+    /// Go by a modeline or a shebang in the content of a file for its
+    /// language, which was picked from its path before it was loaded.
+    fn detect_language(&self, content: &Rope) {
+        let DocContent::File { path, .. } = self.content.get_untracked() else {
+            return;
+        };
+        let language = LapceLanguage::from_path_and_text(&path, content);
+        if self.syntax.with_untracked(|syntax| syntax.language) != language {
+            self.syntax.set(Syntax::from_language(language));
+        }
+    }
+
     fn init_parser(&self) {
         let code = self.buffer.get_untracked().to_string();
         self.syntax.with_untracked(|syntax| {
@@ -508,9 +647,13 @@ pub fn do_insert(
         if self.content.with_untracked(|c| c.read_only()) {
             return Vec::new();
         }
+        if self.content.with_untracked(|c| c.is_hex()) {
+            return self.hex_overwrite(cursor, s);
+        }
 
         let old_cursor = cursor.mode.clone();
-        let deltas = self.syntax.with_untracked(|syntax| {
+        let linked_range = self.linked_editing_range_index(cursor);
+        let mut deltas = self.syntax.with_untracked(|syntax| {
             self.buffer
                 .try_update(|buffer| {
                     Action::insert(
@@ -532,6 +675,80 @@ pub fn do_insert(
             buffer.set_cursor_after(cursor.mode.clone());
         });
         self.apply_deltas(&deltas);
+        if let Some(index) = linked_range {
+            deltas.extend(self.mirror_linked_edit(
+                index,
+                cursor,
+                EditType::InsertChars,
+            ));
+        }
+        deltas
+    }
+
+    /// Type over the bytes of a hex document, which can't be inserted into
+    /// or deleted from, moving past each digit or character typed.
+    fn hex_overwrite(
+        &self,
+        cursor: &mut Cursor,
+        s: &str,
+    ) -> Vec<(Rope, RopeDelta, InvalLines)> {
+        let old_cursor = cursor.mode.clone();
+        let mut deltas = Vec::new();
+        for c in s.chars() {
+            let delta = self
+                .buffer
+                .try_update(|buffer| {
+                    let offset = cursor.offset();
+                    let line = buffer.line_of_offset(offset);
+                    let line_start = buffer.offset_of_line(line);
+                    let (edits, col, next_line) = hex::overwrite(
+                        &buffer.line_content(line),
+                        offset - line_start,
+                        c,
+                    )?;
+                    let edits: Vec<_> = edits
+                        .into_iter()
+                        .map(|(range, text)| {
+                            let selection = Selection::region(
+                                line_start + range.start,
+                                line_start + range.end,
+                            );
+                            (selection, text)
+                        })
+                        .collect();
+                    let edits: Vec<_> = edits
+                        .iter()
+                        .map(|(selection, text)| (selection, text.as_str()))
+                        .collect();
+                    let delta = buffer.edit(&edits, EditType::InsertChars);
+
+                    let next = if !next_line {
+                        line_start + col
+                    } else if line < buffer.last_line() {
+                        buffer.offset_of_line(line + 1) + col
+                    } else {
+                        offset
+                    };
+                    cursor.mode = match cursor.mode {
+                        CursorMode::Normal(_) => CursorMode::Normal(next),
+                        _ => CursorMode::Insert(Selection::caret(next)),
+                    };
+                    Some(delta)
+                })
+                .flatten();
+            match delta {
+                Some(delta) => deltas.push(delta),
+                None => break,
+            }
+        }
+
+        if !deltas.is_empty() {
+            self.buffer.update(|buffer| {
+                buffer.set_cursor_before(old_cursor);
+                buffer.set_cursor_after(cursor.mode.clone());
+            });
+            self.apply_deltas(&deltas);
+        }
         deltas
     }
 
@@ -565,25 +782,46 @@ pub fn do_edit(
         {
             return Vec::new();
         }
+        // The bytes of a hex document are only typed over, never inserted or
+        // deleted, but typing over them can be undone
+        if self.content.with_untracked(|c| c.is_hex())
+            && !cmd.not_changing_buffer()
+            && !matches!(cmd, EditCommand::Undo | EditCommand::Redo)
+        {
+            return Vec::new();
+        }
 
         let mut clipboard = SystemClipboard::new();
         let old_cursor = cursor.mode.clone();
-        let deltas = self.syntax.with_untracked(|syntax| {
+        let linked_range = self.linked_editing_range_index(cursor);
+        let mut deltas = self.syntax.with_untracked(|syntax| {
             self.buffer
                 .try_update(|buffer| {
+                    let conf = EditConf {
+                        comment_token: syntax.language.comment_token(),
+                        modal,
+                        smart_tab,
+                        keep_indent: true,
+                        auto_indent: true,
+                    };
+                    if syntax.rev == buffer.rev() && reindents(cursor, cmd) {
+                        return self.reindented_edit(
+                            syntax,
+                            buffer,
+                            cursor,
+                            cmd,
+                            &mut clipboard,
+                            register,
+                            conf,
+                        );
+                    }
                     Action::do_edit(
                         cursor,
                         buffer,
                         cmd,
                         &mut clipboard,
                         register,
-                        EditConf {
-                            comment_token: syntax.language.comment_token(),
-                            modal,
-                            smart_tab,
-                            keep_indent: true,
-                            auto_indent: true,
-                        },
+                        conf,
                     )
                 })
                 .unwrap()
@@ -595,21 +833,350 @@ pub fn do_edit(
                 buffer.set_cursor_after(cursor.mode.clone());
             });
             self.apply_deltas(&deltas);
+
+            match (linked_range, cmd) {
+                (
+                    Some(index),
+                    EditCommand::DeleteBackward
+                    | EditCommand::DeleteForward
+                    | EditCommand::DeleteWordBackward
+                    | EditCommand::DeleteWordForward,
+                ) => {
+                    deltas.extend(self.mirror_linked_edit(
+                        index,
+                        cursor,
+                        EditType::Delete,
+                    ));
+                }
+                (Some(_), _) => {
+                    // Other edits can't be mirrored sensibly, so stop editing the ranges together
+                    self.linked_editing_ranges.set(None);
+                }
+                (None, _) => {}
+            }
         }
 
         deltas
     }
 
+    /// Make an edit that opens or pastes lines with [`Action::do_edit`] on a
+    /// copy of the buffer, then make it on the buffer together with the
+    /// indentation `indents.scm` gives those lines, so that undoing it undoes
+    /// both.
+    #[allow(clippy::too_many_arguments)]
+    fn reindented_edit(
+        &self,
+        syntax: &Syntax,
+        buffer: &mut Buffer,
+        cursor: &mut Cursor,
+        cmd: &EditCommand,
+        clipboard: &mut SystemClipboard,
+        register: &mut Register,
+        conf: EditConf,
+    ) -> Vec<(Rope, RopeDelta, InvalLines)> {
+        let mut edited = buffer.clone();
+        let mut edited_cursor = cursor.clone();
+        let deltas = Action::do_edit(
+            &mut edited_cursor,
+            &mut edited,
+            cmd,
+            clipboard,
+            register,
+            conf,
+        );
+        let is_paste = matches!(
+            cmd,
+            EditCommand::Paste
+                | EditCommand::PasteBefore
+                | EditCommand::ClipboardPaste
+        );
+        let indents = match deltas.as_slice() {
+            [(_, delta, _)] if is_paste => {
+                self.paste_indents(syntax, &edited, delta)
+            }
+            [(_, delta, _)] => self.new_line_indents(syntax, &edited, cmd, delta),
+            _ => Vec::new(),
+        };
+        if indents.is_empty() {
+            *buffer = edited;
+            *cursor = edited_cursor;
+            return deltas;
+        }
+
+        let edits = compose_edits(&deltas[0].1, &indents);
+        let edits = edits
+            .iter()
+            .map(|(selection, text)| (selection, text.as_str()))
+            .collect::<Vec<_>>();
+        let edit_type = if is_paste {
+            EditType::Other
+        } else {
+            EditType::InsertNewline
+        };
+        let result = buffer.edit(&edits, edit_type);
+
+        // Move the carets along with the indentation of their lines
+        let selections = indents
+            .iter()
+            .map(|(start, end, _)| Selection::region(*start, *end))
+            .collect::<Vec<_>>();
+        let indents = selections
+            .iter()
+            .zip(indents.iter())
+            .map(|(selection, (_, _, indent))| (selection, indent.as_str()))
+            .collect::<Vec<_>>();
+        let (_, delta, _) = edited.edit(&indents, EditType::Other);
+        let selection = edited_cursor.edit_selection(&edited).apply_delta(
+            &delta,
+            true,
+            InsertDrift::Default,
+        );
+        edited_cursor.update_selection(&edited, selection);
+        *cursor = edited_cursor;
+        vec![result]
+    }
+
+    /// The indentation of the lines `delta` opened, as the ranges of the
+    /// edited text to replace and their replacements. A line opened in the
+    /// middle of another one takes the rest of it, so it's indented for the
+    /// offset where it was split.
+    fn new_line_indents(
+        &self,
+        syntax: &Syntax,
+        buffer: &Buffer,
+        cmd: &EditCommand,
+        delta: &RopeDelta,
+    ) -> Vec<(usize, usize, String)> {
+        let tab_width = self.common.config.with_untracked(|c| c.editor.tab_width);
+        let indent_unit = buffer.indent_unit();
+        let old_text = RopeTextRef::new(&syntax.text);
+        let indent_at = |offset: usize| {
+            syntax.indent_for_new_line(offset, indent_unit, tab_width)
+        };
+        let (inserts, _) = delta.clone().factor();
+
+        let mut indents = Vec::new();
+        for insert in InsertsValueIter::new(&inserts) {
+            let inserted = insert.node.to_string();
+            let breaks = inserted.matches('\n').count();
+            if breaks == 0 {
+                continue;
+            }
+            let start = Selection::caret(insert.old_offset)
+                .apply_delta(delta, false, InsertDrift::Default)
+                .min_offset();
+
+            let lines: Vec<(usize, Option<String>)> =
+                if matches!(cmd, EditCommand::NewLineAbove)
+                    && inserted.ends_with('\n')
+                {
+                    // A line opened above another one continues the line
+                    // before it
+                    let old_line = old_text.line_of_offset(insert.old_offset);
+                    let indent = match old_line.checked_sub(1) {
+                        Some(prev) => {
+                            indent_at(old_text.line_end_offset(prev, true))
+                        }
+                        None => Some(String::new()),
+                    };
+                    vec![(buffer.line_of_offset(start), indent)]
+                } else {
+                    let indent = indent_at(insert.old_offset);
+                    let first_line = buffer.line_of_offset(start) + 1;
+                    let last_line = first_line + breaks - 1;
+                    // Between a pair of brackets, the line the cursor goes to
+                    // is indented once more than the closing bracket's line
+                    (first_line..=last_line)
+                        .map(|line| {
+                            let indent = if line < last_line {
+                                indent
+                                    .as_ref()
+                                    .map(|indent| format!("{indent}{indent_unit}"))
+                            } else {
+                                indent.clone()
+                            };
+                            (line, indent)
+                        })
+                        .collect()
+                };
+
+            for (line, indent) in lines {
+                let Some(indent) = indent else {
+                    continue;
+                };
+                let content = buffer.line_content(line);
+                let blank =
+                    content.len() - content.trim_start_matches([' ', '\t']).len();
+                if content[..blank] == indent {
+                    continue;
+                }
+                let line_start = buffer.offset_of_line(line);
+                indents.push((line_start, line_start + blank, indent));
+            }
+        }
+        indents
+    }
+
+    /// The indentation that shifts the lines `delta` pasted at the start of a
+    /// line so that the first one gets the indentation `indents.scm` gives it
+    /// there, keeping how the pasted lines are indented relative to each
+    /// other. It's given as the ranges of the edited text to replace and
+    /// their replacements.
+    fn paste_indents(
+        &self,
+        syntax: &Syntax,
+        buffer: &Buffer,
+        delta: &RopeDelta,
+    ) -> Vec<(usize, usize, String)> {
+        let tab_width = self.common.config.with_untracked(|c| c.editor.tab_width);
+        let indent_unit = buffer.indent_unit();
+        let old_text = RopeTextRef::new(&syntax.text);
+        let (inserts, _) = delta.clone().factor();
+
+        let mut indents = Vec::new();
+        let mut done_line = None;
+        for insert in InsertsValueIter::new(&inserts) {
+            let inserted = insert.node.to_string();
+            if !inserted.contains('\n') {
+                continue;
+            }
+            let start = Selection::caret(insert.old_offset)
+                .apply_delta(delta, false, InsertDrift::Default)
+                .min_offset();
+            let first_line = buffer.line_of_offset(start);
+            let line_start = buffer.offset_of_line(first_line);
+            if !buffer.slice_to_cow(line_start..start).trim().is_empty() {
+                continue;
+            }
+
+            // Whole lines pasted above a line belong after the one before it
+            let old_line = old_text.line_of_offset(insert.old_offset);
+            let desired = if inserted.ends_with('\n') && old_line > 0 {
+                syntax.indent_for_new_line(
+                    old_text.line_end_offset(old_line - 1, true),
+                    indent_unit,
+                    tab_width,
+                )
+            } else {
+                syntax.indent_for_new_line(
+                    old_text.offset_of_line(old_line),
+                    indent_unit,
+                    tab_width,
+                )
+            };
+            let Some(desired) = desired else {
+                continue;
+            };
+            let shift = indent_width(&desired, tab_width) as isize
+                - indent_width(&buffer.line_content(first_line), tab_width) as isize;
+            if shift == 0 {
+                continue;
+            }
+
+            let end = start + inserted.len();
+            let last_line = buffer.line_of_offset(end.saturating_sub(1));
+            for line in first_line..=last_line {
+                if done_line.is_some_and(|done| line <= done) {
+                    continue;
+                }
+                done_line = Some(line);
+                let content = buffer.line_content(line);
+                if content.trim().is_empty() {
+                    continue;
+                }
+                let width = indent_width(&content, tab_width) as isize + shift;
+                let blank =
+                    content.len() - content.trim_start_matches([' ', '\t']).len();
+                let line_start = buffer.offset_of_line(line);
+                indents.push((
+                    line_start,
+                    line_start + blank,
+                    indent_string(width.max(0) as usize, indent_unit, tab_width),
+                ));
+            }
+        }
+        indents
+    }
+
+    /// The index of the linked editing range that contains all the cursor's selections, if any.
+    fn linked_editing_range_index(&self, cursor: &Cursor) -> Option<usize> {
+        if !cursor.is_insert() {
+            return None;
+        }
+        let ranges = self.linked_editing_ranges.get_untracked()?;
+        let selection = self.buffer.with_untracked(|b| cursor.edit_selection(b));
+        ranges.iter().position(|(start, end)| {
+            selection
+                .regions()
+                .iter()
+                .all(|region| *start <= region.min() && region.max() <= *end)
+        })
+    }
+
+    /// Copy the text of the linked editing range at `index`, which was just edited, onto the
+    /// other linked ranges. The `edit_type` should be that of the original edit so that they
+    /// end up in the same undo group.
+    fn mirror_linked_edit(
+        &self,
+        index: usize,
+        cursor: &mut Cursor,
+        edit_type: EditType,
+    ) -> Option<(Rope, RopeDelta, InvalLines)> {
+        let ranges = self.linked_editing_ranges.get_untracked()?;
+        let (start, end) = *ranges.get(index)?;
+        let (text, others) = self.buffer.with_untracked(|buffer| {
+            let text = buffer.slice_to_cow(start..end).to_string();
+            let others = ranges
+                .iter()
+                .enumerate()
+                .filter(|(i, (start, end))| {
+                    *i != index && buffer.slice_to_cow(*start..*end) != text
+                })
+                .map(|(_, (start, end))| Selection::region(*start, *end))
+                .collect::<Vec<_>>();
+            (text, others)
+        });
+
+        if text.contains(char::is_whitespace) {
+            // The edit moved out of the name, such as adding an attribute to a tag
+            self.linked_editing_ranges.set(None);
+            return None;
+        }
+        if others.is_empty() {
+            return None;
+        }
+
+        let edits = others
+            .iter()
+            .map(|selection| (selection, text.as_str()))
+            .collect::<Vec<_>>();
+        let (rope, delta, inval_lines) = self.do_raw_edit(&edits, edit_type)?;
+        self.buffer.update(|buffer| {
+            let selection = cursor.edit_selection(buffer).apply_delta(
+                &delta,
+                true,
+                InsertDrift::Default,
+            );
+            cursor.update_selection(buffer, selection);
+            buffer.set_cursor_after(cursor.mode.clone());
+        });
+        Some((rope, delta, inval_lines))
+    }
+
     pub fn apply_deltas(&self, deltas: &[(Rope, RopeDelta, InvalLines)]) {
         let rev = self.rev() - deltas.len() as u64;
         batch(|| {
             for (i, (_, delta, inval)) in deltas.iter().enumerate() {
                 self.update_styles(delta);
                 self.update_inlay_hints(delta);
+                self.update_document_links(delta);
+                self.update_linked_editing_ranges(delta);
                 self.update_diagnostics(delta);
                 self.update_completion_lens(delta);
                 self.update_find_result(delta);
-                if let DocContent::File { path, .. } = self.content.get_untracked() {
+                if let DocContent::File { path, .. } | DocContent::Hex { path, .. } =
+                    self.content.get_untracked()
+                {
                     self.update_breakpoints(delta, &path, &inval.old_text);
                     self.common.proxy.update(
                         path,
@@ -654,19 +1221,19 @@ pub fn line_ending(&self) -> LineEnding {
             self.trigger_head_change();
             self.check_auto_save();
             self.get_inlay_hints();
+            self.get_document_links();
+            self.get_document_colors();
             self.find_result.reset();
             self.get_semantic_styles();
             self.do_bracket_colorization();
             self.clear_code_actions();
             self.clear_style_cache();
             self.get_code_lens();
-            self.get_document_symbol();
-            self.get_folding_range();
         });
     }
 
     fn do_bracket_colorization(&self) {
-        if self.parser.borrow().active {
+        if self.parser.borrow().active && !self.large.get_untracked() {
             self.syntax.with_untracked(|syntax| {
                 if syntax.rev == self.rev() && syntax.styles.is_some() {
                     self.parser.borrow_mut().update_code(
@@ -782,7 +1349,104 @@ fn update_inlay_hints(&self, delta: &RopeDelta) {
         });
     }
 
+    /// Update the document links and colors so their positions are correct after an edit.
+    fn update_document_links(&self, delta: &RopeDelta) {
+        batch(|| {
+            self.document_links.update(|links| {
+                if let Some(links) = links.as_mut() {
+                    links.apply_shape(delta);
+                }
+            });
+            self.document_colors.update(|colors| {
+                if let Some(colors) = colors.as_mut() {
+                    colors.apply_shape(delta);
+                }
+            });
+        });
+    }
+
+    /// Update the linked editing ranges so their positions are correct after an edit.
+    fn update_linked_editing_ranges(&self, delta: &RopeDelta) {
+        if self.linked_editing_ranges.with_untracked(|r| r.is_none()) {
+            return;
+        }
+        self.linked_editing_ranges.update(|ranges| {
+            if let Some(ranges) = ranges.as_mut() {
+                let mut transformer = Transformer::new(delta);
+                for (start, end) in ranges.iter_mut() {
+                    *start = transformer.transform(*start, false);
+                    *end = transformer.transform(*end, true);
+                }
+            }
+        });
+    }
+
+    /// The linked editing ranges at the offset from the tree-sitter syntax tree, which is used
+    /// when no language server provides them.
+    pub fn syntax_linked_editing_ranges(
+        &self,
+        offset: usize,
+    ) -> Option<Vec<(usize, usize)>> {
+        let rev = self.rev();
+        self.syntax.with_untracked(|syntax| {
+            if syntax.rev != rev {
+                return None;
+            }
+            syntax.linked_tag_ranges(offset)
+        })
+    }
+
+    /// The range of the text object around the offset, with an exclusive end.
+    pub fn text_object_range(
+        &self,
+        offset: usize,
+        object: TextObject,
+        inside: bool,
+    ) -> Option<(usize, usize)> {
+        let (rev, text) =
+            self.buffer.with_untracked(|b| (b.rev(), b.text().clone()));
+        self.syntax.with_untracked(|syntax| {
+            let syntax = (syntax.rev == rev).then_some(syntax);
+            object.range(syntax, &text, offset, inside)
+        })
+    }
+
+    /// The opening and closing ranges of what surrounds the offset, like
+    /// the quotes of a string or the tags of an element.
+    pub fn find_surround(
+        &self,
+        offset: usize,
+        target: char,
+    ) -> Option<[(usize, usize); 2]> {
+        let (rev, text) =
+            self.buffer.with_untracked(|b| (b.rev(), b.text().clone()));
+        self.syntax.with_untracked(|syntax| {
+            let syntax = (syntax.rev == rev).then_some(syntax);
+            surround::find_surround(syntax, &text, offset, target)
+        })
+    }
+
+    /// The start of the next text object after the offset, or the previous
+    /// one before it if not `forward`.
+    pub fn text_object_offset(
+        &self,
+        offset: usize,
+        object: TextObject,
+        forward: bool,
+    ) -> Option<usize> {
+        let rev = self.rev();
+        self.syntax.with_untracked(|syntax| {
+            if syntax.rev != rev {
+                return None;
+            }
+            syntax.textobject_offset(offset, object, forward)
+        })
+    }
+
     pub fn trigger_syntax_change(&self, edits: Option<SmallVec<[SyntaxEdit; 3]>>) {
+        if self.large.get_untracked() {
+            return;
+        }
         let (rev, text) =
             self.buffer.with_untracked(|b| (b.rev(), b.text().clone()));
 
@@ -793,6 +1457,10 @@ fn update_inlay_hints(&self, delta: &RopeDelta) {
                 doc.do_bracket_colorization();
                 doc.clear_style_cache();
                 doc.clear_sticky_headers_cache();
+                // Folding and symbols fall back on the syntax tree, which is
+                // up to date only now
+                doc.get_folding_range();
+                doc.get_document_symbol();
             }
         });
 
@@ -866,7 +1534,7 @@ pub fn line_style(&self, line: usize) -> Arc<Vec<LineStyle>> {
 
     /// Request semantic styles for the buffer from the LSP through the proxy.
     pub fn get_semantic_styles(&self) {
-        if !self.loaded() {
+        if !self.loaded() || self.large.get_untracked() {
             return;
         }
 
@@ -924,6 +1592,9 @@ pub fn get_semantic_styles(&self) {
     }
 
     pub fn get_code_lens(&self) {
+        if self.large.get_untracked() {
+            return;
+        }
         let cx = self.scope;
         let doc = self.clone();
         self.code_lens.update(|code_lens| {
@@ -971,6 +1642,9 @@ pub fn get_code_lens(&self) {
     }
 
     pub fn get_document_symbol(&self) {
+        if self.large.get_untracked() {
+            return;
+        }
         let cx = self.scope;
         let doc = self.clone();
         let rev = self.rev();
@@ -981,26 +1655,31 @@ pub fn get_document_symbol(&self) {
                     if rev != doc.rev() {
                         return;
                     }
-                    if let Ok(ProxyResponse::GetDocumentSymbols { resp }) = result {
-                        let items: Vec<RwSignal<SymbolInformationItemData>> =
-                            match resp {
-                                DocumentSymbolResponse::Flat(_symbols) => {
-                                    Vec::with_capacity(0)
-                                }
-                                DocumentSymbolResponse::Nested(symbols) => symbols
-                                    .into_iter()
-                                    .map(|x| {
-                                        cx.create_rw_signal(
-                                            SymbolInformationItemData::from((x, cx)),
-                                        )
-                                    })
-                                    .collect(),
-                            };
-                        let symbol_new = Some(SymbolData::new(items, path, cx));
-                        doc.document_symbol_data.update(|symbol| {
-                            *symbol = symbol_new;
-                        });
-                    }
+                    let symbols = match result {
+                        Ok(ProxyResponse::GetDocumentSymbols {
+                            resp: DocumentSymbolResponse::Nested(symbols),
+                        }) if !symbols.is_empty() => symbols,
+                        // The outline has no place for flat symbols
+                        Ok(ProxyResponse::GetDocumentSymbols {
+                            resp: DocumentSymbolResponse::Flat(symbols),
+                        }) if !symbols.is_empty() => Vec::new(),
+                        _ => match doc.syntax_document_symbols() {
+                            Some(symbols) => symbols,
+                            None => return,
+                        },
+                    };
+                    let items: Vec<RwSignal<SymbolInformationItemData>> = symbols
+                        .into_iter()
+                        .map(|x| {
+                            cx.create_rw_signal(SymbolInformationItemData::from((
+                                x, cx,
+                            )))
+                        })
+                        .collect();
+                    let symbol_new = Some(SymbolData::new(items, path, cx));
+                    doc.document_symbol_data.update(|symbol| {
+                        *symbol = symbol_new;
+                    });
                 }
             });
 
@@ -1010,9 +1689,22 @@ pub fn get_document_symbol(&self) {
         }
     }
 
+    /// The symbols of the document according to its syntax tree, for when the
+    /// language server gives none.
+    pub fn syntax_document_symbols(&self) -> Option<Vec<DocumentSymbol>> {
+        let rev = self.rev();
+        self.syntax.with_untracked(|syntax| {
+            if syntax.rev == rev {
+                syntax.document_symbols()
+            } else {
+                None
+            }
+        })
+    }
+
     /// Request inlay hints for the buffer from the LSP through the proxy.
     pub fn get_inlay_hints(&self) {
-        if !self.loaded() {
+        if !self.loaded() || self.large.get_untracked() {
             return;
         }
 
@@ -1056,6 +1748,113 @@ pub fn get_inlay_hints(&self) {
         });
     }
 
+    /// Request document links for the buffer from the LSP through the proxy.
+    pub fn get_document_links(&self) {
+        if !self.loaded() || self.large.get_untracked() {
+            return;
+        }
+
+        let path =
+            if let DocContent::File { path, .. } = self.content.get_untracked() {
+                path
+            } else {
+                return;
+            };
+
+        let (buffer, rev, len) = self
+            .buffer
+            .with_untracked(|b| (b.clone(), b.rev(), b.len()));
+
+        let doc = self.clone();
+        let send = create_ext_action(self.scope, move |links| {
+            if doc.buffer.with_untracked(|b| b.rev()) == rev {
+                doc.document_links.set(Some(links));
+            }
+        });
+
+        self.common.proxy.get_document_links(path, move |result| {
+            if let Ok(ProxyResponse::GetDocumentLinks { mut links }) = result {
+                links
+                    .sort_by(|left, right| left.range.start.cmp(&right.range.start));
+
+                let mut links_span = SpansBuilder::new(len);
+                for link in links {
+                    let start = buffer.offset_of_position(&link.range.start);
+                    let end = buffer.offset_of_position(&link.range.end);
+                    if start >= end {
+                        continue;
+                    }
+                    links_span.add_span(Interval::new(start, end), link);
+                }
+                send(links_span.build());
+            }
+        });
+    }
+
+    /// Request the color literals of the buffer from the LSP through the proxy.
+    pub fn get_document_colors(&self) {
+        if !self.loaded() || self.large.get_untracked() {
+            return;
+        }
+
+        let path =
+            if let DocContent::File { path, .. } = self.content.get_untracked() {
+                path
+            } else {
+                return;
+            };
+
+        let (buffer, rev, len) = self
+            .buffer
+            .with_untracked(|b| (b.clone(), b.rev(), b.len()));
+
+        let doc = self.clone();
+        let send = create_ext_action(self.scope, move |colors| {
+            if doc.buffer.with_untracked(|b| b.rev()) == rev {
+                doc.document_colors.set(Some(colors));
+                doc.clear_text_cache();
+            }
+        });
+
+        self.common.proxy.get_document_colors(path, move |result| {
+            if let Ok(ProxyResponse::GetDocumentColors { mut colors }) = result {
+                colors
+                    .sort_by(|left, right| left.range.start.cmp(&right.range.start));
+
+                let mut colors_span = SpansBuilder::new(len);
+                for color in colors {
+                    let start = buffer.offset_of_position(&color.range.start);
+                    let end = buffer.offset_of_position(&color.range.end);
+                    if start >= end {
+                        continue;
+                    }
+                    colors_span.add_span(Interval::new(start, end), color);
+                }
+                send(colors_span.build());
+            }
+        });
+    }
+
+    /// The document link under the offset, if any.
+    pub fn document_link_at(&self, offset: usize) -> Option<DocumentLink> {
+        self.document_links.with_untracked(|links| {
+            links.as_ref()?.iter().find_map(|(interval, link)| {
+                (interval.start <= offset && offset < interval.end)
+                    .then(|| link.clone())
+            })
+        })
+    }
+
+    /// The color literal under the offset, if any.
+    pub fn document_color_at(&self, offset: usize) -> Option<ColorInformation> {
+        self.document_colors.with_untracked(|colors| {
+            colors.as_ref()?.iter().find_map(|(interval, color)| {
+                (interval.start <= offset && offset <= interval.end)
+                    .then(|| color.clone())
+            })
+        })
+    }
+
     pub fn diagnostics(&self) -> &DiagnosticData {
         &self.diagnostics
     }
@@ -1095,41 +1894,74 @@ pub fn init_diagnostics(&self) {
         self.clear_code_actions();
     }
 
+    /// Get the folding ranges from the language server, or work them out
+    /// from the syntax tree or the indentation if it has none to give.
     pub fn get_folding_range(&self) {
-        // let cx = self.scope;
-        // let doc = self.clone();
-        // let rev = self.rev();
-        // if let DocContent::File { path, .. } = doc.content.get_untracked() {
-        //     let send = create_ext_action(cx, {
-        //         move |result| {
-        //             if rev != doc.rev() {
-        //                 return;
-        //             }
-        //             if let Ok(ProxyResponse::LspFoldingRangeResponse {
-        //                 resp, ..
-        //             }) = result
-        //             {
-        //                 let folding = resp
-        //                     .unwrap_or_default()
-        //                     .into_iter()
-        //                     .map(|x| {
-        //                         crate::editor::gutter::FoldingRange::from_lsp(x)
-        //                     })
-        //                     .sorted_by(|x, y| x.start.line.cmp(&y.start.line))
-        //                     .collect();
-        //                 doc.folding_ranges.update(|symbol| {
-        //                     symbol.0 = folding;
-        //                 });
-        //             }
-        //         }
-        //     });
-
-        //     self.common
-        //         .proxy
-        //         .get_lsp_folding_range(path, move |result| {
-        //             send(result);
-        //         });
-        // }
+        if self.content.with_untracked(|content| content.is_local())
+            || self.large.get_untracked()
+        {
+            return;
+        }
+
+        let cx = self.scope;
+        let doc = self.clone();
+        let rev = self.rev();
+        if let DocContent::File { path, .. } = doc.content.get_untracked() {
+            let send = create_ext_action(cx, {
+                move |result| {
+                    if rev != doc.rev() {
+                        return;
+                    }
+                    let folding = match result {
+                        Ok(ProxyResponse::LspFoldingRangeResponse {
+                            resp: Some(resp),
+                            ..
+                        }) if !resp.is_empty() => resp
+                            .into_iter()
+                            .map(FoldingRange::from_lsp)
+                            .sorted_by(|x, y| x.start.line.cmp(&y.start.line))
+                            .collect(),
+                        _ => doc.local_folding_ranges(),
+                    };
+                    doc.folding_ranges.update(|ranges| {
+                        ranges.update_ranges(folding);
+                    });
+                }
+            });
+
+            self.common
+                .proxy
+                .get_lsp_folding_range(path, move |result| {
+                    send(result);
+                });
+        } else {
+            let folding = self.local_folding_ranges();
+            self.folding_ranges.update(|ranges| {
+                ranges.update_ranges(folding);
+            });
+        }
+    }
+
+    /// The folding ranges from the language's `folds.scm`, or from the
+    /// indentation if there is no query for the language.
+    fn local_folding_ranges(&self) -> Vec<FoldingRange> {
+        let (rev, text) =
+            self.buffer.with_untracked(|b| (b.rev(), b.text().clone()));
+        let folds = self
+            .syntax
+            .with_untracked(|syntax| {
+                if syntax.rev == rev {
+                    syntax.folds()
+                } else {
+                    None
+                }
+            })
+            .unwrap_or_else(|| {
+                let tab_width =
+                    self.common.config.with_untracked(|c| c.editor.tab_width);
+                indent_folds(&text, tab_width)
+            });
+        folds.into_iter().map(FoldingRange::from_fold).collect()
     }
 
     /// Get the current completion lens text
@@ -1394,6 +2226,9 @@ pub fn retrieve_head(&self) {
     }
 
     pub fn trigger_head_change(&self) {
+        if self.large.get_untracked() {
+            return;
+        }
         let history = if let Some(text) =
             self.histories.with_untracked(|histories| {
                 histories
@@ -1437,12 +2272,118 @@ pub fn trigger_head_change(&self) {
     }
 
     pub fn save(&self, after_action: impl FnOnce() + 'static) {
+        let content = self.content.get_untracked();
+        let is_hex = content.is_hex();
+        if let DocContent::File { path, .. } | DocContent::Hex { path, .. } = content
+        {
+            let rev = self.rev();
+            let buffer = self.buffer;
+            let doc = self.clone();
+            let saved = (!is_hex).then(|| {
+                (path.clone(), buffer.with_untracked(|b| b.text().clone()))
+            });
+            let error_path = path.clone();
+            let send = create_ext_action(self.scope, move |result| match result {
+                Ok(ProxyResponse::SaveResponse {}) => {
+                    if let Some((path, text)) = saved {
+                        doc.record_local_history(path, text);
+                    }
+                    let current_rev = buffer.with_untracked(|buffer| buffer.rev());
+                    if current_rev == rev {
+                        buffer.update(|buffer| {
+                            buffer.set_pristine();
+                        });
+                        after_action();
+                    }
+                }
+                Err(err) => doc.save_failed(&error_path, err.message),
+                Ok(_) => {}
+            });
+
+            if is_hex {
+                let bytes = self
+                    .buffer
+                    .with_untracked(|buffer| hex::parse_hex_dump(buffer.text()));
+                self.common
+                    .proxy
+                    .save_bytes(rev, path, bytes, move |result| {
+                        send(result);
+                    })
+            } else {
+                self.common.proxy.save(rev, path, true, move |result| {
+                    send(result);
+                })
+            }
+        }
+    }
+
+    /// Tell why the file couldn't be saved, like that it has characters
+    /// its encoding can't hold.
+    fn save_failed(&self, path: &Path, message: String) {
+        self.common
+            .internal_command
+            .send(InternalCommand::ShowMessage {
+                title: "Save".to_string(),
+                message: ShowMessageParams {
+                    typ: MessageType::ERROR,
+                    message: format!("Couldn't save {}: {message}", path.display()),
+                },
+            });
+    }
+
+    /// Keep the saved text of the file in the local history.
+    fn record_local_history(&self, path: PathBuf, text: Rope) {
+        let Some(history) = LocalHistory::new(&self.common.workspace) else {
+            return;
+        };
+        let limits = self
+            .common
+            .config
+            .get_untracked()
+            .core
+            .local_history_limits();
+        let internal_command = self.common.internal_command;
+        let send = create_ext_action(self.scope, move |path| {
+            internal_command.send(InternalCommand::LocalHistoryChanged { path });
+        });
+        std::thread::Builder::new()
+            .name("LocalHistory".to_owned())
+            .spawn(
+                move || match history.save(&path, &text.to_string(), &limits) {
+                    Ok(()) => send(path),
+                    Err(err) => tracing::error!("{:?}", err),
+                },
+            )
+            .unwrap();
+    }
+
+    /// Load the content of an old version of the file into the document.
+    pub fn load_history(&self, history: &DocHistory) {
+        let doc = self.clone();
+        load_history_content(self.scope, &self.common, history, move |content| {
+            doc.init_content(Rope::from(content));
+        });
+    }
+
+    /// Save the file in another encoding, which it is then saved in from
+    /// now on.
+    pub fn save_with_encoding(
+        &self,
+        encoding: String,
+        after_action: impl FnOnce() + 'static,
+    ) {
         let content = self.content.get_untracked();
         if let DocContent::File { path, .. } = content {
             let rev = self.rev();
             let buffer = self.buffer;
-            let send = create_ext_action(self.scope, move |result| {
-                if let Ok(ProxyResponse::SaveResponse {}) = result {
+            let doc_encoding = self.encoding;
+            let label = encoding.clone();
+            let doc = self.clone();
+            let saved = (path.clone(), buffer.with_untracked(|b| b.text().clone()));
+            let send = create_ext_action(self.scope, move |result| match result {
+                Ok(ProxyResponse::SaveResponse {}) => {
+                    doc.record_local_history(saved.0, saved.1);
+                    doc_encoding.set(Some(label));
                     let current_rev = buffer.with_untracked(|buffer| buffer.rev());
                     if current_rev == rev {
                         buffer.update(|buffer| {
@@ -1451,11 +2392,42 @@ pub fn save(&self, after_action: impl FnOnce() + 'static) {
                         after_action();
                     }
                 }
+                Err(err) => doc.save_failed(&saved.0, err.message),
+                Ok(_) => {}
             });
 
-            self.common.proxy.save(rev, path, true, move |result| {
-                send(result);
-            })
+            self.common.proxy.save_with_encoding(
+                rev,
+                path,
+                encoding,
+                move |result| {
+                    send(result);
+                },
+            )
+        }
+    }
+
+    /// Read the file again in another encoding, for when the one it was
+    /// detected to be in is wrong.
+    pub fn reopen_with_encoding(&self, encoding: String) {
+        let content = self.content.get_untracked();
+        if let DocContent::File { path, .. } = content {
+            let doc = self.clone();
+            let label = encoding.clone();
+            let send = create_ext_action(self.scope, move |result| {
+                if let Ok(ProxyResponse::ReopenWithEncodingResponse { content }) =
+                    result
+                {
+                    doc.encoding.set(Some(label));
+                    doc.reload(Rope::from(content), true);
+                }
+            });
+
+            self.common
+                .proxy
+                .reopen_with_encoding(path, encoding, move |result| {
+                    send(result);
+                })
         }
     }
 
@@ -1747,6 +2719,41 @@ fn phantom_text(
         // overall.
         let mut text: SmallVec<[PhantomText; 6]> = hints.collect();
 
+        // If color decorators are enabled, then put a swatch in front of each color literal that
+        // starts on this line
+        let document_colors = self.document_colors.get_untracked();
+        let swatches = config
+            .editor
+            .enable_color_decorators
+            .then_some(())
+            .and(document_colors.as_ref())
+            .map(|colors| colors.iter_chunks(start_offset..end_offset))
+            .into_iter()
+            .flatten()
+            .filter(|(interval, _)| {
+                interval.start >= start_offset && interval.start < end_offset
+            })
+            .map(|(interval, info)| {
+                let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+                let color = Color::rgba8(
+                    channel(info.color.red),
+                    channel(info.color.green),
+                    channel(info.color.blue),
+                    channel(info.color.alpha),
+                );
+                PhantomText {
+                    kind: PhantomTextKind::InlayHint,
+                    col: interval.start - start_offset,
+                    text: "\u{25A0} ".to_string(),
+                    affinity: Some(CursorAffinity::Forward),
+                    fg: Some(color),
+                    font_size: Some(config.editor.inlay_hint_font_size()),
+                    bg: None,
+                    under_line: None,
+                }
+            });
+        text.extend(swatches);
+
         // If error lens is enabled, and the diagnostics field is filled, then get the diagnostics
         // that end on this line which have a severity worse than HINT and convert them into
         // PhantomText instances
@@ -2189,6 +3196,108 @@ fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     }
 }
 
+/// Whether `cmd` opens or pastes lines that `indents.scm` should indent. New
+/// lines that replace a selection keep the indentation of the line above.
+fn reindents(cursor: &Cursor, cmd: &EditCommand) -> bool {
+    match cmd {
+        EditCommand::Paste
+        | EditCommand::PasteBefore
+        | EditCommand::ClipboardPaste => true,
+        EditCommand::InsertNewLine
+        | EditCommand::NewLineAbove
+        | EditCommand::NewLineBelow => match &cursor.mode {
+            CursorMode::Insert(selection) => {
+                selection.regions().iter().all(|region| region.is_caret())
+            }
+            CursorMode::Normal(_) => !matches!(cmd, EditCommand::InsertNewLine),
+            CursorMode::Visual { .. } => false,
+        },
+        _ => false,
+    }
+}
+
+/// The edits of the text `delta` applies to that make both `delta` and then
+/// `fixes`, which are ranges of the text after `delta` and their
+/// replacements, as a single edit.
+fn compose_edits(
+    delta: &RopeDelta,
+    fixes: &[(usize, usize, String)],
+) -> Vec<(Selection, String)> {
+    enum Piece {
+        Copy(usize, usize),
+        Text(String),
+    }
+    let slice = |piece: &Piece, from: usize, to: usize| match piece {
+        Piece::Copy(start, _) => Piece::Copy(start + from, start + to),
+        Piece::Text(text) => Piece::Text(text[from..to].to_string()),
+    };
+
+    // The pieces of the final text, either copied from the text `delta`
+    // applies to or new
+    let mut pieces = Vec::new();
+    let mut next = 0;
+    let mut offset = 0;
+    for el in &delta.els {
+        let piece = match el {
+            DeltaElement::Copy(start, end) => Piece::Copy(*start, *end),
+            DeltaElement::Insert(node) => Piece::Text(node.to_string()),
+        };
+        let end = offset
+            + match &piece {
+                Piece::Copy(start, end) => end - start,
+                Piece::Text(text) => text.len(),
+            };
+        let mut at = offset;
+        while let Some((fix_start, fix_end, text)) =
+            fixes.get(next).filter(|(fix_start, _, _)| *fix_start < end)
+        {
+            if at < *fix_start {
+                pieces.push(slice(&piece, at - offset, fix_start - offset));
+            }
+            // A fix over several pieces replaces them from the first one
+            if *fix_start >= offset {
+                pieces.push(Piece::Text(text.clone()));
+            }
+            at = at.max((*fix_end).min(end));
+            if *fix_end > end {
+                break;
+            }
+            next += 1;
+        }
+        if at < end {
+            pieces.push(slice(&piece, at - offset, end - offset));
+        }
+        offset = end;
+    }
+    pieces.extend(
+        fixes[next..]
+            .iter()
+            .map(|(_, _, text)| Piece::Text(text.clone())),
+    );
+
+    let mut edits = Vec::new();
+    let mut pos = 0;
+    let mut text = String::new();
+    for piece in pieces {
+        match piece {
+            Piece::Text(t) => text.push_str(&t),
+            Piece::Copy(start, end) => {
+                if start > pos || !text.is_empty() {
+                    edits.push((
+                        Selection::region(pos, start),
+                        std::mem::take(&mut text),
+                    ));
+                }
+                pos = end;
+            }
+        }
+    }
+    if pos < delta.base_len || !text.is_empty() {
+        edits.push((Selection::region(pos, delta.base_len), text));
+    }
+    edits
+}
+
 /// Get the previous unmatched character `c` from the `offset` using `syntax` if applicable
 fn syntax_prev_unmatched(
     buffer: &Buffer,
@@ -2278,3 +3387,60 @@ fn extra_styles_for_range(
             })
         })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The text after making `edits`, which are in order and don't overlap.
+    fn apply(text: &str, edits: &[(Selection, String)]) -> String {
+        let mut text = text.to_string();
+        for (selection, new_text) in edits.iter().rev() {
+            let region = selection.regions()[0];
+            text.replace_range(region.min()..region.max(), new_text);
+        }
+        text
+    }
+
+    fn insert(offset: usize, text: &str, base_len: usize) -> RopeDelta {
+        RopeDelta::simple_edit(
+            Interval::new(offset, offset),
+            Rope::from(text),
+            base_len,
+        )
+    }
+
+    #[test]
+    fn test_compose_open_line() {
+        let text = "fn f() {\n}\n";
+        let delta = insert(8, "\n", text.len());
+        let edits = compose_edits(&delta, &[(9, 9, "    ".to_string())]);
+        assert_eq!(edits.len(), 1);
+        assert_eq!(apply(text, &edits), "fn f() {\n    \n}\n");
+    }
+
+    #[test]
+    fn test_compose_paste() {
+        let text = "a\n  b\n";
+        let delta = insert(2, "x\ny\n", text.len());
+        let fixes = [(2, 2, "  ".to_string()), (4, 4, "  ".to_string())];
+        let edits = compose_edits(&delta, &fixes);
+        assert_eq!(apply(text, &edits), "a\n  x\n  y\n  b\n");
+
+        // Indentation replaced in the text that was already there
+        let delta = insert(4, "c", text.len());
+        let edits = compose_edits(&delta, &[(2, 4, "\t".to_string())]);
+        assert_eq!(apply(text, &edits), "a\n\tcb\n");
+    }
+
+    #[test]
+    fn test_compose_over_pieces() {
+        // A fix over both copied and inserted text
+        let delta = insert(1, "X", 2);
+        let edits = compose_edits(&delta, &[(0, 2, String::new())]);
+        assert_eq!(apply("ab", &edits), "b");
+
+        let edits = compose_edits(&delta, &[]);
+        assert_eq!(apply("ab", &edits), "aXb");
+    }
+}
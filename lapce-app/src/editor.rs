@@ -18,10 +18,11 @@
         SignalWith,
     },
     views::editor::{
+        actions::CommonAction,
         command::CommandExecuted,
         id::EditorId,
         movement,
-        text::Document,
+        text::{Document, SystemClipboard},
         view::{
             DiffSection, DiffSectionKind, LineInfo, ScreenLines, ScreenLinesBase,
         },
@@ -43,16 +44,20 @@
     },
     cursor::{Cursor, CursorMode},
     editor::EditType,
-    mode::{Mode, MotionMode},
+    mode::{Mode, MotionMode, VisualMode},
+    register::{Clipboard, Register},
     rope_text_pos::RopeTextPosition,
     selection::{InsertDrift, SelRegion, Selection},
+    surround::{delete_edits as delete_surround_edits, Surround},
+    syntax::textobject::TextObject,
 };
 use lapce_rpc::{buffer::BufferId, plugin::PluginId, proxy::ProxyResponse};
 use lapce_xi_rope::{Rope, RopeDelta, Transformer};
 use lsp_types::{
-    CodeActionResponse, CompletionItem, CompletionTextEdit, GotoDefinitionResponse,
-    HoverContents, InlayHint, InlayHintLabel, InlineCompletionTriggerKind, Location,
-    MarkedString, MarkupKind, Range, TextEdit,
+    CodeActionResponse, ColorPresentation, CompletionItem, CompletionTextEdit,
+    GotoDefinitionResponse, HoverContents, InlayHint, InlayHintLabel,
+    InlineCompletionTriggerKind, Location, MarkedString, MarkupKind, Range,
+    TextEdit,
 };
 use nucleo::Utf32Str;
 use serde::{Deserialize, Serialize};
@@ -61,6 +66,7 @@
 use self::{
     diff::DiffInfo,
     location::{EditorLocation, EditorPosition},
+    repeat::{visual_end, ChangeStep},
 };
 use crate::{
     command::{CommandKind, InternalCommand, LapceCommand, LapceWorkbenchCommand},
@@ -82,6 +88,7 @@
         implementation_view::{init_implementation_root, map_to_location},
         kind::PanelKind,
     },
+    register::RegisterContent,
     snippet::Snippet,
     tracing::*,
     window_tab::{CommonData, Focus, WindowTabData},
@@ -90,6 +97,7 @@
 pub mod diff;
 pub mod gutter;
 pub mod location;
+pub mod repeat;
 pub mod view;
 
 #[derive(Clone, Debug)]
@@ -98,6 +106,35 @@ pub enum InlineFindDirection {
     Right,
 }
 
+/// Waiting for the character that names a text object, like the `f` of `if`.
+#[derive(Clone, Copy, Debug)]
+pub struct TextObjectPrompt {
+    pub inside: bool,
+    /// Whether to enter insert mode after deleting the object
+    pub change: bool,
+}
+
+/// Waiting for the characters that say what to surround, or which pair to
+/// change or delete, like the `)` of `ds)`.
+#[derive(Clone, Debug)]
+pub enum SurroundPrompt {
+    /// The text `ys` surrounds: `i` or `a` and a text object, or `s` for the
+    /// current line
+    Object { inside: Option<bool> },
+    /// The pair to surround the ranges with, or the tag being typed after `<`
+    Add {
+        ranges: Vec<(usize, usize)>,
+        tag: Option<String>,
+    },
+    /// The pair to change, then the pair or tag to change it to
+    Change {
+        target: Option<char>,
+        tag: Option<String>,
+    },
+    /// The pair to delete
+    Delete,
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct EditorInfo {
     pub content: DocContent,
@@ -115,7 +152,7 @@ pub fn to_data(
         let editors = &data.editors;
         let common = data.common.clone();
         match &self.content {
-            DocContent::File { path, .. } => {
+            DocContent::File { path, .. } | DocContent::Hex { path, .. } => {
                 let (doc, new_doc) =
                     data.get_doc(path.clone(), self.unsaved.clone());
                 let editor = editors.make_from_doc(
@@ -214,6 +251,8 @@ pub struct EditorData {
     pub confirmed: RwSignal<bool>,
     pub snippet: RwSignal<Option<SnippetIndex>>,
     pub inline_find: RwSignal<Option<InlineFindDirection>>,
+    pub text_object: RwSignal<Option<TextObjectPrompt>>,
+    pub surround: RwSignal<Option<SurroundPrompt>>,
     pub on_screen_find: RwSignal<OnScreenFind>,
     pub last_inline_find: RwSignal<Option<(InlineFindDirection, String)>>,
     pub find_focus: RwSignal<bool>,
@@ -249,6 +288,8 @@ fn new(
             confirmed,
             snippet: cx.create_rw_signal(None),
             inline_find: cx.create_rw_signal(None),
+            text_object: cx.create_rw_signal(None),
+            surround: cx.create_rw_signal(None),
             on_screen_find: cx.create_rw_signal(OnScreenFind {
                 active: false,
                 pattern: "".to_string(),
@@ -428,7 +469,39 @@ fn run_edit_command(&self, cmd: &EditCommand) -> CommandExecuted {
             .with_untracked(|config| config.editor.smart_tab);
         let doc_before_edit = text.text().clone();
         let mut cursor = self.editor.cursor.get_untracked();
-        let mut register = self.common.register.get_untracked();
+        let mode_before = cursor.get_mode();
+        let visual_before = match &cursor.mode {
+            CursorMode::Visual { start, end, mode } => {
+                Some(ChangeStep::visual(&text, *start, *end, mode.clone()))
+            }
+            _ => None,
+        };
+
+        let selected = self.common.registers.with_untracked(|r| r.selected);
+        if *cmd == EditCommand::ClipboardPaste
+            && !matches!(selected, None | Some('"') | Some('+'))
+        {
+            // Pasting from a register rather than the system clipboard
+            let content = self
+                .common
+                .registers
+                .try_update(|r| {
+                    r.selected = None;
+                    selected.and_then(|c| r.get(c))
+                })
+                .flatten();
+            if let Some(content) = content {
+                self.paste_register_content(content);
+            }
+            return CommandExecuted::Yes;
+        }
+        let clipboard_yank =
+            matches!(cmd, EditCommand::ClipboardCopy | EditCommand::ClipboardCut);
+        let clipboard_before = if selected == Some('_') && clipboard_yank {
+            SystemClipboard::new().get_string()
+        } else {
+            None
+        };
 
         let yank_data =
             if let lapce_core::cursor::CursorMode::Visual { .. } = &cursor.mode {
@@ -437,17 +510,23 @@ fn run_edit_command(&self, cmd: &EditCommand) -> CommandExecuted {
                 None
             };
 
-        let deltas =
-            batch(|| doc.do_edit(&mut cursor, cmd, modal, &mut register, smart_tab));
-
-        if !deltas.is_empty() {
-            if let Some(data) = yank_data {
-                register.add_delete(data);
+        let deltas = self.with_register(&mut cursor, |cursor, register| {
+            let deltas =
+                batch(|| doc.do_edit(cursor, cmd, modal, register, smart_tab));
+            if !deltas.is_empty() {
+                if let Some(data) = yank_data {
+                    register.add_delete(data);
+                }
             }
-        }
+            deltas
+        });
 
         self.editor.cursor.set(cursor);
-        self.editor.register.set(register);
+        self.record_edit_change(cmd, mode_before, visual_before, !deltas.is_empty());
+
+        if clipboard_yank {
+            self.clipboard_yanked(cmd, selected, clipboard_before);
+        }
 
         if show_completion(cmd, &doc_before_edit, &deltas) {
             self.update_completion(false);
@@ -469,12 +548,639 @@ fn run_edit_command(&self, cmd: &EditCommand) -> CommandExecuted {
         self.apply_deltas(&deltas);
         if let EditCommand::NormalMode = cmd {
             self.snippet.set(None);
+            self.text_object.set(None);
+            self.surround.set(None);
             self.quit_on_screen_find();
         }
 
         CommandExecuted::Yes
     }
 
+    /// Run `f` with the shared unnamed register, routing whatever it yanks,
+    /// deletes or pastes through the register selected with `"`.
+    fn with_register<T>(
+        &self,
+        cursor: &mut Cursor,
+        f: impl FnOnce(&mut Cursor, &mut Register) -> T,
+    ) -> T {
+        let registers = self.common.registers;
+        let selected = registers.with_untracked(|r| r.selected);
+        let rev = self.doc().rev();
+        let mut register = self.common.register.get_untracked();
+        let original = RegisterContent::from(&register.unnamed);
+        if let Some(content) =
+            selected.and_then(|c| registers.with_untracked(|r| r.get(c)))
+        {
+            register.unnamed = content.into();
+        }
+        let before = RegisterContent::from(&register.unnamed);
+
+        let result = f(cursor, &mut register);
+
+        let after = RegisterContent::from(&register.unnamed);
+        let changed = after != before;
+        if !changed || selected == Some('_') {
+            register.unnamed = original.into();
+        } else if self.doc().rev() == rev {
+            registers.update(|r| r.yanked(selected, after));
+        } else {
+            registers.update(|r| r.deleted(selected, after));
+        }
+        // Keep the register around while an operator waits for its motion
+        if selected.is_some() && (changed || cursor.motion_mode.is_none()) {
+            registers.update(|r| r.selected = None);
+        }
+        self.common.register.set(register);
+
+        result
+    }
+
+    /// Record what a non-modal copy or cut put on the system clipboard.
+    fn clipboard_yanked(
+        &self,
+        cmd: &EditCommand,
+        selected: Option<char>,
+        clipboard_before: Option<String>,
+    ) {
+        let mut clipboard = SystemClipboard::new();
+        if selected == Some('_') {
+            if let Some(before) = clipboard_before {
+                clipboard.put_string(before);
+            }
+            return;
+        }
+        let Some(content) = clipboard.get_string() else {
+            return;
+        };
+        let content = RegisterContent::from_clipboard(content);
+        self.common.registers.update(|r| {
+            if *cmd == EditCommand::ClipboardCut {
+                r.deleted(selected, content.clone());
+                r.push_history(content);
+            } else {
+                r.yanked(selected, content);
+            }
+        });
+    }
+
+    /// Paste `content` at every cursor, as if it had been yanked.
+    pub fn paste_register_content(&self, content: RegisterContent) {
+        if content.content.is_empty() {
+            return;
+        }
+        let doc = self.doc();
+        let is_local = doc.content.with_untracked(|content| content.is_local());
+        let modal = self.editor.es.with_untracked(|s| s.modal()) && !is_local;
+        if modal {
+            let original = self
+                .common
+                .register
+                .try_update(|r| std::mem::replace(&mut r.unnamed, content.into()))
+                .unwrap();
+            self.run_edit_command(&EditCommand::Paste);
+            self.common.register.update(|r| r.unnamed = original);
+        } else {
+            let mut cursor = self.cursor().get_untracked();
+            let deltas = doc.do_insert(
+                &mut cursor,
+                &content.content,
+                &self.common.config.get_untracked(),
+            );
+            self.cursor().set(cursor);
+            self.apply_deltas(&deltas);
+        }
+    }
+
+    /// The content of the lines `start` to `end`, without their line endings.
+    pub fn lines(&self, start: usize, end: usize) -> Vec<String> {
+        self.doc().buffer.with_untracked(|buffer| {
+            (start..=end.min(buffer.last_line()))
+                .map(|line| {
+                    let content = buffer.line_content(line);
+                    content.trim_end_matches(['\n', '\r']).to_string()
+                })
+                .collect()
+        })
+    }
+
+    /// Replace the lines `start` to `end` with `lines` in a single edit, and
+    /// move the cursor to the first of them. No lines deletes the range.
+    pub fn replace_lines(&self, start: usize, end: usize, lines: &[String]) {
+        let doc = self.doc();
+        let line_ending = doc.line_ending().get_chars();
+        let (start_offset, end_offset, text) = doc.buffer.with_untracked(|buffer| {
+            let last_line = buffer.last_line();
+            let mut text = lines.join(line_ending);
+            if end < last_line {
+                if !lines.is_empty() {
+                    text.push_str(line_ending);
+                }
+                let end_offset = buffer.offset_of_line(end + 1);
+                (buffer.offset_of_line(start), end_offset, text)
+            } else if start > 0 && lines.is_empty() {
+                // There's no line ending after the last line, so the one
+                // before the range goes instead
+                let start_offset = buffer.line_end_offset(start - 1, false);
+                (start_offset, buffer.len(), text)
+            } else {
+                (buffer.offset_of_line(start), buffer.len(), text)
+            }
+        });
+        self.do_edit(
+            &Selection::caret(start_offset),
+            &[(Selection::region(start_offset, end_offset), text.as_str())],
+        );
+
+        let offset = doc.buffer.with_untracked(|buffer| {
+            buffer.first_non_blank_character_on_line(start.min(buffer.last_line()))
+        });
+        self.set_cursor_offset(offset);
+    }
+
+    /// Put a single cursor at the offset, leaving visual mode.
+    fn set_cursor_offset(&self, offset: usize) {
+        self.cursor().update(|cursor| {
+            cursor.mode = if cursor.is_insert() {
+                CursorMode::Insert(Selection::caret(offset))
+            } else {
+                CursorMode::Normal(offset)
+            };
+        });
+    }
+
+    /// Whether changes are being recorded for `.` to repeat.
+    fn is_recording_changes(&self) -> bool {
+        let is_local = self.doc().content.with_untracked(|c| c.is_local());
+        self.editor.es.with_untracked(|s| s.modal())
+            && !is_local
+            && !self.common.repeat.with_untracked(|r| r.replaying)
+    }
+
+    /// Record an edit command for `.`, where `visual_before` is the Visual
+    /// mode selection it was run on, if any.
+    fn record_edit_change(
+        &self,
+        cmd: &EditCommand,
+        mode_before: Mode,
+        visual_before: Option<ChangeStep>,
+        changed: bool,
+    ) {
+        if !self.is_recording_changes() {
+            return;
+        }
+        let mode = self.cursor().with_untracked(|c| c.get_mode());
+        self.common.repeat.update(|repeat| {
+            if mode_before == Mode::Insert {
+                if *cmd != EditCommand::NormalMode {
+                    repeat.push(ChangeStep::Edit(cmd.clone()));
+                }
+                if mode != Mode::Insert {
+                    repeat.commit();
+                }
+                return;
+            }
+
+            repeat.cancel();
+            if !(changed || mode == Mode::Insert)
+                || matches!(cmd, EditCommand::Undo | EditCommand::Redo)
+            {
+                return;
+            }
+            match (mode_before, visual_before) {
+                (Mode::Normal, _) => repeat.start(ChangeStep::Edit(cmd.clone())),
+                (Mode::Visual(_), Some(visual)) => {
+                    repeat.start(visual);
+                    repeat.push(ChangeStep::Edit(cmd.clone()));
+                }
+                _ => return,
+            }
+            // Changes that enter insert mode go on until it's left
+            if mode != Mode::Insert {
+                repeat.commit();
+            }
+        });
+    }
+
+    fn record_operator_change(&self, cmd: &MotionModeCommand, count: Option<usize>) {
+        if !self.is_recording_changes() {
+            return;
+        }
+        let done = self.cursor().with_untracked(|c| c.motion_mode.is_none());
+        self.common.repeat.update(|repeat| {
+            repeat.record_operator(cmd.clone(), count, done);
+        });
+    }
+
+    fn record_movement_change(
+        &self,
+        movement: &lapce_core::movement::Movement,
+        count: Option<usize>,
+    ) {
+        if !self.is_recording_changes()
+            || !self.common.repeat.with_untracked(|r| r.is_recording())
+        {
+            return;
+        }
+        let done = self.cursor().with_untracked(|c| c.motion_mode.is_none());
+        self.common.repeat.update(|repeat| {
+            repeat.record_movement(movement.clone(), count, done);
+        });
+    }
+
+    /// Repeat the last change made in modal mode at the current cursors.
+    fn repeat_last_change(&self, count: Option<usize>) {
+        let (steps, times) = self
+            .common
+            .repeat
+            .with_untracked(|r| r.last_change_with_count(count));
+        if steps.is_empty() {
+            return;
+        }
+
+        self.common.repeat.update(|r| r.replaying = true);
+        for _ in 0..times {
+            for step in steps.iter() {
+                match step {
+                    ChangeStep::Edit(cmd) => {
+                        self.run_edit_command(cmd);
+                    }
+                    ChangeStep::MotionMode(cmd, count) => {
+                        self.run_motion_mode_command(cmd, *count);
+                    }
+                    ChangeStep::Move(movement, count) => {
+                        self.run_move_command(movement, *count, Modifiers::empty());
+                    }
+                    ChangeStep::Insert(text) => self.receive_char(text),
+                    ChangeStep::TextObject {
+                        object,
+                        inside,
+                        change,
+                    } => self.apply_text_object(*object, *inside, *change),
+                    ChangeStep::Visual {
+                        mode,
+                        lines,
+                        columns,
+                    } => {
+                        let text = self.editor.rope_text();
+                        self.cursor().update(|cursor| {
+                            let start = cursor.offset();
+                            let end = visual_end(
+                                &text,
+                                start,
+                                mode.clone(),
+                                *lines,
+                                *columns,
+                            );
+                            cursor.mode = CursorMode::Visual {
+                                start,
+                                end,
+                                mode: mode.clone(),
+                            };
+                        });
+                    }
+                }
+            }
+            if self.cursor().with_untracked(|c| c.get_mode()) == Mode::Insert {
+                self.run_edit_command(&EditCommand::NormalMode);
+            }
+        }
+        self.common.repeat.update(|r| r.replaying = false);
+    }
+
+    /// Run the text object, structural motion and surround commands, which
+    /// the editor core has no notion of.
+    fn run_text_object_command(
+        &self,
+        cmd: &LapceWorkbenchCommand,
+        count: Option<usize>,
+    ) -> CommandExecuted {
+        let (inside, change) = match cmd {
+            LapceWorkbenchCommand::SelectInsideTextObject => (true, false),
+            LapceWorkbenchCommand::SelectAroundTextObject => (false, false),
+            LapceWorkbenchCommand::ChangeInsideTextObject => (true, true),
+            LapceWorkbenchCommand::ChangeAroundTextObject => (false, true),
+            LapceWorkbenchCommand::NextFunction => {
+                return self.move_to_text_object(TextObject::Function, true, count);
+            }
+            LapceWorkbenchCommand::PreviousFunction => {
+                return self.move_to_text_object(TextObject::Function, false, count);
+            }
+            LapceWorkbenchCommand::NextClass => {
+                return self.move_to_text_object(TextObject::Class, true, count);
+            }
+            LapceWorkbenchCommand::PreviousClass => {
+                return self.move_to_text_object(TextObject::Class, false, count);
+            }
+            LapceWorkbenchCommand::Surround
+            | LapceWorkbenchCommand::SurroundAdd
+            | LapceWorkbenchCommand::SurroundChange
+            | LapceWorkbenchCommand::SurroundDelete => {
+                return self.run_surround_command(cmd);
+            }
+            _ => return CommandExecuted::No,
+        };
+
+        if change {
+            self.run_motion_mode_command(
+                &MotionModeCommand::MotionModeDelete,
+                count,
+            );
+        }
+        self.text_object
+            .set(Some(TextObjectPrompt { inside, change }));
+        CommandExecuted::Yes
+    }
+
+    /// Apply the pending operator to the text object around the cursor, or
+    /// select the object if there is no operator.
+    fn apply_text_object(&self, object: TextObject, inside: bool, change: bool) {
+        let doc = self.doc();
+        let mut cursor = self.cursor().get_untracked();
+        let Some((start, end)) =
+            doc.text_object_range(cursor.offset(), object, inside)
+        else {
+            cursor.motion_mode = None;
+            self.cursor().set(cursor);
+            if self.is_recording_changes() {
+                self.common.repeat.update(|repeat| repeat.cancel());
+            }
+            return;
+        };
+
+        if let Some(motion_mode) = cursor.motion_mode.take() {
+            self.with_register(&mut cursor, |cursor, register| {
+                doc.exec_motion_mode(
+                    &self.editor,
+                    cursor,
+                    motion_mode,
+                    start..end,
+                    false,
+                    register,
+                );
+            });
+            if change {
+                cursor.set_insert(Selection::caret(start));
+            }
+        } else if start < end {
+            match cursor.mode {
+                CursorMode::Insert(_) => {
+                    cursor.set_insert(Selection::region(start, end));
+                }
+                CursorMode::Normal(_) | CursorMode::Visual { .. } => {
+                    let mode = match cursor.mode {
+                        CursorMode::Visual { mode, .. } => mode,
+                        _ => VisualMode::Normal,
+                    };
+                    let end = doc
+                        .buffer
+                        .with_untracked(|b| b.prev_grapheme_offset(end, 1, start));
+                    cursor.mode = CursorMode::Visual { start, end, mode };
+                }
+            }
+        }
+        self.cursor().set(cursor);
+
+        if self.is_recording_changes()
+            && self.common.repeat.with_untracked(|r| r.is_pending_motion())
+        {
+            self.common.repeat.update(|repeat| {
+                repeat.record_text_object(object, inside, change);
+            });
+        }
+    }
+
+    fn run_surround_command(&self, cmd: &LapceWorkbenchCommand) -> CommandExecuted {
+        let motion_mode = self.cursor().with_untracked(|c| c.motion_mode.clone());
+        let prompt = match cmd {
+            // `ds` and `ys`, where the operator is already pending
+            LapceWorkbenchCommand::Surround => match motion_mode {
+                Some(MotionMode::Delete { .. }) => SurroundPrompt::Delete,
+                Some(MotionMode::Yank { .. }) => {
+                    SurroundPrompt::Object { inside: None }
+                }
+                _ => return CommandExecuted::No,
+            },
+            LapceWorkbenchCommand::SurroundAdd => SurroundPrompt::Add {
+                ranges: self.surround_selection(),
+                tag: None,
+            },
+            LapceWorkbenchCommand::SurroundChange => SurroundPrompt::Change {
+                target: None,
+                tag: None,
+            },
+            LapceWorkbenchCommand::SurroundDelete => SurroundPrompt::Delete,
+            _ => return CommandExecuted::No,
+        };
+
+        // The operator is used up, and surrounding isn't repeated with `.`
+        self.cursor().update(|cursor| cursor.motion_mode = None);
+        if self.is_recording_changes() {
+            self.common.repeat.update(|repeat| repeat.cancel());
+        }
+        self.surround.set(Some(prompt));
+        CommandExecuted::Yes
+    }
+
+    /// What `surround_add` wraps: the selections, or the word at the cursor
+    /// if nothing is selected.
+    fn surround_selection(&self) -> Vec<(usize, usize)> {
+        let cursor = self.cursor().get_untracked();
+        self.doc()
+            .buffer
+            .with_untracked(|buffer| match &cursor.mode {
+                CursorMode::Visual {
+                    start,
+                    end,
+                    mode: VisualMode::Linewise,
+                } => {
+                    let start_line = buffer.line_of_offset(*start.min(end));
+                    let end_line = buffer.line_of_offset(*start.max(end));
+                    vec![(
+                        buffer.first_non_blank_character_on_line(start_line),
+                        buffer.line_end_offset(end_line, false),
+                    )]
+                }
+                CursorMode::Visual { start, end, .. } => {
+                    let last = *start.max(end);
+                    vec![(
+                        *start.min(end),
+                        buffer.next_grapheme_offset(last, 1, buffer.len()),
+                    )]
+                }
+                CursorMode::Insert(selection) if !selection.is_caret() => selection
+                    .regions()
+                    .iter()
+                    .filter(|region| !region.is_caret())
+                    .map(|region| (region.min(), region.max()))
+                    .collect(),
+                _ => vec![buffer.select_word(cursor.offset())],
+            })
+    }
+
+    fn receive_surround_char(&self, prompt: SurroundPrompt, c: char) {
+        let next = match prompt {
+            SurroundPrompt::Object { inside: None } => match c {
+                'i' => Some(SurroundPrompt::Object { inside: Some(true) }),
+                'a' => Some(SurroundPrompt::Object {
+                    inside: Some(false),
+                }),
+                's' => Some(SurroundPrompt::Add {
+                    ranges: vec![self.surround_line()],
+                    tag: None,
+                }),
+                _ => None,
+            },
+            SurroundPrompt::Object {
+                inside: Some(inside),
+            } => self
+                .surround_object(c, inside)
+                .map(|range| SurroundPrompt::Add {
+                    ranges: vec![range],
+                    tag: None,
+                }),
+            SurroundPrompt::Add { ranges, tag: None } if c == '<' || c == 't' => {
+                Some(SurroundPrompt::Add {
+                    ranges,
+                    tag: Some(String::new()),
+                })
+            }
+            SurroundPrompt::Add {
+                ranges,
+                tag: Some(mut tag),
+            } if c != '>' => {
+                tag.push(c);
+                Some(SurroundPrompt::Add {
+                    ranges,
+                    tag: Some(tag),
+                })
+            }
+            SurroundPrompt::Add { ranges, tag } => {
+                if let Some(surround) =
+                    tag.map(Surround::Tag).or_else(|| Surround::from_char(c))
+                {
+                    self.surround_add(&ranges, &surround);
+                }
+                None
+            }
+            SurroundPrompt::Delete => {
+                self.surround_delete(c);
+                None
+            }
+            SurroundPrompt::Change { target: None, .. } => {
+                Some(SurroundPrompt::Change {
+                    target: Some(c),
+                    tag: None,
+                })
+            }
+            SurroundPrompt::Change { target, tag: None } if c == '<' || c == 't' => {
+                Some(SurroundPrompt::Change {
+                    target,
+                    tag: Some(String::new()),
+                })
+            }
+            SurroundPrompt::Change {
+                target,
+                tag: Some(mut tag),
+            } if c != '>' => {
+                tag.push(c);
+                Some(SurroundPrompt::Change {
+                    target,
+                    tag: Some(tag),
+                })
+            }
+            SurroundPrompt::Change {
+                target: Some(target),
+                tag,
+            } => {
+                if let Some(surround) =
+                    tag.map(Surround::Tag).or_else(|| Surround::from_char(c))
+                {
+                    self.surround_change(target, &surround);
+                }
+                None
+            }
+        };
+        self.surround.set(next);
+    }
+
+    /// The current line, without its indentation.
+    fn surround_line(&self) -> (usize, usize) {
+        let offset = self.cursor().with_untracked(|c| c.offset());
+        self.doc().buffer.with_untracked(|buffer| {
+            let line = buffer.line_of_offset(offset);
+            (
+                buffer.first_non_blank_character_on_line(line),
+                buffer.line_end_offset(line, false),
+            )
+        })
+    }
+
+    /// The range of the word, or of the text object, that `c` names.
+    fn surround_object(&self, c: char, inside: bool) -> Option<(usize, usize)> {
+        let doc = self.doc();
+        let offset = self.cursor().with_untracked(|c| c.offset());
+        if c == 'w' || c == 'W' {
+            return Some(doc.buffer.with_untracked(|b| b.select_word(offset)));
+        }
+        doc.text_object_range(offset, TextObject::from_char(c)?, inside)
+    }
+
+    fn surround_add(&self, ranges: &[(usize, usize)], surround: &Surround) {
+        let Some(&(start, _)) = ranges.first() else {
+            return;
+        };
+        let edits = ranges
+            .iter()
+            .flat_map(|(start, end)| surround.add_edits(*start, *end))
+            .collect::<Vec<_>>();
+        self.apply_surround_edits(start, &edits);
+    }
+
+    fn surround_change(&self, target: char, surround: &Surround) {
+        let offset = self.cursor().with_untracked(|c| c.offset());
+        if let Some(ranges) = self.doc().find_surround(offset, target) {
+            self.apply_surround_edits(ranges[0].0, &surround.change_edits(ranges));
+        }
+    }
+
+    fn surround_delete(&self, target: char) {
+        let offset = self.cursor().with_untracked(|c| c.offset());
+        if let Some(ranges) = self.doc().find_surround(offset, target) {
+            self.apply_surround_edits(ranges[0].0, &delete_surround_edits(ranges));
+        }
+    }
+
+    fn apply_surround_edits(&self, offset: usize, edits: &[(Selection, String)]) {
+        let edits = edits
+            .iter()
+            .map(|(selection, text)| (selection, text.as_str()))
+            .collect::<Vec<_>>();
+        self.do_edit(&Selection::caret(offset), &edits);
+        self.set_cursor_offset(offset);
+    }
+
+    /// Move to the start of the next, or previous, function or class.
+    fn move_to_text_object(
+        &self,
+        object: TextObject,
+        forward: bool,
+        count: Option<usize>,
+    ) -> CommandExecuted {
+        let doc = self.doc();
+        let mut offset = self.cursor().with_untracked(|c| c.offset());
+        for _ in 0..count.unwrap_or(1) {
+            match doc.text_object_offset(offset, object, forward) {
+                Some(next) => offset = next,
+                None => break,
+            }
+        }
+        self.run_move_command(
+            &lapce_core::movement::Movement::Offset(offset),
+            None,
+            Modifiers::empty(),
+        )
+    }
+
     fn run_motion_mode_command(
         &self,
         cmd: &MotionModeCommand,
@@ -488,18 +1194,19 @@ fn run_motion_mode_command(
             MotionModeCommand::MotionModeYank => MotionMode::Yank { count },
         };
         let mut cursor = self.editor.cursor.get_untracked();
-        let mut register = self.common.register.get_untracked();
 
-        movement::do_motion_mode(
-            &self.editor,
-            &*self.doc(),
-            &mut cursor,
-            motion_mode,
-            &mut register,
-        );
+        self.with_register(&mut cursor, |cursor, register| {
+            movement::do_motion_mode(
+                &self.editor,
+                &*self.doc(),
+                cursor,
+                motion_mode,
+                register,
+            );
+        });
 
         self.editor.cursor.set(cursor);
-        self.common.register.set(register);
+        self.record_operator_change(cmd, Some(count));
 
         CommandExecuted::Yes
     }
@@ -680,11 +1387,11 @@ fn run_move_command(
         self.editor.last_movement.set(movement.clone());
 
         let mut cursor = self.cursor().get_untracked();
-        self.common.register.update(|register| {
+        self.with_register(&mut cursor, |cursor, register| {
             movement::move_cursor(
                 &self.editor,
                 &*self.doc(),
-                &mut cursor,
+                cursor,
                 movement,
                 count.unwrap_or(1),
                 mods.shift(),
@@ -693,6 +1400,7 @@ fn run_move_command(
         });
 
         self.editor.cursor.set(cursor);
+        self.record_movement_change(movement, count);
 
         if self.snippet.with_untracked(|s| s.is_some()) {
             self.snippet.update(|snippet| {
@@ -1541,6 +2249,173 @@ pub fn go_to_implementation(&self, window_tab_data: WindowTabData) {
         );
     }
 
+    /// Find the ranges that should be edited together with the one under the cursor, such
+    /// as the name of the matching tag, falling back to the syntax tree when no language
+    /// server provides them.
+    pub fn update_linked_editing_ranges(&self) {
+        let doc = self.doc();
+        if !doc.loaded() {
+            return;
+        }
+
+        let offset = self.cursor().with_untracked(|c| c.offset());
+        let is_inside = doc.linked_editing_ranges.with_untracked(|ranges| {
+            ranges.as_ref().is_some_and(|ranges| {
+                ranges
+                    .iter()
+                    .any(|(start, end)| *start <= offset && offset <= *end)
+            })
+        });
+        if is_inside {
+            return;
+        }
+        doc.linked_editing_ranges.set(None);
+
+        let Some(path) = doc.content.with_untracked(|c| c.path().cloned()) else {
+            let ranges = doc.syntax_linked_editing_ranges(offset);
+            doc.linked_editing_ranges.set(ranges);
+            return;
+        };
+
+        let rev = doc.rev();
+        let position = doc
+            .buffer
+            .with_untracked(|buffer| buffer.offset_to_position(offset));
+        let cursor = self.cursor().read_only();
+        let send = create_ext_action(self.scope, move |result| {
+            if doc.rev() != rev || cursor.with_untracked(|c| c.offset()) != offset {
+                return;
+            }
+            let ranges = match result {
+                Ok(ProxyResponse::GetLinkedEditingRanges { ranges }) => {
+                    ranges.map(|ranges| {
+                        doc.buffer.with_untracked(|buffer| {
+                            ranges
+                                .ranges
+                                .iter()
+                                .map(|range| {
+                                    (
+                                        buffer.offset_of_position(&range.start),
+                                        buffer.offset_of_position(&range.end),
+                                    )
+                                })
+                                .collect()
+                        })
+                    })
+                }
+                _ => doc.syntax_linked_editing_ranges(offset),
+            };
+            doc.linked_editing_ranges.set(ranges);
+        });
+        self.common
+            .proxy
+            .get_linked_editing_ranges(path, position, move |result| {
+                send(result);
+            });
+    }
+
+    /// Open the target of the document link at the offset, if there is one.
+    /// Returns whether a link was opened.
+    fn open_document_link(&self, offset: usize) -> bool {
+        let Some(target) = self
+            .doc()
+            .document_link_at(offset)
+            .and_then(|link| link.target)
+        else {
+            return false;
+        };
+
+        if target.scheme() == "file" {
+            let position = target
+                .fragment()
+                .and_then(line_of_link_fragment)
+                .map(EditorPosition::Line);
+            self.common
+                .internal_command
+                .send(InternalCommand::JumpToLocation {
+                    location: EditorLocation {
+                        path: path_from_url(&target),
+                        position,
+                        scroll_offset: None,
+                        ignore_unconfirmed: true,
+                        same_editor_tab: false,
+                    },
+                });
+        } else {
+            self.common
+                .internal_command
+                .send(InternalCommand::OpenWebUri {
+                    uri: target.to_string(),
+                });
+        }
+        true
+    }
+
+    /// Show the alternative representations of the color literal under the cursor,
+    /// replacing the literal with the one that is picked.
+    pub fn show_color_presentations(&self) {
+        let doc = self.doc();
+        let Some(path) = doc.content.with_untracked(|c| c.path().cloned()) else {
+            return;
+        };
+        let offset = self.cursor().with_untracked(|c| c.offset());
+        let Some(info) = doc.document_color_at(offset) else {
+            return;
+        };
+
+        let range = info.range;
+        let rev = doc.rev();
+        let send = create_ext_action(
+            self.scope,
+            move |presentations: Vec<ColorPresentation>| {
+                if doc.rev() != rev || presentations.is_empty() {
+                    return;
+                }
+
+                let mut menu = Menu::new("");
+                for presentation in presentations {
+                    let doc = doc.clone();
+                    menu = menu.entry(
+                        MenuItem::new(presentation.label.clone()).action(
+                            move || {
+                                if doc.rev() != rev {
+                                    return;
+                                }
+                                let mut edits = vec![presentation
+                                    .text_edit
+                                    .clone()
+                                    .unwrap_or_else(|| TextEdit {
+                                        range,
+                                        new_text: presentation.label.clone(),
+                                    })];
+                                edits.extend(
+                                    presentation
+                                        .additional_text_edits
+                                        .clone()
+                                        .unwrap_or_default(),
+                                );
+                                doc.do_text_edit(&edits);
+                            },
+                        ),
+                    );
+                }
+                show_context_menu(menu, None);
+            },
+        );
+        self.common.proxy.get_color_presentations(
+            path,
+            info.color,
+            range,
+            move |result| {
+                if let Ok(ProxyResponse::GetColorPresentations { presentations }) =
+                    result
+                {
+                    send(presentations);
+                }
+            },
+        );
+    }
+
     fn scroll(&self, down: bool, count: usize, mods: Modifiers) {
         self.editor.scroll(
             self.sticky_header_height.get_untracked(),
@@ -2239,7 +3114,7 @@ pub fn get_code_actions(&self) {
 
         // insert some empty data, so that we won't make the request again
         doc.code_actions().update(|c| {
-            c.insert(offset, (PluginId(0), im::Vector::new()));
+            c.insert(offset, im::Vector::new());
         });
 
         let (position, rev, diagnostics) = doc.buffer.with_untracked(|buffer| {
@@ -2263,10 +3138,16 @@ pub fn get_code_actions(&self) {
 
         let send = create_ext_action(
             self.scope,
-            move |resp: (PluginId, CodeActionResponse)| {
+            move |actions: Vec<(PluginId, CodeActionResponse)>| {
                 if doc.rev() == rev {
+                    let actions = actions
+                        .into_iter()
+                        .flat_map(|(plugin_id, resp)| {
+                            resp.into_iter().map(move |action| (plugin_id, action))
+                        })
+                        .collect();
                     doc.code_actions().update(|c| {
-                        c.insert(offset, (resp.0, resp.1.into()));
+                        c.insert(offset, actions);
                     });
                 }
             },
@@ -2277,12 +3158,9 @@ pub fn get_code_actions(&self) {
             position,
             diagnostics,
             move |result| {
-                if let Ok(ProxyResponse::GetCodeActionsResponse {
-                    plugin_id,
-                    resp,
-                }) = result
+                if let Ok(ProxyResponse::GetCodeActionsResponse { actions }) = result
                 {
-                    send((plugin_id, resp))
+                    send(actions)
                 }
             },
         );
@@ -2294,13 +3172,12 @@ pub fn show_code_actions(&self, mouse_click: bool) {
         let code_actions = doc
             .code_actions()
             .with_untracked(|c| c.get(&offset).cloned());
-        if let Some((plugin_id, code_actions)) = code_actions {
+        if let Some(code_actions) = code_actions {
             if !code_actions.is_empty() {
                 self.common.internal_command.send(
                     InternalCommand::ShowCodeActions {
                         offset,
                         mouse_click,
-                        plugin_id,
                         code_actions,
                     },
                 );
@@ -2332,6 +3209,11 @@ pub fn save(
             return;
         }
 
+        if content.is_hex() {
+            self.do_save(after_action);
+            return;
+        }
+
         let config = self.common.config.get_untracked();
         let DocContent::File { path, .. } = content else {
             return;
@@ -2707,6 +3589,13 @@ pub fn pointer_down(&self, pointer_event: &PointerInputEvent) {
                     || (cfg!(not(target_os = "macos"))
                         && pointer_event.modifiers.control())
                 {
+                    let mode = self.cursor().with_untracked(|c| c.get_mode());
+                    let (offset, is_inside) =
+                        self.editor.offset_of_point(mode, pointer_event.pos);
+                    if is_inside && self.open_document_link(offset) {
+                        return;
+                    }
+
                     let rs = self.find_hint(pointer_event.pos);
                     match rs {
                         FindHintRs::NoMatchBreak
@@ -2889,7 +3778,7 @@ fn right_click(&self, pointer_event: &PointerInputEvent) {
         }
 
         let (path, is_file) = doc.content.with_untracked(|content| match content {
-            DocContent::File { path, .. } => {
+            DocContent::File { path, .. } | DocContent::Hex { path, .. } => {
                 (Some(path.to_path_buf()), path.is_file())
             }
             DocContent::Local
@@ -2979,6 +3868,16 @@ fn right_click(&self, pointer_event: &PointerInputEvent) {
                 LapceWorkbenchCommand::GoToLocation,
             )));
         }
+        let cursor_offset = self.cursor().with_untracked(|c| c.offset());
+        if is_file && doc.document_color_at(cursor_offset).is_some() {
+            cmds.insert(0, None);
+            cmds.insert(
+                0,
+                Some(CommandKind::Workbench(
+                    LapceWorkbenchCommand::ShowColorPresentations,
+                )),
+            );
+        }
         let lapce_command = self.common.lapce_command;
         for cmd in cmds {
             if let Some(cmd) = cmd {
@@ -3257,6 +4156,9 @@ fn check_condition(&self, condition: Condition) -> bool {
                 self.on_screen_find.with_untracked(|f| f.active)
             }
             Condition::InSnippet => self.snippet.with_untracked(|s| s.is_some()),
+            Condition::OperatorPending => {
+                self.cursor().with_untracked(|c| c.motion_mode.is_some())
+            }
             Condition::EditorFocus => self
                 .doc()
                 .content
@@ -3321,7 +4223,15 @@ fn run_command(
         }
 
         match &command.kind {
-            crate::command::CommandKind::Workbench(_) => CommandExecuted::No,
+            crate::command::CommandKind::Workbench(
+                LapceWorkbenchCommand::RepeatLastChange,
+            ) => {
+                self.repeat_last_change(count);
+                CommandExecuted::Yes
+            }
+            crate::command::CommandKind::Workbench(cmd) => {
+                self.run_text_object_command(cmd, count)
+            }
             crate::command::CommandKind::Edit(cmd) => self.run_edit_command(cmd),
             crate::command::CommandKind::Move(cmd) => {
                 let movement = cmd.to_movement(count);
@@ -3357,7 +4267,13 @@ fn run_command(
     }
 
     fn expect_char(&self) -> bool {
-        if self.common.find.visual.get_untracked() && self.find_focus.get_untracked()
+        if self.common.registers.with_untracked(|r| r.selecting)
+            || self.text_object.with_untracked(|t| t.is_some())
+            || self.surround.with_untracked(|s| s.is_some())
+        {
+            true
+        } else if self.common.find.visual.get_untracked()
+            && self.find_focus.get_untracked()
         {
             false
         } else {
@@ -3367,7 +4283,28 @@ fn expect_char(&self) -> bool {
     }
 
     fn receive_char(&self, c: &str) {
-        if self.common.find.visual.get_untracked() && self.find_focus.get_untracked()
+        if self.common.registers.with_untracked(|r| r.selecting) {
+            self.common.registers.update(|r| r.select(c));
+        } else if let Some(prompt) = self.text_object.get_untracked() {
+            self.text_object.set(None);
+            let mut chars = c.chars();
+            match (chars.next().and_then(TextObject::from_char), chars.next()) {
+                (Some(object), None) => {
+                    self.apply_text_object(object, prompt.inside, prompt.change);
+                }
+                _ => {
+                    self.cursor().update(|cursor| cursor.motion_mode = None);
+                    self.common.repeat.update(|repeat| repeat.cancel());
+                }
+            }
+        } else if let Some(prompt) = self.surround.get_untracked() {
+            let mut chars = c.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => self.receive_surround_char(prompt, c),
+                _ => self.surround.set(None),
+            }
+        } else if self.common.find.visual.get_untracked()
+            && self.find_focus.get_untracked()
         {
             // find/relace editor receive char
             if self.common.find.replace_focus.get_untracked() {
@@ -3404,6 +4341,11 @@ fn receive_char(&self, c: &str) {
                 );
 
                 self.apply_deltas(&deltas);
+                if self.is_recording_changes() {
+                    self.common.repeat.update(|repeat| {
+                        repeat.push(ChangeStep::Insert(c.to_string()));
+                    });
+                }
             } else if let Some(direction) = self.inline_find.get_untracked() {
                 self.inline_find(direction.clone(), c);
                 self.last_inline_find.set(Some((direction, c.to_string())));
@@ -3896,3 +4838,14 @@ fn find_hint(mut pre_hint_len: u32, index: u32, hint: &InlayHint) -> FindHintRs
         }
     }
 }
+
+/// Get the (zero-based) line from the fragment of a file link,
+/// such as `L12`, `L12,3` or `12`.
+fn line_of_link_fragment(fragment: &str) -> Option<usize> {
+    let fragment = fragment.strip_prefix('L').unwrap_or(fragment);
+    let end = fragment
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(fragment.len());
+    let line: usize = fragment[..end].parse().ok()?;
+    Some(line.saturating_sub(1))
+}
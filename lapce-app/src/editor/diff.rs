@@ -14,8 +14,7 @@
     diff::{expand_diff_lines, rope_diff, DiffExpand, DiffLines},
     rope_text::RopeText,
 };
-use lapce_rpc::{buffer::BufferId, proxy::ProxyResponse};
-use lapce_xi_rope::Rope;
+use lapce_rpc::buffer::BufferId;
 use serde::{Deserialize, Serialize};
 
 use super::{EditorData, EditorViewKind};
@@ -54,7 +53,7 @@ pub fn to_data(
             let data = data.clone();
             let common = data.common.clone();
             move |content: &DocContent| match content {
-                DocContent::File { path, .. } => {
+                DocContent::File { path, .. } | DocContent::Hex { path, .. } => {
                     let (doc, _) = data.get_doc(path.clone(), None);
                     doc
                 }
@@ -68,28 +67,8 @@ pub fn to_data(
                         data.editors,
                         common.clone(),
                     );
-                    let doc = Rc::new(doc);
-
-                    {
-                        let doc = doc.clone();
-                        let send = create_ext_action(cx, move |result| {
-                            if let Ok(ProxyResponse::BufferHeadResponse {
-                                content,
-                                ..
-                            }) = result
-                            {
-                                doc.init_content(Rope::from(content));
-                            }
-                        });
-                        common.proxy.get_buffer_head(
-                            history.path.clone(),
-                            move |result| {
-                                send(result);
-                            },
-                        );
-                    }
-
-                    doc
+                    doc.load_history(history);
+                    Rc::new(doc)
                 }
                 DocContent::Scratch { name, .. } => {
                     let doc_content = DocContent::Scratch {
@@ -5,8 +5,10 @@
     text::{Attrs, AttrsList, FamilyOwned, TextLayout},
     Renderer, View, ViewId,
 };
+use std::collections::HashSet;
+
 use im::HashMap;
-use lapce_core::{buffer::rope_text::RopeText, mode::Mode};
+use lapce_core::{buffer::rope_text::RopeText, mode::Mode, syntax::fold::Fold};
 use serde::{Deserialize, Serialize};
 
 use super::{view::changes_colors_screen, EditorData};
@@ -211,6 +213,83 @@ fn debug_name(&self) -> std::borrow::Cow<'static, str> {
 pub struct FoldedRanges(pub Vec<FoldedRange>);
 
 impl FoldingRanges {
+    /// Replace the ranges with newly found ones, keeping folded the ones that
+    /// start on the same line as a folded one did.
+    pub fn update_ranges(&mut self, mut ranges: Vec<FoldingRange>) {
+        let folded: HashSet<u32> = self
+            .0
+            .iter()
+            .filter(|range| range.status.is_folded())
+            .map(|range| range.start.line)
+            .collect();
+        for range in ranges.iter_mut() {
+            if folded.contains(&range.start.line) {
+                range.status = FoldingRangeStatus::Fold;
+            }
+        }
+        self.0 = ranges;
+    }
+
+    pub fn fold_all(&mut self) {
+        self.set_status(FoldingRangeStatus::Fold, |_, _| true);
+    }
+
+    pub fn unfold_all(&mut self) {
+        self.set_status(FoldingRangeStatus::Unfold, |_, _| true);
+    }
+
+    /// Fold the ranges nested `level` deep or deeper, where the outermost
+    /// ones are at level 1, leaving the others as they are.
+    pub fn fold_level(&mut self, level: usize) {
+        self.set_status(FoldingRangeStatus::Fold, |_, l| l >= level);
+    }
+
+    /// Whether the line is hidden in a folded range.
+    pub fn hides_line(&self, line: u32) -> bool {
+        self.0.iter().any(|range| {
+            range.status.is_folded()
+                && range.start.line < line
+                && line < range.end.line
+        })
+    }
+
+    /// Unfold the ranges that hide the line, so that the cursor is never on a
+    /// line that can't be seen.
+    pub fn unfold_line(&mut self, line: u32) {
+        for range in self.0.iter_mut() {
+            if range.start.line < line && line < range.end.line {
+                range.status = FoldingRangeStatus::Unfold;
+            }
+        }
+    }
+
+    pub fn fold_comments(&mut self) {
+        self.set_status(FoldingRangeStatus::Fold, |range, _| {
+            range.kind == Some(FoldingRangeKind::Comment)
+        });
+    }
+
+    /// Set the status of the ranges `f` picks, given each range and how deep
+    /// it is nested.
+    fn set_status(
+        &mut self,
+        status: FoldingRangeStatus,
+        f: impl Fn(&FoldingRange, usize) -> bool,
+    ) {
+        // The ends of the ranges that contain the current one, which come
+        // before it as the ranges are sorted by their start
+        let mut parents: Vec<u32> = Vec::new();
+        for range in self.0.iter_mut() {
+            while parents.last().is_some_and(|end| *end < range.end.line) {
+                parents.pop();
+            }
+            if f(range, parents.len() + 1) {
+                range.status = status.clone();
+            }
+            parents.push(range.end.line);
+        }
+    }
+
     pub fn get_folded_range(&self) -> FoldedRanges {
         let mut range = Vec::new();
         let mut limit_line = 0;
@@ -302,6 +381,7 @@ pub struct FoldingRange {
     pub end: FoldingPosition,
     pub status: FoldingRangeStatus,
     pub collapsed_text: Option<String>,
+    pub kind: Option<FoldingRangeKind>,
 }
 
 impl FoldingRange {
@@ -312,7 +392,7 @@ pub fn from_lsp(value: lsp_types::FoldingRange) -> Self {
             end_line,
             end_character,
             collapsed_text,
-            ..
+            kind,
         } = value;
         let status = FoldingRangeStatus::Unfold;
         Self {
@@ -328,6 +408,23 @@ pub fn from_lsp(value: lsp_types::FoldingRange) -> Self {
             },
             status,
             collapsed_text,
+            kind: kind.map(FoldingRangeKind::from),
+        }
+    }
+
+    pub fn from_fold(fold: Fold) -> Self {
+        Self {
+            start: FoldingPosition {
+                line: fold.start_line as u32,
+                character: None,
+            },
+            end: FoldingPosition {
+                line: fold.end_line as u32,
+                character: None,
+            },
+            status: FoldingRangeStatus::Unfold,
+            collapsed_text: None,
+            kind: fold.comment.then_some(FoldingRangeKind::Comment),
         }
     }
 }
@@ -348,14 +445,14 @@ pub enum FoldingRangeStatus {
 
 impl FoldingRangeStatus {
     pub fn click(&mut self) {
-        // match self {
-        //     FoldingRangeStatus::Fold => {
-        //         *self = FoldingRangeStatus::Unfold;
-        //     }
-        //     FoldingRangeStatus::Unfold => {
-        //         *self = FoldingRangeStatus::Fold;
-        //     }
-        // }
+        match self {
+            FoldingRangeStatus::Fold => {
+                *self = FoldingRangeStatus::Unfold;
+            }
+            FoldingRangeStatus::Unfold => {
+                *self = FoldingRangeStatus::Fold;
+            }
+        }
     }
     pub fn is_folded(&self) -> bool {
         *self == Self::Fold
@@ -394,3 +491,54 @@ fn from(value: lsp_types::FoldingRangeKind) -> Self {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranges(lines: &[(usize, usize)]) -> FoldingRanges {
+        FoldingRanges(
+            lines
+                .iter()
+                .map(|&(start_line, end_line)| {
+                    FoldingRange::from_fold(Fold {
+                        start_line,
+                        end_line,
+                        comment: false,
+                    })
+                })
+                .collect(),
+        )
+    }
+
+    fn folded(ranges: &FoldingRanges) -> Vec<u32> {
+        ranges
+            .0
+            .iter()
+            .filter(|range| range.status.is_folded())
+            .map(|range| range.start.line)
+            .collect()
+    }
+
+    #[test]
+    fn test_fold_level() {
+        let mut folds = ranges(&[(0, 10), (1, 4), (2, 3), (5, 8), (12, 14)]);
+        folds.fold_level(2);
+        assert_eq!(folded(&folds), vec![1, 2, 5]);
+        folds.fold_level(1);
+        assert_eq!(folded(&folds), vec![0, 1, 2, 5, 12]);
+    }
+
+    #[test]
+    fn test_unfold_line() {
+        let mut folds = ranges(&[(0, 10), (1, 4), (5, 8)]);
+        folds.fold_all();
+        // The last line of a range is still shown
+        assert!(!folds.hides_line(10));
+        assert!(folds.hides_line(6));
+        folds.unfold_line(6);
+        assert!(!folds.hides_line(6));
+        assert_eq!(folded(&folds), vec![1]);
+        assert!(folds.hides_line(2));
+    }
+}
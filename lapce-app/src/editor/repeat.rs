@@ -0,0 +1,299 @@
+use lapce_core::{
+    buffer::rope_text::RopeText,
+    command::{EditCommand, MotionModeCommand},
+    mode::VisualMode,
+    movement::Movement,
+    syntax::textobject::TextObject,
+};
+
+/// One step of a modal change, replayed in order by `.`
+#[derive(Clone, Debug)]
+pub enum ChangeStep {
+    Edit(EditCommand),
+    MotionMode(MotionModeCommand, Option<usize>),
+    Move(Movement, Option<usize>),
+    Insert(String),
+    TextObject {
+        object: TextObject,
+        inside: bool,
+        change: bool,
+    },
+    /// A Visual mode selection of as much text as the change was made to,
+    /// made again from the cursor. A selection within a line, or a block,
+    /// is `columns` wide, and one over several lines ends at column
+    /// `columns` of the `lines`th line down.
+    Visual {
+        mode: VisualMode,
+        lines: usize,
+        columns: usize,
+    },
+}
+
+impl ChangeStep {
+    /// The step that selects as much text as the Visual mode selection from
+    /// `start` to `end`.
+    pub fn visual(
+        text: &impl RopeText,
+        start: usize,
+        end: usize,
+        mode: VisualMode,
+    ) -> Self {
+        let (start_line, start_col) = text.offset_to_line_col(start.min(end));
+        let (end_line, end_col) = text.offset_to_line_col(start.max(end));
+        let lines = end_line - start_line;
+        let columns = if lines == 0 || mode == VisualMode::Blockwise {
+            end_col.saturating_sub(start_col)
+        } else {
+            end_col
+        };
+        ChangeStep::Visual {
+            mode,
+            lines,
+            columns,
+        }
+    }
+}
+
+/// Where the selection of a [`ChangeStep::Visual`] made from `offset` ends.
+pub fn visual_end(
+    text: &impl RopeText,
+    offset: usize,
+    mode: VisualMode,
+    lines: usize,
+    columns: usize,
+) -> usize {
+    let (line, col) = text.offset_to_line_col(offset);
+    let end_line = (line + lines).min(text.last_line());
+    let end_col = if lines == 0 || mode == VisualMode::Blockwise {
+        col + columns
+    } else {
+        columns
+    };
+    let line_end = text.line_end_offset(end_line, false);
+    text.offset_of_line_col(end_line, end_col).min(line_end)
+}
+
+/// The last change made in modal mode and the one currently being made.
+#[derive(Clone, Debug, Default)]
+pub struct RepeatData {
+    recording: Option<Vec<ChangeStep>>,
+    last_change: Vec<ChangeStep>,
+    pub replaying: bool,
+}
+
+impl RepeatData {
+    /// The steps of the last change that `.` replays with `count`, and how
+    /// many times. Like in Vim, the count replaces the one the change was
+    /// made with, so `3.` after `2dd` deletes three lines, while a change
+    /// made without an operator is made `count` times.
+    pub fn last_change_with_count(
+        &self,
+        count: Option<usize>,
+    ) -> (Vec<ChangeStep>, usize) {
+        let mut steps = self.last_change.clone();
+        let Some(count) = count else {
+            return (steps, 1);
+        };
+        let mut counted = false;
+        for step in steps.iter_mut() {
+            if let ChangeStep::MotionMode(_, step_count)
+            | ChangeStep::Move(_, step_count) = step
+            {
+                *step_count = (!counted).then_some(count);
+                counted = true;
+            }
+        }
+        if counted {
+            (steps, 1)
+        } else {
+            (steps, count)
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Whether an operator is waiting for the motion it applies to.
+    pub fn is_pending_motion(&self) -> bool {
+        matches!(
+            self.recording.as_deref(),
+            Some([.., ChangeStep::MotionMode(..)])
+        )
+    }
+
+    /// Start recording a change with its first step.
+    pub fn start(&mut self, step: ChangeStep) {
+        self.recording = Some(vec![step]);
+    }
+
+    pub fn push(&mut self, step: ChangeStep) {
+        let Some(recording) = self.recording.as_mut() else {
+            return;
+        };
+        match (recording.last_mut(), step) {
+            (Some(ChangeStep::Insert(text)), ChangeStep::Insert(s)) => {
+                text.push_str(&s);
+            }
+            (_, step) => recording.push(step),
+        }
+    }
+
+    /// Record an operator, or a repeated one like the second `d` of `dd`.
+    /// `done` is whether the operator has been applied.
+    pub fn record_operator(
+        &mut self,
+        cmd: MotionModeCommand,
+        count: Option<usize>,
+        done: bool,
+    ) {
+        let step = ChangeStep::MotionMode(cmd, count);
+        if self.is_pending_motion() {
+            self.push(step);
+        } else {
+            self.start(step);
+        }
+        if done {
+            self.finish_operator();
+        }
+    }
+
+    /// Record a movement, which either completes a pending operator or ends
+    /// the insert session being recorded.
+    pub fn record_movement(
+        &mut self,
+        movement: Movement,
+        count: Option<usize>,
+        done: bool,
+    ) {
+        if self.is_pending_motion() {
+            self.push(ChangeStep::Move(movement, count));
+            if done {
+                self.finish_operator();
+            }
+        } else {
+            self.commit();
+        }
+    }
+
+    /// Record the text object a pending operator applied to. A change goes
+    /// on until insert mode is left.
+    pub fn record_text_object(
+        &mut self,
+        object: TextObject,
+        inside: bool,
+        change: bool,
+    ) {
+        self.push(ChangeStep::TextObject {
+            object,
+            inside,
+            change,
+        });
+        if !change {
+            self.finish_operator();
+        }
+    }
+
+    fn finish_operator(&mut self) {
+        // Yanking doesn't change anything, so there's nothing to repeat
+        if matches!(
+            self.recording.as_deref(),
+            Some([
+                ChangeStep::MotionMode(MotionModeCommand::MotionModeYank, _),
+                ..
+            ])
+        ) {
+            self.cancel();
+        } else {
+            self.commit();
+        }
+    }
+
+    pub fn cancel(&mut self) {
+        self.recording = None;
+    }
+
+    /// Finish the change being recorded, making it the one `.` repeats.
+    pub fn commit(&mut self) {
+        if let Some(recording) = self.recording.take() {
+            self.last_change = recording;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lapce_core::buffer::rope_text::RopeTextRef;
+    use lapce_xi_rope::Rope;
+
+    use super::*;
+
+    fn counts(steps: &[ChangeStep]) -> Vec<Option<usize>> {
+        steps
+            .iter()
+            .filter_map(|step| match step {
+                ChangeStep::MotionMode(_, count) | ChangeStep::Move(_, count) => {
+                    Some(*count)
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_repeat_count() {
+        let mut repeat = RepeatData::default();
+        // `2dd`
+        repeat.record_operator(MotionModeCommand::MotionModeDelete, Some(2), false);
+        repeat.record_operator(MotionModeCommand::MotionModeDelete, Some(1), true);
+
+        let (steps, times) = repeat.last_change_with_count(None);
+        assert_eq!(counts(&steps), vec![Some(2), Some(1)]);
+        assert_eq!(times, 1);
+
+        // `3.` deletes three lines rather than three times two
+        let (steps, times) = repeat.last_change_with_count(Some(3));
+        assert_eq!(counts(&steps), vec![Some(3), None]);
+        assert_eq!(times, 1);
+
+        // `x`, which has no count to replace, is made three times
+        repeat.start(ChangeStep::Edit(EditCommand::DeleteForward));
+        repeat.commit();
+        let (steps, times) = repeat.last_change_with_count(Some(3));
+        assert_eq!(steps.len(), 1);
+        assert_eq!(times, 3);
+    }
+
+    #[test]
+    fn test_visual_step() {
+        let rope = Rope::from("abcdef\nghijkl\nmnopqr\n");
+        let text = RopeTextRef::new(&rope);
+
+        // Within a line the width is kept
+        let step = ChangeStep::visual(&text, 3, 1, VisualMode::Normal);
+        assert!(matches!(
+            step,
+            ChangeStep::Visual {
+                lines: 0,
+                columns: 2,
+                ..
+            }
+        ));
+        assert_eq!(visual_end(&text, 8, VisualMode::Normal, 0, 2), 10);
+        // but not past the end of the line
+        assert_eq!(visual_end(&text, 12, VisualMode::Normal, 0, 2), 12);
+
+        // Over several lines it ends at the same column
+        let step = ChangeStep::visual(&text, 2, 11, VisualMode::Normal);
+        assert!(matches!(
+            step,
+            ChangeStep::Visual {
+                lines: 1,
+                columns: 4,
+                ..
+            }
+        ));
+        assert_eq!(visual_end(&text, 7, VisualMode::Normal, 1, 4), 18);
+        assert_eq!(visual_end(&text, 7, VisualMode::Linewise, 1, 0), 14);
+    }
+}
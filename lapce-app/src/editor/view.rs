@@ -42,6 +42,7 @@
 use lapce_core::{
     buffer::{diff::DiffLines, rope_text::RopeText, Buffer},
     cursor::{CursorAffinity, CursorMode},
+    rope_text_pos::RopeTextPosition,
     selection::SelRegion,
 };
 use lapce_rpc::{
@@ -59,6 +60,7 @@
     debug::{DapData, LapceBreakpoint},
     doc::DocContent,
     editor::gutter::FoldingDisplayItem,
+    panel::document_symbol::symbol_path_at,
     text_input::TextInputBuilder,
     window_tab::{CommonData, Focus, WindowTabData},
     workspace::LapceWorkspace,
@@ -251,6 +253,32 @@ pub fn editor_view(
         }
     });
 
+    let linked_editor = e_data.clone();
+    let linked_cursor = e_data.cursor();
+    create_effect(move |last_offset| {
+        let offset = linked_cursor.with(|c| c.offset());
+        if last_offset != Some(offset) && is_active.get_untracked() {
+            linked_editor.update_linked_editing_ranges();
+        }
+        offset
+    });
+
+    // Moving, searching or going to a line in a folded range unfolds it
+    let fold_editor = e_data.clone();
+    let fold_cursor = e_data.cursor();
+    create_effect(move |last_offset| {
+        let offset = fold_cursor.with(|c| c.offset());
+        if last_offset != Some(offset) {
+            let doc = fold_editor.doc();
+            let line =
+                doc.buffer.with_untracked(|b| b.line_of_offset(offset)) as u32;
+            if doc.folding_ranges.with_untracked(|r| r.hides_line(line)) {
+                doc.folding_ranges.update(|r| r.unfold_line(line));
+            }
+        }
+        offset
+    });
+
     let doc = e_data.doc_signal();
     EditorView {
         id,
@@ -1793,7 +1821,7 @@ fn editor_gutter_code_actions(
             cursor.with(|cursor| (cursor.offset(), cursor.affinity));
         let has_code_actions = doc
             .code_actions()
-            .with(|c| c.get(&offset).map(|c| !c.1.is_empty()).unwrap_or(false));
+            .with(|c| c.get(&offset).map(|c| !c.is_empty()).unwrap_or(false));
         if has_code_actions {
             let vline = ed.vline_of_offset(offset, affinity);
             Some(vline)
@@ -1935,6 +1963,23 @@ fn editor_breadcrumbs(
             content.path().cloned()
         }
     });
+    // The symbols around the cursor, from the language server or the syntax
+    let cursor = e_data.cursor();
+    let symbol_path = create_memo(move |_| {
+        let doc = doc.get();
+        let offset = cursor.with(|cursor| cursor.offset());
+        let position = doc
+            .buffer
+            .with_untracked(|buffer| buffer.offset_to_position(offset));
+        doc.document_symbol_data.with(|data| {
+            data.as_ref()
+                .map(|data| {
+                    data.file
+                        .with(|file| symbol_path_at(&file.children, position))
+                })
+                .unwrap_or_default()
+        })
+    });
     container(
         scroll(
             stack((
@@ -1963,6 +2008,7 @@ fn editor_breadcrumbs(
                                 .collect::<Vec<_>>()
                                 .into_iter()
                                 .rev()
+                                .chain(symbol_path.get())
                                 .enumerate()
                         },
                         |(i, section)| (*i, section.to_string()),
@@ -1995,7 +2041,7 @@ fn editor_breadcrumbs(
                 label(move || {
                     let doc = doc.get();
                     if let DocContent::History(history) = doc.content.get() {
-                        format!("({})", history.version)
+                        format!("({})", history.label())
                     } else {
                         "".to_string()
                     }
@@ -217,7 +217,8 @@ pub fn view_info(
                         editor_data.confirmed,
                     );
                     match content {
-                        DocContent::File { path, .. } => {
+                        DocContent::File { path, .. }
+                        | DocContent::Hex { path, .. } => {
                             Some((path, confirmed, is_pristine))
                         }
                         DocContent::Local => None,
@@ -277,7 +278,8 @@ pub fn view_info(
                                     )
                                 });
                             match content {
-                                DocContent::File { path, .. } => {
+                                DocContent::File { path, .. }
+                                | DocContent::Hex { path, .. } => {
                                     Some((path, is_pristine))
                                 }
                                 DocContent::Local => None,
@@ -423,7 +425,9 @@ pub fn get_editor(
             if let (_, _, EditorTabChild::Editor(editor_id)) = child {
                 if let Some(editor) = editors.editor_untracked(*editor_id) {
                     let is_path = editor.doc().content.with_untracked(|content| {
-                        if let DocContent::File { path: p, .. } = content {
+                        if let DocContent::File { path: p, .. }
+                        | DocContent::Hex { path: p, .. } = content
+                        {
                             p == path
                         } else {
                             false
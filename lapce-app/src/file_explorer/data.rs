@@ -1,7 +1,9 @@
 use std::{
     borrow::Cow,
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     ffi::OsStr,
+    mem::Discriminant,
+    ops::Range,
     path::{Path, PathBuf},
     rc::Rc,
     sync::Arc,
@@ -13,30 +15,39 @@
     ext_event::create_ext_action,
     keyboard::Modifiers,
     menu::{Menu, MenuItem},
-    reactive::{ReadSignal, RwSignal, Scope, SignalGet, SignalUpdate, SignalWith},
+    reactive::{
+        Memo, ReadSignal, RwSignal, Scope, SignalGet, SignalUpdate, SignalWith,
+    },
     views::editor::text::SystemClipboard,
 };
 use globset::Glob;
 use lapce_core::{
-    command::{EditCommand, FocusCommand},
+    command::{EditCommand, FocusCommand, MoveCommand},
     mode::Mode,
     register::Clipboard,
 };
 use lapce_rpc::{
     file::{
-        Duplicating, FileNodeItem, FileNodeViewKind, Naming, NamingState, NewNode,
-        Renaming,
+        Duplicating, FileNodeItem, FileNodeViewKind, FileNodeViewOptions, Naming,
+        NamingState, NewNode, Renaming,
     },
     proxy::ProxyResponse,
 };
+use url::Url;
 
+use super::node::FileNodeVirtualList;
 use crate::{
-    command::{CommandExecuted, CommandKind, InternalCommand, LapceCommand},
+    alert::AlertButton,
+    command::{
+        CommandExecuted, CommandKind, InternalCommand, LapceCommand,
+        LapceWorkbenchCommand,
+    },
     config::LapceConfig,
     editor::EditorData,
     keypress::{condition::Condition, KeyPressFocus},
     main_split::Editors,
     window_tab::CommonData,
+    workspace::relative_to_roots,
 };
 
 enum RenamedPath {
@@ -48,15 +59,47 @@ enum RenamedPath {
     },
 }
 
+/// The paths that were cut or copied in the file explorer, to be pasted
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileClipboard {
+    pub paths: Vec<PathBuf>,
+    pub cut: bool,
+}
+
+/// A file operation of the file explorer, kept so that it can be undone
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FileOperation {
+    /// The paths that were copied and where their copies are
+    Copy(Vec<(PathBuf, PathBuf)>),
+    /// Where the paths were moved from and to
+    Move(Vec<(PathBuf, PathBuf)>),
+}
+
 #[derive(Clone, Debug)]
 pub struct FileExplorerData {
-    pub root: RwSignal<FileNodeItem>,
+    /// The trees of the root folders of the workspace, in order
+    pub roots: RwSignal<Vec<FileNodeItem>>,
     pub naming: RwSignal<Naming>,
     pub naming_editor_data: EditorData,
     pub common: Rc<CommonData>,
     pub scroll_to_line: RwSignal<Option<f64>>,
     left_diff_path: RwSignal<Option<PathBuf>>,
+    /// The node the keyboard is on
     pub select: RwSignal<Option<FileNodeViewKind>>,
+    /// The paths that file operations apply to
+    pub selected: RwSignal<Vec<PathBuf>>,
+    /// Where a range selection with shift starts
+    anchor: RwSignal<Option<PathBuf>>,
+    /// Where the text that the tree is filtered by is typed
+    pub filter_editor: EditorData,
+    /// The paths shown while filtering, which are the ones whose names
+    /// match the filter along with their ancestors
+    pub filter_matches: RwSignal<Option<Arc<HashSet<PathBuf>>>>,
+    /// How the rows of the tree are laid out, which the row counts of the
+    /// nodes in `roots` are kept up to date with
+    view_options: Memo<FileNodeViewOptions>,
+    pub clipboard: RwSignal<Option<FileClipboard>>,
+    pub last_operation: RwSignal<Option<FileOperation>>,
 }
 
 impl KeyPressFocus for FileExplorerData {
@@ -65,8 +108,11 @@ fn get_mode(&self) -> Mode {
     }
 
     fn check_condition(&self, condition: Condition) -> bool {
-        self.naming.with_untracked(Naming::is_accepting_input)
-            && condition == Condition::ModalFocus
+        if self.naming.with_untracked(Naming::is_accepting_input) {
+            condition == Condition::ModalFocus
+        } else {
+            condition == Condition::PanelFocus
+        }
     }
 
     fn run_command(
@@ -100,7 +146,37 @@ fn run_command(
                 _ => self.naming_editor_data.run_command(command, count, mods),
             }
         } else {
-            CommandExecuted::No
+            match &command.kind {
+                CommandKind::Move(MoveCommand::Up) => {
+                    self.move_selection(-1, mods.shift());
+                }
+                CommandKind::Move(MoveCommand::Down) => {
+                    self.move_selection(1, mods.shift());
+                }
+                CommandKind::Edit(EditCommand::InsertNewLine) => {
+                    self.open_selected();
+                }
+                CommandKind::Edit(EditCommand::ClipboardCopy) => {
+                    self.copy_selected(false);
+                }
+                CommandKind::Edit(EditCommand::ClipboardCut) => {
+                    self.copy_selected(true);
+                }
+                CommandKind::Edit(EditCommand::ClipboardPaste) => {
+                    self.paste();
+                }
+                CommandKind::Edit(EditCommand::Undo) => {
+                    self.undo();
+                }
+                CommandKind::Edit(EditCommand::DeleteForward) => {
+                    self.ask_trash_selected();
+                }
+                CommandKind::Focus(FocusCommand::Rename) => {
+                    self.rename_selected();
+                }
+                _ => return CommandExecuted::No,
+            }
+            CommandExecuted::Yes
         }
     }
 
@@ -119,62 +195,181 @@ fn receive_char(&self, c: &str) {
 
 impl FileExplorerData {
     pub fn new(cx: Scope, editors: Editors, common: Rc<CommonData>) -> Self {
-        let path = common.workspace.path.clone().unwrap_or_default();
-        let root = cx.create_rw_signal(FileNodeItem {
-            path: path.clone(),
-            is_dir: true,
-            read: false,
-            open: false,
-            children: HashMap::new(),
-            children_open_count: 0,
-        });
+        let paths = common.workspace_roots.get_untracked();
+        let roots = cx.create_rw_signal(Vec::new());
         let naming = cx.create_rw_signal(Naming::None);
         let naming_editor_data = editors.make_local(cx, common.clone());
+        let filter_editor = editors.make_local(cx, common.clone());
+        let filter_matches = cx.create_rw_signal(None);
+        let config = common.config;
+        let nesting_rules = cx.create_memo(move |_| {
+            Arc::new(config.with(|config| config.core.file_explorer_nesting_rules()))
+        });
+        let view_options = cx.create_memo(move |_| FileNodeViewOptions {
+            compact_folders: config
+                .with(|config| config.core.file_explorer_compact_folders),
+            nesting: nesting_rules.get(),
+            filter: filter_matches.get(),
+        });
         let data = Self {
-            root,
+            roots,
             naming,
             naming_editor_data,
             common,
             scroll_to_line: cx.create_rw_signal(None),
             left_diff_path: cx.create_rw_signal(None),
             select: cx.create_rw_signal(None),
+            selected: cx.create_rw_signal(Vec::new()),
+            anchor: cx.create_rw_signal(None),
+            filter_editor,
+            filter_matches,
+            view_options,
+            clipboard: cx.create_rw_signal(None),
+            last_operation: cx.create_rw_signal(None),
         };
-        if data.common.workspace.path.is_some() {
-            // only fill in the child files if there is open folder
-            data.toggle_expand(&path);
+        data.set_roots(&paths);
+
+        {
+            let buffer = data.filter_editor.doc().buffer;
+            let roots = data.roots;
+            let filter_matches = data.filter_matches;
+            cx.create_effect(move |_| {
+                let pattern = buffer.with(|buffer| buffer.to_string());
+                let matches = if pattern.trim().is_empty() {
+                    None
+                } else {
+                    Some(Arc::new(
+                        roots.with(|roots| filter_file_nodes(roots, &pattern)),
+                    ))
+                };
+                filter_matches.set(matches);
+            });
         }
+
+        // Count the rows again when how they're laid out changes
+        cx.create_effect(move |_| {
+            let options = view_options.get();
+            naming.with_untracked(|naming| {
+                roots.update(|roots| {
+                    for root in roots.iter_mut() {
+                        root.update_all_node_counts(naming, &options);
+                    }
+                });
+            });
+        });
+
+        // The input for naming a node adds a row to the folder it's in, and
+        // keeps folders from being compacted around it
+        cx.create_effect(move |prev: Option<Option<NamingTarget>>| {
+            let target = naming.with(naming_target);
+            if prev.as_ref() != Some(&target) {
+                let options = view_options.get_untracked();
+                naming.with_untracked(|naming| {
+                    roots.update(|roots| {
+                        let paths = prev.flatten().into_iter().chain(target.clone());
+                        for (_, path) in paths {
+                            if let Some(i) = root_index(roots, &path) {
+                                roots[i].update_node_count_recursive(
+                                    &path, naming, &options,
+                                );
+                            }
+                        }
+                    });
+                });
+            }
+            target
+        });
+
         data
     }
 
-    /// Reload the file explorer data via reading the root directory.  
+    /// Show these root folders, keeping the trees of the ones already shown.
+    pub fn set_roots(&self, paths: &[PathBuf]) {
+        let mut added = Vec::new();
+        self.roots.update(|roots| {
+            let mut old_roots = std::mem::take(roots);
+            for path in paths {
+                let root = match old_roots.iter().position(|root| &root.path == path)
+                {
+                    Some(i) => old_roots.remove(i),
+                    None => {
+                        added.push(path.clone());
+                        FileNodeItem {
+                            path: path.clone(),
+                            is_dir: true,
+                            read: false,
+                            open: false,
+                            children: HashMap::new(),
+                            children_open_count: 0,
+                        }
+                    }
+                };
+                roots.push(root);
+            }
+        });
+        for path in added {
+            self.toggle_expand(&path);
+        }
+    }
+
+    /// Reload the file explorer data via reading the root directories.  
     /// Note that this will not update immediately.
     pub fn reload(&self) {
-        let path = self.root.with_untracked(|root| root.path.clone());
-        self.read_dir(&path);
+        let paths: Vec<PathBuf> = self.roots.with_untracked(|roots| {
+            roots.iter().map(|root| root.path.clone()).collect()
+        });
+        for path in paths {
+            self.read_dir(&path);
+        }
+    }
+
+    /// Update the tree of the root folder `path` is in, counting the rows of
+    /// `path` and its ancestors again after.
+    fn update_root<R>(
+        &self,
+        path: &Path,
+        f: impl FnOnce(&mut FileNodeItem) -> R,
+    ) -> Option<R> {
+        let options = self.view_options.get_untracked();
+        let naming = self.naming;
+        self.roots
+            .try_update(|roots| {
+                let i = root_index(roots, path)?;
+                let root = &mut roots[i];
+                let result = f(root);
+                naming.with_untracked(|naming| {
+                    root.update_node_count_recursive(path, naming, &options);
+                });
+                Some(result)
+            })
+            .flatten()
+    }
+
+    /// Look into the tree of the root folder `path` is in.
+    fn with_root<R>(
+        &self,
+        path: &Path,
+        f: impl FnOnce(&FileNodeItem) -> R,
+    ) -> Option<R> {
+        self.roots.with_untracked(|roots| {
+            let i = root_index(roots, path)?;
+            Some(f(&roots[i]))
+        })
     }
 
     /// Toggle whether the directory is expanded or not.  
     /// Does nothing if the path does not exist or is not a directory.
     pub fn toggle_expand(&self, path: &Path) {
         let Some(read) = self
-            .root
-            .try_update(|root| {
-                let read = if let Some(node) = root.get_file_node_mut(path) {
-                    if !node.is_dir {
-                        return None;
-                    }
-                    node.open = !node.open;
-                    Some(node.read)
-                } else {
-                    None
-                };
-
-                if Some(true) == read {
-                    root.update_node_count_recursive(path);
+            .update_root(path, |root| {
+                let node = root.get_file_node_mut(path)?;
+                if !node.is_dir {
+                    return None;
                 }
-                read
+                node.open = !node.open;
+                Some(node.read)
             })
-            .unwrap()
+            .flatten()
         else {
             return;
         };
@@ -185,6 +380,17 @@ pub fn toggle_expand(&self, path: &Path) {
         }
     }
 
+    /// Toggle whether the files nested under the file are shown.
+    pub fn toggle_nested(&self, path: &Path) {
+        self.update_root(path, |root| {
+            if let Some(node) = root.get_file_node_mut(path) {
+                if !node.is_dir {
+                    node.open = !node.open;
+                }
+            }
+        });
+    }
+
     pub fn read_dir(&self, path: &Path) {
         self.read_dir_cb(path, |_| {});
     }
@@ -193,7 +399,6 @@ pub fn read_dir(&self, path: &Path) {
     /// `done : FnOnce(was_read: bool)` is called when the operation is completed, whether success,
     /// failure, or ignored.
     pub fn read_dir_cb(&self, path: &Path, done: impl FnOnce(bool) + 'static) {
-        let root = self.root;
         let data = self.clone();
         let config = self.common.config;
         let send = {
@@ -204,7 +409,7 @@ pub fn read_dir_cb(&self, path: &Path, done: impl FnOnce(bool) + 'static) {
                     return;
                 };
 
-                root.update(|root| {
+                data.update_root(&path, |root| {
                     // Get the node for this path, which should already exist if we're calling
                     // read_dir on it.
                     if let Some(node) = root.get_file_node_mut(&path) {
@@ -246,7 +451,6 @@ pub fn read_dir_cb(&self, path: &Path, done: impl FnOnce(bool) + 'static) {
                             }
                         }
                     }
-                    root.update_node_count_recursive(&path);
                 });
 
                 done(true);
@@ -260,9 +464,10 @@ pub fn read_dir_cb(&self, path: &Path, done: impl FnOnce(bool) + 'static) {
     /// Returns `true` if `path` exists in the file explorer tree and is a directory, `false`
     /// otherwise.
     fn is_dir(&self, path: &Path) -> bool {
-        self.root.with_untracked(|root| {
+        self.with_root(path, |root| {
             root.get_file_node(path).is_some_and(|node| node.is_dir)
         })
+        .unwrap_or(false)
     }
 
     /// The current path that we're renaming to / creating or duplicating a node at.  
@@ -396,7 +601,38 @@ pub fn cancel_naming(&self) {
         self.naming.set(Naming::None);
     }
 
-    pub fn click(&self, path: &Path, config: ReadSignal<Arc<LapceConfig>>) {
+    /// Click on `path`, which selects a range from the last selected path with
+    /// shift, and adds it to or removes it from the selection with ctrl (cmd
+    /// on macOS).
+    pub fn click(
+        &self,
+        path: &Path,
+        mods: Modifiers,
+        config: ReadSignal<Arc<LapceConfig>>,
+    ) {
+        let toggle = if cfg!(target_os = "macos") {
+            mods.meta()
+        } else {
+            mods.control()
+        };
+        if mods.shift() {
+            self.select_range(path);
+            return;
+        }
+        if toggle {
+            self.selected.update(|selected| {
+                if let Some(i) = selected.iter().position(|p| p == path) {
+                    selected.remove(i);
+                } else {
+                    selected.push(path.to_path_buf());
+                }
+            });
+            self.anchor.set(Some(path.to_path_buf()));
+            self.set_select(path);
+            return;
+        }
+
+        self.select_only(path);
         if self.is_dir(path) {
             self.toggle_expand(path);
         } else if !config.get_untracked().core.file_explorer_double_click {
@@ -410,8 +646,7 @@ pub fn click(&self, path: &Path, config: ReadSignal<Arc<LapceConfig>>) {
 
     pub fn reveal_in_file_tree(&self, path: PathBuf) {
         let done = self
-            .root
-            .try_update(|root| {
+            .update_root(&path, |root| {
                 // the directories in which the file are located are all readed and opened
                 if root.get_file_node(&path).is_some() {
                     for current_path in path.ancestors() {
@@ -421,7 +656,6 @@ pub fn reveal_in_file_tree(&self, path: PathBuf) {
                             }
                         }
                     }
-                    root.update_node_count_recursive(&path);
                     true
                 } else {
                     // read and open the directories in which the file are located
@@ -452,11 +686,9 @@ pub fn reveal_in_file_tree(&self, path: PathBuf) {
             })
             .unwrap_or(false);
         if done {
-            let (found, line) =
-                self.root.with_untracked(|x| x.find_file_at_line(&path));
-            if found {
-                self.scroll_to_line.set(Some(line));
-                self.select.set(Some(FileNodeViewKind::Path(path)));
+            if let Some(line) = self.find_row(&path) {
+                self.scroll_to_line.set(Some(line as f64));
+                self.select_only(&path);
             }
         }
     }
@@ -495,7 +727,8 @@ pub fn secondary_click(&self, path: &Path) {
             return;
         };
 
-        let is_workspace = path == workspace_path;
+        let roots = self.common.workspace_roots.get_untracked();
+        let is_workspace = roots.iter().any(|root| root == path);
 
         let base_path_a = if is_dir {
             Some(path_a.clone())
@@ -599,19 +832,44 @@ pub fn secondary_click(&self, path: &Path) {
 
             // TODO: it is common for shift+right click to make 'Move file to trash' an actual
             // Delete, which can be useful for large files.
-            let path = path_a.clone();
-            let proxy = common.proxy.clone();
-            let trash_text = if is_dir {
-                "Move Directory to Trash"
+            let count = self.selected_paths().len();
+            let trash_text = if count > 1 {
+                format!("Move {count} Items to Trash")
+            } else if is_dir {
+                "Move Directory to Trash".to_string()
             } else {
-                "Move File to Trash"
+                "Move File to Trash".to_string()
             };
+            let data = self.clone();
             menu = menu.entry(MenuItem::new(trash_text).action(move || {
-                proxy.trash_path(path.clone(), |res| {
-                    if let Err(err) = res {
-                        tracing::warn!("Failed to trash path: {:?}", err);
-                    }
-                })
+                data.trash_selected();
+            }));
+        }
+
+        menu = menu.separator();
+
+        if !is_workspace {
+            let data = self.clone();
+            menu = menu.entry(MenuItem::new("Cut").action(move || {
+                data.copy_selected(true);
+            }));
+            let data = self.clone();
+            menu = menu.entry(MenuItem::new("Copy").action(move || {
+                data.copy_selected(false);
+            }));
+        }
+        let data = self.clone();
+        menu = menu.entry(MenuItem::new("Paste").action(move || {
+            data.paste();
+        }));
+        if let Some(operation) = self.last_operation.get_untracked() {
+            let title = match operation {
+                FileOperation::Copy(_) => "Undo Copy",
+                FileOperation::Move(_) => "Undo Move",
+            };
+            let data = self.clone();
+            menu = menu.entry(MenuItem::new(title).action(move || {
+                data.undo();
             }));
         }
 
@@ -624,13 +882,10 @@ pub fn secondary_click(&self, path: &Path) {
         }));
 
         let path = path_a.clone();
-        let workspace = common.workspace.clone();
+        let workspace_roots = common.workspace_roots;
         menu = menu.entry(MenuItem::new("Copy Relative Path").action(move || {
-            let relative_path = if let Some(workspace_path) = &workspace.path {
-                path.strip_prefix(workspace_path).unwrap_or(&path)
-            } else {
-                path.as_ref()
-            };
+            let relative_path = workspace_roots
+                .with_untracked(|roots| relative_to_roots(roots, &path));
 
             let mut clipboard = SystemClipboard::new();
             clipboard.put_string(relative_path.to_string_lossy());
@@ -663,6 +918,26 @@ pub fn secondary_click(&self, path: &Path) {
         menu = menu.separator();
 
         let internal_command = common.internal_command;
+        if !common.workspace.kind.is_remote() {
+            let workbench_command = common.workbench_command;
+            menu = menu.entry(MenuItem::new("Add Folder to Workspace").action(
+                move || {
+                    workbench_command
+                        .send(LapceWorkbenchCommand::AddFolderToWorkspace);
+                },
+            ));
+        }
+        if is_workspace && roots.len() > 1 {
+            let path = path_a.clone();
+            menu = menu.entry(MenuItem::new("Remove Folder from Workspace").action(
+                move || {
+                    internal_command.send(InternalCommand::RemoveWorkspaceFolder {
+                        path: path.clone(),
+                    });
+                },
+            ));
+        }
+
         menu = menu.entry(MenuItem::new("Refresh").action(move || {
             internal_command.send(InternalCommand::ReloadFileExplorer);
         }));
@@ -682,4 +957,452 @@ pub fn middle_click(&self, path: &Path) -> EventPropagation {
             EventPropagation::Stop
         }
     }
+
+    /// The path of the node the keyboard is on.
+    fn select_path(&self) -> Option<PathBuf> {
+        self.select.with_untracked(|select| {
+            select
+                .as_ref()
+                .and_then(|select| select.path())
+                .map(Path::to_path_buf)
+        })
+    }
+
+    fn set_select(&self, path: &Path) {
+        self.select
+            .set(Some(FileNodeViewKind::Path(path.to_path_buf())));
+    }
+
+    /// Select only `path`, and start range selections from it.
+    pub fn select_only(&self, path: &Path) {
+        self.selected.set(vec![path.to_path_buf()]);
+        self.anchor.set(Some(path.to_path_buf()));
+        self.set_select(path);
+    }
+
+    /// Select the shown nodes from where the last selection started to `path`.
+    fn select_range(&self, path: &Path) {
+        let Some(anchor) = self.anchor.get_untracked() else {
+            self.select_only(path);
+            return;
+        };
+        let (Some(start), Some(end)) = (self.find_row(&anchor), self.find_row(path))
+        else {
+            self.select_only(path);
+            return;
+        };
+        self.selected
+            .set(self.row_paths(start.min(end)..start.max(end) + 1));
+        self.set_select(path);
+    }
+
+    /// The rows of the tree as they are shown.
+    pub fn view_list(&self) -> FileNodeVirtualList {
+        FileNodeVirtualList::new(
+            self.roots.get(),
+            self.naming.get(),
+            self.view_options.get(),
+        )
+    }
+
+    /// The number of rows shown in the tree.
+    fn row_count(&self) -> usize {
+        self.roots
+            .with_untracked(|roots| FileNodeVirtualList::view_len(roots))
+    }
+
+    /// The index of the row that `path` is shown in.
+    fn find_row(&self, path: &Path) -> Option<usize> {
+        let options = self.view_options.get_untracked();
+        self.naming.with_untracked(|naming| {
+            self.roots.with_untracked(|roots| {
+                FileNodeVirtualList::find_row(roots, naming, &options, path)
+            })
+        })
+    }
+
+    /// The paths of the nodes shown in the rows in `range`, in order.
+    fn row_paths(&self, range: Range<usize>) -> Vec<PathBuf> {
+        let options = self.view_options.get_untracked();
+        let rows = self.naming.with_untracked(|naming| {
+            self.roots.with_untracked(|roots| {
+                FileNodeVirtualList::view_slice(roots, naming, &options, range)
+            })
+        });
+        rows.into_iter()
+            .filter_map(|row| match row.kind {
+                FileNodeViewKind::Path(path)
+                | FileNodeViewKind::Renaming { path, .. } => Some(path),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Move the keyboard up or down the tree, extending the selection with
+    /// `extend`.
+    fn move_selection(&self, delta: isize, extend: bool) {
+        let len = self.row_count();
+        if len == 0 {
+            return;
+        }
+        let current = self.select_path().and_then(|path| self.find_row(&path));
+        let mut index = match current {
+            Some(current) => current.saturating_add_signed(delta).min(len - 1),
+            None => 0,
+        };
+        let path = loop {
+            if let Some(path) = self.row_paths(index..index + 1).pop() {
+                break path;
+            }
+            // Step over the input for naming a node
+            match index.checked_add_signed(delta.signum()) {
+                Some(next) if next < len && next != index => index = next,
+                _ => return,
+            }
+        };
+        if extend {
+            self.select_range(&path);
+        } else {
+            self.select_only(&path);
+        }
+        self.scroll_to_line.set(Some(index as f64));
+    }
+
+    /// The selected paths that can be moved around, which leaves out the root
+    /// folders and the paths in a selected directory.
+    pub fn selected_paths(&self) -> Vec<PathBuf> {
+        let roots = self.common.workspace_roots.get_untracked();
+        self.selected.with_untracked(|selected| {
+            selected
+                .iter()
+                .filter(|path| {
+                    !roots.contains(path)
+                        && !selected
+                            .iter()
+                            .any(|other| other != *path && path.starts_with(other))
+                })
+                .cloned()
+                .collect()
+        })
+    }
+
+    /// The paths to drag when dragging `path`, which are the selected ones if
+    /// it is selected.
+    pub fn drag_paths(&self, path: &Path) -> Vec<PathBuf> {
+        if self
+            .selected
+            .with_untracked(|selected| selected.iter().any(|p| p == path))
+        {
+            self.selected_paths()
+        } else {
+            vec![path.to_path_buf()]
+        }
+    }
+
+    fn open_selected(&self) {
+        let Some(path) = self.select_path() else {
+            return;
+        };
+        if self.is_dir(&path) {
+            self.toggle_expand(&path);
+        } else {
+            self.common
+                .internal_command
+                .send(InternalCommand::OpenFile { path });
+        }
+    }
+
+    fn rename_selected(&self) {
+        let Some(path) = self.select_path() else {
+            return;
+        };
+        if self
+            .common
+            .workspace_roots
+            .with_untracked(|roots| roots.contains(&path))
+        {
+            return;
+        }
+        self.naming.set(Naming::Renaming(Renaming {
+            state: NamingState::Naming,
+            path,
+            editor_needs_reset: true,
+        }));
+    }
+
+    pub fn trash_selected(&self) {
+        self.trash_paths(self.selected_paths());
+    }
+
+    fn trash_paths(&self, paths: Vec<PathBuf>) {
+        for path in paths {
+            self.common.proxy.trash_path(path, |res| {
+                if let Err(err) = res {
+                    tracing::warn!("Failed to trash path: {:?}", err);
+                }
+            });
+        }
+    }
+
+    /// Move the selected paths to the trash, after asking, as a key press is
+    /// easier to make by accident than picking it from the menu.
+    pub fn ask_trash_selected(&self) {
+        let paths = self.selected_paths();
+        let title = match paths.as_slice() {
+            [] => return,
+            [path] => format!(
+                "Do you want to move {} to the trash?",
+                path.file_name()
+                    .unwrap_or(path.as_os_str())
+                    .to_string_lossy()
+            ),
+            paths => {
+                format!("Do you want to move {} items to the trash?", paths.len())
+            }
+        };
+        let data = self.clone();
+        let internal_command = self.common.internal_command;
+        internal_command.send(InternalCommand::ShowAlert {
+            title,
+            msg: "What is in the trash can be restored from it.".to_string(),
+            buttons: vec![AlertButton {
+                text: "Move to Trash".to_string(),
+                action: Rc::new(move || {
+                    internal_command.send(InternalCommand::HideAlert);
+                    data.trash_paths(paths.clone());
+                }),
+            }],
+        });
+    }
+
+    /// Cut or copy the selected paths, which also puts them in the system
+    /// clipboard as text.
+    pub fn copy_selected(&self, cut: bool) {
+        let paths = self.selected_paths();
+        if paths.is_empty() {
+            return;
+        }
+        let mut clipboard = SystemClipboard::new();
+        clipboard.put_string(paths_text(&paths));
+        self.clipboard.set(Some(FileClipboard { paths, cut }));
+    }
+
+    /// Paste the cut or copied paths into the selected directory, or the
+    /// directory of the selected file.  
+    /// In local workspaces, paths copied elsewhere as text, one per line, are
+    /// pasted too.
+    pub fn paste(&self) {
+        let Some(to) = self.paste_dir() else {
+            return;
+        };
+        let text = SystemClipboard::new().get_string();
+        // What was cut or copied here, unless something else was copied since
+        let clipboard = self.clipboard.get_untracked().filter(|clipboard| {
+            text.as_ref()
+                .map_or(true, |text| text == &paths_text(&clipboard.paths))
+        });
+
+        let internal_command = self.common.internal_command;
+        if let Some(clipboard) = clipboard {
+            if clipboard.cut {
+                self.clipboard.set(None);
+                internal_command.send(InternalCommand::MovePaths {
+                    paths: clipboard.paths,
+                    to,
+                });
+            } else {
+                internal_command.send(InternalCommand::CopyPaths {
+                    paths: clipboard.paths,
+                    to,
+                });
+            }
+        } else if !self.common.workspace.kind.is_remote() {
+            let paths = text
+                .map(|text| clipboard_file_list(&text))
+                .unwrap_or_default();
+            if !paths.is_empty() {
+                internal_command.send(InternalCommand::CopyPaths { paths, to });
+            }
+        }
+    }
+
+    fn paste_dir(&self) -> Option<PathBuf> {
+        match self.select_path() {
+            Some(path) if self.is_dir(&path) => Some(path),
+            Some(path) => path.parent().map(Path::to_path_buf),
+            None => self.common.workspace_roots.get_untracked().first().cloned(),
+        }
+    }
+
+    /// Move the paths dropped on the directory `to`, after asking.
+    pub fn drop_paths(&self, paths: Vec<PathBuf>, to: PathBuf) {
+        let paths: Vec<PathBuf> = paths
+            .into_iter()
+            .filter(|path| {
+                path.parent() != Some(to.as_path()) && !to.starts_with(path)
+            })
+            .collect();
+        if paths.is_empty() {
+            return;
+        }
+
+        let name = |path: &Path| {
+            path.file_name()
+                .unwrap_or(path.as_os_str())
+                .to_string_lossy()
+                .to_string()
+        };
+        let title = if let [path] = paths.as_slice() {
+            format!("Do you want to move {} into {}?", name(path), name(&to))
+        } else {
+            format!(
+                "Do you want to move {} items into {}?",
+                paths.len(),
+                name(&to)
+            )
+        };
+        let internal_command = self.common.internal_command;
+        internal_command.send(InternalCommand::ShowAlert {
+            title,
+            msg: "The move can be undone in the file explorer.".to_string(),
+            buttons: vec![AlertButton {
+                text: "Move".to_string(),
+                action: Rc::new(move || {
+                    internal_command.send(InternalCommand::HideAlert);
+                    internal_command.send(InternalCommand::MovePaths {
+                        paths: paths.clone(),
+                        to: to.clone(),
+                    });
+                }),
+            }],
+        });
+    }
+
+    /// Undo the last copy or move.
+    pub fn undo(&self) {
+        self.common
+            .internal_command
+            .send(InternalCommand::UndoFileOperation);
+    }
+}
+
+/// The text that cut or copied paths are put in the system clipboard as.
+fn paths_text(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|path| path.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The files that text in the system clipboard lists, one absolute path or
+/// `file://` URL per line.  
+/// It's empty when any line isn't an existing file.
+pub fn clipboard_file_list(text: &str) -> Vec<PathBuf> {
+    let lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+
+    let mut paths = Vec::new();
+    for line in lines {
+        let path = if line.starts_with("file://") {
+            match Url::parse(line)
+                .ok()
+                .and_then(|url| url.to_file_path().ok())
+            {
+                Some(path) => path,
+                None => return Vec::new(),
+            }
+        } else {
+            PathBuf::from(line)
+        };
+        if !path.is_absolute() || !path.exists() {
+            return Vec::new();
+        }
+        paths.push(path);
+    }
+    paths
+}
+
+/// The index of the root folder whose tree `path` is in, the innermost one
+/// when root folders are nested.
+fn root_index(roots: &[FileNodeItem], path: &Path) -> Option<usize> {
+    roots
+        .iter()
+        .enumerate()
+        .filter(|(_, root)| path.starts_with(&root.path))
+        .max_by_key(|(_, root)| root.path.components().count())
+        .map(|(i, _)| i)
+}
+
+/// The kind of naming going on, with the path of the node it's shown at.
+type NamingTarget = (Discriminant<Naming>, PathBuf);
+
+fn naming_target(naming: &Naming) -> Option<NamingTarget> {
+    let path = match naming {
+        Naming::None => return None,
+        Naming::NewNode(n) => &n.base_path,
+        Naming::Renaming(r) => &r.path,
+        Naming::Duplicating(d) => &d.path,
+    };
+    Some((std::mem::discriminant(naming), path.clone()))
+}
+
+/// The paths in the trees whose names fuzzy match `pattern`, along with
+/// their ancestors. Only the folders that have been read are searched.
+fn filter_file_nodes(roots: &[FileNodeItem], pattern: &str) -> HashSet<PathBuf> {
+    let pattern = nucleo::pattern::Pattern::parse(
+        pattern,
+        nucleo::pattern::CaseMatching::Ignore,
+        nucleo::pattern::Normalization::Smart,
+    );
+    let mut matcher = nucleo::Matcher::new(nucleo::Config::DEFAULT);
+    let mut buf = Vec::new();
+
+    let mut matches = HashSet::new();
+    let mut nodes = roots.iter().collect::<Vec<_>>();
+    while let Some(node) = nodes.pop() {
+        nodes.extend(node.children.values());
+        let name = node.path.file_name().unwrap_or_default().to_string_lossy();
+        if pattern
+            .score(nucleo::Utf32Str::new(&name, &mut buf), &mut matcher)
+            .is_some()
+        {
+            for path in node.path.ancestors() {
+                if !matches.insert(path.to_path_buf()) {
+                    break;
+                }
+            }
+        }
+    }
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clipboard_file_list() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("main.rs");
+        std::fs::write(&file, "").unwrap();
+        let url = Url::from_file_path(&file).unwrap();
+
+        assert_eq!(
+            clipboard_file_list(&file.to_string_lossy()),
+            vec![file.clone()]
+        );
+        assert_eq!(
+            clipboard_file_list(&format!("{url}\n{}\n", dir.path().display())),
+            vec![file.clone(), dir.path().to_path_buf()]
+        );
+        assert_eq!(
+            clipboard_file_list(&format!("{}\nsome text", file.display())),
+            Vec::<PathBuf>::new()
+        );
+        assert_eq!(clipboard_file_list("main.rs"), Vec::<PathBuf>::new());
+        assert_eq!(
+            clipboard_file_list(&format!("copy\n{url}")),
+            Vec::<PathBuf>::new()
+        );
+    }
 }
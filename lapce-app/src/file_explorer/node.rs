@@ -1,44 +1,92 @@
+use std::{ops::Range, path::Path};
+
 use floem::views::VirtualVector;
-use lapce_rpc::file::{FileNodeItem, FileNodeViewData, Naming};
+use lapce_rpc::file::{FileNodeItem, FileNodeViewData, FileNodeViewOptions, Naming};
 
 pub struct FileNodeVirtualList {
-    file_node_item: FileNodeItem,
+    roots: Vec<FileNodeItem>,
     naming: Naming,
+    options: FileNodeViewOptions,
 }
 
 impl FileNodeVirtualList {
-    pub fn new(file_node_item: FileNodeItem, naming: Naming) -> Self {
+    pub fn new(
+        roots: Vec<FileNodeItem>,
+        naming: Naming,
+        options: FileNodeViewOptions,
+    ) -> Self {
         Self {
-            file_node_item,
+            roots,
             naming,
+            options,
+        }
+    }
+
+    /// The number of rows of the root folders.
+    pub fn view_len(roots: &[FileNodeItem]) -> usize {
+        shown_roots(roots)
+            .map(|root| root.children_open_count + 1)
+            .sum()
+    }
+
+    /// The rows of the root folders in `range`, laid out one after the
+    /// other.
+    pub fn view_slice(
+        roots: &[FileNodeItem],
+        naming: &Naming,
+        options: &FileNodeViewOptions,
+        range: Range<usize>,
+    ) -> Vec<FileNodeViewData> {
+        let mut view_items = Vec::new();
+        let mut current = 0;
+        for root in shown_roots(roots) {
+            if current >= range.end {
+                break;
+            }
+            current = root.append_view_slice(
+                &mut view_items,
+                naming,
+                options,
+                &range,
+                current,
+                1,
+            );
+        }
+        view_items
+    }
+
+    /// The index of the row that `path` is shown in, if it's shown.
+    pub fn find_row(
+        roots: &[FileNodeItem],
+        naming: &Naming,
+        options: &FileNodeViewOptions,
+        path: &Path,
+    ) -> Option<usize> {
+        let mut current = 0;
+        for root in shown_roots(roots) {
+            if path.starts_with(&root.path) {
+                return root.find_view_row(path, naming, options, current);
+            }
+            current += root.children_open_count + 1;
         }
+        None
     }
 }
 
+/// The root folders that are shown, one after the other.
+fn shown_roots(roots: &[FileNodeItem]) -> impl Iterator<Item = &FileNodeItem> {
+    roots.iter().filter(|root| root.path.exists())
+}
+
 impl VirtualVector<FileNodeViewData> for FileNodeVirtualList {
     fn total_len(&self) -> usize {
-        if !self.file_node_item.path.exists() {
-            return 0;
-        }
-        self.file_node_item.children_open_count + 1
+        Self::view_len(&self.roots)
     }
 
     fn slice(
         &mut self,
         range: std::ops::Range<usize>,
     ) -> impl Iterator<Item = FileNodeViewData> {
-        let naming = &self.naming;
-        let root = &self.file_node_item;
-
-        if !root.path.exists() {
-            return Vec::new().into_iter();
-        }
-        let min = range.start;
-        let max = range.end;
-        let mut view_items = Vec::new();
-
-        root.append_view_slice(&mut view_items, naming, min, max, 0, 1);
-
-        view_items.into_iter()
+        Self::view_slice(&self.roots, &self.naming, &self.options, range).into_iter()
     }
 }
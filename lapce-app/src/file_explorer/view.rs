@@ -1,7 +1,8 @@
 use std::{path::Path, rc::Rc, sync::Arc};
 
 use floem::{
-    event::{Event, EventListener},
+    event::{Event, EventListener, EventPropagation},
+    keyboard::Modifiers,
     kurbo::Rect,
     peniko::Color,
     reactive::{
@@ -22,7 +23,7 @@
 };
 use lapce_xi_rope::Rope;
 
-use super::{data::FileExplorerData, node::FileNodeVirtualList};
+use super::data::FileExplorerData;
 use crate::{
     app::clickable_icon,
     command::InternalCommand,
@@ -35,7 +36,7 @@
     plugin::PluginData,
     source_control::SourceControlData,
     text_input::TextInputBuilder,
-    window_tab::{Focus, WindowTabData},
+    window_tab::{DragContent, Focus, WindowTabData},
 };
 
 /// Blends `foreground` with `background`.
@@ -89,8 +90,16 @@ pub fn file_explorer_panel(
         )
         .add(
             "File Explorer",
-            container(file_explorer_view(data, source_control))
-                .style(|s| s.size_full()),
+            stack((
+                file_explorer_filter_view(data.clone()),
+                container(file_explorer_view(data, source_control)).style(|s| {
+                    s.width_full()
+                        .flex_grow(1.0)
+                        .flex_basis(0.0)
+                        .min_height(0.0)
+                }),
+            ))
+            .style(|s| s.size_full().flex_col()),
             window_tab_data
                 .panel
                 .section_open(PanelSection::FileExplorer),
@@ -207,22 +216,20 @@ fn file_node_text_view(
                     ),
                 ))
             } else {
-                container(
-                    label(move || {
-                        path.file_name()
-                            .map(|f| f.to_string_lossy().to_string())
-                            .unwrap_or_default()
-                    })
-                    .style(move |s| {
-                        s.height(ui_line_height.get())
-                            .color(file_node_text_color(
-                                config,
-                                node.clone(),
-                                source_control.clone(),
-                            ))
-                            .selectable(false)
-                    }),
-                )
+                let name = node.label.clone().unwrap_or_else(|| {
+                    path.file_name()
+                        .map(|f| f.to_string_lossy().to_string())
+                        .unwrap_or_default()
+                });
+                container(label(move || name.clone()).style(move |s| {
+                    s.height(ui_line_height.get())
+                        .color(file_node_text_color(
+                            config,
+                            node.clone(),
+                            source_control.clone(),
+                        ))
+                        .selectable(false)
+                }))
             }
         }
         FileNodeViewKind::Renaming { path, err } => {
@@ -315,25 +322,63 @@ fn file_node_input_view(data: FileExplorerData, err: Option<String>) -> Containe
     .style(move |s| s.width_full())
 }
 
+/// The input that the tree is filtered by
+fn file_explorer_filter_view(data: FileExplorerData) -> impl View {
+    let config = data.common.config;
+    let focus = data.common.focus;
+
+    container(
+        TextInputBuilder::new()
+            .build_editor(data.filter_editor.clone())
+            .placeholder(|| "Filter files".to_owned())
+            .on_event_cont(EventListener::PointerDown, move |_| {
+                focus.set(Focus::Panel(PanelKind::FileExplorer));
+            })
+            .style(move |s| {
+                s.width_full()
+                    .border(1.0)
+                    .border_radius(6.0)
+                    .border_color(config.get().color(LapceColor::LAPCE_BORDER))
+            }),
+    )
+    .style(|s| s.width_full().padding(6.0))
+}
+
 fn file_explorer_view(
     data: FileExplorerData,
     source_control: SourceControlData,
 ) -> impl View {
-    let root = data.root;
     let ui_line_height = data.common.ui_line_height;
     let config = data.common.config;
     let naming = data.naming;
     let scroll_to_line = data.scroll_to_line;
-    let select = data.select;
+    let selected = data.selected;
+    let focus = data.common.focus;
+    let dragging = data.common.dragging;
+    let is_dragging_paths = move || {
+        dragging.with_untracked(|dragging| {
+            matches!(dragging, Some(DragContent::FilePaths(_)))
+        })
+    };
     let secondary_click_data = data.clone();
+    let list_data = data.clone();
     let scroll_rect = create_rw_signal(Rect::ZERO);
 
     scroll(
         virtual_stack(
             VirtualDirection::Vertical,
             VirtualItemSize::Fixed(Box::new(move || ui_line_height.get())),
-            move || FileNodeVirtualList::new(root.get(), data.naming.get()),
-            move |node| (node.kind.clone(), node.is_dir, node.open, node.level),
+            move || list_data.view_list(),
+            move |node| {
+                (
+                    node.kind.clone(),
+                    node.is_dir,
+                    node.open,
+                    node.level,
+                    node.label.clone(),
+                    node.has_nested,
+                )
+            },
             move |node| {
                 let level = node.level;
                 let data = data.clone();
@@ -344,6 +389,17 @@ fn file_explorer_view(
                 let kind = node.kind.clone();
                 let open = node.open;
                 let is_dir = node.is_dir;
+                let has_nested = node.has_nested;
+                let nested_data = data.clone();
+                let drag_over = create_rw_signal(false);
+                let is_selected = {
+                    let path = kind.path().map(Path::to_path_buf);
+                    move || {
+                        path.as_ref().is_some_and(|path| {
+                            selected.with(|selected| selected.contains(path))
+                        })
+                    }
+                };
 
                 let view = stack((
                     svg(move || {
@@ -358,7 +414,7 @@ fn file_explorer_view(
                         let config = config.get();
                         let size = config.ui.icon_size() as f32;
 
-                        let color = if is_dir {
+                        let color = if is_dir || has_nested {
                             config.color(LapceColor::LAPCE_ICON_ACTIVE)
                         } else {
                             Color::TRANSPARENT
@@ -367,6 +423,18 @@ fn file_explorer_view(
                             .flex_shrink(0.0)
                             .margin_left(10.0)
                             .color(color)
+                    })
+                    .on_click({
+                        let path = kind.path().map(Path::to_path_buf);
+                        move |_| match &path {
+                            // Show or hide the files nested under it, rather
+                            // than opening the file
+                            Some(path) if has_nested => {
+                                nested_data.toggle_nested(path);
+                                EventPropagation::Stop
+                            }
+                            _ => EventPropagation::Continue,
+                        }
                     }),
                     {
                         let kind = kind.clone();
@@ -410,32 +478,29 @@ fn file_explorer_view(
                     },
                     file_node_text_view(data, node, source_control.clone()),
                 ))
-                .style({
-                    let kind = kind.clone();
-                    move |s| {
-                        s.padding_right(15.0)
-                            .min_width_full()
-                            .padding_left((level * 10) as f32)
-                            .align_items(AlignItems::Center)
-                            .hover(|s| {
-                                s.background(
-                                    config
-                                        .get()
-                                        .color(LapceColor::PANEL_HOVERED_BACKGROUND),
-                                )
-                                .cursor(CursorStyle::Pointer)
-                            })
-                            .apply_if(
-                                select.get().map(|x| x == kind).unwrap_or_default(),
-                                |x| {
-                                    x.background(
-                                        config.get().color(
-                                            LapceColor::PANEL_CURRENT_BACKGROUND,
-                                        ),
-                                    )
-                                },
+                .style(move |s| {
+                    let config = config.get();
+                    s.padding_right(15.0)
+                        .min_width_full()
+                        .padding_left((level * 10) as f32)
+                        .align_items(AlignItems::Center)
+                        .hover(|s| {
+                            s.background(
+                                config.color(LapceColor::PANEL_HOVERED_BACKGROUND),
                             )
-                    }
+                            .cursor(CursorStyle::Pointer)
+                        })
+                        .apply_if(is_selected(), |x| {
+                            x.background(
+                                config.color(LapceColor::PANEL_CURRENT_BACKGROUND),
+                            )
+                        })
+                        .apply_if(drag_over.get(), |s| {
+                            s.background(
+                                config
+                                    .color(LapceColor::EDITOR_DRAG_DROP_BACKGROUND),
+                            )
+                        })
                 })
                 .debug_name("file item");
 
@@ -445,12 +510,19 @@ fn file_explorer_view(
                     let double_click_path = path.clone();
                     let secondary_click_path = path.clone();
                     let aux_click_path = path.clone();
-                    view.on_click_stop({
-                        let kind = kind.clone();
-                        move |_| {
-                            click_data.click(&click_path, config);
-                            select.update(|x| *x = Some(kind.clone()));
-                        }
+                    let drag_path = path.clone();
+                    let drop_path = path.clone();
+                    let drag_data = data.clone();
+                    let drop_data = data.clone();
+                    view.on_click_stop(move |event| {
+                        let mods = match event {
+                            Event::PointerUp(pointer_event) => {
+                                pointer_event.modifiers
+                            }
+                            _ => Modifiers::empty(),
+                        };
+                        focus.set(Focus::Panel(PanelKind::FileExplorer));
+                        click_data.click(&click_path, mods, config);
                     })
                     .on_double_click({
                         move |_| {
@@ -459,7 +531,58 @@ fn file_explorer_view(
                         }
                     })
                     .on_secondary_click_stop(move |_| {
-                        secondary_click_data.secondary_click(&secondary_click_path);
+                        let data = &secondary_click_data;
+                        let path = &secondary_click_path;
+                        if !data.selected.with_untracked(|s| s.contains(path)) {
+                            data.select_only(path);
+                        }
+                        data.secondary_click(path);
+                    })
+                    .draggable()
+                    .on_event_stop(EventListener::DragStart, move |_| {
+                        let paths = drag_data.drag_paths(&drag_path);
+                        dragging.set(Some(DragContent::FilePaths(paths)));
+                    })
+                    .on_event_stop(EventListener::DragEnd, move |_| {
+                        dragging.set(None);
+                    })
+                    .on_event_stop(EventListener::DragEnter, move |_| {
+                        if is_dragging_paths() {
+                            drag_over.set(true);
+                        }
+                    })
+                    .on_event_stop(EventListener::DragLeave, move |_| {
+                        drag_over.set(false);
+                    })
+                    .on_event(EventListener::Drop, move |_| {
+                        drag_over.set(false);
+                        let Some(DragContent::FilePaths(paths)) =
+                            dragging.get_untracked()
+                        else {
+                            return EventPropagation::Continue;
+                        };
+                        dragging.set(None);
+                        // Dropping on a file moves into its directory
+                        let to = if is_dir {
+                            Some(drop_path.clone())
+                        } else {
+                            drop_path.parent().map(Path::to_path_buf)
+                        };
+                        if let Some(to) = to {
+                            drop_data.drop_paths(paths, to);
+                        }
+                        EventPropagation::Stop
+                    })
+                    .dragging_style(move |s| {
+                        let config = config.get();
+                        s.border(1.0)
+                            .border_radius(6.0)
+                            .border_color(config.color(LapceColor::LAPCE_BORDER))
+                            .background(
+                                config
+                                    .color(LapceColor::PANEL_BACKGROUND)
+                                    .with_alpha_factor(0.7),
+                            )
                     })
                     .on_event_stop(
                         EventListener::PointerDown,
@@ -2,6 +2,7 @@
 mod key;
 pub mod keymap;
 mod loader;
+pub mod macros;
 mod press;
 
 use std::{path::PathBuf, rc::Rc, str::FromStr, time::SystemTime};
@@ -21,6 +22,7 @@
     key::KeyInput,
     keymap::{KeyMap, KeyMapPress},
     loader::KeyMapLoader,
+    macros::MacroData,
 };
 use crate::{
     command::{lapce_internal_commands, CommandExecuted, CommandKind, LapceCommand},
@@ -124,6 +126,9 @@ fn receive_char(&self, c: &str) {
 pub enum EventRef<'a> {
     Keyboard(&'a floem::keyboard::KeyEvent),
     Pointer(&'a floem::pointer::PointerInputEvent),
+    /// A key press that didn't come from the windowing system, such as one
+    /// replayed from a keyboard macro.
+    KeyPress(&'a KeyPress),
 }
 
 impl<'a> From<&'a KeyEvent> for EventRef<'a> {
@@ -138,6 +143,12 @@ fn from(ev: &'a PointerInputEvent) -> Self {
     }
 }
 
+impl<'a> From<&'a KeyPress> for EventRef<'a> {
+    fn from(keypress: &'a KeyPress) -> Self {
+        Self::KeyPress(keypress)
+    }
+}
+
 pub struct KeyPressHandle {
     pub handled: bool,
     pub keypress: KeyPress,
@@ -153,6 +164,7 @@ pub struct KeyPressData {
     pub command_keymaps: Rc<IndexMap<String, Vec<KeyMap>>>,
     pub commands_with_keymap: Rc<Vec<KeyMap>>,
     pub commands_without_keymap: Rc<Vec<LapceCommand>>,
+    pub macros: MacroData,
 }
 
 impl KeyPressData {
@@ -167,6 +179,7 @@ pub fn new(cx: Scope, config: &LapceConfig) -> Self {
             commands: Rc::new(lapce_internal_commands()),
             commands_with_keymap: Rc::new(Vec::new()),
             commands_without_keymap: Rc::new(Vec::new()),
+            macros: MacroData::new(cx),
         };
         keypress.load_commands();
         keypress
@@ -254,6 +267,13 @@ fn run_command<T: KeyPressFocus + ?Sized>(
         }
     }
 
+    fn is_workbench_command(&self, command: &str) -> bool {
+        self.commands
+            .get(command)
+            .map(|cmd| matches!(cmd.kind, CommandKind::Workbench(_)))
+            .unwrap_or(false)
+    }
+
     pub fn keypress<'a>(event: impl Into<EventRef<'a>>) -> Option<KeyPress> {
         let event = event.into();
 
@@ -272,6 +292,7 @@ pub fn keypress<'a>(event: impl Into<EventRef<'a>>) -> Option<KeyPress> {
                 key: KeyInput::Pointer(ev.button),
                 mods: ev.modifiers,
             },
+            EventRef::KeyPress(keypress) => keypress.clone(),
         };
         Some(keypress)
     }
@@ -343,6 +364,11 @@ pub fn handle_keymatch<T: KeyPressFocus + ?Sized>(
                 let count = self.count.try_update(|count| count.take()).unwrap();
                 let handled = self.run_command(command, count, mods, focus)
                     == CommandExecuted::Yes;
+                if !handled && self.is_workbench_command(command) {
+                    // The window tab runs workbench commands after the focus
+                    // declines them, so keep the count for it.
+                    self.count.set(count);
+                }
                 return KeyPressHandle {
                     handled,
                     keymatch,
@@ -55,6 +55,8 @@ pub enum Condition {
     ModalFocus,
     #[strum(serialize = "in_snippet")]
     InSnippet,
+    #[strum(serialize = "operator_pending")]
+    OperatorPending,
     #[strum(serialize = "terminal_focus")]
     TerminalFocus,
     #[strum(serialize = "source_control_focus")]
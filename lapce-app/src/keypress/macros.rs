@@ -0,0 +1,246 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use floem::{
+    keyboard::{Key, KeyCode, KeyLocation, Modifiers, PhysicalKey},
+    reactive::{use_context, RwSignal, Scope, SignalGet, SignalUpdate, SignalWith},
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    key::KeyInput,
+    keymap::{KeyMapKey, KeyMapPress},
+    KeyPress,
+};
+use crate::db::LapceDb;
+
+/// How deep a macro may replay other macros (or itself) before giving up.
+const MAX_REPLAY_DEPTH: usize = 32;
+
+pub type KeyboardMacros = BTreeMap<char, Vec<MacroKeyPress>>;
+
+/// A recorded key press, stored in the same notation as the keymaps so that it
+/// survives being written to disk.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MacroKeyPress {
+    /// The key press as written in a keymap, e.g. `Ctrl+Shift+p`
+    pub key: String,
+    /// The text the key produced, when it differs from what `key` would give
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+impl MacroKeyPress {
+    pub fn from_keypress(keypress: &KeyPress) -> Option<Self> {
+        let KeyInput::Keyboard { logical, .. } = &keypress.key else {
+            return None;
+        };
+        let press = keypress.keymap_press()?;
+        if press.is_modifiers() {
+            return None;
+        }
+
+        let text = match logical {
+            Key::Character(c) => Some(c.to_string()),
+            _ => None,
+        };
+        let press = match press.key {
+            KeyMapKey::Logical(_) => press,
+            // Physical keys don't round trip through the keymap notation, but
+            // the text they produced is all that is needed to replay them.
+            KeyMapKey::Physical(_) => KeyMapPress {
+                key: KeyMapKey::Logical(Key::Character(text.clone()?.into())),
+                mods: press.mods,
+            },
+            KeyMapKey::Pointer(_) => return None,
+        };
+        let key = press.to_string();
+        let text = text.filter(|text| text != &key);
+
+        Some(Self { key, text })
+    }
+
+    pub fn to_keypress(&self) -> Option<KeyPress> {
+        let press = KeyMapPress::parse(&self.key).into_iter().next()?;
+        let KeyMapKey::Logical(key_without_modifiers) = press.key else {
+            return None;
+        };
+        let logical = match &self.text {
+            Some(text) => Key::Character(text.as_str().into()),
+            None => key_without_modifiers.clone(),
+        };
+
+        Some(KeyPress {
+            key: KeyInput::Keyboard {
+                physical: PhysicalKey::Code(KeyCode::Fn),
+                logical,
+                location: KeyLocation::Standard,
+                key_without_modifiers,
+                repeat: false,
+            },
+            mods: press.mods,
+        })
+    }
+}
+
+/// What the next typed character names a register for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MacroRegisterPrompt {
+    Record,
+    Replay { count: Option<usize> },
+}
+
+#[derive(Clone, Debug)]
+pub struct MacroRecording {
+    pub register: char,
+    pub keys: Vec<MacroKeyPress>,
+    /// Where the keys typed into the currently open palette start, so that
+    /// stopping the recording from the palette doesn't record that as well.
+    palette_start: Option<usize>,
+}
+
+#[derive(Clone, Debug)]
+pub struct MacroData {
+    pub macros: RwSignal<KeyboardMacros>,
+    pub recording: RwSignal<Option<MacroRecording>>,
+    pub prompt: RwSignal<Option<MacroRegisterPrompt>>,
+    pub last_register: RwSignal<Option<char>>,
+    replay_depth: RwSignal<usize>,
+}
+
+impl MacroData {
+    pub fn new(cx: Scope) -> Self {
+        let macros = use_context::<Arc<LapceDb>>()
+            .and_then(|db| db.get_keyboard_macros().ok())
+            .unwrap_or_default();
+        Self {
+            macros: cx.create_rw_signal(macros),
+            recording: cx.create_rw_signal(None),
+            prompt: cx.create_rw_signal(None),
+            last_register: cx.create_rw_signal(None),
+            replay_depth: cx.create_rw_signal(0),
+        }
+    }
+
+    /// The register a key press names, if it can name one. Uppercase letters
+    /// name the same register as their lowercase counterpart.
+    pub fn register(keypress: &KeyPress) -> Option<char> {
+        let KeyInput::Keyboard {
+            logical: Key::Character(c),
+            ..
+        } = &keypress.key
+        else {
+            return None;
+        };
+        let mut mods = keypress.mods;
+        mods.set(Modifiers::SHIFT, false);
+        if !mods.is_empty() {
+            return None;
+        }
+        let mut chars = c.chars();
+        let c = chars.next()?;
+        (chars.next().is_none() && (c.is_ascii_alphanumeric() || c == '@'))
+            .then_some(c)
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.with_untracked(|r| r.is_some())
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        self.replay_depth.get_untracked() > 0
+    }
+
+    /// Start recording into `register`. An uppercase register appends to the
+    /// macro already in its lowercase counterpart.
+    pub fn start_recording(&self, register: char) {
+        if register == '@' {
+            return;
+        }
+        let keys = if register.is_ascii_uppercase() {
+            self.macros.with_untracked(|macros| {
+                macros
+                    .get(&register.to_ascii_lowercase())
+                    .cloned()
+                    .unwrap_or_default()
+            })
+        } else {
+            Vec::new()
+        };
+        self.recording.set(Some(MacroRecording {
+            register: register.to_ascii_lowercase(),
+            keys,
+            palette_start: None,
+        }));
+    }
+
+    /// Stop recording and store the macro in its register.
+    pub fn stop_recording(&self) {
+        let Some(mut recording) = self
+            .recording
+            .try_update(|recording| recording.take())
+            .flatten()
+        else {
+            return;
+        };
+        if let Some(start) = recording.palette_start {
+            recording.keys.truncate(start);
+        }
+        self.last_register.set(Some(recording.register));
+        self.macros.update(|macros| {
+            macros.insert(recording.register, recording.keys);
+        });
+        self.save();
+    }
+
+    /// Add a key press to the macro being recorded. `in_palette` is whether
+    /// the palette has focus after the key was handled.
+    pub fn record(&self, keypress: &KeyPress, in_palette: bool) {
+        let Some(key) = MacroKeyPress::from_keypress(keypress) else {
+            return;
+        };
+        self.recording.update(|recording| {
+            if let Some(recording) = recording.as_mut() {
+                recording.keys.push(key);
+                if in_palette {
+                    recording
+                        .palette_start
+                        .get_or_insert(recording.keys.len() - 1);
+                } else {
+                    recording.palette_start = None;
+                }
+            }
+        });
+    }
+
+    /// The key presses to replay for `register`, where `@` is the last macro
+    /// that was recorded or replayed.
+    pub fn replay_keys(&self, register: char) -> Option<(char, Vec<KeyPress>)> {
+        let register = if register == '@' {
+            self.last_register.get_untracked()?
+        } else {
+            register.to_ascii_lowercase()
+        };
+        let keys = self.macros.with_untracked(|macros| {
+            macros
+                .get(&register)
+                .map(|keys| keys.iter().filter_map(|k| k.to_keypress()).collect())
+        })?;
+        Some((register, keys))
+    }
+
+    /// Run `f` as a replay, unless macros are already nested too deeply.
+    pub fn replay(&self, register: char, f: impl FnOnce()) {
+        if self.replay_depth.get_untracked() >= MAX_REPLAY_DEPTH {
+            return;
+        }
+        self.last_register.set(Some(register));
+        self.replay_depth.update(|depth| *depth += 1);
+        f();
+        self.replay_depth.update(|depth| *depth -= 1);
+    }
+
+    fn save(&self) {
+        let db: Arc<LapceDb> = use_context().unwrap();
+        db.save_keyboard_macros(self.macros.get_untracked());
+    }
+}
@@ -1,4 +1,4 @@
-use floem::keyboard::Modifiers;
+use floem::keyboard::{Key, KeyCode, KeyLocation, Modifiers, NamedKey, PhysicalKey};
 
 use super::{key::KeyInput, keymap::KeyMapPress};
 
@@ -15,4 +15,61 @@ pub fn keymap_press(&self) -> Option<KeyMapPress> {
             mods: self.mods,
         })
     }
+
+    /// The key press that types `c`, assuming a US layout for the symbols
+    /// that need shift, so that keymaps like `shift+4` match `$`.
+    pub fn from_char(c: char) -> Self {
+        let (unshifted, shift) = match c {
+            '\n' => return Self::named(NamedKey::Enter),
+            '\t' => return Self::named(NamedKey::Tab),
+            c if c.is_ascii_uppercase() => (c.to_ascii_lowercase(), true),
+            '~' => ('`', true),
+            '!' => ('1', true),
+            '@' => ('2', true),
+            '#' => ('3', true),
+            '$' => ('4', true),
+            '%' => ('5', true),
+            '^' => ('6', true),
+            '&' => ('7', true),
+            '*' => ('8', true),
+            '(' => ('9', true),
+            ')' => ('0', true),
+            '_' => ('-', true),
+            '+' => ('=', true),
+            '{' => ('[', true),
+            '}' => (']', true),
+            '|' => ('\\', true),
+            ':' => (';', true),
+            '"' => ('\'', true),
+            '<' => (',', true),
+            '>' => ('.', true),
+            '?' => ('/', true),
+            c => (c, false),
+        };
+        let mut mods = Modifiers::empty();
+        mods.set(Modifiers::SHIFT, shift);
+        Self {
+            key: KeyInput::Keyboard {
+                physical: PhysicalKey::Code(KeyCode::Fn),
+                logical: Key::Character(c.to_string().into()),
+                location: KeyLocation::Standard,
+                key_without_modifiers: Key::Character(unshifted.to_string().into()),
+                repeat: false,
+            },
+            mods,
+        }
+    }
+
+    fn named(key: NamedKey) -> Self {
+        Self {
+            key: KeyInput::Keyboard {
+                physical: PhysicalKey::Code(KeyCode::Fn),
+                logical: Key::Named(key),
+                location: KeyLocation::Standard,
+                key_without_modifiers: Key::Named(key),
+                repeat: false,
+            },
+            mods: Modifiers::empty(),
+        }
+    }
 }
@@ -22,13 +22,16 @@
 pub mod keymap;
 pub mod keypress;
 pub mod listener;
+pub mod local_history;
 pub mod lsp;
 pub mod main_split;
 pub mod markdown;
 pub mod palette;
 pub mod panel;
 pub mod plugin;
+pub mod ports;
 pub mod proxy;
+pub mod register;
 pub mod rename;
 pub mod settings;
 pub mod snippet;
@@ -37,6 +40,7 @@
 pub mod terminal;
 pub mod text_area;
 pub mod text_input;
+pub mod timeline;
 pub mod title;
 pub mod tracing;
 pub mod update;
@@ -0,0 +1,279 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+use chrono::{Local, TimeZone};
+use lapce_core::directory::Directory;
+use parking_lot::Mutex;
+
+use crate::{
+    db::{doc_path_name, workspace_folder_name},
+    workspace::LapceWorkspace,
+};
+
+/// How much of the local history is kept
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LocalHistoryLimits {
+    /// The most saved versions kept of a file, where `0` keeps none
+    pub max_entries: usize,
+    /// How long saved versions are kept
+    pub max_age: Duration,
+    /// The size in bytes above which a saved version isn't kept
+    pub max_file_size: usize,
+    /// The size in bytes that the versions of all the files of the workspace
+    /// are kept under, by dropping the oldest ones
+    pub max_total_size: usize,
+}
+
+/// A saved version of a file in the local history
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LocalHistoryEntry {
+    /// When it was saved, in milliseconds since the Unix epoch, which also
+    /// tells it apart from the other versions of the file
+    pub time: u64,
+}
+
+/// Held while the local history is written to, as the files of different
+/// documents are saved on threads of their own, and pruning the history
+/// while another version is written would remove it from under the write.
+static WRITE_LOCK: Mutex<()> = parking_lot::const_mutex(());
+
+/// The versions of the files of a workspace as they were saved, kept in the
+/// cache directory so that they can be compared with and restored.
+#[derive(Clone, Debug)]
+pub struct LocalHistory {
+    folder: PathBuf,
+    /// The time now, in milliseconds since the Unix epoch
+    clock: fn() -> u64,
+}
+
+impl LocalHistory {
+    pub fn new(workspace: &LapceWorkspace) -> Option<Self> {
+        let folder = Directory::cache_directory()?
+            .join("local-history")
+            .join(workspace_folder_name(workspace));
+        Some(Self::from_folder(folder))
+    }
+
+    pub fn from_folder(folder: PathBuf) -> Self {
+        Self { folder, clock: now }
+    }
+
+    /// The folder the versions of the file are in, one file each named by
+    /// the time it was saved.
+    fn file_folder(&self, path: &Path) -> PathBuf {
+        self.folder.join(doc_path_name(path))
+    }
+
+    /// Keep `content` as the latest version of the file, unless it's the same
+    /// as the one before, and drop the versions of the workspace beyond the
+    /// limits.
+    pub fn save(
+        &self,
+        path: &Path,
+        content: &str,
+        limits: &LocalHistoryLimits,
+    ) -> Result<()> {
+        if limits.max_entries == 0 || content.len() > limits.max_file_size {
+            return Ok(());
+        }
+
+        let _lock = WRITE_LOCK.lock();
+        let folder = self.file_folder(path);
+        let latest = self.entries(path).first().copied();
+        if let Some(latest) = latest {
+            let latest = fs::read(folder.join(latest.time.to_string()))?;
+            if latest == content.as_bytes() {
+                return Ok(());
+            }
+        }
+
+        fs::create_dir_all(&folder)?;
+        // The path the versions are of, for anyone looking in the folder
+        fs::write(folder.join("path"), path.to_string_lossy().as_bytes())?;
+        let now = (self.clock)();
+        let time = latest.map_or(now, |latest| now.max(latest.time + 1));
+        fs::write(folder.join(time.to_string()), content)?;
+
+        self.prune(limits)
+    }
+
+    /// Remove the versions of every file that are too many or too old, then
+    /// the oldest of the rest until they fit in the total size, along with
+    /// the folders of the files that have no versions left.
+    pub fn prune_all(&self, limits: &LocalHistoryLimits) -> Result<()> {
+        let _lock = WRITE_LOCK.lock();
+        self.prune(limits)
+    }
+
+    /// Like [`Self::prune_all`], with the write lock already held. What's
+    /// already gone, like when another Lapce pruned it, is passed over.
+    fn prune(&self, limits: &LocalHistoryLimits) -> Result<()> {
+        let Ok(dir) = fs::read_dir(&self.folder) else {
+            return Ok(());
+        };
+        let folders = dir
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir())
+            .collect::<Vec<_>>();
+
+        let oldest =
+            (self.clock)().saturating_sub(limits.max_age.as_millis() as u64);
+        // The versions that are kept, with their files and sizes
+        let mut kept = Vec::new();
+        for folder in &folders {
+            for (i, entry) in folder_entries(folder).iter().enumerate() {
+                let file = folder.join(entry.time.to_string());
+                if i >= limits.max_entries || entry.time < oldest {
+                    ignore_not_found(fs::remove_file(&file))?;
+                } else {
+                    match fs::metadata(&file) {
+                        Ok(metadata) => {
+                            kept.push((entry.time, file, metadata.len()))
+                        }
+                        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                        Err(err) => return Err(err.into()),
+                    }
+                }
+            }
+        }
+
+        let mut total_size = kept.iter().map(|(_, _, size)| size).sum::<u64>();
+        kept.sort_by_key(|(time, _, _)| *time);
+        for (_, file, size) in kept {
+            if total_size <= limits.max_total_size as u64 {
+                break;
+            }
+            ignore_not_found(fs::remove_file(&file))?;
+            total_size -= size;
+        }
+
+        for folder in &folders {
+            if folder_entries(folder).is_empty() {
+                ignore_not_found(fs::remove_dir_all(folder))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The saved versions of the file, newest first.
+    pub fn entries(&self, path: &Path) -> Vec<LocalHistoryEntry> {
+        folder_entries(&self.file_folder(path))
+    }
+
+    /// The content of the file as it was saved at `time`.
+    pub fn read(&self, path: &Path, time: u64) -> Result<String> {
+        let content =
+            fs::read_to_string(self.file_folder(path).join(time.to_string()))?;
+        Ok(content)
+    }
+}
+
+/// The versions in the folder of a file, newest first.
+fn folder_entries(folder: &Path) -> Vec<LocalHistoryEntry> {
+    let Ok(dir) = fs::read_dir(folder) else {
+        return Vec::new();
+    };
+    let mut entries = dir
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse().ok())
+        .map(|time| LocalHistoryEntry { time })
+        .collect::<Vec<_>>();
+    entries.sort_by(|a, b| b.time.cmp(&a.time));
+    entries
+}
+
+fn ignore_not_found(result: io::Result<()>) -> io::Result<()> {
+    match result {
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+/// Show a time, in milliseconds since the Unix epoch, in the local time zone.
+pub fn format_time(millis: i64) -> String {
+    Local
+        .timestamp_millis_opt(millis)
+        .single()
+        .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_default()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_millis() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use super::*;
+
+    static CLOCK: AtomicU64 = AtomicU64::new(1_000_000);
+
+    fn clock() -> u64 {
+        CLOCK.load(Ordering::SeqCst)
+    }
+
+    #[test]
+    fn test_local_history() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = LocalHistory {
+            folder: dir.path().to_path_buf(),
+            clock,
+        };
+        let path = Path::new("/workspace/src/main.rs");
+        let limits = LocalHistoryLimits {
+            max_entries: 2,
+            max_age: Duration::from_secs(60 * 60),
+            max_file_size: 10,
+            max_total_size: 10,
+        };
+
+        history.save(path, "one", &limits).unwrap();
+        // The same content as the latest version isn't kept again
+        history.save(path, "one", &limits).unwrap();
+        assert_eq!(history.entries(path).len(), 1);
+
+        history.save(path, "two", &limits).unwrap();
+        history.save(path, "three", &limits).unwrap();
+        let entries = history.entries(path);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(history.read(path, entries[0].time).unwrap(), "three");
+        assert_eq!(history.read(path, entries[1].time).unwrap(), "two");
+
+        // Files larger than the limit aren't kept
+        history.save(path, "more than ten bytes", &limits).unwrap();
+        assert_eq!(history.entries(path), entries);
+
+        // Saving another file drops the oldest versions of the workspace
+        // until they all fit in the total size
+        let other = Path::new("/workspace/other.rs");
+        // Versions saved in the same millisecond are told apart by adding
+        // one to their times, so move the clock past them
+        CLOCK.fetch_add(10, Ordering::SeqCst);
+        history.save(other, "four", &limits).unwrap();
+        assert_eq!(history.entries(path), entries[..1]);
+        assert_eq!(history.entries(other).len(), 1);
+
+        // The folder of a file is removed with its last version
+        let limits = LocalHistoryLimits {
+            max_total_size: 4,
+            ..limits
+        };
+        history.prune_all(&limits).unwrap();
+        assert!(history.entries(path).is_empty());
+        assert!(!history.file_folder(path).exists());
+        assert_eq!(history.entries(other).len(), 1);
+
+        assert!(history
+            .entries(Path::new("/workspace/another.rs"))
+            .is_empty());
+    }
+}
@@ -22,7 +22,7 @@
     buffer::BufferId,
     core::FileChanged,
     plugin::{PluginId, VoltID},
-    proxy::ProxyResponse,
+    proxy::{ProxyResponse, ProxyRpcHandler},
 };
 use lapce_xi_rope::{spans::SpansBuilder, Rope};
 use lsp_types::{
@@ -37,7 +37,10 @@
     alert::AlertButton,
     code_lens::CodeLensData,
     command::InternalCommand,
-    doc::{DiagnosticData, Doc, DocContent, DocHistory, EditorDiagnostic},
+    doc::{
+        load_history_content, DiagnosticData, Doc, DocContent, DocHistory,
+        EditorDiagnostic,
+    },
     editor::{
         diff::DiffEditorData,
         location::{EditorLocation, EditorPosition},
@@ -386,6 +389,40 @@ pub fn with_editors_untracked<O>(
     ) -> O {
         self.0.with_untracked(f)
     }
+
+    /// Point the editors of the file `from`, or of the files in it when it
+    /// is a directory, at their new paths under `to`.
+    pub fn path_moved(&self, from: &Path, to: &Path) {
+        let moved_contents: Vec<_> = self.with_editors_untracked(|editors| {
+            editors
+                .values()
+                .map(|editor| editor.doc().content)
+                .filter(|content| {
+                    content.with_untracked(|content| match content {
+                        DocContent::File { path, .. }
+                        | DocContent::Hex { path, .. } => path.starts_with(from),
+                        _ => false,
+                    })
+                })
+                .collect()
+        });
+
+        for content in moved_contents {
+            content.update(|content| {
+                if let DocContent::File { path, .. } | DocContent::Hex { path, .. } =
+                    content
+                {
+                    if let Ok(suffix) = path.strip_prefix(from) {
+                        *path = if suffix.as_os_str().is_empty() {
+                            to.to_path_buf()
+                        } else {
+                            to.join(suffix)
+                        };
+                    }
+                }
+            });
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -641,30 +678,69 @@ pub fn get_doc(
             {
                 let doc = doc.clone();
                 let local_doc = doc.clone();
+                let proxy = self.common.proxy.clone();
+                let chunk_path = path.clone();
                 let send = create_ext_action(cx, move |result| {
                     if let Ok(ProxyResponse::NewBufferResponse {
                         content,
                         read_only,
+                        encoding,
+                        large,
+                        binary,
+                        next_offset,
                     }) = result
                     {
-                        local_doc.init_content(Rope::from(content));
-                        if read_only {
-                            local_doc.content.update(|content| {
-                                if let DocContent::File { read_only, .. } = content {
-                                    *read_only = true;
-                                }
-                            });
-                        } else if let Some(unsaved) = unsaved {
-                            local_doc.reload(Rope::from(unsaved), false);
+                        local_doc.encoding.set((!binary).then_some(encoding));
+                        local_doc.large.set(large || binary);
+                        let init = move |content: Rope| {
+                            local_doc.init_content(content);
+                            if binary {
+                                // Binary files are edited as a hex dump, unless the
+                                // dump is cut short
+                                local_doc.content.update(|content| {
+                                    if let DocContent::File { path, .. } = content {
+                                        *content = DocContent::Hex {
+                                            path: path.clone(),
+                                            read_only: read_only || large,
+                                        };
+                                    }
+                                });
+                            } else if read_only || large {
+                                local_doc.content.update(|content| {
+                                    if let DocContent::File { read_only, .. } =
+                                        content
+                                    {
+                                        *read_only = true;
+                                    }
+                                });
+                            } else if let Some(unsaved) = unsaved {
+                                local_doc.reload(Rope::from(unsaved), false);
+                            }
+                        };
+                        match next_offset {
+                            Some(offset) => load_buffer_chunks(
+                                cx,
+                                proxy,
+                                chunk_path,
+                                Rope::from(content),
+                                offset,
+                                init,
+                            ),
+                            None => init(Rope::from(content)),
                         }
                     }
                 });
 
-                self.common
-                    .proxy
-                    .new_buffer(doc.buffer_id, path, move |result| {
+                let large_file_size =
+                    self.common.config.get_untracked().editor.large_file_size();
+                self.common.proxy.new_buffer(
+                    doc.buffer_id,
+                    path,
+                    large_file_size,
+                    move |result| {
                         send(result);
-                    });
+                    },
+                );
             }
             doc.get_code_lens();
             doc.get_folding_range();
@@ -697,31 +773,24 @@ pub fn go_to_location(
     }
 
     pub fn open_file_changes(&self, path: PathBuf) {
-        let (right, _) = self.get_doc(path.clone(), None);
+        self.open_history_diff(DocHistory {
+            path,
+            version: "head".to_string(),
+        });
+    }
+
+    /// Compare an old version of a file with its current content.
+    pub fn open_history_diff(&self, history: DocHistory) {
+        let (right, _) = self.get_doc(history.path.clone(), None);
         let left = Doc::new_history(
             self.scope,
-            DocContent::History(DocHistory {
-                path: path.clone(),
-                version: "head".to_string(),
-            }),
+            DocContent::History(history.clone()),
             self.editors,
             self.common.clone(),
         );
+        left.load_history(&history);
         let left = Rc::new(left);
 
-        let send = {
-            let left = left.clone();
-            create_ext_action(self.scope, move |result| {
-                if let Ok(ProxyResponse::BufferHeadResponse { content, .. }) = result
-                {
-                    left.init_content(Rope::from(content));
-                }
-            })
-        };
-        self.common.proxy.get_buffer_head(path, move |result| {
-            send(result);
-        });
-
         self.get_editor_tab_child(
             EditorTabChildSource::DiffEditor { left, right },
             false,
@@ -729,6 +798,45 @@ pub fn open_file_changes(&self, path: PathBuf) {
         );
     }
 
+    /// Replace the content of the file with an old version of it, and save
+    /// it.
+    pub fn restore_history(&self, history: DocHistory) {
+        let (doc, _) = self.get_doc(history.path.clone(), None);
+        load_history_content(self.scope, &self.common, &history, move |content| {
+            doc.reload(Rope::from(content), false);
+            doc.save(|| {});
+        });
+    }
+
+    /// The old version of a file that the active editor tab shows, either
+    /// compared with the current content or on its own.
+    pub fn active_history(&self) -> Option<DocHistory> {
+        let active_editor_tab = self.active_editor_tab.get_untracked()?;
+        let editor_tab = self.editor_tabs.with_untracked(|editor_tabs| {
+            editor_tabs.get(&active_editor_tab).copied()
+        })?;
+        let (_, _, child) = editor_tab.with_untracked(|editor_tab| {
+            editor_tab.children.get(editor_tab.active).cloned()
+        })?;
+        let doc = match child {
+            EditorTabChild::Editor(editor_id) => {
+                self.editors.editor_untracked(editor_id)?.doc()
+            }
+            EditorTabChild::DiffEditor(diff_editor_id) => self
+                .diff_editors
+                .with_untracked(|diff_editors| {
+                    diff_editors.get(&diff_editor_id).cloned()
+                })?
+                .left
+                .doc(),
+            _ => return None,
+        };
+        match doc.content.get_untracked() {
+            DocContent::History(history) => Some(history),
+            _ => None,
+        }
+    }
+
     pub fn open_diff_files(&self, left_path: PathBuf, right_path: PathBuf) {
         let [left, right] =
             [left_path, right_path].map(|path| self.get_doc(path, None).0);
@@ -1897,7 +2005,8 @@ fn editor_tab_child_close_warning(
                     });
                     if !exists {
                         return match doc_content {
-                            DocContent::File { path, .. } => Some((
+                            DocContent::File { path, .. }
+                            | DocContent::Hex { path, .. } => Some((
                                 path.file_name()?.to_str()?.to_string(),
                                 doc,
                                 editor,
@@ -2061,7 +2170,7 @@ pub fn editor_tab_child_close(
                             action: save_action,
                         })
                     }
-                    DocContent::File { .. } => {
+                    DocContent::File { .. } | DocContent::Hex { .. } => {
                         let editor = editor.clone();
                         let editors = self.editors;
                         let editor_id = editor.id();
@@ -2482,6 +2591,7 @@ pub fn save_as(&self, doc: Rc<Doc>, path: PathBuf, action: impl Fn() + 'static)
             DocContent::Local => {}
             DocContent::File { .. } => {}
             DocContent::History(_) => {}
+            DocContent::Hex { .. } => {}
         }
     }
 
@@ -2537,6 +2647,7 @@ pub fn save_as2(
             DocContent::Local => {}
             DocContent::File { .. } => {}
             DocContent::History(_) => {}
+            DocContent::Hex { .. } => {}
         }
     }
 
@@ -2992,6 +3103,42 @@ pub fn get_active_editor(&self) -> Option<EditorData> {
     }
 }
 
+/// Read the rest of a large file a chunk at a time after `content`, and then
+/// give the whole of it to `f`.
+fn load_buffer_chunks(
+    cx: Scope,
+    proxy: ProxyRpcHandler,
+    path: PathBuf,
+    content: Rope,
+    offset: usize,
+    f: impl FnOnce(Rope) + 'static,
+) {
+    let send = {
+        let proxy = proxy.clone();
+        let path = path.clone();
+        create_ext_action(cx, move |result| {
+            if let Ok(ProxyResponse::BufferChunkResponse {
+                content: chunk,
+                next_offset,
+            }) = result
+            {
+                let mut content = content;
+                let len = content.len();
+                content.edit(len..len, chunk);
+                match next_offset {
+                    Some(offset) => {
+                        load_buffer_chunks(cx, proxy, path, content, offset, f)
+                    }
+                    None => f(content),
+                }
+            }
+        })
+    };
+    proxy.get_buffer_chunk(path, offset, move |result| {
+        send(result);
+    });
+}
+
 fn workspace_edits(edit: &WorkspaceEdit) -> Option<HashMap<Url, Vec<TextEdit>>> {
     if let Some(changes) = edit.changes.as_ref() {
         return Some(changes.clone());
@@ -23,11 +23,14 @@
 use im::Vector;
 use itertools::Itertools;
 use lapce_core::{
-    buffer::rope_text::RopeText, command::FocusCommand, language::LapceLanguage,
-    line_ending::LineEnding, mode::Mode, movement::Movement, selection::Selection,
-    syntax::Syntax,
+    buffer::rope_text::RopeText, command::FocusCommand, hex,
+    language::LapceLanguage, line_ending::LineEnding, mode::Mode,
+    movement::Movement, selection::Selection, syntax::Syntax,
+};
+use lapce_rpc::{
+    buffer::FILE_ENCODINGS,
+    proxy::{ProxyResponse, RemotePrompt},
 };
-use lapce_rpc::proxy::ProxyResponse;
 use lapce_xi_rope::Rope;
 use lsp_types::{DocumentSymbol, DocumentSymbolResponse};
 use nucleo::Utf32Str;
@@ -35,6 +38,7 @@
 use tracing::error;
 
 use self::{
+    ex_command::EX_COMMAND_HISTORY_SIZE,
     item::{PaletteItem, PaletteItemContent},
     kind::PaletteKind,
 };
@@ -51,11 +55,15 @@
     keypress::{condition::Condition, KeyPressData, KeyPressFocus},
     lsp::path_from_url,
     main_split::MainSplitData,
+    proxy::ssh_config_hosts,
     source_control::SourceControlData,
     window_tab::{CommonData, Focus},
-    workspace::{LapceWorkspace, LapceWorkspaceType, SshHost},
+    workspace::{
+        relative_to_roots, CommandHost, LapceWorkspace, LapceWorkspaceType, SshHost,
+    },
 };
 
+pub mod ex_command;
 pub mod item;
 pub mod kind;
 
@@ -107,6 +115,10 @@ pub struct PaletteData {
     pub source_control: SourceControlData,
     pub common: Rc<CommonData>,
     left_diff_path: RwSignal<Option<PathBuf>>,
+    /// The prompt from the connection to the remote that is being answered
+    pub remote_prompt: RwSignal<Option<RemotePrompt>>,
+    /// The command lines that were run, most recent first
+    ex_command_history: RwSignal<Vec<String>>,
 }
 
 impl std::fmt::Debug for PaletteData {
@@ -214,6 +226,12 @@ pub fn new(
 
         let clicked_index = cx.create_rw_signal(Option::<usize>::None);
         let left_diff_path = cx.create_rw_signal(None);
+        let remote_prompt = cx.create_rw_signal(None);
+        let ex_command_history = cx.create_rw_signal(
+            use_context::<Arc<LapceDb>>()
+                .and_then(|db| db.get_ex_command_history(&workspace).ok())
+                .unwrap_or_default(),
+        );
 
         let palette = Self {
             run_id_counter,
@@ -238,6 +256,8 @@ pub fn new(
             source_control,
             common,
             left_diff_path,
+            remote_prompt,
+            ex_command_history,
         };
 
         {
@@ -330,31 +350,65 @@ pub fn new(
 
     /// Start and focus the palette for the given kind.
     pub fn run(&self, kind: PaletteKind) {
+        if kind != PaletteKind::RemotePrompt {
+            self.answer_remote_prompt(None);
+        }
         self.common.focus.set(Focus::Palette);
         self.status.set(PaletteStatus::Started);
         let symbol = kind.symbol();
         self.kind.set(kind);
         // Refresh the palette input with only the symbol prefix, losing old content.
-        self.input_editor.doc().reload(Rope::from(symbol), true);
+        self.set_input(symbol);
+    }
+
+    /// Replace what is typed in the palette, with the cursor at its end.
+    pub fn set_input(&self, input: &str) {
+        self.input_editor.doc().reload(Rope::from(input), true);
         self.input_editor
             .cursor()
-            .update(|cursor| cursor.set_insert(Selection::caret(symbol.len())));
+            .update(|cursor| cursor.set_insert(Selection::caret(input.len())));
     }
 
     /// Get the placeholder text to use in the palette input field.
-    pub fn placeholder_text(&self) -> &'static str {
+    pub fn placeholder_text(&self) -> String {
         match self.kind.get() {
             PaletteKind::SshHost => {
                 "Type [user@]host or select a previously connected workspace below"
+                    .to_string()
+            }
+            PaletteKind::CommandHost => {
+                "Type remote-command:name, like podman:devbox, or select a previously connected workspace below".to_string()
             }
+            PaletteKind::RemotePrompt => self.remote_prompt.with(|prompt| {
+                prompt
+                    .as_ref()
+                    .map(|prompt| prompt.prompt.lines().join(" "))
+                    .unwrap_or_default()
+            }),
             PaletteKind::DiffFiles => {
                 if self.left_diff_path.with(Option::is_some) {
-                    "Select right file"
+                    "Select right file".to_string()
                 } else {
-                    "Seleft left file"
+                    "Seleft left file".to_string()
                 }
             }
-            _ => "",
+            _ => String::new(),
+        }
+    }
+
+    /// Answer the prompt from the connection to the remote, if there is one.
+    pub fn answer_remote_prompt(&self, answer: Option<String>) {
+        if let Some(prompt) = self
+            .remote_prompt
+            .try_update(|prompt| prompt.take())
+            .flatten()
+        {
+            self.common
+                .internal_command
+                .send(InternalCommand::AnswerRemotePrompt {
+                    id: prompt.id,
+                    answer,
+                });
         }
     }
 
@@ -393,6 +447,9 @@ fn run_inner(&self, kind: PaletteKind) {
             PaletteKind::SshHost => {
                 self.get_ssh_hosts();
             }
+            PaletteKind::CommandHost => {
+                self.get_command_hosts();
+            }
             #[cfg(windows)]
             PaletteKind::WslHost => {
                 self.get_wsl_hosts();
@@ -412,10 +469,25 @@ fn run_inner(&self, kind: PaletteKind) {
             PaletteKind::LineEnding => {
                 self.get_line_endings();
             }
+            PaletteKind::ReopenWithEncoding => {
+                self.get_encodings(false);
+            }
+            PaletteKind::SaveWithEncoding => {
+                self.get_encodings(true);
+            }
+            PaletteKind::HexGotoOffset
+            | PaletteKind::HexFindBytes
+            | PaletteKind::RemotePrompt => {
+                // What is typed is all there is to pick
+                self.items.set(im::Vector::new());
+            }
             PaletteKind::SCMReferences => {
                 self.get_scm_references();
             }
             PaletteKind::TerminalProfile => self.get_terminal_profiles(),
+            PaletteKind::KeyboardMacro => self.get_keyboard_macros(),
+            PaletteKind::PasteHistory => self.get_paste_history(),
+            PaletteKind::ExCommand => self.get_ex_command_history(),
         }
     }
 
@@ -465,23 +537,16 @@ fn get_palette_help_and_file(&self) {
     // and prepend items if prepend is some
     // e.g. help_and_file
     fn get_files_and_prepend(&self, prepend: Option<im::Vector<PaletteItem>>) {
-        let workspace = self.workspace.clone();
+        let workspace_roots = self.common.workspace_roots;
         let set_items = self.items.write_only();
         let send =
             create_ext_action(self.common.scope, move |items: Vec<PathBuf>| {
+                let roots = workspace_roots.get_untracked();
                 let items = items
                     .into_iter()
                     .map(|full_path| {
                         // Strip the workspace prefix off the path, to avoid clutter
-                        let path =
-                            if let Some(workspace_path) = workspace.path.as_ref() {
-                                full_path
-                                    .strip_prefix(workspace_path)
-                                    .unwrap_or(&full_path)
-                                    .to_path_buf()
-                            } else {
-                                full_path.clone()
-                            };
+                        let path = relative_to_roots(&roots, &full_path);
                         let filter_text = path.to_string_lossy().into_owned();
                         PaletteItem {
                             content: PaletteItemContent::File { path, full_path },
@@ -612,6 +677,9 @@ fn get_workspaces(&self) {
                     LapceWorkspaceType::RemoteSSH(remote) => {
                         format!("[{remote}] {text}")
                     }
+                    LapceWorkspaceType::RemoteCommand(remote) => {
+                        format!("[{remote}] {text}")
+                    }
                     #[cfg(windows)]
                     LapceWorkspaceType::RemoteWSL(remote) => {
                         format!("[{remote}] {text}")
@@ -636,14 +704,10 @@ fn get_references(&self) {
             .get_untracked()
             .into_iter()
             .map(|l| {
-                let full_path = l.path.clone();
-                let mut path = l.path.clone();
-                if let Some(workspace_path) = self.workspace.path.as_ref() {
-                    path = path
-                        .strip_prefix(workspace_path)
-                        .unwrap_or(&full_path)
-                        .to_path_buf();
-                }
+                let path = self
+                    .common
+                    .workspace_roots
+                    .with_untracked(|roots| relative_to_roots(roots, &l.path));
                 let filter_text = path.to_str().unwrap_or("").to_string();
                 PaletteItem {
                     content: PaletteItemContent::Reference { path, location: l },
@@ -679,7 +743,17 @@ fn get_document_symbols(&self) {
 
         let set_items = self.items.write_only();
         let send = create_ext_action(self.common.scope, move |result| {
-            if let Ok(ProxyResponse::GetDocumentSymbols { resp }) = result {
+            let resp = match result {
+                Ok(ProxyResponse::GetDocumentSymbols { resp })
+                    if !document_symbol_resp_is_empty(&resp) =>
+                {
+                    Some(resp)
+                }
+                _ => doc
+                    .syntax_document_symbols()
+                    .map(DocumentSymbolResponse::Nested),
+            };
+            if let Some(resp) = resp {
                 let items = Self::format_document_symbol_resp(resp);
                 set_items.set(items);
             } else {
@@ -799,11 +873,45 @@ fn get_workspace_symbols(&self) {
     }
 
     fn get_ssh_hosts(&self) {
+        let db: Arc<LapceDb> = use_context().unwrap();
+        let workspaces = db.recent_workspaces().unwrap_or_default();
+        let mut hosts = Vec::new();
+        let mut seen = HashSet::new();
+        let recent_hosts = workspaces.iter().filter_map(|workspace| {
+            if let LapceWorkspaceType::RemoteSSH(host) = &workspace.kind {
+                Some(host.clone())
+            } else {
+                None
+            }
+        });
+        // Then the hosts that ~/.ssh/config knows of, but weren't connected to
+        let config_hosts = ssh_config_hosts()
+            .into_iter()
+            .map(|host| SshHost::from_string(&host));
+        for host in recent_hosts.chain(config_hosts) {
+            if seen.insert(host.clone()) {
+                hosts.push(host);
+            }
+        }
+
+        let items = hosts
+            .iter()
+            .map(|host| PaletteItem {
+                content: PaletteItemContent::SshHost { host: host.clone() },
+                filter_text: host.to_string(),
+                score: 0,
+                indices: vec![],
+            })
+            .collect();
+        self.items.set(items);
+    }
+
+    fn get_command_hosts(&self) {
         let db: Arc<LapceDb> = use_context().unwrap();
         let workspaces = db.recent_workspaces().unwrap_or_default();
         let mut hosts = HashSet::new();
         for workspace in workspaces.iter() {
-            if let LapceWorkspaceType::RemoteSSH(host) = &workspace.kind {
+            if let LapceWorkspaceType::RemoteCommand(host) = &workspace.kind {
                 hosts.insert(host.clone());
             }
         }
@@ -811,7 +919,7 @@ fn get_ssh_hosts(&self) {
         let items = hosts
             .iter()
             .map(|host| PaletteItem {
-                content: PaletteItemContent::SshHost { host: host.clone() },
+                content: PaletteItemContent::CommandHost { host: host.clone() },
                 filter_text: host.to_string(),
                 score: 0,
                 indices: vec![],
@@ -1047,6 +1155,131 @@ fn get_line_endings(&self) {
         self.items.set(items);
     }
 
+    fn get_encodings(&self, save: bool) {
+        let items: im::Vector<PaletteItem> = FILE_ENCODINGS
+            .iter()
+            .map(|(label, script)| PaletteItem {
+                content: PaletteItemContent::Encoding {
+                    label: label.to_string(),
+                    save,
+                },
+                filter_text: format!("{label} ({script})"),
+                score: 0,
+                indices: Vec::new(),
+            })
+            .collect();
+        if let Some(editor) = self.main_split.active_editor.get_untracked() {
+            let encoding = editor.doc().encoding.get_untracked();
+            if let Some((label, script)) = FILE_ENCODINGS
+                .iter()
+                .find(|(label, _)| Some(*label) == encoding.as_deref())
+            {
+                self.preselect_matching(&items, &format!("{label} ({script})"));
+            }
+        }
+        self.items.set(items);
+    }
+
+    /// Move the cursor of the active hex editor to the byte at the offset
+    /// typed, or to the next place the bytes typed are found.
+    fn run_hex_input(&self, input: &str) {
+        let Some(editor) = self.main_split.active_editor.get_untracked() else {
+            return;
+        };
+        let doc = editor.doc();
+        let Some(path) = doc.content.with_untracked(|content| {
+            content.is_hex().then(|| content.path().cloned()).flatten()
+        }) else {
+            return;
+        };
+        let text = doc.buffer.with_untracked(|buffer| buffer.text().clone());
+
+        let byte = if self.kind.get_untracked() == PaletteKind::HexGotoOffset {
+            hex::parse_offset(input)
+        } else {
+            let cursor = editor.cursor().with_untracked(|cursor| cursor.offset());
+            let line = text.line_of_offset(cursor);
+            let current = hex::byte_offset(line, cursor - text.offset_of_line(line));
+            hex::parse_bytes(input).and_then(|needle| {
+                hex::find_bytes(&hex::parse_hex_dump(&text), &needle, current + 1)
+            })
+        };
+        let Some(byte) = byte else {
+            return;
+        };
+
+        let last_line = text.line_of_offset(text.len());
+        let line = (byte / hex::BYTES_PER_LINE).min(last_line);
+        let offset =
+            text.offset_of_line(line) + hex::hex_column(byte % hex::BYTES_PER_LINE);
+        self.common
+            .internal_command
+            .send(InternalCommand::JumpToLocation {
+                location: EditorLocation {
+                    path,
+                    position: Some(EditorPosition::Offset(offset.min(text.len()))),
+                    scroll_offset: None,
+                    ignore_unconfirmed: false,
+                    same_editor_tab: false,
+                },
+            });
+    }
+
+    fn get_keyboard_macros(&self) {
+        let macros = self.common.keypress.get_untracked().macros.macros;
+        let items = macros.with_untracked(|macros| {
+            macros
+                .iter()
+                .map(|(register, keys)| PaletteItem {
+                    content: PaletteItemContent::KeyboardMacro {
+                        register: *register,
+                    },
+                    filter_text: format!(
+                        "@{register} {}",
+                        keys.iter().map(|key| key.key.as_str()).join(" ")
+                    ),
+                    score: 0,
+                    indices: Vec::new(),
+                })
+                .collect()
+        });
+        self.items.set(items);
+    }
+
+    fn get_paste_history(&self) {
+        let items = self.common.registers.with_untracked(|registers| {
+            registers
+                .history()
+                .map(|content| PaletteItem {
+                    content: PaletteItemContent::PasteHistory {
+                        content: content.clone(),
+                    },
+                    filter_text: content.summary(),
+                    score: 0,
+                    indices: Vec::new(),
+                })
+                .collect()
+        });
+        self.items.set(items);
+    }
+
+    fn get_ex_command_history(&self) {
+        let items = self.ex_command_history.with_untracked(|history| {
+            history
+                .iter()
+                .map(|command| PaletteItem {
+                    content: PaletteItemContent::ExCommand {
+                        command: command.clone(),
+                    },
+                    filter_text: command.clone(),
+                    score: 0,
+                    indices: Vec::new(),
+                })
+                .collect()
+        });
+        self.items.set(items);
+    }
+
     fn get_scm_references(&self) {
         let branches = self.source_control.branches.get_untracked();
         let tags = self.source_control.tags.get_untracked();
@@ -1124,7 +1357,28 @@ fn preselect_matching(&self, items: &im::Vector<PaletteItem>, matching: &str) {
     fn select(&self) {
         let index = self.index.get_untracked();
         let items = self.filtered_items.get_untracked();
+        let input = self.input.with_untracked(|input| input.input.clone());
         self.close();
+        if self.kind.get_untracked() == PaletteKind::ExCommand
+            && !input.trim().is_empty()
+            && (index == 0 || items.get(index).is_none())
+        {
+            // What was typed is run as is, unless an older command was picked
+            // from the history
+            self.run_ex_command(input);
+            return;
+        }
+        if matches!(
+            self.kind.get_untracked(),
+            PaletteKind::HexGotoOffset | PaletteKind::HexFindBytes
+        ) {
+            self.run_hex_input(&input);
+            return;
+        }
+        if self.kind.get_untracked() == PaletteKind::RemotePrompt {
+            self.answer_remote_prompt(Some(input));
+            return;
+        }
         if let Some(item) = items.get(index) {
             match &item.content {
                 PaletteItemContent::PaletteHelp { cmd } => {
@@ -1208,7 +1462,20 @@ fn select(&self) {
                             workspace: LapceWorkspace {
                                 kind: LapceWorkspaceType::RemoteSSH(host.clone()),
                                 path: None,
-                                last_open: 0,
+                                ..Default::default()
+                            },
+                        },
+                    );
+                }
+                PaletteItemContent::CommandHost { host } => {
+                    self.common.window_common.window_command.send(
+                        WindowCommand::SetWorkspace {
+                            workspace: LapceWorkspace {
+                                kind: LapceWorkspaceType::RemoteCommand(
+                                    host.clone(),
+                                ),
+                                path: None,
+                                ..Default::default()
                             },
                         },
                     );
@@ -1220,7 +1487,7 @@ fn select(&self) {
                             workspace: LapceWorkspace {
                                 kind: LapceWorkspaceType::RemoteWSL(host.clone()),
                                 path: None,
-                                last_open: 0,
+                                ..Default::default()
                             },
                         },
                     );
@@ -1313,6 +1580,18 @@ fn select(&self) {
                         buffer.set_line_ending(*kind);
                     });
                 }
+                PaletteItemContent::Encoding { label, save } => {
+                    let Some(editor) = self.main_split.active_editor.get_untracked()
+                    else {
+                        return;
+                    };
+                    let doc = editor.doc();
+                    if *save {
+                        doc.save_with_encoding(label.clone(), || {});
+                    } else {
+                        doc.reopen_with_encoding(label.clone());
+                    }
+                }
                 PaletteItemContent::SCMReference { name } => {
                     self.common
                         .lapce_command
@@ -1329,22 +1608,74 @@ fn select(&self) {
                     .send(InternalCommand::NewTerminal {
                         profile: Some(profile.to_owned()),
                     }),
+                PaletteItemContent::KeyboardMacro { register } => {
+                    self.common.internal_command.send(
+                        InternalCommand::ReplayKeyboardMacro {
+                            register: *register,
+                        },
+                    );
+                }
+                PaletteItemContent::PasteHistory { content } => {
+                    if let Some(editor) =
+                        self.main_split.active_editor.get_untracked()
+                    {
+                        editor.paste_register_content(content.clone());
+                    }
+                }
+                PaletteItemContent::ExCommand { command } => {
+                    self.run_ex_command(command.clone());
+                }
             }
         } else if self.kind.get_untracked() == PaletteKind::SshHost {
-            let input = self.input.with_untracked(|input| input.input.clone());
             let ssh = SshHost::from_string(&input);
             self.common.window_common.window_command.send(
                 WindowCommand::SetWorkspace {
                     workspace: LapceWorkspace {
                         kind: LapceWorkspaceType::RemoteSSH(ssh),
                         path: None,
-                        last_open: 0,
+                        ..Default::default()
                     },
                 },
             );
+        } else if self.kind.get_untracked() == PaletteKind::CommandHost {
+            let remote_commands = self
+                .common
+                .config
+                .get_untracked()
+                .core
+                .remote_commands
+                .clone();
+            if let Some(host) = CommandHost::from_string(&input, &remote_commands) {
+                self.common.window_common.window_command.send(
+                    WindowCommand::SetWorkspace {
+                        workspace: LapceWorkspace {
+                            kind: LapceWorkspaceType::RemoteCommand(host),
+                            path: None,
+                            ..Default::default()
+                        },
+                    },
+                );
+            }
         }
     }
 
+    /// Run a command line and move it to the front of the history.
+    fn run_ex_command(&self, command: String) {
+        self.ex_command_history.update(|history| {
+            history.retain(|c| c != &command);
+            history.insert(0, command.clone());
+            history.truncate(EX_COMMAND_HISTORY_SIZE);
+        });
+        let db: Arc<LapceDb> = use_context().unwrap();
+        db.save_ex_command_history(
+            self.workspace.clone(),
+            self.ex_command_history.get_untracked(),
+        );
+        self.common
+            .internal_command
+            .send(InternalCommand::RunExCommand { command });
+    }
+
     /// Update the preview for the currently active palette item, if it has one.
     fn preview(&self) {
         if self.status.get_untracked() == PaletteStatus::Inactive {
@@ -1390,10 +1721,12 @@ fn preview(&self) {
                 PaletteItemContent::Workspace { .. } => {}
                 PaletteItemContent::RunAndDebug { .. } => {}
                 PaletteItemContent::SshHost { .. } => {}
+                PaletteItemContent::CommandHost { .. } => {}
                 #[cfg(windows)]
                 PaletteItemContent::WslHost { .. } => {}
                 PaletteItemContent::Language { .. } => {}
                 PaletteItemContent::LineEnding { .. } => {}
+                PaletteItemContent::Encoding { .. } => {}
                 PaletteItemContent::Reference { location, .. } => {
                     self.has_preview.set(true);
                     let (doc, new_doc) =
@@ -1461,6 +1794,9 @@ fn preview(&self) {
                     }),
                 PaletteItemContent::SCMReference { .. } => {}
                 PaletteItemContent::TerminalProfile { .. } => {}
+                PaletteItemContent::KeyboardMacro { .. } => {}
+                PaletteItemContent::PasteHistory { .. } => {}
+                PaletteItemContent::ExCommand { .. } => {}
             }
         }
     }
@@ -1477,6 +1813,7 @@ fn cancel(&self) {
         }
 
         self.left_diff_path.set(None);
+        self.answer_remote_prompt(None);
         self.close();
     }
 
@@ -1698,3 +2035,10 @@ fn receive_char(&self, c: &str) {
         self.input_editor.receive_char(c);
     }
 }
+
+fn document_symbol_resp_is_empty(resp: &DocumentSymbolResponse) -> bool {
+    match resp {
+        DocumentSymbolResponse::Flat(symbols) => symbols.is_empty(),
+        DocumentSymbolResponse::Nested(symbols) => symbols.is_empty(),
+    }
+}
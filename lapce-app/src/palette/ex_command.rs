@@ -0,0 +1,517 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use regex::{Regex, RegexBuilder};
+
+/// How many command lines are remembered per workspace.
+pub const EX_COMMAND_HISTORY_SIZE: usize = 100;
+
+/// A line in the range of an ex command, before it's resolved against a
+/// document.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineAddress {
+    /// A one based line number
+    Number(usize),
+    /// `.`, the line of the cursor
+    Current,
+    /// `$`, the last line
+    Last,
+    /// `'<`, the first line of the visual selection
+    VisualStart,
+    /// `'>`, the last line of the visual selection
+    VisualEnd,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LineSpec {
+    pub address: LineAddress,
+    pub offset: isize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExRange {
+    /// `%`, every line
+    Whole,
+    Lines(LineSpec, Option<LineSpec>),
+}
+
+/// What the addresses of a range resolve against, with zero based lines.
+pub struct LineContext {
+    pub current: usize,
+    pub last: usize,
+    pub visual: Option<(usize, usize)>,
+}
+
+impl ExRange {
+    /// Resolve the range into zero based, inclusive start and end lines.
+    pub fn resolve(&self, cx: &LineContext) -> Result<(usize, usize)> {
+        let resolve_line = |spec: &LineSpec| -> Result<usize> {
+            let line = match spec.address {
+                LineAddress::Number(n) => n.saturating_sub(1),
+                LineAddress::Current => cx.current,
+                LineAddress::Last => cx.last,
+                LineAddress::VisualStart => {
+                    cx.visual.ok_or_else(|| anyhow!("no visual selection"))?.0
+                }
+                LineAddress::VisualEnd => {
+                    cx.visual.ok_or_else(|| anyhow!("no visual selection"))?.1
+                }
+            };
+            let line = line
+                .checked_add_signed(spec.offset)
+                .ok_or_else(|| anyhow!("invalid range"))?;
+            Ok(line.min(cx.last))
+        };
+
+        match self {
+            ExRange::Whole => Ok((0, cx.last)),
+            ExRange::Lines(start, end) => {
+                let start = resolve_line(start)?;
+                let end = match end {
+                    Some(end) => resolve_line(end)?,
+                    None => start,
+                };
+                Ok((start.min(end), start.max(end)))
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Substitute {
+    pub pattern: String,
+    /// The replacement, already in the syntax of the `regex` crate
+    pub replacement: String,
+    pub global: bool,
+    pub ignore_case: bool,
+}
+
+impl Substitute {
+    pub fn regex(&self) -> Result<Regex> {
+        Ok(RegexBuilder::new(&self.pattern)
+            .case_insensitive(self.ignore_case)
+            .build()?)
+    }
+
+    /// The line after substituting, or `None` if the pattern doesn't match.
+    pub fn apply(&self, regex: &Regex, line: &str) -> Option<String> {
+        if !regex.is_match(line) {
+            return None;
+        }
+        let limit = if self.global { 0 } else { 1 };
+        Some(
+            regex
+                .replacen(line, limit, self.replacement.as_str())
+                .into_owned(),
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SortOptions {
+    pub reverse: bool,
+    pub unique: bool,
+    pub ignore_case: bool,
+    pub numeric: bool,
+}
+
+impl SortOptions {
+    pub fn sort(&self, lines: &mut Vec<String>) {
+        let key = |line: &String| -> (Option<i64>, String) {
+            let number = self.numeric.then(|| leading_number(line)).flatten();
+            let text = if self.ignore_case {
+                line.to_lowercase()
+            } else {
+                line.clone()
+            };
+            (number, text)
+        };
+        lines.sort_by_cached_key(key);
+        if self.unique {
+            lines.dedup_by(|a, b| key(a) == key(b));
+        }
+        if self.reverse {
+            lines.reverse();
+        }
+    }
+}
+
+/// The first number in the line, which is what `:sort n` sorts on.
+fn leading_number(line: &str) -> Option<i64> {
+    let start = line.find(|c: char| c.is_ascii_digit())?;
+    let negative = line[..start].ends_with('-');
+    let digits: String = line[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    let number: i64 = digits.parse().ok()?;
+    Some(if negative { -number } else { number })
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExCommandKind {
+    /// Only a range, which goes to its last line
+    GoToLine,
+    Write,
+    WriteAll,
+    Quit,
+    QuitAll,
+    WriteQuit,
+    Edit(PathBuf),
+    Delete,
+    Substitute(Substitute),
+    Sort(SortOptions),
+    Global {
+        pattern: String,
+        invert: bool,
+        command: String,
+    },
+    Normal(String),
+    /// Any other command, by the name it has in the keymaps
+    Command(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExCommand {
+    pub range: Option<ExRange>,
+    pub kind: ExCommandKind,
+}
+
+impl ExCommand {
+    pub fn parse(input: &str) -> Result<Self> {
+        let input = input.trim_start();
+        let input = input.strip_prefix(':').unwrap_or(input).trim_start();
+
+        let (range, rest) = parse_range(input)?;
+        let rest = rest.trim_start();
+
+        let name_len = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.'))
+            .unwrap_or(rest.len());
+        let (name, rest) = rest.split_at(name_len);
+        let (bang, rest) = match rest.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, rest),
+        };
+        let args = rest.trim();
+
+        let kind = match name {
+            "" if range.is_some() && args.is_empty() => ExCommandKind::GoToLine,
+            "" => return Err(anyhow!("not an editor command: {input}")),
+            "w" | "write" => {
+                if !args.is_empty() {
+                    return Err(anyhow!("writing to another file is not supported"));
+                }
+                ExCommandKind::Write
+            }
+            "wa" | "wall" => ExCommandKind::WriteAll,
+            "q" | "quit" | "clo" | "close" => ExCommandKind::Quit,
+            "qa" | "qall" | "quita" | "quitall" => ExCommandKind::QuitAll,
+            "wq" | "x" | "xit" | "exi" | "exit" => ExCommandKind::WriteQuit,
+            "e" | "edit" => {
+                if args.is_empty() {
+                    return Err(anyhow!("no file name"));
+                }
+                ExCommandKind::Edit(PathBuf::from(args))
+            }
+            "d" | "delete" => ExCommandKind::Delete,
+            "s" | "substitute" => ExCommandKind::Substitute(parse_substitute(rest)?),
+            "sor" | "sort" => ExCommandKind::Sort(parse_sort(bang, args)?),
+            "g" | "global" | "v" | "vglobal" => {
+                let invert = bang || name.starts_with('v');
+                let (pattern, command) = parse_global(rest)?;
+                ExCommandKind::Global {
+                    pattern,
+                    invert,
+                    command,
+                }
+            }
+            "norm" | "normal" => {
+                let keys = rest.strip_prefix(' ').unwrap_or(rest);
+                if keys.is_empty() {
+                    return Err(anyhow!("argument required"));
+                }
+                ExCommandKind::Normal(keys.to_string())
+            }
+            "sp" | "split" => ExCommandKind::Command("split_horizontal".to_string()),
+            "vs" | "vsplit" => ExCommandKind::Command("split_vertical".to_string()),
+            "bn" | "bnext" => ExCommandKind::Command("next_editor_tab".to_string()),
+            "bp" | "bprevious" => {
+                ExCommandKind::Command("previous_editor_tab".to_string())
+            }
+            name => ExCommandKind::Command(name.to_string()),
+        };
+
+        Ok(ExCommand { range, kind })
+    }
+}
+
+fn parse_range(input: &str) -> Result<(Option<ExRange>, &str)> {
+    if let Some(rest) = input.strip_prefix('%') {
+        return Ok((Some(ExRange::Whole), rest));
+    }
+
+    let Some((start, rest)) = parse_line_spec(input)? else {
+        return Ok((None, input));
+    };
+    if let Some(rest) = rest.strip_prefix(',') {
+        let (end, rest) =
+            parse_line_spec(rest)?.ok_or_else(|| anyhow!("invalid range"))?;
+        return Ok((Some(ExRange::Lines(start, Some(end))), rest));
+    }
+    Ok((Some(ExRange::Lines(start, None)), rest))
+}
+
+fn parse_line_spec(input: &str) -> Result<Option<(LineSpec, &str)>> {
+    let digits = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    let (address, mut rest) = if digits > 0 {
+        let number = input[..digits].parse()?;
+        (Some(LineAddress::Number(number)), &input[digits..])
+    } else if let Some(rest) = input.strip_prefix('.') {
+        (Some(LineAddress::Current), rest)
+    } else if let Some(rest) = input.strip_prefix('$') {
+        (Some(LineAddress::Last), rest)
+    } else if let Some(rest) = input.strip_prefix("'<") {
+        (Some(LineAddress::VisualStart), rest)
+    } else if let Some(rest) = input.strip_prefix("'>") {
+        (Some(LineAddress::VisualEnd), rest)
+    } else {
+        (None, input)
+    };
+
+    let mut offset = 0;
+    let mut has_offset = false;
+    while let Some(sign) = rest.chars().next().filter(|c| *c == '+' || *c == '-') {
+        rest = &rest[1..];
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let n: isize = if digits == 0 {
+            1
+        } else {
+            rest[..digits].parse()?
+        };
+        rest = &rest[digits..];
+        offset += if sign == '+' { n } else { -n };
+        has_offset = true;
+    }
+
+    match address {
+        Some(address) => Ok(Some((LineSpec { address, offset }, rest))),
+        None if has_offset => Ok(Some((
+            LineSpec {
+                address: LineAddress::Current,
+                offset,
+            },
+            rest,
+        ))),
+        None => Ok(None),
+    }
+}
+
+/// Split `input` at the delimiter it starts with, into at most `n` parts.
+/// The delimiter can be escaped with a backslash.
+fn split_delimited(input: &str, n: usize) -> Result<Vec<String>> {
+    let mut chars = input.chars();
+    let delimiter = chars
+        .next()
+        .filter(|c| !c.is_alphanumeric() && !c.is_whitespace() && *c != '\\')
+        .ok_or_else(|| anyhow!("expected a delimiter, like in s/pattern/"))?;
+
+    let mut parts = vec![String::new()];
+    let mut chars = chars.peekable();
+    while let Some(c) = chars.next() {
+        if parts.len() == n {
+            parts.last_mut().unwrap().push(c);
+        } else if c == '\\' && chars.peek() == Some(&delimiter) {
+            parts.last_mut().unwrap().push(delimiter);
+            chars.next();
+        } else if c == '\\' {
+            let part = parts.last_mut().unwrap();
+            part.push(c);
+            if let Some(c) = chars.next() {
+                part.push(c);
+            }
+        } else if c == delimiter {
+            parts.push(String::new());
+        } else {
+            parts.last_mut().unwrap().push(c);
+        }
+    }
+    Ok(parts)
+}
+
+fn parse_substitute(input: &str) -> Result<Substitute> {
+    let mut parts = split_delimited(input, 3)?.into_iter();
+    let pattern = parts.next().unwrap_or_default();
+    if pattern.is_empty() {
+        return Err(anyhow!("no previous regular expression"));
+    }
+    let replacement = convert_replacement(&parts.next().unwrap_or_default());
+    let flags = parts.next().unwrap_or_default();
+
+    let mut substitute = Substitute {
+        pattern,
+        replacement,
+        global: false,
+        ignore_case: false,
+    };
+    for flag in flags.trim().chars() {
+        match flag {
+            'g' => substitute.global = true,
+            'i' => substitute.ignore_case = true,
+            'I' => substitute.ignore_case = false,
+            _ => return Err(anyhow!("unsupported substitute flag: {flag}")),
+        }
+    }
+    Ok(substitute)
+}
+
+/// Turn a vim replacement, with `&` and `\1` for the groups, into the
+/// replacement syntax of the `regex` crate.
+fn convert_replacement(replacement: &str) -> String {
+    let mut result = String::new();
+    let mut chars = replacement.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(d) if d.is_ascii_digit() => {
+                    result.push_str(&format!("${{{d}}}"));
+                }
+                Some('n') | Some('r') => result.push('\n'),
+                Some('t') => result.push('\t'),
+                Some('$') => result.push_str("$$"),
+                Some(c) => result.push(c),
+                None => result.push('\\'),
+            },
+            '&' => result.push_str("${0}"),
+            '$' => result.push_str("$$"),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+fn parse_sort(reverse: bool, args: &str) -> Result<SortOptions> {
+    let mut options = SortOptions {
+        reverse,
+        ..Default::default()
+    };
+    for flag in args.chars().filter(|c| !c.is_whitespace()) {
+        match flag {
+            'u' => options.unique = true,
+            'i' => options.ignore_case = true,
+            'n' => options.numeric = true,
+            _ => return Err(anyhow!("unsupported sort option: {flag}")),
+        }
+    }
+    Ok(options)
+}
+
+fn parse_global(input: &str) -> Result<(String, String)> {
+    let mut parts = split_delimited(input, 2)?.into_iter();
+    let pattern = parts.next().unwrap_or_default();
+    if pattern.is_empty() {
+        return Err(anyhow!("no previous regular expression"));
+    }
+    let command = parts.next().unwrap_or_default();
+    if command.trim().is_empty() {
+        return Err(anyhow!("a command is required, like in g/pattern/d"));
+    }
+    Ok((pattern, command))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_range() {
+        let cx = LineContext {
+            current: 4,
+            last: 99,
+            visual: Some((10, 12)),
+        };
+        let range = |input: &str| {
+            ExCommand::parse(input)
+                .unwrap()
+                .range
+                .map(|range| range.resolve(&cx).unwrap())
+        };
+
+        assert_eq!(range("%d"), Some((0, 99)));
+        assert_eq!(range("10,20d"), Some((9, 19)));
+        assert_eq!(range("'<,'>d"), Some((10, 12)));
+        assert_eq!(range(".,$d"), Some((4, 99)));
+        assert_eq!(range(".,+2d"), Some((4, 6)));
+        assert_eq!(range("$-1d"), Some((98, 98)));
+        assert_eq!(range("d"), None);
+    }
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(
+            ExCommand::parse(":42").unwrap().kind,
+            ExCommandKind::GoToLine
+        );
+        assert_eq!(
+            ExCommand::parse("wq").unwrap().kind,
+            ExCommandKind::WriteQuit
+        );
+        assert_eq!(
+            ExCommand::parse("e src/main.rs").unwrap().kind,
+            ExCommandKind::Edit(PathBuf::from("src/main.rs"))
+        );
+        assert_eq!(
+            ExCommand::parse("sort! u").unwrap().kind,
+            ExCommandKind::Sort(SortOptions {
+                reverse: true,
+                unique: true,
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            ExCommand::parse("g!/^$/norm dd").unwrap().kind,
+            ExCommandKind::Global {
+                pattern: "^$".to_string(),
+                invert: true,
+                command: "norm dd".to_string(),
+            }
+        );
+        assert_eq!(
+            ExCommand::parse("toggle_terminal_focus").unwrap().kind,
+            ExCommandKind::Command("toggle_terminal_focus".to_string())
+        );
+        assert!(ExCommand::parse("").is_err());
+    }
+
+    #[test]
+    fn test_substitute() {
+        let ExCommandKind::Substitute(substitute) =
+            ExCommand::parse(r"%s/(\w+)\/(\w+)/\2 & $1/g").unwrap().kind
+        else {
+            panic!("not a substitute command");
+        };
+        assert_eq!(substitute.pattern, r"(\w+)/(\w+)");
+        let regex = substitute.regex().unwrap();
+        assert_eq!(
+            substitute.apply(&regex, "a/b c/d").as_deref(),
+            Some("b a/b $1 d c/d $1")
+        );
+        assert_eq!(substitute.apply(&regex, "nothing"), None);
+    }
+
+    #[test]
+    fn test_sort() {
+        let mut lines = ["b10", "a2", "b10", "c1"].map(String::from).to_vec();
+        SortOptions {
+            unique: true,
+            numeric: true,
+            ..Default::default()
+        }
+        .sort(&mut lines);
+        assert_eq!(lines, ["c1", "a2", "b10"]);
+    }
+}
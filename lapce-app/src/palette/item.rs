@@ -8,7 +8,8 @@
     command::{LapceCommand, LapceWorkbenchCommand},
     debug::RunDebugMode,
     editor::location::EditorLocation,
-    workspace::{LapceWorkspace, SshHost},
+    register::RegisterContent,
+    workspace::{CommandHost, LapceWorkspace, SshHost},
 };
 
 #[derive(Clone, Debug, PartialEq)]
@@ -57,6 +58,9 @@ pub enum PaletteItemContent {
     SshHost {
         host: SshHost,
     },
+    CommandHost {
+        host: CommandHost,
+    },
     #[cfg(windows)]
     WslHost {
         host: crate::workspace::WslHost,
@@ -77,6 +81,12 @@ pub enum PaletteItemContent {
     LineEnding {
         kind: LineEnding,
     },
+    Encoding {
+        /// The label of the encoding, as in [`lapce_rpc::buffer::FILE_ENCODINGS`]
+        label: String,
+        /// Whether the file is saved in the encoding rather than reopened
+        save: bool,
+    },
     SCMReference {
         name: String,
     },
@@ -84,4 +94,13 @@ pub enum PaletteItemContent {
         name: String,
         profile: lapce_rpc::terminal::TerminalProfile,
     },
+    KeyboardMacro {
+        register: char,
+    },
+    PasteHistory {
+        content: RegisterContent,
+    },
+    ExCommand {
+        command: String,
+    },
 }
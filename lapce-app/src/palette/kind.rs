@@ -13,6 +13,8 @@ pub enum PaletteKind {
     DocumentSymbol,
     WorkspaceSymbol,
     SshHost,
+    CommandHost,
+    RemotePrompt,
     #[cfg(windows)]
     WslHost,
     RunAndDebug,
@@ -20,10 +22,17 @@ pub enum PaletteKind {
     IconTheme,
     Language,
     LineEnding,
+    ReopenWithEncoding,
+    SaveWithEncoding,
+    HexGotoOffset,
+    HexFindBytes,
     SCMReferences,
     TerminalProfile,
     DiffFiles,
     HelpAndFile,
+    KeyboardMacro,
+    PasteHistory,
+    ExCommand,
 }
 
 impl PaletteKind {
@@ -41,14 +50,23 @@ pub fn symbol(&self) -> &'static str {
             PaletteKind::File
             | PaletteKind::Reference
             | PaletteKind::SshHost
+            | PaletteKind::CommandHost
+            | PaletteKind::RemotePrompt
             | PaletteKind::RunAndDebug
             | PaletteKind::ColorTheme
             | PaletteKind::IconTheme
             | PaletteKind::Language
             | PaletteKind::LineEnding
+            | PaletteKind::ReopenWithEncoding
+            | PaletteKind::SaveWithEncoding
+            | PaletteKind::HexGotoOffset
+            | PaletteKind::HexFindBytes
             | PaletteKind::SCMReferences
             | PaletteKind::HelpAndFile
-            | PaletteKind::DiffFiles => "",
+            | PaletteKind::DiffFiles
+            | PaletteKind::KeyboardMacro
+            | PaletteKind::PasteHistory
+            | PaletteKind::ExCommand => "",
             #[cfg(windows)]
             PaletteKind::WslHost => "",
         }
@@ -87,6 +105,10 @@ pub fn command(self) -> Option<LapceWorkbenchCommand> {
             }
             PaletteKind::Reference => None, // InternalCommand::PaletteReferences
             PaletteKind::SshHost => Some(LapceWorkbenchCommand::ConnectSshHost),
+            PaletteKind::CommandHost => {
+                Some(LapceWorkbenchCommand::ConnectCommandHost)
+            }
+            PaletteKind::RemotePrompt => None, // CoreNotification::RemotePrompt
             #[cfg(windows)]
             PaletteKind::WslHost => Some(LapceWorkbenchCommand::ConnectWslHost),
             PaletteKind::RunAndDebug => {
@@ -98,11 +120,26 @@ pub fn command(self) -> Option<LapceWorkbenchCommand> {
             PaletteKind::LineEnding => {
                 Some(LapceWorkbenchCommand::ChangeFileLineEnding)
             }
+            PaletteKind::ReopenWithEncoding => {
+                Some(LapceWorkbenchCommand::ReopenWithEncoding)
+            }
+            PaletteKind::SaveWithEncoding => {
+                Some(LapceWorkbenchCommand::SaveWithEncoding)
+            }
+            PaletteKind::HexGotoOffset => Some(LapceWorkbenchCommand::HexGotoOffset),
+            PaletteKind::HexFindBytes => Some(LapceWorkbenchCommand::HexFindBytes),
             PaletteKind::SCMReferences => {
                 Some(LapceWorkbenchCommand::PaletteSCMReferences)
             }
             PaletteKind::TerminalProfile => None, // InternalCommand::NewTerminal
             PaletteKind::DiffFiles => Some(LapceWorkbenchCommand::DiffFiles),
+            PaletteKind::KeyboardMacro => {
+                Some(LapceWorkbenchCommand::PaletteKeyboardMacros)
+            }
+            PaletteKind::PasteHistory => {
+                Some(LapceWorkbenchCommand::PalettePasteHistory)
+            }
+            PaletteKind::ExCommand => Some(LapceWorkbenchCommand::PaletteExCommand),
         }
     }
 
@@ -124,13 +161,22 @@ pub fn get_input<'a>(&self, input: &'a str) -> &'a str {
             PaletteKind::File
             | PaletteKind::Reference
             | PaletteKind::SshHost
+            | PaletteKind::CommandHost
+            | PaletteKind::RemotePrompt
             | PaletteKind::RunAndDebug
             | PaletteKind::ColorTheme
             | PaletteKind::IconTheme
             | PaletteKind::Language
             | PaletteKind::LineEnding
+            | PaletteKind::ReopenWithEncoding
+            | PaletteKind::SaveWithEncoding
+            | PaletteKind::HexGotoOffset
+            | PaletteKind::HexFindBytes
             | PaletteKind::SCMReferences | PaletteKind::HelpAndFile
-            | PaletteKind::DiffFiles => input,
+            | PaletteKind::DiffFiles
+            | PaletteKind::KeyboardMacro
+            | PaletteKind::PasteHistory
+            | PaletteKind::ExCommand => input,
             PaletteKind::PaletteHelp
             | PaletteKind::Command
             | PaletteKind::Workspace
@@ -10,7 +10,7 @@
     },
     View,
 };
-use lsp_types::{DocumentSymbol, SymbolKind};
+use lsp_types::{DocumentSymbol, Position, SymbolKind};
 
 use super::position::PanelPosition;
 use crate::{
@@ -118,6 +118,26 @@ pub fn child_count(&self) -> usize {
     }
 }
 
+/// The names of the symbols that contain the position, outermost first.
+pub fn symbol_path_at(
+    items: &[RwSignal<SymbolInformationItemData>],
+    position: Position,
+) -> Vec<String> {
+    let mut path = Vec::new();
+    let mut items = items.to_vec();
+    while let Some(item) = items.into_iter().find(|item| {
+        item.with(|item| {
+            item.item.range.start <= position && position < item.item.range.end
+        })
+    }) {
+        items = item.with(|item| {
+            path.push(item.name.clone());
+            item.children.clone()
+        });
+    }
+    path
+}
+
 fn get_children(
     data: RwSignal<SymbolInformationItemData>,
     next: &mut usize,
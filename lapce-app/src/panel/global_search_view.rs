@@ -2,7 +2,7 @@
 
 use floem::{
     event::EventListener,
-    reactive::{ReadSignal, SignalGet, SignalUpdate},
+    reactive::{ReadSignal, RwSignal, SignalGet, SignalUpdate, SignalWith},
     style::{CursorStyle, Style},
     views::{
         container, label, scroll, stack, svg, virtual_stack, Decorators,
@@ -23,7 +23,7 @@
     listener::Listener,
     text_input::TextInputBuilder,
     window_tab::{Focus, WindowTabData},
-    workspace::LapceWorkspace,
+    workspace::relative_to_roots,
 };
 
 pub fn global_search_panel(
@@ -33,7 +33,7 @@ pub fn global_search_panel(
     let global_search = window_tab_data.global_search.clone();
     let editor = global_search.editor.clone();
     let config = global_search.common.config;
-    let workspace = global_search.common.workspace.clone();
+    let workspace_roots = global_search.common.workspace_roots;
     let internal_command = global_search.common.internal_command;
     let case_matching = global_search.common.find.case_matching;
     let whole_word = global_search.common.find.whole_words;
@@ -104,14 +104,14 @@ pub fn global_search_panel(
             }),
         )
         .style(|s| s.width_pct(100.0).padding(10.0)),
-        search_result(workspace, global_search, internal_command, config),
+        search_result(workspace_roots, global_search, internal_command, config),
     ))
     .style(|s| s.absolute().size_pct(100.0, 100.0).flex_col())
     .debug_name("Global Search Panel")
 }
 
 fn search_result(
-    workspace: Arc<LapceWorkspace>,
+    workspace_roots: RwSignal<Vec<PathBuf>>,
     global_search_data: GlobalSearchData,
     internal_command: Listener<InternalCommand>,
     config: ReadSignal<Arc<LapceConfig>>,
@@ -130,14 +130,8 @@ fn search_result(
                 move |(path, _)| path.to_owned(),
                 move |(path, match_data)| {
                     let full_path = path.clone();
-                    let path = if let Some(workspace_path) = workspace.path.as_ref()
-                    {
-                        path.strip_prefix(workspace_path)
-                            .unwrap_or(&full_path)
-                            .to_path_buf()
-                    } else {
-                        path
-                    };
+                    let path = workspace_roots
+                        .with_untracked(|roots| relative_to_roots(roots, &path));
                     let style_path = path.clone();
 
                     let file_name = path
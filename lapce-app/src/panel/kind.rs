@@ -19,6 +19,8 @@ pub enum PanelKind {
     DocumentSymbol,
     References,
     Implementation,
+    Ports,
+    Timeline,
 }
 
 impl PanelKind {
@@ -35,6 +37,8 @@ pub fn svg_name(&self) -> &'static str {
             PanelKind::DocumentSymbol => LapceIcons::DOCUMENT_SYMBOL,
             PanelKind::References => LapceIcons::REFERENCES,
             PanelKind::Implementation => LapceIcons::IMPLEMENTATION,
+            PanelKind::Ports => LapceIcons::PORTS,
+            PanelKind::Timeline => LapceIcons::TIMELINE,
         }
     }
 
@@ -61,6 +65,8 @@ pub fn default_position(&self) -> PanelPosition {
             PanelKind::DocumentSymbol => PanelPosition::RightTop,
             PanelKind::References => PanelPosition::BottomLeft,
             PanelKind::Implementation => PanelPosition::BottomLeft,
+            PanelKind::Ports => PanelPosition::BottomLeft,
+            PanelKind::Timeline => PanelPosition::RightTop,
         }
     }
 }
@@ -6,10 +6,12 @@
 pub mod implementation_view;
 pub mod kind;
 pub mod plugin_view;
+pub mod ports_view;
 pub mod position;
 pub mod problem_view;
 pub mod references_view;
 pub mod source_control_view;
 pub mod style;
 pub mod terminal_view;
+pub mod timeline_view;
 pub mod view;
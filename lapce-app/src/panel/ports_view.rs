@@ -0,0 +1,141 @@
+use std::{rc::Rc, sync::Arc};
+
+use floem::{
+    event::EventListener,
+    reactive::{ReadSignal, SignalGet, SignalUpdate},
+    style::CursorStyle,
+    views::{container, dyn_stack, label, scroll, stack, Decorators},
+    View,
+};
+
+use super::{kind::PanelKind, position::PanelPosition};
+use crate::{
+    app::clickable_icon,
+    config::{color::LapceColor, icon::LapceIcons, LapceConfig},
+    ports::{PortForward, PortForwardStatus, PortsData},
+    text_input::TextInputBuilder,
+    window_tab::{Focus, WindowTabData},
+};
+
+pub fn ports_panel(
+    window_tab_data: Rc<WindowTabData>,
+    _position: PanelPosition,
+) -> impl View {
+    let ports = window_tab_data.ports.clone();
+    let config = ports.common.config;
+    let focus = ports.common.focus;
+    let is_focused = move || focus.get() == Focus::Panel(PanelKind::Ports);
+    let forwards = ports.forwards;
+    let is_ssh = ports.ssh_host().is_some();
+
+    stack((
+        container(
+            TextInputBuilder::new()
+                .is_focused(is_focused)
+                .build_editor(ports.input_editor.clone())
+                .placeholder(|| "Port to forward, like 3000 or 8080:3000".to_owned())
+                .on_event_cont(EventListener::PointerDown, move |_| {
+                    focus.set(Focus::Panel(PanelKind::Ports));
+                })
+                .style(move |s| {
+                    s.width_pct(100.0)
+                        .border(1.0)
+                        .border_radius(6.0)
+                        .border_color(config.get().color(LapceColor::LAPCE_BORDER))
+                }),
+        )
+        .style(move |s| {
+            s.width_pct(100.0)
+                .padding(10.0)
+                .apply_if(!is_ssh, |s| s.hide())
+        }),
+        label(|| "Ports can be forwarded in remote SSH workspaces".to_owned())
+            .style(move |s| {
+                s.padding(10.0)
+                    .color(config.get().color(LapceColor::EDITOR_DIM))
+                    .apply_if(is_ssh, |s| s.hide())
+            }),
+        scroll(
+            dyn_stack(
+                move || forwards.get(),
+                |forward| (forward.local, forward.status.clone()),
+                move |forward| port_forward_view(ports.clone(), forward, config),
+            )
+            .style(|s| s.flex_col().width_pct(100.0)),
+        )
+        .style(|s| s.flex_grow(1.0).flex_basis(0.0).min_height(0.0)),
+    ))
+    .style(|s| s.absolute().size_pct(100.0, 100.0).flex_col())
+    .debug_name("Ports Panel")
+}
+
+fn port_forward_view(
+    ports: PortsData,
+    forward: PortForward,
+    config: ReadSignal<Arc<LapceConfig>>,
+) -> impl View {
+    let local = forward.local;
+    let failed = matches!(forward.status, PortForwardStatus::Failed(_));
+    let mut status = match &forward.status {
+        PortForwardStatus::Starting => "Starting".to_owned(),
+        PortForwardStatus::Forwarded => "Forwarded".to_owned(),
+        PortForwardStatus::Failed(err) => format!("Failed: {err}"),
+    };
+    if forward.detected {
+        status.push_str(", detected");
+    }
+    let can_open = forward.status == PortForwardStatus::Forwarded;
+
+    stack((
+        label(move || format!("localhost:{} → {}", forward.local, forward.remote))
+            .style(|s| s.margin_left(10.0).margin_right(10.0)),
+        label(move || status.clone()).style(move |s| {
+            let config = config.get();
+            s.flex_grow(1.0)
+                .flex_basis(0.0)
+                .min_width(0.0)
+                .text_ellipsis()
+                .color(if failed {
+                    config.color(LapceColor::LAPCE_ERROR)
+                } else {
+                    config.color(LapceColor::EDITOR_DIM)
+                })
+        }),
+        {
+            let ports = ports.clone();
+            clickable_icon(
+                || LapceIcons::LINK,
+                move || {
+                    ports.open_in_browser(local);
+                },
+                || false,
+                move || !can_open,
+                || "Open in Browser",
+                config,
+            )
+            .style(|s| s.margin_right(4.0))
+        },
+        clickable_icon(
+            || LapceIcons::CLOSE,
+            move || {
+                ports.stop(local);
+            },
+            || false,
+            || false,
+            || "Stop Forwarding",
+            config,
+        )
+        .style(|s| s.margin_right(10.0)),
+    ))
+    .style(move |s| {
+        s.padding_vert(4.0)
+            .width_pct(100.0)
+            .items_center()
+            .cursor(CursorStyle::Default)
+            .hover(|s| {
+                s.background(
+                    config.get().color(LapceColor::PANEL_HOVERED_BACKGROUND),
+                )
+            })
+    })
+}
@@ -12,7 +12,9 @@
 };
 use lsp_types::{DiagnosticRelatedInformation, DiagnosticSeverity};
 
-use super::{data::PanelSection, position::PanelPosition, view::PanelBuilder};
+use super::{
+    data::PanelSection, kind::PanelKind, position::PanelPosition, view::PanelBuilder,
+};
 use crate::{
     command::InternalCommand,
     config::{color::LapceColor, icon::LapceIcons, LapceConfig},
@@ -30,6 +32,20 @@ pub fn problem_panel(
 ) -> impl View {
     let config = window_tab_data.common.config;
     let is_bottom = position.is_bottom();
+
+    // Servers using the pull model only report problems of files which aren't
+    // open when asked for, so refresh them whenever the panel is shown.
+    let panel = window_tab_data.panel.clone();
+    let proxy = window_tab_data.common.proxy.clone();
+    create_effect(move |was_visible: Option<bool>| {
+        panel.styles.track();
+        let is_visible = panel.is_panel_visible(&PanelKind::Problem);
+        if is_visible && was_visible != Some(true) {
+            proxy.pull_workspace_diagnostics();
+        }
+        is_visible
+    });
+
     PanelBuilder::new(config, position)
         .add_style(
             "Errors",
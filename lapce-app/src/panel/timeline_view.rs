@@ -0,0 +1,115 @@
+use std::{rc::Rc, sync::Arc};
+
+use floem::{
+    reactive::{ReadSignal, SignalGet, SignalWith},
+    style::CursorStyle,
+    views::{container, dyn_stack, label, scroll, stack, svg, Decorators},
+    View,
+};
+
+use super::position::PanelPosition;
+use crate::{
+    app::clickable_icon,
+    config::{color::LapceColor, icon::LapceIcons, LapceConfig},
+    timeline::{TimelineData, TimelineEntry},
+    window_tab::WindowTabData,
+};
+
+pub fn timeline_panel(
+    window_tab_data: Rc<WindowTabData>,
+    _position: PanelPosition,
+) -> impl View {
+    let timeline = window_tab_data.timeline.clone();
+    let config = timeline.common.config;
+    let path = timeline.path;
+    let entries = timeline.entries;
+
+    stack((
+        label(move || {
+            if path.with(Option::is_none) {
+                "Open a file to see its saved versions and commits".to_owned()
+            } else {
+                "The file has no saved versions or commits".to_owned()
+            }
+        })
+        .style(move |s| {
+            s.padding(10.0)
+                .color(config.get().color(LapceColor::EDITOR_DIM))
+                .apply_if(entries.with(|entries| !entries.is_empty()), |s| s.hide())
+        }),
+        scroll(
+            dyn_stack(
+                move || entries.get(),
+                |entry| entry.history.clone(),
+                move |entry| timeline_entry_view(timeline.clone(), entry, config),
+            )
+            .style(|s| s.flex_col().width_pct(100.0)),
+        )
+        .style(|s| s.flex_grow(1.0).flex_basis(0.0).min_height(0.0)),
+    ))
+    .style(|s| s.absolute().size_pct(100.0, 100.0).flex_col())
+    .debug_name("Timeline Panel")
+}
+
+fn timeline_entry_view(
+    timeline: TimelineData,
+    entry: TimelineEntry,
+    config: ReadSignal<Arc<LapceConfig>>,
+) -> impl View {
+    let icon = if entry.is_local() {
+        LapceIcons::TIMELINE
+    } else {
+        LapceIcons::SCM
+    };
+    let title = entry.title.clone();
+    let detail = entry.detail.clone();
+    let open_history = entry.history.clone();
+    let restore_history = entry.history;
+    let open_timeline = timeline.clone();
+
+    stack((
+        svg(move || config.get().ui_svg(icon)).style(move |s| {
+            let config = config.get();
+            let size = config.ui.icon_size() as f32;
+            s.size(size, size)
+                .flex_shrink(0.0)
+                .margin_left(10.0)
+                .margin_right(6.0)
+                .color(config.color(LapceColor::LAPCE_ICON_ACTIVE))
+        }),
+        container(label(move || title.clone()).style(|s| s.text_ellipsis()))
+            .style(|s| s.margin_right(6.0).min_width(0.0)),
+        label(move || detail.clone()).style(move |s| {
+            s.flex_grow(1.0)
+                .flex_basis(0.0)
+                .min_width(0.0)
+                .text_ellipsis()
+                .color(config.get().color(LapceColor::EDITOR_DIM))
+        }),
+        clickable_icon(
+            || LapceIcons::TIMELINE_RESTORE,
+            move || {
+                timeline.restore(restore_history.clone());
+            },
+            || false,
+            || false,
+            || "Restore",
+            config,
+        )
+        .style(|s| s.margin_right(10.0)),
+    ))
+    .on_click_stop(move |_| {
+        open_timeline.open(open_history.clone());
+    })
+    .style(move |s| {
+        s.padding_vert(4.0)
+            .width_pct(100.0)
+            .items_center()
+            .cursor(CursorStyle::Pointer)
+            .hover(|s| {
+                s.background(
+                    config.get().color(LapceColor::PANEL_HOVERED_BACKGROUND),
+                )
+            })
+    })
+}
@@ -21,10 +21,12 @@
     global_search_view::global_search_panel,
     kind::PanelKind,
     plugin_view::plugin_panel,
+    ports_view::ports_panel,
     position::{PanelContainerPosition, PanelPosition},
     problem_view::problem_panel,
     source_control_view::source_control_panel,
     terminal_view::terminal_panel,
+    timeline_view::timeline_panel,
 };
 use crate::{
     app::{clickable_icon, clickable_icon_base},
@@ -505,6 +507,12 @@ fn panel_view(
                     implementation_panel(window_tab_data.clone(), position)
                         .into_any()
                 }
+                PanelKind::Ports => {
+                    ports_panel(window_tab_data.clone(), position).into_any()
+                }
+                PanelKind::Timeline => {
+                    timeline_panel(window_tab_data.clone(), position).into_any()
+                }
             };
             view.style(|s| s.size_pct(100.0, 100.0))
         },
@@ -561,6 +569,8 @@ fn panel_picker(
                 PanelKind::DocumentSymbol => "Document Symbol",
                 PanelKind::References => "References",
                 PanelKind::Implementation => "Implementation",
+                PanelKind::Ports => "Ports",
+                PanelKind::Timeline => "Timeline",
             };
             let icon = p.svg_name();
             let is_active = {
@@ -0,0 +1,389 @@
+use std::{collections::HashSet, rc::Rc};
+
+use floem::{
+    ext_event::create_ext_action,
+    keyboard::Modifiers,
+    reactive::{RwSignal, Scope, SignalGet, SignalUpdate, SignalWith},
+};
+use lapce_core::{command::EditCommand, mode::Mode};
+use lapce_xi_rope::Rope;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::{
+    command::{CommandExecuted, CommandKind, InternalCommand},
+    config::core::SshHostConfig,
+    editor::EditorData,
+    keypress::{condition::Condition, KeyPressFocus},
+    main_split::Editors,
+    proxy::{forward_port, stop_port_forward},
+    window_tab::CommonData,
+    workspace::{LapceWorkspaceType, SshHost},
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PortForwardStatus {
+    Starting,
+    Forwarded,
+    Failed(String),
+}
+
+/// A port on the remote host that is reachable on this machine.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PortForward {
+    pub local: u16,
+    pub remote: u16,
+    /// Whether it was forwarded because a terminal said something listens on
+    /// it, rather than by hand
+    pub detected: bool,
+    pub status: PortForwardStatus,
+}
+
+#[derive(Clone, Debug)]
+pub struct PortsData {
+    pub forwards: RwSignal<im::Vector<PortForward>>,
+    /// The remote ports whose detected forward was stopped, which aren't
+    /// forwarded again when a terminal mentions them
+    pub stopped: RwSignal<HashSet<u16>>,
+    /// Where the port to forward is typed, as `remote` or `local:remote`
+    pub input_editor: EditorData,
+    pub common: Rc<CommonData>,
+}
+
+impl KeyPressFocus for PortsData {
+    fn get_mode(&self) -> Mode {
+        Mode::Insert
+    }
+
+    fn check_condition(&self, condition: Condition) -> bool {
+        matches!(condition, Condition::PanelFocus)
+    }
+
+    fn run_command(
+        &self,
+        command: &crate::command::LapceCommand,
+        count: Option<usize>,
+        mods: Modifiers,
+    ) -> CommandExecuted {
+        match &command.kind {
+            CommandKind::Workbench(_) => {}
+            CommandKind::Scroll(_) => {}
+            CommandKind::Focus(_) => {}
+            CommandKind::Edit(EditCommand::InsertNewLine) => {
+                self.forward_input();
+                return CommandExecuted::Yes;
+            }
+            CommandKind::Edit(_)
+            | CommandKind::Move(_)
+            | CommandKind::MultiSelection(_) => {
+                return self.input_editor.run_command(command, count, mods);
+            }
+            CommandKind::MotionMode(_) => {}
+        }
+        CommandExecuted::No
+    }
+
+    fn receive_char(&self, c: &str) {
+        self.input_editor.receive_char(c);
+    }
+}
+
+impl PortsData {
+    pub fn new(cx: Scope, editors: Editors, common: Rc<CommonData>) -> Self {
+        Self {
+            forwards: cx.create_rw_signal(im::Vector::new()),
+            stopped: cx.create_rw_signal(HashSet::new()),
+            input_editor: editors.make_local(cx, common.clone()),
+            common,
+        }
+    }
+
+    /// The host that ports are forwarded from, as only SSH workspaces can
+    /// forward ports.
+    pub fn ssh_host(&self) -> Option<SshHost> {
+        match &self.common.workspace.kind {
+            LapceWorkspaceType::RemoteSSH(ssh) => Some(ssh.clone()),
+            _ => None,
+        }
+    }
+
+    fn ssh_config(&self, ssh: &SshHost) -> SshHostConfig {
+        self.common
+            .config
+            .get_untracked()
+            .core
+            .ssh_hosts
+            .get(&ssh.host)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Forward the port typed in the input.
+    fn forward_input(&self) {
+        let input = self
+            .input_editor
+            .doc()
+            .buffer
+            .with_untracked(|b| b.to_string());
+        if let Some((local, remote)) = parse_port_forward(&input) {
+            self.input_editor.doc().reload(Rope::from(""), true);
+            self.forward(local, remote, false);
+        }
+    }
+
+    pub fn forward(&self, local: u16, remote: u16, detected: bool) {
+        let Some(ssh) = self.ssh_host() else {
+            return;
+        };
+        if detected {
+            if self
+                .stopped
+                .with_untracked(|stopped| stopped.contains(&remote))
+            {
+                return;
+            }
+        } else {
+            self.stopped.update(|stopped| {
+                stopped.remove(&remote);
+            });
+        }
+        if self
+            .forwards
+            .with_untracked(|forwards| forwards.iter().any(|f| f.local == local))
+        {
+            return;
+        }
+
+        self.forwards.update(|forwards| {
+            forwards.push_back(PortForward {
+                local,
+                remote,
+                detected,
+                status: PortForwardStatus::Starting,
+            });
+        });
+
+        let config = self.ssh_config(&ssh);
+        let forwards = self.forwards;
+        let send = create_ext_action(self.common.scope, move |result| {
+            let status = match result {
+                Ok(()) => PortForwardStatus::Forwarded,
+                Err(err) => PortForwardStatus::Failed(err),
+            };
+            forwards.update(|forwards| {
+                if let Some(forward) = forwards.iter_mut().find(|f| f.local == local)
+                {
+                    forward.status = status;
+                }
+            });
+        });
+        std::thread::Builder::new()
+            .name("PortForward".to_owned())
+            .spawn(move || {
+                send(
+                    forward_port(&ssh, &config, local, remote)
+                        .map_err(|err| err.to_string()),
+                );
+            })
+            .unwrap();
+    }
+
+    pub fn stop(&self, local: u16) {
+        let Some(forward) = self
+            .forwards
+            .try_update(|forwards| {
+                let index = forwards.iter().position(|f| f.local == local)?;
+                Some(forwards.remove(index))
+            })
+            .flatten()
+        else {
+            return;
+        };
+        if forward.detected {
+            self.stopped.update(|stopped| {
+                stopped.insert(forward.remote);
+            });
+        }
+        if forward.status != PortForwardStatus::Forwarded {
+            return;
+        }
+        let Some(ssh) = self.ssh_host() else {
+            return;
+        };
+        let config = self.ssh_config(&ssh);
+        std::thread::Builder::new()
+            .name("PortForward".to_owned())
+            .spawn(move || {
+                if let Err(err) =
+                    stop_port_forward(&ssh, &config, forward.local, forward.remote)
+                {
+                    tracing::error!("{:?}", err);
+                }
+            })
+            .unwrap();
+    }
+
+    pub fn open_in_browser(&self, local: u16) {
+        self.common
+            .internal_command
+            .send(InternalCommand::OpenWebUri {
+                uri: format!("http://localhost:{local}"),
+            });
+    }
+
+    /// Forward the ports that a terminal said something listens on, if the
+    /// settings ask for it.
+    pub fn ports_detected(&self, ports: &[u16]) {
+        if !self.common.config.get_untracked().core.auto_forward_ports {
+            return;
+        }
+        for port in ports {
+            self.forward(*port, *port, true);
+        }
+    }
+}
+
+/// Parse a port to forward typed as `remote`, to the same port on this
+/// machine, or as `local:remote`.
+pub fn parse_port_forward(input: &str) -> Option<(u16, u16)> {
+    let input = input.trim();
+    match input.split_once(':') {
+        Some((local, remote)) => {
+            Some((local.trim().parse().ok()?, remote.trim().parse().ok()?))
+        }
+        None => {
+            let port = input.parse().ok()?;
+            Some((port, port))
+        }
+    }
+    .filter(|(local, remote)| *local != 0 && *remote != 0)
+}
+
+/// Finds the ports that a terminal's output says something listens on, across
+/// the chunks the output arrives in. Only whole lines are looked at, so that a
+/// message split between two chunks is still found, and a port whose digits
+/// haven't all arrived isn't taken for a shorter one.
+#[derive(Default)]
+pub struct PortDetector {
+    /// The start of the line that hasn't ended yet
+    line: Vec<u8>,
+}
+
+impl PortDetector {
+    /// Lines longer than this are cut from the front, as servers announce
+    /// themselves in short lines.
+    const MAX_LINE_LEN: usize = 1024;
+
+    pub fn detect(&mut self, content: &[u8]) -> Vec<u16> {
+        let mut ports = Vec::new();
+        let mut rest = content;
+        while let Some(end) = rest.iter().position(|b| *b == b'\n' || *b == b'\r') {
+            self.line.extend_from_slice(&rest[..end]);
+            for port in detect_listening_ports(&String::from_utf8_lossy(&self.line))
+            {
+                if !ports.contains(&port) {
+                    ports.push(port);
+                }
+            }
+            self.line.clear();
+            rest = &rest[end + 1..];
+        }
+        self.line.extend_from_slice(rest);
+        if self.line.len() > Self::MAX_LINE_LEN {
+            self.line.drain(..self.line.len() - Self::MAX_LINE_LEN);
+        }
+        ports
+    }
+}
+
+/// The ports that terminal output says something listens on, like
+/// `Listening on http://localhost:3000`. Addresses only count when the line
+/// says something listens or serves there before them, so that commands like
+/// `curl localhost:3000` aren't taken for servers.
+pub fn detect_listening_ports(output: &str) -> Vec<u16> {
+    static ANSI_ESCAPE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"\x1b\[[0-9;?]*[A-Za-z]").unwrap());
+    static LISTENING_PORT: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(concat!(
+            r"(?i)\b(?:listen(?:s|ing)?|serving|local:",
+            r"|(?:server|started|running|available) (?:at|on))",
+            r"[^\n]*?(?:localhost|127\.0\.0\.1|0\.0\.0\.0|\[::1?\]):(\d{2,5})\b",
+            r"|listening on (?:port )?(\d{2,5})\b",
+        ))
+        .unwrap()
+    });
+
+    let output = ANSI_ESCAPE.replace_all(output, "");
+    let mut ports = Vec::new();
+    for captures in LISTENING_PORT.captures_iter(&output) {
+        let port = captures
+            .get(1)
+            .or_else(|| captures.get(2))
+            .and_then(|port| port.as_str().parse::<u16>().ok());
+        if let Some(port) = port {
+            if port != 0 && !ports.contains(&port) {
+                ports.push(port);
+            }
+        }
+    }
+    ports
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_port_forward() {
+        assert_eq!(parse_port_forward("3000"), Some((3000, 3000)));
+        assert_eq!(parse_port_forward(" 8080:3000 "), Some((8080, 3000)));
+        assert_eq!(parse_port_forward("0"), None);
+        assert_eq!(parse_port_forward("70000"), None);
+        assert_eq!(parse_port_forward("web"), None);
+    }
+
+    #[test]
+    fn test_detect_listening_ports() {
+        assert_eq!(
+            detect_listening_ports("Listening on http://localhost:3000"),
+            vec![3000]
+        );
+        assert_eq!(
+            detect_listening_ports(
+                "  \x1b[32m➜\x1b[39m  Local: \x1b[36mhttp://localhost:\x1b[1m5173\x1b[22m/\x1b[39m"
+            ),
+            vec![5173]
+        );
+        assert_eq!(
+            detect_listening_ports(
+                "Serving on 0.0.0.0:8000 and [::]:8000, listening on port 9229"
+            ),
+            vec![8000, 9229]
+        );
+        assert_eq!(
+            detect_listening_ports(
+                "Starting development server at http://127.0.0.1:8000/"
+            ),
+            vec![8000]
+        );
+        assert_eq!(detect_listening_ports("Compiled in 3000ms"), vec![]);
+        assert_eq!(
+            detect_listening_ports("user@server:~$ curl localhost:3000/listen"),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_port_detector() {
+        let mut detector = PortDetector::default();
+        assert_eq!(detector.detect(b"Listening on http://local"), vec![]);
+        assert_eq!(detector.detect(b"host:30"), vec![]);
+        assert_eq!(detector.detect(b"00\r\nready\n"), vec![3000]);
+        assert_eq!(detector.detect(b"curl localhost:8080\n"), vec![]);
+
+        // A character split between chunks isn't lost.
+        let line = "➜ Local: http://localhost:5173/\n".as_bytes();
+        assert_eq!(detector.detect(&line[..1]), vec![]);
+        assert_eq!(detector.detect(&line[1..]), vec![5173]);
+    }
+}
@@ -11,12 +11,18 @@
 };
 use tracing::error;
 
-use self::{remote::start_remote, ssh::SshRemote};
+use self::{command::CommandRemote, remote::start_remote, ssh::SshRemote};
+pub use self::{
+    remote::RemotePrompts,
+    ssh::{askpass, forward_port, ssh_config_hosts, stop_port_forward, ASKPASS_ENV},
+};
 use crate::{
+    config::core::SshHostConfig,
     terminal::event::TermEvent,
     workspace::{LapceWorkspace, LapceWorkspaceType},
 };
 
+mod command;
 mod remote;
 mod ssh;
 #[cfg(windows)]
@@ -32,6 +38,7 @@ pub struct ProxyData {
     pub proxy_rpc: ProxyRpcHandler,
     pub core_rpc: CoreRpcHandler,
     pub notification: ReadSignal<Option<CoreNotification>>,
+    pub remote_prompts: RemotePrompts,
 }
 
 impl ProxyData {
@@ -46,14 +53,18 @@ pub fn new_proxy(
     disabled_volts: Vec<VoltID>,
     extra_plugin_paths: Vec<PathBuf>,
     plugin_configurations: HashMap<String, HashMap<String, serde_json::Value>>,
+    language_server_priority: HashMap<String, Vec<String>>,
+    ssh_hosts: HashMap<String, SshHostConfig>,
     term_tx: Sender<(TermId, TermEvent)>,
 ) -> ProxyData {
     let proxy_rpc = ProxyRpcHandler::new();
     let core_rpc = CoreRpcHandler::new();
+    let remote_prompts = RemotePrompts::default();
 
     {
         let core_rpc = core_rpc.clone();
         let proxy_rpc = proxy_rpc.clone();
+        let remote_prompts = remote_prompts.clone();
         std::thread::Builder::new()
             .name("ProxyRpcHandler".to_owned())
             .spawn(move || {
@@ -62,9 +73,11 @@ pub fn new_proxy(
                 });
                 proxy_rpc.initialize(
                     workspace.path.clone(),
+                    workspace.roots(),
                     disabled_volts,
                     extra_plugin_paths,
                     plugin_configurations,
+                    language_server_priority,
                     1,
                     1,
                 );
@@ -84,14 +97,32 @@ pub fn new_proxy(
                             .unwrap();
                     }
                     LapceWorkspaceType::RemoteSSH(remote) => {
+                        let config =
+                            ssh_hosts.get(&remote.host).cloned().unwrap_or_default();
                         if let Err(e) = start_remote(
-                            SshRemote {
-                                ssh: remote.clone(),
-                            },
+                            SshRemote::new(
+                                remote.clone(),
+                                config,
+                                core_rpc.clone(),
+                                remote_prompts,
+                            ),
                             core_rpc.clone(),
                             proxy_rpc.clone(),
                         ) {
                             error!("Failed to start SSH remote: {e}");
+                            connection_failed(&core_rpc, e);
+                        }
+                    }
+                    LapceWorkspaceType::RemoteCommand(remote) => {
+                        if let Err(e) = start_remote(
+                            CommandRemote {
+                                host: remote.clone(),
+                            },
+                            core_rpc.clone(),
+                            proxy_rpc.clone(),
+                        ) {
+                            error!("Failed to start command remote: {e}");
+                            connection_failed(&core_rpc, e);
                         }
                     }
                     #[cfg(windows)]
@@ -104,6 +135,7 @@ pub fn new_proxy(
                             proxy_rpc.clone(),
                         ) {
                             error!("Failed to start SSH remote: {e}");
+                            connection_failed(&core_rpc, e);
                         }
                     }
                 }
@@ -132,9 +164,18 @@ pub fn new_proxy(
         proxy_rpc,
         core_rpc,
         notification,
+        remote_prompts,
     }
 }
 
+fn connection_failed(core_rpc: &CoreRpcHandler, err: anyhow::Error) {
+    core_rpc.notification(CoreNotification::ProxyStatus {
+        status: ProxyStatus::Error {
+            message: err.to_string(),
+        },
+    });
+}
+
 impl CoreHandler for Proxy {
     fn handle_notification(&mut self, rpc: lapce_rpc::core::CoreNotification) {
         if let CoreNotification::UpdateTerminal { term_id, content } = &rpc {
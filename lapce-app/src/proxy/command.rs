@@ -0,0 +1,168 @@
+use std::{fs::File, path::Path, process::Command};
+
+use anyhow::{anyhow, Result};
+use tracing::debug;
+
+use super::remote::Remote;
+use crate::{proxy::new_command, workspace::CommandHost};
+
+/// A remote reached by running a command on this machine, like
+/// `podman exec -i <container>`, that runs the arguments after it on the host.
+pub struct CommandRemote {
+    pub host: CommandHost,
+}
+
+impl CommandRemote {
+    /// The command with the placeholders of a template filled in.
+    fn fill(&self, template: &[String], local: &Path, remote: &str) -> Vec<String> {
+        template
+            .iter()
+            .map(|arg| {
+                arg.replace("{name}", &self.host.name)
+                    .replace("{local}", &local.to_string_lossy())
+                    .replace("{remote}", remote)
+            })
+            .collect()
+    }
+
+    /// The command on this machine that runs the arguments after it on the
+    /// host.
+    fn host_command(&self) -> Command {
+        let command = self.fill(&self.host.command, Path::new(""), "");
+        let mut cmd = new_command(command.first().map(String::as_str).unwrap_or(""));
+        cmd.args(command.iter().skip(1));
+        cmd
+    }
+}
+
+/// The arguments that make the shell on the host write what it's given on
+/// stdin to `remote`. The path is passed on its own rather than in the
+/// script, so that nothing in it is run. Remote paths are written for a
+/// shell, so `~` and escaped spaces are turned into what they stand for.
+fn cat_args(remote: &str) -> [String; 5] {
+    let (script, path) = match remote.strip_prefix("~/") {
+        Some(path) => ("cat > \"$HOME/$1\"", path),
+        None => ("cat > \"$1\"", remote),
+    };
+    [
+        "sh".to_string(),
+        "-c".to_string(),
+        script.to_string(),
+        "sh".to_string(),
+        path.replace("\\ ", " "),
+    ]
+}
+
+impl Remote for CommandRemote {
+    fn upload_file(&self, local: impl AsRef<Path>, remote: &str) -> Result<()> {
+        let local = local.as_ref();
+        let output = if self.host.upload.is_empty() {
+            // The shell on the host writes the file it's given on stdin
+            self.host_command()
+                .args(cat_args(remote))
+                .stdin(File::open(local)?)
+                .output()?
+        } else {
+            let upload = self.fill(&self.host.upload, local, remote);
+            let (program, args) = upload
+                .split_first()
+                .ok_or_else(|| anyhow!("the upload command is empty"))?;
+            new_command(program).args(args).output()?
+        };
+
+        debug!("{}", String::from_utf8_lossy(&output.stderr));
+        debug!("{}", String::from_utf8_lossy(&output.stdout));
+
+        if !output.status.success() {
+            return Err(anyhow!("failed to upload {local:?} to {remote}"));
+        }
+        Ok(())
+    }
+
+    fn command_builder(&self) -> Command {
+        let mut cmd = self.host_command();
+        // Unlike ssh, these commands run their arguments as they are, so they
+        // go through a shell to get the pipes and `~` the remote setup needs
+        cmd.args(["sh", "-c", "eval \"$*\"", "sh"]);
+        cmd
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::config::core::RemoteCommand;
+
+    fn podman() -> HashMap<String, RemoteCommand> {
+        HashMap::from([(
+            "podman".to_string(),
+            RemoteCommand {
+                command: vec![
+                    "podman".to_string(),
+                    "exec".to_string(),
+                    "-i".to_string(),
+                    "{name}".to_string(),
+                ],
+                upload: vec![
+                    "podman".to_string(),
+                    "cp".to_string(),
+                    "{local}".to_string(),
+                    "{name}:{remote}".to_string(),
+                ],
+            },
+        )])
+    }
+
+    #[test]
+    fn test_host_from_string() {
+        let commands = podman();
+        let host = CommandHost::from_string(" podman:dev ", &commands).unwrap();
+        assert_eq!(host.template, "podman");
+        assert_eq!(host.name, "dev");
+        assert_eq!(host.to_string(), "podman:dev");
+
+        assert_eq!(CommandHost::from_string("podman:", &commands), None);
+        assert_eq!(CommandHost::from_string("docker:dev", &commands), None);
+        assert_eq!(CommandHost::from_string("dev", &commands), None);
+    }
+
+    #[test]
+    fn test_fill() {
+        let host = CommandHost::from_string("podman:dev", &podman()).unwrap();
+        let remote = CommandRemote { host };
+        assert_eq!(
+            remote.fill(&remote.host.upload, Path::new("/tmp/lapce"), "/opt/lapce"),
+            ["podman", "cp", "/tmp/lapce", "dev:/opt/lapce"]
+        );
+        assert_eq!(
+            remote.fill(&remote.host.command, Path::new(""), ""),
+            ["podman", "exec", "-i", "dev"]
+        );
+    }
+
+    #[test]
+    fn test_cat_args() {
+        assert_eq!(
+            cat_args("~/.local/share/my lapce/proxy; rm -rf x"),
+            [
+                "sh",
+                "-c",
+                "cat > \"$HOME/$1\"",
+                "sh",
+                ".local/share/my lapce/proxy; rm -rf x"
+            ]
+        );
+        assert_eq!(
+            cat_args("/opt/Application\\ Support/lapce"),
+            [
+                "sh",
+                "-c",
+                "cat > \"$1\"",
+                "sh",
+                "/opt/Application Support/lapce"
+            ]
+        );
+    }
+}
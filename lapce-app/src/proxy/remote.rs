@@ -1,26 +1,77 @@
 use std::{
+    collections::HashMap,
     io::{BufReader, Write},
     path::Path,
-    process::{Command, Stdio},
+    process::{Command, Output, Stdio},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
 };
 
 use anyhow::{anyhow, Result};
+use crossbeam_channel::Sender;
 use flate2::read::GzDecoder;
 use lapce_core::{
     directory::Directory,
     meta::{self, ReleaseType},
 };
 use lapce_rpc::{
-    core::CoreRpcHandler,
-    proxy::{ProxyRpc, ProxyRpcHandler},
+    core::{CoreNotification, CoreRpcHandler},
+    proxy::{
+        ProxyRpc, ProxyRpcHandler, ProxyStatus, RemotePrompt, RemotePromptKind,
+    },
     stdio_transport, RpcMessage,
 };
+use parking_lot::Mutex;
 use thiserror::Error;
 use tracing::{debug, error};
 
 const UNIX_PROXY_SCRIPT: &[u8] = include_bytes!("../../../extra/proxy.sh");
 const WINDOWS_PROXY_SCRIPT: &[u8] = include_bytes!("../../../extra/proxy.ps1");
 
+/// How long a prompt waits for the user before it's taken as cancelled
+const PROMPT_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// The prompts from connections to remotes, like for passwords, that are
+/// waiting for the user to answer them.
+#[derive(Clone, Default)]
+pub struct RemotePrompts {
+    id: Arc<AtomicU64>,
+    pending: Arc<Mutex<HashMap<u64, Sender<Option<String>>>>>,
+}
+
+impl RemotePrompts {
+    /// Ask the user through the window, and wait for the answer, which is
+    /// `None` if the prompt was cancelled.
+    pub fn ask(
+        &self,
+        core_rpc: &CoreRpcHandler,
+        prompt: String,
+        kind: RemotePromptKind,
+    ) -> Option<String> {
+        let id = self.id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = crossbeam_channel::bounded(1);
+        self.pending.lock().insert(id, tx);
+        core_rpc.notification(CoreNotification::RemotePrompt {
+            prompt: RemotePrompt { id, prompt, kind },
+        });
+        let answer = rx.recv_timeout(PROMPT_TIMEOUT).ok().flatten();
+        self.pending.lock().remove(&id);
+        answer
+    }
+
+    pub fn answer(&self, id: u64, answer: Option<String>) {
+        let tx = self.pending.lock().remove(&id);
+        if let Some(tx) = tx {
+            if let Err(err) = tx.send(answer) {
+                tracing::error!("{:?}", err);
+            }
+        }
+    }
+}
+
 #[derive(Clone, Copy, Error, Debug, PartialEq, Eq, strum_macros::Display)]
 #[strum(ascii_case_insensitive)]
 enum HostPlatform {
@@ -70,6 +121,20 @@ fn home_dir(&self) -> Result<String> {
     fn upload_file(&self, local: impl AsRef<Path>, remote: &str) -> Result<()>;
 
     fn command_builder(&self) -> Command;
+
+    /// Why a command run with [`Remote::command_builder`] couldn't reach the
+    /// host, if that's why it failed rather than the command itself failing.
+    fn connection_error(&self, _output: &Output) -> Option<String> {
+        None
+    }
+}
+
+fn progress(core_rpc: &CoreRpcHandler, message: &str) {
+    core_rpc.notification(CoreNotification::ProxyStatus {
+        status: ProxyStatus::Progress {
+            message: message.to_string(),
+        },
+    });
 }
 
 pub fn start_remote(
@@ -88,7 +153,8 @@ pub fn start_remote(
     // shells retain similar syntax, although shells like Nushell might not
     // work (hopefully no one uses it as login shell)
     use HostPlatform::*;
-    let (platform, architecture) = host_specification(&remote).unwrap();
+    progress(&core_rpc, "Detecting the remote platform");
+    let (platform, architecture) = host_specification(&remote)?;
 
     if platform == UnknownOS || architecture == HostArchitecture::UnknownArch {
         error!("detected remote host: {platform}/{architecture}");
@@ -130,6 +196,7 @@ pub fn start_remote(
         })
         .unwrap_or(false)
     {
+        progress(&core_rpc, "Installing the proxy on the remote");
         download_remote(
             &remote,
             &platform,
@@ -140,6 +207,7 @@ pub fn start_remote(
     };
 
     debug!("remote proxy path: {remote_proxy_path}");
+    progress(&core_rpc, "Starting the remote proxy");
 
     let mut child = match platform {
         // Force cmd.exe usage to resolve %envvar% variables
@@ -246,6 +314,10 @@ pub fn start_remote(
                     }
                 }
             }
+            // The proxy went away, like when the connection dropped
+            core_rpc.notification(CoreNotification::ProxyStatus {
+                status: ProxyStatus::Disconnected,
+            });
         })
         .unwrap();
 
@@ -403,6 +475,14 @@ fn host_specification(
 
     let cmd = remote.command_builder().args(["uname", "-sm"]).output();
 
+    if let Some(err) = cmd
+        .as_ref()
+        .ok()
+        .and_then(|cmd| remote.connection_error(cmd))
+    {
+        return Err(anyhow!(err));
+    }
+
     let spec = match cmd {
         Ok(cmd) => {
             let stdout = String::from_utf8_lossy(&cmd.stdout).to_lowercase();
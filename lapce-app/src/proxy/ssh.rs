@@ -1,13 +1,34 @@
-use std::{path::Path, process::Command};
+use std::{
+    collections::HashSet,
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    process::{Command, Output},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+};
 
-use anyhow::Result;
-use tracing::debug;
+use anyhow::{anyhow, Result};
+use interprocess::local_socket::{LocalSocketListener, LocalSocketStream};
+use lapce_core::directory::Directory;
+use lapce_rpc::{core::CoreRpcHandler, proxy::RemotePromptKind};
+use tracing::{debug, error};
 
-use super::remote::Remote;
-use crate::{proxy::new_command, workspace::SshHost};
+use super::remote::{Remote, RemotePrompts};
+use crate::{config::core::SshHostConfig, proxy::new_command, workspace::SshHost};
+
+/// Set to the socket of the window that is connecting when Lapce is run by
+/// `ssh` as its `SSH_ASKPASS`, see [`askpass`].
+pub const ASKPASS_ENV: &str = "LAPCE_ASKPASS";
+
+/// How many `Include`s deep `~/.ssh/config` is read
+const MAX_INCLUDE_DEPTH: usize = 8;
 
 pub struct SshRemote {
     pub ssh: SshHost,
+    pub config: SshHostConfig,
+    askpass: Option<AskpassListener>,
 }
 
 impl SshRemote {
@@ -25,13 +46,62 @@ impl SshRemote {
         "-o",
         "ConnectTimeout=15",
     ];
+
+    /// `ssh` asks for passwords, passphrases and whether to trust the host
+    /// through `prompts`, instead of on a terminal there isn't.
+    pub fn new(
+        ssh: SshHost,
+        config: SshHostConfig,
+        core_rpc: CoreRpcHandler,
+        prompts: RemotePrompts,
+    ) -> Self {
+        // Release builds on Windows have no stdout to give `ssh` the answers
+        // through, so `ssh` asks on its own there
+        let askpass = if cfg!(windows) {
+            None
+        } else {
+            match AskpassListener::start(core_rpc, prompts) {
+                Ok(askpass) => Some(askpass),
+                Err(err) => {
+                    error!("Failed to listen for ssh prompts: {err}");
+                    None
+                }
+            }
+        };
+        Self {
+            ssh,
+            config,
+            askpass,
+        }
+    }
+
+    /// The arguments and environment that `ssh` and `scp` share.
+    fn common_args(&self, cmd: &mut Command) {
+        cmd.args(Self::SSH_ARGS);
+
+        if let Some(jump) = self.config.proxy_jump.as_ref() {
+            cmd.arg("-J").arg(jump);
+        }
+
+        if let Some(identity_file) = self.config.identity_file.as_ref() {
+            cmd.arg("-i").arg(expand_home(identity_file));
+        }
+
+        if let Some(askpass) = self.askpass.as_ref() {
+            if let Ok(exe) = std::env::current_exe() {
+                cmd.env("SSH_ASKPASS", exe)
+                    .env("SSH_ASKPASS_REQUIRE", "force")
+                    .env(ASKPASS_ENV, &askpass.socket);
+            }
+        }
+    }
 }
 
 impl Remote for SshRemote {
     fn upload_file(&self, local: impl AsRef<Path>, remote: &str) -> Result<()> {
         let mut cmd = new_command("scp");
 
-        cmd.args(Self::SSH_ARGS);
+        self.common_args(&mut cmd);
 
         if let Some(port) = self.ssh.port {
             cmd.arg("-P").arg(port.to_string());
@@ -39,18 +109,25 @@ fn upload_file(&self, local: impl AsRef<Path>, remote: &str) -> Result<()> {
 
         let output = cmd
             .arg(local.as_ref())
-            .arg(dbg!(format!("{}:{remote}", self.ssh.user_host())))
+            .arg(format!("{}:{remote}", self.ssh.user_host()))
             .output()?;
 
         debug!("{}", String::from_utf8_lossy(&output.stderr));
         debug!("{}", String::from_utf8_lossy(&output.stdout));
 
+        if !output.status.success() {
+            return Err(anyhow!(
+                "failed to upload {remote}: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
         Ok(())
     }
 
     fn command_builder(&self) -> Command {
         let mut cmd = new_command("ssh");
-        cmd.args(Self::SSH_ARGS);
+        self.common_args(&mut cmd);
 
         if let Some(port) = self.ssh.port {
             cmd.arg("-p").arg(port.to_string());
@@ -64,4 +141,373 @@ fn command_builder(&self) -> Command {
 
         cmd
     }
+
+    fn connection_error(&self, output: &Output) -> Option<String> {
+        // ssh exits with 255 when it fails itself, rather than the command
+        if output.status.code() != Some(255) {
+            return None;
+        }
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message = stderr
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with("debug"))
+            .last()
+            .unwrap_or("ssh failed to connect");
+        Some(message.to_string())
+    }
+}
+
+/// Forward `local` on this machine to `remote` on the host, through the
+/// connection to the host that is already open.
+pub fn forward_port(
+    ssh: &SshHost,
+    config: &SshHostConfig,
+    local: u16,
+    remote: u16,
+) -> Result<()> {
+    control_port_forward(ssh, config, "forward", local, remote)
+}
+
+/// Stop forwarding `local` on this machine to `remote` on the host.
+pub fn stop_port_forward(
+    ssh: &SshHost,
+    config: &SshHostConfig,
+    local: u16,
+    remote: u16,
+) -> Result<()> {
+    control_port_forward(ssh, config, "cancel", local, remote)
+}
+
+#[cfg(unix)]
+fn control_port_forward(
+    ssh: &SshHost,
+    config: &SshHostConfig,
+    operation: &str,
+    local: u16,
+    remote: u16,
+) -> Result<()> {
+    let remote_ssh = SshRemote {
+        ssh: ssh.clone(),
+        config: config.clone(),
+        askpass: None,
+    };
+    let mut cmd = new_command("ssh");
+    remote_ssh.common_args(&mut cmd);
+
+    if let Some(port) = ssh.port {
+        cmd.arg("-p").arg(port.to_string());
+    }
+
+    // The control master does the forwarding, so this returns right away
+    let output = cmd
+        .arg("-O")
+        .arg(operation)
+        .arg("-L")
+        .arg(format!("{local}:localhost:{remote}"))
+        .arg(ssh.user_host())
+        .output()?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(windows)]
+fn control_port_forward(
+    _ssh: &SshHost,
+    _config: &SshHostConfig,
+    _operation: &str,
+    _local: u16,
+    _remote: u16,
+) -> Result<()> {
+    Err(anyhow!(
+        "forwarding ports needs an ssh control master, which Windows doesn't have"
+    ))
+}
+
+/// The socket that the `SSH_ASKPASS` run by `ssh` sends its prompts to, for
+/// as long as the [`SshRemote`] is around.
+struct AskpassListener {
+    socket: PathBuf,
+    closed: Arc<AtomicBool>,
+}
+
+impl AskpassListener {
+    fn start(core_rpc: CoreRpcHandler, prompts: RemotePrompts) -> Result<Self> {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+        let socket = Directory::proxy_directory()
+            .ok_or_else(|| anyhow!("can't find proxy directory"))?
+            .join(format!(
+                "askpass-{}-{}.sock",
+                std::process::id(),
+                NEXT_ID.fetch_add(1, Ordering::Relaxed)
+            ));
+        if socket.exists() {
+            std::fs::remove_file(&socket)?;
+        }
+        let listener = LocalSocketListener::bind(socket.clone())?;
+        let closed = Arc::new(AtomicBool::new(false));
+
+        {
+            let closed = closed.clone();
+            std::thread::Builder::new()
+                .name("SshAskpass".to_owned())
+                .spawn(move || {
+                    for stream in listener.incoming().flatten() {
+                        if closed.load(Ordering::Relaxed) {
+                            return;
+                        }
+                        let core_rpc = core_rpc.clone();
+                        let prompts = prompts.clone();
+                        std::thread::spawn(move || {
+                            if let Err(err) =
+                                answer_askpass(stream, &core_rpc, &prompts)
+                            {
+                                error!("{:?}", err);
+                            }
+                        });
+                    }
+                })?;
+        }
+
+        Ok(Self { socket, closed })
+    }
+}
+
+impl Drop for AskpassListener {
+    fn drop(&mut self) {
+        self.closed.store(true, Ordering::Relaxed);
+        // Wake the listener up so it sees it's closed
+        let _ = LocalSocketStream::connect(self.socket.clone());
+        if let Err(err) = std::fs::remove_file(&self.socket) {
+            error!("{:?}", err);
+        }
+    }
+}
+
+/// Read a prompt sent by [`askpass`], and send back what the user answers.
+fn answer_askpass(
+    stream: LocalSocketStream,
+    core_rpc: &CoreRpcHandler,
+    prompts: &RemotePrompts,
+) -> Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let prompt: String = serde_json::from_str(&line)?;
+
+    let kind = prompt_kind(&prompt);
+    let answer = prompts.ask(core_rpc, prompt.trim().to_string(), kind);
+
+    let stream = reader.get_mut();
+    writeln!(stream, "{}", serde_json::to_string(&answer)?)?;
+    stream.flush()?;
+    Ok(())
+}
+
+/// Act as `SSH_ASKPASS`: ask the window listening on `socket` the `prompt`
+/// that `ssh` has, and print the answer for `ssh` to read.
+pub fn askpass(socket: &str, prompt: &str) -> Result<()> {
+    let stream = LocalSocketStream::connect(socket)?;
+    let mut reader = BufReader::new(stream);
+    {
+        let stream = reader.get_mut();
+        writeln!(stream, "{}", serde_json::to_string(prompt)?)?;
+        stream.flush()?;
+    }
+
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let answer: Option<String> = serde_json::from_str(&line)?;
+    let answer = answer.ok_or_else(|| anyhow!("the prompt was cancelled"))?;
+    println!("{answer}");
+    Ok(())
+}
+
+/// What an `ssh` prompt asks for, from its text.
+pub fn prompt_kind(prompt: &str) -> RemotePromptKind {
+    let prompt = prompt.to_lowercase();
+    if prompt.contains("(yes/no") {
+        RemotePromptKind::Confirm
+    } else if prompt.contains("fingerprint:") {
+        // Asked again after neither yes nor no was answered
+        RemotePromptKind::Text
+    } else {
+        // Passwords, passphrases, PINs and verification codes
+        RemotePromptKind::Secret
+    }
+}
+
+/// The hosts named in `~/.ssh/config` and the files it includes, leaving out
+/// patterns.
+pub fn ssh_config_hosts() -> Vec<String> {
+    let Some(ssh_dir) = Directory::home_dir().map(|home| home.join(".ssh")) else {
+        return Vec::new();
+    };
+    let mut hosts = Vec::new();
+    read_ssh_config(&ssh_dir, &ssh_dir.join("config"), 0, &mut hosts);
+
+    let mut seen = HashSet::new();
+    hosts.retain(|host| seen.insert(host.clone()));
+    hosts
+}
+
+fn read_ssh_config(
+    ssh_dir: &Path,
+    path: &Path,
+    depth: usize,
+    hosts: &mut Vec<String>,
+) {
+    let Ok(content) = std::fs::read_to_string(path) else {
+        return;
+    };
+    let config = parse_ssh_config(&content);
+    hosts.extend(config.hosts);
+
+    if depth >= MAX_INCLUDE_DEPTH {
+        return;
+    }
+    for include in config.includes {
+        let include = expand_home(&include);
+        // Relative includes are relative to `~/.ssh`
+        let include = ssh_dir.join(include);
+        for path in glob_paths(&include) {
+            read_ssh_config(ssh_dir, &path, depth + 1, hosts);
+        }
+    }
+}
+
+/// The files that `pattern` matches, where only the file name may have
+/// wildcards.
+fn glob_paths(pattern: &Path) -> Vec<PathBuf> {
+    let Some(file_name) = pattern.file_name().and_then(|n| n.to_str()) else {
+        return Vec::new();
+    };
+    if !file_name.contains(['*', '?']) {
+        return vec![pattern.to_path_buf()];
+    }
+    let (Some(dir), Ok(glob)) = (pattern.parent(), globset::Glob::new(file_name))
+    else {
+        return Vec::new();
+    };
+    let matcher = glob.compile_matcher();
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.file_name().is_some_and(|name| matcher.is_match(name)))
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// What [`ssh_config_hosts`] needs out of one ssh config file.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct SshConfig {
+    hosts: Vec<String>,
+    includes: Vec<String>,
+}
+
+fn parse_ssh_config(content: &str) -> SshConfig {
+    let mut config = SshConfig::default();
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (keyword, args) =
+            match line.find(|c: char| c.is_whitespace() || c == '=') {
+                Some(i) => (
+                    &line[..i],
+                    line[i..]
+                        .trim_start_matches(|c: char| c.is_whitespace() || c == '='),
+                ),
+                None => (line, ""),
+            };
+        if keyword.eq_ignore_ascii_case("host") {
+            config.hosts.extend(
+                args.split_whitespace()
+                    .map(|host| host.trim_matches('"'))
+                    .filter(|host| !host.contains(['*', '?', '!']))
+                    .map(str::to_string),
+            );
+        } else if keyword.eq_ignore_ascii_case("include") {
+            config.includes.extend(
+                args.split_whitespace()
+                    .map(|path| path.trim_matches('"').to_string()),
+            );
+        }
+    }
+    config
+}
+
+/// Expand a leading `~` to the home directory, like ssh does for paths.
+fn expand_home(path: &str) -> PathBuf {
+    if let Some(rest) = path.strip_prefix("~/") {
+        if let Some(home) = Directory::home_dir() {
+            return home.join(rest);
+        }
+    }
+    PathBuf::from(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ssh_config() {
+        let config = parse_ssh_config(
+            "# comment\n\
+             Include config.d/*\n\
+             Host dev staging *.internal !prod\n\
+             \tHostName dev.example.com\n\
+             \tProxyJump bastion\n\
+             host=box\n\
+             Match host foo\n",
+        );
+        assert_eq!(
+            config,
+            SshConfig {
+                hosts: vec![
+                    "dev".to_string(),
+                    "staging".to_string(),
+                    "box".to_string()
+                ],
+                includes: vec!["config.d/*".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn test_prompt_kind() {
+        assert_eq!(
+            prompt_kind(
+                "The authenticity of host 'dev (10.0.0.1)' can't be established.\n\
+                 Are you sure you want to continue connecting (yes/no/[fingerprint])? "
+            ),
+            RemotePromptKind::Confirm
+        );
+        assert_eq!(
+            prompt_kind("Please type 'yes', 'no' or the fingerprint: "),
+            RemotePromptKind::Text
+        );
+        assert_eq!(
+            prompt_kind("user@dev's password: "),
+            RemotePromptKind::Secret
+        );
+        assert_eq!(
+            prompt_kind("Enter passphrase for key '/home/user/.ssh/id_ed25519': "),
+            RemotePromptKind::Secret
+        );
+    }
 }
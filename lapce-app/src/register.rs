@@ -0,0 +1,178 @@
+use std::collections::{HashMap, VecDeque};
+
+use floem::views::editor::text::SystemClipboard;
+use lapce_core::{
+    mode::VisualMode,
+    register::{Clipboard, RegisterData},
+};
+
+/// How many deletes the numbered registers `"1` to `"9` hold.
+const DELETE_RING_SIZE: usize = 9;
+/// How many yanks are kept for pasting from history.
+const YANK_HISTORY_SIZE: usize = 50;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RegisterContent {
+    pub content: String,
+    pub mode: VisualMode,
+}
+
+impl RegisterContent {
+    pub fn from_clipboard(content: String) -> Self {
+        let mode = if content.ends_with('\n') {
+            VisualMode::Linewise
+        } else {
+            VisualMode::Normal
+        };
+        Self { content, mode }
+    }
+
+    /// A single line summary of the content, for listing it.
+    pub fn summary(&self) -> String {
+        self.content
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl From<&RegisterData> for RegisterContent {
+    fn from(data: &RegisterData) -> Self {
+        Self {
+            content: data.content.clone(),
+            mode: data.mode,
+        }
+    }
+}
+
+impl From<RegisterContent> for RegisterData {
+    fn from(content: RegisterContent) -> Self {
+        RegisterData {
+            content: content.content,
+            mode: content.mode,
+        }
+    }
+}
+
+/// The vim style registers on top of the unnamed one that the editor core
+/// yanks into and pastes from.
+#[derive(Clone, Debug, Default)]
+pub struct Registers {
+    named: HashMap<char, RegisterContent>,
+    last_yank: Option<RegisterContent>,
+    deletes: VecDeque<RegisterContent>,
+    history: VecDeque<RegisterContent>,
+    /// The register the next yank, delete or paste uses
+    pub selected: Option<char>,
+    /// Whether the next typed character picks the register
+    pub selecting: bool,
+}
+
+impl Registers {
+    pub fn is_register(c: char) -> bool {
+        c.is_ascii_alphanumeric() || matches!(c, '"' | '+' | '_')
+    }
+
+    /// Select the register named by `c` for the next yank, delete or paste.
+    pub fn select(&mut self, c: &str) {
+        self.selecting = false;
+        let mut chars = c.chars();
+        self.selected = match (chars.next(), chars.next()) {
+            (Some(c), None) if Self::is_register(c) => Some(c),
+            _ => None,
+        };
+    }
+
+    /// The content of `register`, or `None` if it's the unnamed register or
+    /// holds nothing.
+    pub fn get(&self, register: char) -> Option<RegisterContent> {
+        match register {
+            'a'..='z' | 'A'..='Z' => {
+                self.named.get(&register.to_ascii_lowercase()).cloned()
+            }
+            '0' => self.last_yank.clone(),
+            '1'..='9' => {
+                let index = register as usize - '1' as usize;
+                self.deletes.get(index).cloned()
+            }
+            '+' => SystemClipboard::new()
+                .get_string()
+                .map(RegisterContent::from_clipboard),
+            '_' => Some(RegisterContent {
+                content: String::new(),
+                mode: VisualMode::Normal,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Recently yanked content, most recent first.
+    pub fn history(&self) -> impl Iterator<Item = &RegisterContent> {
+        self.history.iter()
+    }
+
+    pub fn yanked(&mut self, register: Option<char>, content: RegisterContent) {
+        self.push_history(content.clone());
+        self.last_yank = Some(content.clone());
+        self.store(register, content);
+    }
+
+    pub fn deleted(&mut self, register: Option<char>, content: RegisterContent) {
+        self.deletes.push_front(content.clone());
+        self.deletes.truncate(DELETE_RING_SIZE);
+        self.store(register, content);
+    }
+
+    pub fn push_history(&mut self, content: RegisterContent) {
+        if content.content.is_empty() {
+            return;
+        }
+        self.history.retain(|c| c != &content);
+        self.history.push_front(content);
+        self.history.truncate(YANK_HISTORY_SIZE);
+    }
+
+    fn store(&mut self, register: Option<char>, content: RegisterContent) {
+        match register {
+            Some(c @ 'a'..='z') => {
+                self.named.insert(c, content);
+            }
+            Some(c @ 'A'..='Z') => {
+                let c = c.to_ascii_lowercase();
+                let content = match self.named.remove(&c) {
+                    Some(existing) => append(existing, content),
+                    None => content,
+                };
+                self.named.insert(c, content);
+            }
+            Some('+') => {
+                SystemClipboard::new().put_string(&content.content);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Append to a register the way vim does, where anything linewise makes the
+/// whole register linewise.
+fn append(
+    mut existing: RegisterContent,
+    content: RegisterContent,
+) -> RegisterContent {
+    if existing.mode == VisualMode::Linewise || content.mode == VisualMode::Linewise
+    {
+        if !existing.content.ends_with('\n') {
+            existing.content.push('\n');
+        }
+        existing.content.push_str(&content.content);
+        if !existing.content.ends_with('\n') {
+            existing.content.push('\n');
+        }
+        existing.mode = VisualMode::Linewise;
+    } else {
+        existing.content.push_str(&content.content);
+    }
+    existing
+}
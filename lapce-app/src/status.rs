@@ -71,6 +71,9 @@ pub fn status(
     };
 
     let progresses = window_tab_data.progresses;
+    let macros = window_tab_data.common.keypress.get_untracked().macros;
+    let macro_recording = macros.recording;
+    let macro_prompt = macros.prompt;
     let mode = create_memo(move |_| window_tab_data.mode());
     let pointer_down = floem::reactive::create_rw_signal(false);
 
@@ -124,6 +127,34 @@ pub fn status(
                     .align_items(Some(AlignItems::Center))
                     .selectable(false)
             }),
+            label(move || {
+                if macro_prompt.with(|prompt| prompt.is_some()) {
+                    "Macro Register?".to_string()
+                } else {
+                    macro_recording
+                        .with(|recording| {
+                            recording.as_ref().map(|recording| {
+                                format!("Recording @{}", recording.register)
+                            })
+                        })
+                        .unwrap_or_default()
+                }
+            })
+            .style(move |s| {
+                let recording = macro_recording
+                    .with(|recording| recording.is_some())
+                    || macro_prompt.with(|prompt| prompt.is_some());
+                s.display(if recording {
+                    Display::Flex
+                } else {
+                    Display::None
+                })
+                .padding_horiz(10.0)
+                .height_pct(100.0)
+                .align_items(Some(AlignItems::Center))
+                .color(config.get().color(LapceColor::STATUS_FOREGROUND))
+                .selectable(false)
+            }),
             stack((
                 svg(move || config.get().ui_svg(LapceIcons::SCM)).style(move |s| {
                     let config = config.get();
@@ -369,6 +400,16 @@ pub fn status(
                 palette_clone.run(PaletteKind::LineEnding);
             });
             let palette_clone = palette.clone();
+            let encoding_info = status_text(config, editor, move || {
+                editor
+                    .get()
+                    .and_then(|editor| editor.doc_signal().get().encoding.get())
+                    .unwrap_or_default()
+            })
+            .on_click_stop(move |_| {
+                palette_clone.run(PaletteKind::ReopenWithEncoding);
+            });
+            let palette_clone = palette.clone();
             let language_info = status_text(config, editor, move || {
                 if let Some(editor) = editor.get() {
                     let doc = editor.doc_signal().get();
@@ -380,7 +421,7 @@ pub fn status(
             .on_click_stop(move |_| {
                 palette_clone.run(PaletteKind::Language);
             });
-            (cursor_info, line_ending_info, language_info)
+            (cursor_info, line_ending_info, encoding_info, language_info)
         })
         .style(|s| {
             s.height_pct(100.0)
@@ -449,7 +490,12 @@ fn status_text<S: std::fmt::Display + 'static>(
             .map(|editor| {
                 editor.doc_signal().get().content.with(|c| {
                     use crate::doc::DocContent;
-                    matches!(c, DocContent::File { .. } | DocContent::Scratch { .. })
+                    matches!(
+                        c,
+                        DocContent::File { .. }
+                            | DocContent::Scratch { .. }
+                            | DocContent::Hex { .. }
+                    )
                 })
             })
             .unwrap_or(false)
@@ -5,11 +5,19 @@
 use parking_lot::RwLock;
 
 use super::raw::RawTerminal;
+use crate::ports::PortDetector;
 
 /// The notifications for terminals to send back to main thread
 pub enum TermNotification {
-    SetTitle { term_id: TermId, title: String },
+    SetTitle {
+        term_id: TermId,
+        title: String,
+    },
     RequestPaint,
+    /// Ports that the terminal output says something listens on
+    PortsDetected {
+        ports: Vec<u16>,
+    },
 }
 
 pub enum TermEvent {
@@ -21,8 +29,10 @@ pub enum TermEvent {
 pub fn terminal_update_process(
     receiver: Receiver<(TermId, TermEvent)>,
     term_notification_tx: Sender<TermNotification>,
+    detect_ports: bool,
 ) {
     let mut terminals = HashMap::new();
+    let mut port_detectors: HashMap<TermId, PortDetector> = HashMap::new();
     let mut last_redraw = Instant::now();
     let mut last_event = None;
     loop {
@@ -37,12 +47,26 @@ pub fn terminal_update_process(
         match event {
             TermEvent::CloseTerminal => {
                 terminals.remove(&term_id);
+                port_detectors.remove(&term_id);
             }
             TermEvent::NewTerminal(raw) => {
                 terminals.insert(term_id, raw);
             }
             TermEvent::UpdateContent(content) => {
                 if let Some(raw) = terminals.get(&term_id) {
+                    if detect_ports {
+                        let ports = port_detectors
+                            .entry(term_id)
+                            .or_default()
+                            .detect(&content);
+                        if !ports.is_empty() {
+                            if let Err(err) = term_notification_tx
+                                .send(TermNotification::PortsDetected { ports })
+                            {
+                                tracing::error!("{:?}", err);
+                            }
+                        }
+                    }
                     {
                         raw.write().update_content(content);
                     }
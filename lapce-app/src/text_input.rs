@@ -226,6 +226,7 @@ fn text_input_full<T: KeyPressFocus + 'static>(
         cursor_line,
         placeholder: "".to_string(),
         placeholder_text_layout: None,
+        masked: false,
         editor: e_data.clone(),
         cursor_pos: Point::ZERO,
         on_cursor_pos: None,
@@ -307,6 +308,7 @@ enum TextInputState {
     },
     Focus(bool),
     Placeholder(String),
+    Masked(bool),
 }
 
 pub struct TextInput {
@@ -324,6 +326,8 @@ pub struct TextInput {
     cursor_line: RwSignal<Line>,
     placeholder: String,
     placeholder_text_layout: Option<TextLayout>,
+    /// Whether the content is shown as `*`s, like for a password
+    masked: bool,
     cursor_pos: Point,
     on_cursor_pos: Option<Box<dyn Fn(Point)>>,
     hide_cursor: RwSignal<bool>,
@@ -341,6 +345,16 @@ pub fn placeholder(self, placeholder: impl Fn() -> String + 'static) -> Self {
         self
     }
 
+    /// Show the content as `*`s while `masked` returns true.
+    pub fn masked(self, masked: impl Fn() -> bool + 'static) -> Self {
+        let id = self.id;
+        create_effect(move |_| {
+            let masked = masked();
+            id.update_state(TextInputState::Masked(masked));
+        });
+        self
+    }
+
     pub fn on_cursor_pos(mut self, cursor_pos: impl Fn(Point) + 'static) -> Self {
         self.on_cursor_pos = Some(Box::new(cursor_pos));
         self
@@ -386,9 +400,14 @@ fn set_text_layout(&mut self) {
         if let Some(line_height) = self.style.line_height() {
             attrs = attrs.line_height(line_height);
         }
+        // One `*` for each byte keeps the offsets of the content valid
+        let masked_content;
         text_layout.set_text(
             if self.content.is_empty() {
                 " "
+            } else if self.masked {
+                masked_content = "*".repeat(self.content.len());
+                masked_content.as_str()
             } else {
                 self.content.as_str()
             },
@@ -539,6 +558,10 @@ fn update(
                     self.placeholder = placeholder;
                     self.placeholder_text_layout = None;
                 }
+                TextInputState::Masked(masked) => {
+                    self.masked = masked;
+                    self.text_layout.set(None);
+                }
             }
             self.id.request_layout();
         }
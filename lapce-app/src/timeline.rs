@@ -0,0 +1,174 @@
+use std::{
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use floem::{
+    ext_event::create_ext_action,
+    reactive::{Memo, RwSignal, Scope, SignalGet, SignalUpdate},
+};
+use lapce_rpc::proxy::ProxyResponse;
+
+use crate::{
+    command::InternalCommand,
+    doc::{DocContent, DocHistory},
+    editor::EditorData,
+    local_history::{format_time, LocalHistory},
+    window_tab::CommonData,
+};
+
+/// A version of a file in its timeline, either saved in the local history
+/// or committed to source control.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TimelineEntry {
+    pub history: DocHistory,
+    pub title: String,
+    pub detail: String,
+    /// In milliseconds since the Unix epoch
+    pub time: i64,
+}
+
+impl TimelineEntry {
+    pub fn is_local(&self) -> bool {
+        self.history.local_time().is_some()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TimelineData {
+    /// The file of the active editor, which the timeline is of
+    pub path: RwSignal<Option<PathBuf>>,
+    /// The versions of the file, newest first
+    pub entries: RwSignal<Vec<TimelineEntry>>,
+    pub common: Rc<CommonData>,
+}
+
+impl TimelineData {
+    pub fn new(
+        cx: Scope,
+        active_editor: Memo<Option<EditorData>>,
+        common: Rc<CommonData>,
+    ) -> Self {
+        let data = Self {
+            path: cx.create_rw_signal(None),
+            entries: cx.create_rw_signal(Vec::new()),
+            common,
+        };
+
+        {
+            let data = data.clone();
+            let active_path = cx.create_memo(move |_| {
+                let doc = active_editor.get()?.doc_signal().get();
+                let content = doc.content.get();
+                match content {
+                    DocContent::History(history) => Some(history.path),
+                    content => content.path().cloned(),
+                }
+            });
+            cx.create_effect(move |_| {
+                let path = active_path.get();
+                data.path.set(path);
+                data.entries.set(Vec::new());
+                data.load_local_history();
+                data.load_commits();
+            });
+        }
+
+        data
+    }
+
+    /// Replace the entries that are from the local history, or that are
+    /// commits, if they are still of the file shown.
+    fn set_entries(&self, path: &Path, local: bool, new: Vec<TimelineEntry>) {
+        if self.path.get_untracked().as_deref() != Some(path) {
+            return;
+        }
+        self.entries.update(|entries| {
+            entries.retain(|entry| entry.is_local() != local);
+            entries.extend(new);
+            entries.sort_by(|a, b| b.time.cmp(&a.time));
+        });
+    }
+
+    pub fn load_local_history(&self) {
+        let Some(path) = self.path.get_untracked() else {
+            return;
+        };
+        let Some(history) = LocalHistory::new(&self.common.workspace) else {
+            return;
+        };
+
+        let data = self.clone();
+        let send = create_ext_action(
+            self.common.scope,
+            move |(path, entries): (PathBuf, Vec<TimelineEntry>)| {
+                data.set_entries(&path, true, entries);
+            },
+        );
+        std::thread::Builder::new()
+            .name("LocalHistory".to_owned())
+            .spawn(move || {
+                let entries = history
+                    .entries(&path)
+                    .into_iter()
+                    .map(|entry| TimelineEntry {
+                        history: DocHistory::local(path.clone(), entry.time),
+                        title: "Saved".to_string(),
+                        detail: format_time(entry.time as i64),
+                        time: entry.time as i64,
+                    })
+                    .collect();
+                send((path, entries));
+            })
+            .unwrap();
+    }
+
+    fn load_commits(&self) {
+        let Some(path) = self.path.get_untracked() else {
+            return;
+        };
+
+        let data = self.clone();
+        let send = {
+            let path = path.clone();
+            create_ext_action(self.common.scope, move |result| {
+                if let Ok(ProxyResponse::GitFileHistoryResponse { commits }) = result
+                {
+                    let entries = commits
+                        .into_iter()
+                        .map(|commit| TimelineEntry {
+                            history: DocHistory {
+                                path: path.clone(),
+                                version: commit.id,
+                            },
+                            title: commit.summary,
+                            detail: format!(
+                                "{}, {}",
+                                commit.author,
+                                format_time(commit.time * 1000)
+                            ),
+                            time: commit.time * 1000,
+                        })
+                        .collect();
+                    data.set_entries(&path, false, entries);
+                }
+            })
+        };
+        self.common.proxy.git_file_history(path, move |result| {
+            send(result);
+        });
+    }
+
+    /// Compare the version with the current content of the file.
+    pub fn open(&self, history: DocHistory) {
+        self.common
+            .internal_command
+            .send(InternalCommand::OpenHistoryDiff { history });
+    }
+
+    pub fn restore(&self, history: DocHistory) {
+        self.common
+            .internal_command
+            .send(InternalCommand::RestoreHistory { history });
+    }
+}
@@ -67,32 +67,69 @@ fn left(
         }),
         tooltip_label(
             config,
-            container(svg(move || config.get().ui_svg(LapceIcons::REMOTE)).style(
-                move |s| {
-                    let config = config.get();
-                    let size = (config.ui.icon_size() as f32 + 2.0).min(30.0);
-                    s.size(size, size).color(if is_local {
-                        config.color(LapceColor::LAPCE_ICON_ACTIVE)
-                    } else {
-                        match proxy_status.get() {
-                            Some(_) => Color::WHITE,
-                            None => config.color(LapceColor::LAPCE_ICON_ACTIVE),
-                        }
-                    })
-                },
-            )),
-            || "Connect to Remote",
+            stack((
+                svg(move || config.get().ui_svg(LapceIcons::REMOTE)).style(
+                    move |s| {
+                        let config = config.get();
+                        let size = (config.ui.icon_size() as f32 + 2.0).min(30.0);
+                        s.size(size, size).color(if is_local {
+                            config.color(LapceColor::LAPCE_ICON_ACTIVE)
+                        } else {
+                            match proxy_status.get() {
+                                Some(_) => Color::WHITE,
+                                None => config.color(LapceColor::LAPCE_ICON_ACTIVE),
+                            }
+                        })
+                    },
+                ),
+                label(move || match proxy_status.get() {
+                    Some(ProxyStatus::Progress { message }) => message,
+                    Some(ProxyStatus::Error { .. }) => {
+                        "Failed to connect".to_string()
+                    }
+                    _ => String::new(),
+                })
+                .style(move |s| {
+                    let has_message = matches!(
+                        proxy_status.get(),
+                        Some(
+                            ProxyStatus::Progress { .. } | ProxyStatus::Error { .. }
+                        )
+                    );
+                    s.margin_left(6.0)
+                        .color(Color::WHITE)
+                        .selectable(false)
+                        .apply_if(is_local || !has_message, |s| s.hide())
+                }),
+            ))
+            .style(|s| s.items_center()),
+            move || match proxy_status.get() {
+                Some(ProxyStatus::Error { message }) if !is_local => {
+                    format!("Failed to connect: {message}")
+                }
+                _ => "Connect to Remote".to_string(),
+            },
         )
         .popout_menu(move || {
             #[allow(unused_mut)]
-            let mut menu = Menu::new("").entry(
-                MenuItem::new("Connect to SSH Host").action(move || {
+            let mut menu = Menu::new("")
+                .entry(MenuItem::new("Connect to SSH Host").action(move || {
                     workbench_command.send(LapceWorkbenchCommand::ConnectSshHost);
-                }),
-            );
+                }))
+                .entry(MenuItem::new("Connect to Container or Sandbox").action(
+                    move || {
+                        workbench_command
+                            .send(LapceWorkbenchCommand::ConnectCommandHost);
+                    },
+                ));
             if !is_local
                 && proxy_status.get().is_some_and(|p| {
-                    matches!(p, ProxyStatus::Connecting | ProxyStatus::Connected)
+                    matches!(
+                        p,
+                        ProxyStatus::Connecting
+                            | ProxyStatus::Progress { .. }
+                            | ProxyStatus::Connected
+                    )
                 })
             {
                 menu = menu.entry(MenuItem::new("Disconnect remote").action(
@@ -122,10 +159,10 @@ fn left(
                     Some(ProxyStatus::Connected) => {
                         config.color(LapceColor::LAPCE_REMOTE_CONNECTED)
                     }
-                    Some(ProxyStatus::Connecting) => {
+                    Some(ProxyStatus::Connecting | ProxyStatus::Progress { .. }) => {
                         config.color(LapceColor::LAPCE_REMOTE_CONNECTING)
                     }
-                    Some(ProxyStatus::Disconnected) => {
+                    Some(ProxyStatus::Disconnected | ProxyStatus::Error { .. }) => {
                         config.color(LapceColor::LAPCE_REMOTE_DISCONNECTED)
                     }
                     None => Color::TRANSPARENT,
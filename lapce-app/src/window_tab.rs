@@ -12,7 +12,7 @@
 use floem::{
     action::{open_file, remove_overlay, TimerToken},
     ext_event::{create_ext_action, create_signal_from_channel},
-    file::FileDialogOptions,
+    file::{FileDialogOptions, FileSpec},
     keyboard::Modifiers,
     kurbo::Size,
     peniko::kurbo::{Point, Rect, Vec2},
@@ -28,23 +28,30 @@
 use indexmap::IndexMap;
 use itertools::Itertools;
 use lapce_core::{
-    command::FocusCommand, cursor::CursorAffinity, directory::Directory, meta,
-    mode::Mode, register::Register,
+    command::{EditCommand, FocusCommand},
+    cursor::{CursorAffinity, CursorMode},
+    directory::Directory,
+    meta,
+    mode::Mode,
+    register::Register,
 };
 use lapce_rpc::{
     core::CoreNotification,
     dap_types::{ConfigSource, RunDebugConfig},
     file::{Naming, PathObject},
     plugin::PluginId,
-    proxy::{ProxyResponse, ProxyRpcHandler, ProxyStatus},
+    proxy::{
+        ProxyResponse, ProxyRpcHandler, ProxyStatus, RemotePrompt, RemotePromptKind,
+    },
     source_control::FileDiff,
     terminal::TermId,
     RpcError,
 };
 use lsp_types::{
-    CodeActionOrCommand, CodeLens, Diagnostic, ProgressParams, ProgressToken,
-    ShowMessageParams,
+    CodeActionOrCommand, CodeLens, Diagnostic, MessageType, ProgressParams,
+    ProgressToken, ShowMessageParams,
 };
+use regex::Regex;
 use serde_json::Value;
 use tracing::{debug, error, event, Level};
 
@@ -61,19 +68,32 @@
     db::LapceDb,
     debug::{DapData, LapceBreakpoint, RunDebugMode, RunDebugProcess},
     doc::DocContent,
-    editor::location::{EditorLocation, EditorPosition},
+    editor::{
+        gutter::FoldingRanges,
+        location::{EditorLocation, EditorPosition},
+        repeat::RepeatData,
+        EditorData,
+    },
     editor_tab::EditorTabChild,
-    file_explorer::data::FileExplorerData,
+    file_explorer::data::{FileExplorerData, FileOperation},
     find::Find,
     global_search::GlobalSearchData,
     hover::HoverData,
     id::WindowTabId,
     inline_completion::InlineCompletionData,
-    keypress::{condition::Condition, EventRef, KeyPressData, KeyPressFocus},
+    keypress::{
+        condition::Condition,
+        macros::{MacroData, MacroRegisterPrompt},
+        EventRef, KeyPress, KeyPressData, KeyPressFocus,
+    },
     listener::Listener,
     lsp::path_from_url,
     main_split::{MainSplitData, SplitData, SplitDirection, SplitMoveDirection},
-    palette::{kind::PaletteKind, PaletteData, PaletteStatus, DEFAULT_RUN_TOML},
+    palette::{
+        ex_command::{ExCommand, ExCommandKind, LineContext},
+        kind::PaletteKind,
+        PaletteData, PaletteStatus, DEFAULT_RUN_TOML,
+    },
     panel::{
         call_hierarchy_view::{CallHierarchyData, CallHierarchyItemData},
         data::{default_panel_order, PanelData, PanelSection},
@@ -81,13 +101,16 @@
         position::PanelContainerPosition,
     },
     plugin::PluginData,
+    ports::PortsData,
     proxy::{new_proxy, ProxyData},
+    register::Registers,
     rename::RenameData,
     source_control::SourceControlData,
     terminal::{
         event::{terminal_update_process, TermEvent, TermNotification},
         panel::TerminalPanelData,
     },
+    timeline::TimelineData,
     tracing::*,
     window::WindowCommonData,
     workspace::{LapceWorkspace, LapceWorkspaceType, WorkspaceInfo},
@@ -107,6 +130,8 @@ pub enum Focus {
 pub enum DragContent {
     Panel(PanelKind),
     EditorTab(EditorTabChild),
+    /// Files and directories dragged in the file explorer
+    FilePaths(Vec<PathBuf>),
 }
 
 impl DragContent {
@@ -126,6 +151,9 @@ pub struct WorkProgress {
 #[derive(Clone)]
 pub struct CommonData {
     pub workspace: Arc<LapceWorkspace>,
+    /// The root folders of the workspace, which can be added and removed
+    /// while it's open
+    pub workspace_roots: RwSignal<Vec<PathBuf>>,
     pub scope: Scope,
     pub focus: RwSignal<Focus>,
     pub keypress: RwSignal<KeyPressData>,
@@ -133,6 +161,8 @@ pub struct CommonData {
     pub inline_completion: RwSignal<InlineCompletionData>,
     pub hover: HoverData,
     pub register: RwSignal<Register>,
+    pub registers: RwSignal<Registers>,
+    pub repeat: RwSignal<RepeatData>,
     pub find: Find,
     pub workbench_size: RwSignal<Size>,
     pub window_origin: RwSignal<Point>,
@@ -178,6 +208,8 @@ pub struct WindowTabData {
     pub source_control: SourceControlData,
     pub rename: RenameData,
     pub global_search: GlobalSearchData,
+    pub ports: PortsData,
+    pub timeline: TimelineData,
     pub call_hierarchy_data: CallHierarchyData,
     pub about_data: AboutData,
     pub alert_data: AlertBoxData,
@@ -221,10 +253,16 @@ fn check_condition(&self, condition: Condition) -> bool {
     fn run_command(
         &self,
         command: &LapceCommand,
-        _count: Option<usize>,
+        count: Option<usize>,
         _mods: Modifiers,
     ) -> CommandExecuted {
         match &command.kind {
+            CommandKind::Workbench(LapceWorkbenchCommand::ReplayMacro) => {
+                self.prompt_macro_register(MacroRegisterPrompt::Replay { count });
+            }
+            CommandKind::Workbench(LapceWorkbenchCommand::ReplayLastMacro) => {
+                self.replay_macro('@', count);
+            }
             CommandKind::Workbench(cmd) => {
                 self.run_workbench_command(cmd.clone(), None);
             }
@@ -319,10 +357,16 @@ pub fn new(
             crossbeam_channel::unbounded();
         {
             let term_notification_tx = term_notification_tx.clone();
+            let detect_ports =
+                matches!(workspace.kind, LapceWorkspaceType::RemoteSSH(_));
             std::thread::Builder::new()
                 .name("terminal update process".to_owned())
                 .spawn(move || {
-                    terminal_update_process(term_rx, term_notification_tx);
+                    terminal_update_process(
+                        term_rx,
+                        term_notification_tx,
+                        detect_ports,
+                    );
                 })
                 .unwrap();
         }
@@ -332,8 +376,14 @@ pub fn new(
             all_disabled_volts,
             window_common.extra_plugin_paths.as_ref().clone(),
             config.plugins.clone(),
+            config.editor.language_server_priority.clone(),
+            config.core.ssh_hosts.clone(),
             term_tx.clone(),
         );
+        proxy.proxy_rpc.update_user_languages(
+            config.editor.languages.clone(),
+            config.editor.file_associations.clone(),
+        );
         let (config, set_config) = cx.create_signal(Arc::new(config));
 
         let focus = cx.create_rw_signal(Focus::Workbench);
@@ -362,6 +412,7 @@ pub fn new(
 
         let common = Rc::new(CommonData {
             workspace: workspace.clone(),
+            workspace_roots: cx.create_rw_signal(workspace.roots()),
             scope: cx,
             keypress,
             focus,
@@ -369,6 +420,8 @@ pub fn new(
             inline_completion,
             hover,
             register,
+            registers: cx.create_rw_signal(Registers::default()),
+            repeat: cx.create_rw_signal(RepeatData::default()),
             find,
             internal_command,
             lapce_command,
@@ -519,9 +572,14 @@ pub fn new(
             proxy.core_rpc.clone(),
         );
 
+        let ports = PortsData::new(cx, main_split.editors, common.clone());
+        let timeline =
+            TimelineData::new(cx, main_split.active_editor, common.clone());
+
         {
             let notification = create_signal_from_channel(term_notification_rx);
             let terminal = terminal.clone();
+            let ports = ports.clone();
             cx.create_effect(move |_| {
                 notification.with(|notification| {
                     if let Some(notification) = notification.as_ref() {
@@ -532,6 +590,9 @@ pub fn new(
                             TermNotification::RequestPaint => {
                                 view_id.get_untracked().request_paint();
                             }
+                            TermNotification::PortsDetected { ports: detected } => {
+                                ports.ports_detected(detected);
+                            }
                         }
                     }
                 });
@@ -556,6 +617,8 @@ pub fn new(
             plugin,
             rename,
             global_search,
+            ports,
+            timeline,
             call_hierarchy_data: CallHierarchyData {
                 root: cx.create_rw_signal(None),
                 common: common.clone(),
@@ -657,6 +720,27 @@ pub fn reload_config(&self) {
                 change_plugins.push(key.clone());
             }
         }
+        if config.editor.language_server_priority
+            != self
+                .common
+                .config
+                .get_untracked()
+                .editor
+                .language_server_priority
+        {
+            self.common.proxy.update_language_server_priority(
+                config.editor.language_server_priority.clone(),
+            );
+        }
+        let old_config = self.common.config.get_untracked();
+        if config.editor.languages != old_config.editor.languages
+            || config.editor.file_associations != old_config.editor.file_associations
+        {
+            self.common.proxy.update_user_languages(
+                config.editor.languages.clone(),
+                config.editor.file_associations.clone(),
+            );
+        }
         self.set_config.set(Arc::new(config.clone()));
         if !change_plugins.is_empty() {
             self.common
@@ -753,6 +837,7 @@ pub fn run_workbench_command(
                                     .duration_since(std::time::UNIX_EPOCH)
                                     .unwrap()
                                     .as_secs(),
+                                ..Default::default()
                             };
                             window_command
                                 .send(WindowCommand::SetWorkspace { workspace });
@@ -760,13 +845,53 @@ pub fn run_workbench_command(
                     });
                 }
             }
+            OpenWorkspaceFile => {
+                if !self.workspace.kind.is_remote() {
+                    let window_command = self.common.window_common.window_command;
+                    let options =
+                        FileDialogOptions::new().allowed_types(vec![FileSpec {
+                            name: "Workspace",
+                            extensions: &[LapceWorkspace::FILE_EXTENSION],
+                        }]);
+                    open_file(options, move |file| {
+                        let Some(path) = file.and_then(|mut file| file.path.pop())
+                        else {
+                            return;
+                        };
+                        match LapceWorkspace::from_file(&path) {
+                            Ok(workspace) => {
+                                window_command
+                                    .send(WindowCommand::SetWorkspace { workspace });
+                            }
+                            Err(err) => {
+                                tracing::error!(
+                                    "Failed to read workspace file {path:?}: {err}"
+                                );
+                            }
+                        }
+                    });
+                }
+            }
+            AddFolderToWorkspace => {
+                if !self.workspace.kind.is_remote() {
+                    let internal_command = self.common.internal_command;
+                    let options = FileDialogOptions::new().select_directories();
+                    open_file(options, move |file| {
+                        if let Some(path) = file.and_then(|mut file| file.path.pop())
+                        {
+                            internal_command
+                                .send(InternalCommand::AddWorkspaceFolder { path });
+                        }
+                    });
+                }
+            }
             CloseFolder => {
                 if !self.workspace.kind.is_remote() {
                     let window_command = self.common.window_common.window_command;
                     let workspace = LapceWorkspace {
                         kind: LapceWorkspaceType::Local,
                         path: None,
-                        last_open: 0,
+                        ..Default::default()
                     };
                     window_command.send(WindowCommand::SetWorkspace { workspace });
                 }
@@ -795,8 +920,8 @@ pub fn run_workbench_command(
             RevealActiveFileInFileExplorer => {
                 if let Some(editor_data) = self.main_split.active_editor.get() {
                     let doc = editor_data.doc();
-                    let path = if let DocContent::File { path, .. } =
-                        doc.content.get_untracked()
+                    let path = if let DocContent::File { path, .. }
+                    | DocContent::Hex { path, .. } = doc.content.get_untracked()
                     {
                         Some(path)
                     } else {
@@ -814,7 +939,8 @@ pub fn run_workbench_command(
                     let mut paths = HashSet::new();
                     for (_, editor_data) in editors.iter() {
                         let doc = editor_data.doc();
-                        let should_save = if let DocContent::File { path, .. } =
+                        let should_save = if let DocContent::File { path, .. }
+                        | DocContent::Hex { path, .. } =
                             doc.content.get_untracked()
                         {
                             if paths.contains(&path) {
@@ -1085,6 +1211,9 @@ pub fn run_workbench_command(
             ConnectSshHost => {
                 self.palette.run(PaletteKind::SshHost);
             }
+            ConnectCommandHost => {
+                self.palette.run(PaletteKind::CommandHost);
+            }
             #[cfg(windows)]
             ConnectWslHost => {
                 self.palette.run(PaletteKind::WslHost);
@@ -1095,7 +1224,7 @@ pub fn run_workbench_command(
                         workspace: LapceWorkspace {
                             kind: LapceWorkspaceType::Local,
                             path: None,
-                            last_open: 0,
+                            ..Default::default()
                         },
                     },
                 );
@@ -1138,7 +1267,104 @@ pub fn run_workbench_command(
             ChangeFileLineEnding => {
                 self.palette.run(PaletteKind::LineEnding);
             }
+            HexGotoOffset => {
+                self.palette.run(PaletteKind::HexGotoOffset);
+            }
+            HexFindBytes => {
+                self.palette.run(PaletteKind::HexFindBytes);
+            }
+            ToggleReadOnly => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.doc().content.update(|content| {
+                        if let DocContent::File { read_only, .. }
+                        | DocContent::Hex { read_only, .. } = content
+                        {
+                            *read_only = !*read_only;
+                        }
+                    });
+                }
+            }
+            ReopenWithEncoding => {
+                self.palette.run(PaletteKind::ReopenWithEncoding);
+            }
+            SaveWithEncoding => {
+                self.palette.run(PaletteKind::SaveWithEncoding);
+            }
             DiffFiles => self.palette.run(PaletteKind::DiffFiles),
+            PaletteKeyboardMacros => self.palette.run(PaletteKind::KeyboardMacro),
+
+            // ==== Keyboard Macros ====
+            ToggleMacroRecording => {
+                let macros = self.common.keypress.get_untracked().macros;
+                if macros.is_recording() {
+                    macros.stop_recording();
+                } else {
+                    self.prompt_macro_register(MacroRegisterPrompt::Record);
+                }
+            }
+            StartMacroRecording => {
+                self.prompt_macro_register(MacroRegisterPrompt::Record);
+            }
+            StopMacroRecording => {
+                self.common.keypress.get_untracked().macros.stop_recording();
+            }
+            ReplayMacro => {
+                self.prompt_macro_register(MacroRegisterPrompt::Replay {
+                    count: None,
+                });
+            }
+            ReplayLastMacro => self.replay_macro('@', None),
+
+            // ==== Registers ====
+            SelectRegister => {
+                self.common.registers.update(|registers| {
+                    registers.selecting = true;
+                });
+            }
+            PalettePasteHistory => self.palette.run(PaletteKind::PasteHistory),
+
+            // ==== Command Line ====
+            PaletteExCommand => {
+                let visual = self.main_split.active_editor.get_untracked().map(
+                    |editor| {
+                        editor.cursor().with_untracked(|cursor| {
+                            matches!(cursor.mode, CursorMode::Visual { .. })
+                        })
+                    },
+                );
+                self.palette.run(PaletteKind::ExCommand);
+                if visual == Some(true) {
+                    self.palette.set_input("'<,'>");
+                }
+            }
+            // Handled by the focused editor
+            RepeatLastChange
+            | SelectInsideTextObject
+            | SelectAroundTextObject
+            | ChangeInsideTextObject
+            | ChangeAroundTextObject
+            | NextFunction
+            | PreviousFunction
+            | NextClass
+            | PreviousClass
+            | Surround
+            | SurroundAdd
+            | SurroundChange
+            | SurroundDelete => {}
+
+            // ==== Folding ====
+            FoldAll => self.update_folding_ranges(|ranges| ranges.fold_all()),
+            UnfoldAll => self.update_folding_ranges(|ranges| ranges.unfold_all()),
+            FoldLevel1 => self.fold_level(1),
+            FoldLevel2 => self.fold_level(2),
+            FoldLevel3 => self.fold_level(3),
+            FoldLevel4 => self.fold_level(4),
+            FoldLevel5 => self.fold_level(5),
+            FoldLevel6 => self.fold_level(6),
+            FoldLevel7 => self.fold_level(7),
+            FoldComments => {
+                self.update_folding_ranges(|ranges| ranges.fold_comments())
+            }
 
             // ==== Running / Debugging ====
             RunAndDebugRestart => {
@@ -1266,6 +1492,12 @@ pub fn run_workbench_command(
             ToggleSearchFocus => {
                 self.toggle_panel_focus(PanelKind::Search);
             }
+            TogglePortsFocus => {
+                self.toggle_panel_focus(PanelKind::Ports);
+            }
+            ToggleTimelineFocus => {
+                self.toggle_panel_focus(PanelKind::Timeline);
+            }
             ToggleTerminalVisual => {
                 self.toggle_panel_visual(PanelKind::Terminal);
             }
@@ -1287,12 +1519,23 @@ pub fn run_workbench_command(
             ToggleSearchVisual => {
                 self.toggle_panel_visual(PanelKind::Search);
             }
+            TogglePortsVisual => {
+                self.toggle_panel_visual(PanelKind::Ports);
+            }
+            ToggleTimelineVisual => {
+                self.toggle_panel_visual(PanelKind::Timeline);
+            }
             FocusEditor => {
                 self.common.focus.set(Focus::Workbench);
             }
             FocusTerminal => {
                 self.common.focus.set(Focus::Panel(PanelKind::Terminal));
             }
+            RestoreFileVersion => {
+                if let Some(history) = self.main_split.active_history() {
+                    self.main_split.restore_history(history);
+                }
+            }
             OpenUIInspector => {
                 self.common.view_id.get_untracked().inspect();
             }
@@ -1441,7 +1684,7 @@ pub fn run_workbench_command(
                         .section_open(PanelSection::FileExplorer).update(|x| {
                         *x = true;
                     });
-                    if let DocContent::File {path, ..} = editor_data.doc().content.get_untracked() {
+                    if let DocContent::File {path, ..} | DocContent::Hex {path, ..} = editor_data.doc().content.get_untracked() {
                         self.file_explorer.reveal_in_file_tree(path);
                     }
                 }
@@ -1476,7 +1719,7 @@ pub fn run_workbench_command(
                 if let Some(editor_data) =
                     self.main_split.active_editor.get_untracked()
                 {
-                    if let DocContent::File {path, ..} = editor_data.doc().content.get_untracked() {
+                    if let DocContent::File {path, ..} | DocContent::Hex {path, ..} = editor_data.doc().content.get_untracked() {
                         let path = path.parent().unwrap_or(&path);
                         if !path.exists() {
                             return;
@@ -1511,6 +1754,13 @@ pub fn run_workbench_command(
                     editor_data.go_to_implementation(self.clone());
                 }
             }
+            ShowColorPresentations => {
+                if let Some(editor_data) =
+                    self.main_split.active_editor.get_untracked()
+                {
+                    editor_data.show_color_presentations();
+                }
+            }
             RunInTerminal => {
                 if let Some(editor_data) =
                     self.main_split.active_editor.get_untracked()
@@ -1642,6 +1892,31 @@ pub fn run_internal_command(&self, cmd: InternalCommand) {
             InternalCommand::ReloadFileExplorer => {
                 self.file_explorer.reload();
             }
+            InternalCommand::AddWorkspaceFolder { path } => {
+                let mut roots = self.common.workspace_roots.get_untracked();
+                if roots.is_empty() {
+                    // Without a folder open there is nothing to add to
+                    self.common.window_common.window_command.send(
+                        WindowCommand::SetWorkspace {
+                            workspace: LapceWorkspace {
+                                kind: self.workspace.kind.clone(),
+                                path: Some(path),
+                                ..Default::default()
+                            },
+                        },
+                    );
+                } else if !roots.contains(&path) {
+                    roots.push(path);
+                    self.set_workspace_roots(roots);
+                }
+            }
+            InternalCommand::RemoveWorkspaceFolder { path } => {
+                let mut roots = self.common.workspace_roots.get_untracked();
+                roots.retain(|root| root != &path);
+                if !roots.is_empty() {
+                    self.set_workspace_roots(roots);
+                }
+            }
             InternalCommand::TestPathCreation { new_path } => {
                 let naming = self.file_explorer.naming;
 
@@ -1682,43 +1957,7 @@ pub fn run_internal_command(&self, cmd: InternalCommand) {
                                     send_new_path
                                 };
 
-                            // If the renamed item is a file, update any editors the file is open
-                            // in to use the new path.
-                            // If the renamed item is a directory, update any editors in which a
-                            // file the renamed directory is an ancestor of is open to use the
-                            // file's new path.
-                            let renamed_editors_content: Vec<_> = editors
-                                .with_editors_untracked(|editors| {
-                                    editors
-                                        .values()
-                                        .map(|editor| editor.doc().content)
-                                        .filter(|content| {
-                                            content.with_untracked(|content| {
-                                                match content {
-                                                    DocContent::File {
-                                                        path,
-                                                        ..
-                                                    } => path.starts_with(
-                                                        &send_current_path,
-                                                    ),
-                                                    _ => false,
-                                                }
-                                            })
-                                        })
-                                        .collect()
-                                });
-
-                            for content in renamed_editors_content {
-                                content.update(|content| {
-                                    if let DocContent::File { path, .. } = content {
-                                        if let Ok(suffix) =
-                                            path.strip_prefix(&send_current_path)
-                                        {
-                                            *path = new_path.join(suffix);
-                                        }
-                                    }
-                                });
-                            }
+                            editors.path_moved(&send_current_path, &new_path);
 
                             file_explorer.reload();
                             file_explorer.naming.set(Naming::None);
@@ -1731,10 +1970,27 @@ pub fn run_internal_command(&self, cmd: InternalCommand) {
                     },
                 );
 
+                // Let the language servers update imports and module paths first.
+                let proxy = self.common.proxy.clone();
+                let main_split = self.main_split.clone();
+                let (from, to) = (current_path.clone(), new_path.clone());
+                let rename = create_ext_action(
+                    self.scope,
+                    move |response: Result<ProxyResponse, RpcError>| {
+                        if let Ok(ProxyResponse::WillRenamePath {
+                            edit: Some(edit),
+                        }) = response
+                        {
+                            main_split.apply_workspace_edit(&edit);
+                        }
+                        proxy.rename_path(from, to, send);
+                    },
+                );
+
                 self.file_explorer.naming.update(Naming::set_pending);
                 self.common
                     .proxy
-                    .rename_path(current_path.clone(), new_path, send);
+                    .will_rename_path(current_path, new_path, rename);
             }
             InternalCommand::FinishNewNode { is_dir, path } => {
                 let file_explorer = self.file_explorer.clone();
@@ -1795,6 +2051,70 @@ pub fn run_internal_command(&self, cmd: InternalCommand) {
                 self.file_explorer.naming.update(Naming::set_pending);
                 self.common.proxy.duplicate_path(source, path, send);
             }
+            InternalCommand::CopyPaths { paths, to } => {
+                let file_explorer = self.file_explorer.clone();
+                let messages = self.messages;
+                let send = create_ext_action(
+                    self.scope,
+                    move |response: Result<ProxyResponse, RpcError>| {
+                        match response {
+                            Ok(ProxyResponse::PathsResponse { paths, error }) => {
+                                if !paths.is_empty() {
+                                    file_explorer
+                                        .last_operation
+                                        .set(Some(FileOperation::Copy(paths)));
+                                }
+                                if let Some(error) = error {
+                                    file_operation_failed(messages, error);
+                                }
+                            }
+                            Ok(_) => {}
+                            Err(err) => {
+                                file_operation_failed(messages, err.message);
+                            }
+                        }
+                        file_explorer.reload();
+                    },
+                );
+                self.common.proxy.copy_paths(paths, to, send);
+            }
+            InternalCommand::MovePaths { paths, to } => {
+                self.move_paths(paths, to, true);
+            }
+            InternalCommand::UndoFileOperation => {
+                match self.file_explorer.last_operation.get_untracked() {
+                    Some(FileOperation::Copy(paths)) => {
+                        self.file_explorer.last_operation.set(None);
+                        for (_, copy) in paths {
+                            self.common.proxy.trash_path(copy, |res| {
+                                if let Err(err) = res {
+                                    tracing::warn!(
+                                        "Failed to trash path: {:?}",
+                                        err
+                                    );
+                                }
+                            });
+                        }
+                    }
+                    Some(FileOperation::Move(paths)) => {
+                        self.file_explorer.last_operation.set(None);
+                        // Move the paths back, which can be from several directories
+                        let mut back: BTreeMap<PathBuf, Vec<PathBuf>> =
+                            BTreeMap::new();
+                        for (from, to) in paths {
+                            if let Some(parent) = from.parent() {
+                                back.entry(parent.to_path_buf())
+                                    .or_default()
+                                    .push(to);
+                            }
+                        }
+                        for (to, paths) in back {
+                            self.move_paths(paths, to, false);
+                        }
+                    }
+                    None => {}
+                }
+            }
             InternalCommand::GoToLocation { location } => {
                 self.main_split.go_to_location(location, None);
             }
@@ -1844,11 +2164,10 @@ pub fn run_internal_command(&self, cmd: InternalCommand) {
             InternalCommand::ShowCodeActions {
                 offset,
                 mouse_click,
-                plugin_id,
                 code_actions,
             } => {
                 let mut code_action = self.code_action.get_untracked();
-                code_action.show(plugin_id, code_actions, offset, mouse_click);
+                code_action.show(code_actions, offset, mouse_click);
                 self.code_action.set(code_action);
             }
             InternalCommand::RunCodeAction { plugin_id, action } => {
@@ -1983,6 +2302,9 @@ pub fn run_internal_command(&self, cmd: InternalCommand) {
             InternalCommand::HideAlert => {
                 self.alert_data.active.set(false);
             }
+            InternalCommand::ShowMessage { title, message } => {
+                self.show_message(&title, &message);
+            }
             InternalCommand::SaveScratchDoc { doc } => {
                 self.main_split.save_scratch_doc(doc);
             }
@@ -1992,6 +2314,9 @@ pub fn run_internal_command(&self, cmd: InternalCommand) {
             InternalCommand::UpdateProxyStatus { status } => {
                 self.common.proxy_status.set(Some(status));
             }
+            InternalCommand::AnswerRemotePrompt { id, answer } => {
+                self.proxy.remote_prompts.answer(id, answer);
+            }
             InternalCommand::DapFrameScopes { dap_id, frame_id } => {
                 self.terminal.dap_frame_scopes(dap_id, frame_id);
             }
@@ -2009,6 +2334,23 @@ pub fn run_internal_command(&self, cmd: InternalCommand) {
                 left_path,
                 right_path,
             } => self.main_split.open_diff_files(left_path, right_path),
+            InternalCommand::OpenHistoryDiff { history } => {
+                self.main_split.open_history_diff(history);
+            }
+            InternalCommand::RestoreHistory { history } => {
+                self.main_split.restore_history(history);
+            }
+            InternalCommand::LocalHistoryChanged { path } => {
+                if self.timeline.path.get_untracked().as_ref() == Some(&path) {
+                    self.timeline.load_local_history();
+                }
+            }
+            InternalCommand::ReplayKeyboardMacro { register } => {
+                self.replay_macro(register, None);
+            }
+            InternalCommand::RunExCommand { command } => {
+                self.run_ex_command(&command);
+            }
             InternalCommand::ExecuteProcess { program, arguments } => {
                 let mut cmd = match std::process::Command::new(program)
                     .args(arguments)
@@ -2084,6 +2426,9 @@ fn handle_core_notification(&self, rpc: &CoreNotification) {
             CoreNotification::ProxyStatus { status } => {
                 self.common.proxy_status.set(Some(status.to_owned()));
             }
+            CoreNotification::RemotePrompt { prompt } => {
+                self.show_remote_prompt(prompt.clone());
+            }
             CoreNotification::DiffInfo { diff } => {
                 self.source_control.branch.set(diff.head.clone());
                 self.source_control
@@ -2165,6 +2510,8 @@ fn handle_core_notification(&self, rpc: &CoreNotification) {
                             doc.get_semantic_styles();
                             doc.get_folding_range();
                             doc.get_inlay_hints();
+                            doc.get_document_links();
+                            doc.get_document_colors();
                         }
                     });
                 }
@@ -2332,10 +2679,34 @@ pub fn key_down<'a>(&self, event: impl Into<EventRef<'a>> + Copy) -> bool {
         if self.alert_data.active.get_untracked() {
             return false;
         }
-        let focus = self.common.focus.get_untracked();
         let keypress = self.common.keypress.get_untracked();
+        let macros = keypress.macros.clone();
+        let recorded = (macros.is_recording() && !macros.is_replaying())
+            .then(|| KeyPressData::keypress(event))
+            .flatten();
+        let handled = if macros.prompt.with_untracked(|prompt| prompt.is_some()) {
+            self.receive_macro_register(event, &macros)
+        } else {
+            self.key_down_focus(event, &keypress)
+        };
+        if let Some(recorded) = recorded {
+            // Keys that start or stop the recording aren't part of the macro
+            if macros.is_recording() {
+                let in_palette = self.common.focus.get_untracked() == Focus::Palette;
+                macros.record(&recorded, in_palette);
+            }
+        }
+        handled
+    }
+
+    fn key_down_focus<'a>(
+        &self,
+        event: impl Into<EventRef<'a>> + Copy,
+        keypress: &KeyPressData,
+    ) -> bool {
+        let focus = self.common.focus.get_untracked();
         let handle = match focus {
-            Focus::Workbench => self.main_split.key_down(event, &keypress),
+            Focus::Workbench => self.main_split.key_down(event, keypress),
             Focus::Palette => Some(keypress.key_down(event, &self.palette)),
             Focus::CodeAction => {
                 let code_action = self.code_action.get_untracked();
@@ -2344,7 +2715,7 @@ pub fn key_down<'a>(&self, event: impl Into<EventRef<'a>> + Copy) -> bool {
             Focus::Rename => Some(keypress.key_down(event, &self.rename)),
             Focus::AboutPopup => Some(keypress.key_down(event, &self.about_data)),
             Focus::Panel(PanelKind::Terminal) => {
-                self.terminal.key_down(event, &keypress)
+                self.terminal.key_down(event, keypress)
             }
             Focus::Panel(PanelKind::Search) => {
                 Some(keypress.key_down(event, &self.global_search))
@@ -2355,6 +2726,12 @@ pub fn key_down<'a>(&self, event: impl Into<EventRef<'a>> + Copy) -> bool {
             Focus::Panel(PanelKind::SourceControl) => {
                 Some(keypress.key_down(event, &self.source_control))
             }
+            Focus::Panel(PanelKind::Ports) => {
+                Some(keypress.key_down(event, &self.ports))
+            }
+            Focus::Panel(PanelKind::FileExplorer) => {
+                Some(keypress.key_down(event, &self.file_explorer))
+            }
             _ => None,
         };
 
@@ -2375,6 +2752,301 @@ pub fn key_down<'a>(&self, event: impl Into<EventRef<'a>> + Copy) -> bool {
         }
     }
 
+    /// Have the next typed character name the register of a keyboard macro.
+    fn prompt_macro_register(&self, prompt: MacroRegisterPrompt) {
+        let macros = self.common.keypress.get_untracked().macros;
+        macros.prompt.set(Some(prompt));
+    }
+
+    fn receive_macro_register<'a>(
+        &self,
+        event: impl Into<EventRef<'a>>,
+        macros: &MacroData,
+    ) -> bool {
+        let Some(keypress) = KeyPressData::keypress(event) else {
+            return false;
+        };
+        if keypress
+            .keymap_press()
+            .map(|press| press.is_modifiers())
+            .unwrap_or(true)
+        {
+            return false;
+        }
+
+        let Some(prompt) =
+            macros.prompt.try_update(|prompt| prompt.take()).flatten()
+        else {
+            return false;
+        };
+        // Any key that can't name a register cancels the prompt
+        if let Some(register) = MacroData::register(&keypress) {
+            match prompt {
+                MacroRegisterPrompt::Record => macros.start_recording(register),
+                MacroRegisterPrompt::Replay { count } => {
+                    self.replay_macro(register, count)
+                }
+            }
+        }
+        true
+    }
+
+    /// Fold the ranges `level` deep or deeper in the active editor's document.
+    fn fold_level(&self, level: usize) {
+        self.update_folding_ranges(|ranges| ranges.fold_level(level));
+    }
+
+    /// Fold or unfold ranges in the active editor's document.
+    fn update_folding_ranges(&self, f: impl FnOnce(&mut FoldingRanges)) {
+        if let Some(editor) = self.main_split.active_editor.get_untracked() {
+            editor.doc().folding_ranges.update(f);
+        }
+    }
+
+    /// Replay the keyboard macro in `register` `count` times, feeding its keys
+    /// through the same path as typed ones.
+    pub fn replay_macro(&self, register: char, count: Option<usize>) {
+        let macros = self.common.keypress.get_untracked().macros;
+        let Some((register, keys)) = macros.replay_keys(register) else {
+            return;
+        };
+        macros.replay(register, || {
+            for _ in 0..count.unwrap_or(1) {
+                for key in keys.iter() {
+                    self.key_down(key);
+                }
+            }
+        });
+    }
+
+    /// Run a line typed into the command line, reporting what went wrong with
+    /// it as a message.
+    pub fn run_ex_command(&self, input: &str) {
+        let editor = self.main_split.active_editor.get_untracked();
+        let result = ExCommand::parse(input).and_then(|command| {
+            let range = match (&command.range, &editor) {
+                (Some(range), Some(editor)) => {
+                    Some(range.resolve(&ex_line_context(editor))?)
+                }
+                (Some(_), None) => anyhow::bail!("no file is open"),
+                (None, _) => None,
+            };
+            self.exec_ex_command(command.kind, editor.as_ref(), range)
+        });
+
+        // A range taken from the selection uses it up, like in vim
+        if let Some(editor) = editor {
+            editor.cursor().update(|cursor| {
+                if let CursorMode::Visual { end, .. } = cursor.mode {
+                    cursor.mode = CursorMode::Normal(end);
+                }
+            });
+        }
+
+        if let Err(err) = result {
+            self.show_message(
+                "Command Line",
+                &ShowMessageParams {
+                    typ: MessageType::ERROR,
+                    message: format!("{input}: {err}"),
+                },
+            );
+        }
+    }
+
+    fn exec_ex_command(
+        &self,
+        kind: ExCommandKind,
+        editor: Option<&EditorData>,
+        range: Option<(usize, usize)>,
+    ) -> anyhow::Result<()> {
+        let editor_or_err =
+            || editor.ok_or_else(|| anyhow::anyhow!("no file is open"));
+        // Without a range, commands apply to the current line, apart from the
+        // ones that default to the whole file
+        let lines = |editor: &EditorData, whole: bool| {
+            range.unwrap_or_else(|| {
+                let cx = ex_line_context(editor);
+                if whole {
+                    (0, cx.last)
+                } else {
+                    (cx.current, cx.current)
+                }
+            })
+        };
+
+        match kind {
+            ExCommandKind::GoToLine => {
+                let editor = editor_or_err()?;
+                if let Some((_, line)) = range {
+                    editor.go_to_position(EditorPosition::Line(line), None, None);
+                }
+            }
+            ExCommandKind::Write => editor_or_err()?.save(true, || {}),
+            ExCommandKind::WriteAll => {
+                self.run_workbench_command(LapceWorkbenchCommand::SaveAll, None);
+            }
+            ExCommandKind::Quit => {
+                self.main_split.editor_tab_child_close_active();
+            }
+            ExCommandKind::QuitAll => {
+                self.run_workbench_command(LapceWorkbenchCommand::Quit, None);
+            }
+            ExCommandKind::WriteQuit => {
+                let main_split = self.main_split.clone();
+                editor_or_err()?.save(true, move || {
+                    main_split.editor_tab_child_close_active();
+                });
+            }
+            ExCommandKind::Edit(path) => {
+                let path = match self.workspace.path.as_ref() {
+                    Some(workspace_path) => workspace_path.join(path),
+                    None => path,
+                };
+                self.common
+                    .internal_command
+                    .send(InternalCommand::OpenFile { path });
+            }
+            ExCommandKind::Delete => {
+                let editor = editor_or_err()?;
+                let (start, end) = lines(editor, false);
+                editor.replace_lines(start, end, &[]);
+            }
+            ExCommandKind::Substitute(substitute) => {
+                let editor = editor_or_err()?;
+                let regex = substitute.regex()?;
+                let (start, end) = lines(editor, false);
+                let mut changed = false;
+                let new_lines = editor
+                    .lines(start, end)
+                    .into_iter()
+                    .map(|line| match substitute.apply(&regex, &line) {
+                        Some(new_line) => {
+                            changed = true;
+                            new_line
+                        }
+                        None => line,
+                    })
+                    .collect::<Vec<_>>();
+                if !changed {
+                    anyhow::bail!("pattern not found: {}", substitute.pattern);
+                }
+                editor.replace_lines(start, end, &new_lines);
+            }
+            ExCommandKind::Sort(options) => {
+                let editor = editor_or_err()?;
+                let (start, end) = lines(editor, true);
+                let mut new_lines = editor.lines(start, end);
+                options.sort(&mut new_lines);
+                editor.replace_lines(start, end, &new_lines);
+            }
+            ExCommandKind::Global {
+                pattern,
+                invert,
+                command,
+            } => {
+                let editor = editor_or_err()?;
+                let regex = Regex::new(&pattern)?;
+                let command = ExCommand::parse(&command)?;
+                if matches!(command.kind, ExCommandKind::Global { .. }) {
+                    anyhow::bail!("cannot use :global recursively");
+                }
+                let (start, end) = lines(editor, true);
+                let contents = editor.lines(start, end);
+                let matched = contents
+                    .iter()
+                    .map(|line| regex.is_match(line) != invert)
+                    .collect::<Vec<_>>();
+                let first = matched.iter().position(|m| *m);
+                let last = matched.iter().rposition(|m| *m);
+                let (Some(first), Some(last)) = (first, last) else {
+                    anyhow::bail!("pattern not found: {pattern}");
+                };
+
+                // Substituting and deleting are made as one edit of all the
+                // lines, so that undoing the command takes a single step
+                let substitute = match &command.kind {
+                    ExCommandKind::Substitute(substitute) => {
+                        Some(Some((substitute, substitute.regex()?)))
+                    }
+                    ExCommandKind::Delete => Some(None),
+                    _ => None,
+                };
+                if let Some(substitute) = substitute {
+                    let mut changed = false;
+                    let new_lines = (first..=last)
+                        .filter_map(|i| {
+                            let line = &contents[i];
+                            if !matched[i] {
+                                return Some(line.clone());
+                            }
+                            let Some((substitute, regex)) = &substitute else {
+                                changed = true;
+                                return None;
+                            };
+                            match substitute.apply(regex, line) {
+                                Some(new_line) => {
+                                    changed = true;
+                                    Some(new_line)
+                                }
+                                None => Some(line.clone()),
+                            }
+                        })
+                        .collect::<Vec<_>>();
+                    if changed {
+                        editor.replace_lines(
+                            start + first,
+                            start + last,
+                            &new_lines,
+                        );
+                    }
+                    return Ok(());
+                }
+
+                // Going bottom up keeps the lines still to go where they were
+                for i in (first..=last).rev().filter(|i| matched[*i]) {
+                    self.exec_ex_command(
+                        command.kind.clone(),
+                        Some(editor),
+                        Some((start + i, start + i)),
+                    )?;
+                }
+            }
+            ExCommandKind::Normal(keys) => {
+                let editor = editor_or_err()?;
+                let (start, end) = lines(editor, false);
+                for line in (start..=end).rev() {
+                    let offset = editor
+                        .doc()
+                        .buffer
+                        .with_untracked(|buffer| buffer.offset_of_line(line));
+                    editor
+                        .cursor()
+                        .update(|cursor| cursor.mode = CursorMode::Normal(offset));
+                    for c in keys.chars() {
+                        self.key_down(&KeyPress::from_char(c));
+                    }
+                    editor.run_command(
+                        &LapceCommand {
+                            kind: CommandKind::Edit(EditCommand::NormalMode),
+                            data: None,
+                        },
+                        None,
+                        Modifiers::empty(),
+                    );
+                }
+            }
+            ExCommandKind::Command(name) => {
+                let keypress = self.common.keypress.get_untracked();
+                let Some(cmd) = keypress.commands.get(&name).cloned() else {
+                    anyhow::bail!("not an editor command");
+                };
+                self.common.lapce_command.send(cmd);
+            }
+        }
+        Ok(())
+    }
+
     pub fn workspace_info(&self) -> WorkspaceInfo {
         let main_split_data = self
             .main_split
@@ -2629,21 +3301,23 @@ pub fn toggle_panel_visual(&self, kind: PanelKind) {
     /// Toggle a specific kind of panel.
     fn toggle_panel_focus(&self, kind: PanelKind) {
         let should_hide = match kind {
-            PanelKind::FileExplorer
-            | PanelKind::Plugin
+            PanelKind::Plugin
             | PanelKind::Problem
             | PanelKind::Debug
             | PanelKind::CallHierarchy
             | PanelKind::DocumentSymbol
             | PanelKind::References
-            | PanelKind::Implementation => {
+            | PanelKind::Implementation
+            | PanelKind::Timeline => {
                 // Some panels don't accept focus (yet). Fall back to visibility check
                 // in those cases.
                 self.panel.is_panel_visible(&kind)
             }
-            PanelKind::Terminal | PanelKind::SourceControl | PanelKind::Search => {
-                self.is_panel_focused(kind)
-            }
+            PanelKind::Terminal
+            | PanelKind::FileExplorer
+            | PanelKind::SourceControl
+            | PanelKind::Search
+            | PanelKind::Ports => self.is_panel_focused(kind),
         };
         if should_hide {
             self.hide_panel(kind);
@@ -2808,6 +3482,30 @@ fn run_in_terminal(
         }
     }
 
+    /// The workspace as it is now, with the root folders added and removed
+    /// since it was opened.
+    pub fn current_workspace(&self) -> LapceWorkspace {
+        self.workspace
+            .with_roots(&self.common.workspace_roots.get_untracked())
+    }
+
+    /// Change the root folders of the workspace, telling the file explorer,
+    /// the proxy and the workspace file.
+    fn set_workspace_roots(&self, roots: Vec<PathBuf>) {
+        self.common.workspace_roots.set(roots.clone());
+        self.file_explorer.set_roots(&roots);
+        self.common.proxy.set_workspace_folders(roots);
+
+        let workspace = self.current_workspace();
+        if let Err(err) = workspace.save_file() {
+            tracing::error!("Failed to save workspace file: {err}");
+        }
+        let db: Arc<LapceDb> = use_context().unwrap();
+        if let Err(err) = db.update_recent_workspace(&workspace) {
+            tracing::error!("{:?}", err);
+        }
+    }
+
     pub fn open_paths(&self, paths: &[PathObject]) {
         let (folders, files): (Vec<&PathObject>, Vec<&PathObject>) =
             paths.iter().partition(|p| p.is_dir);
@@ -2818,7 +3516,7 @@ pub fn open_paths(&self, paths: &[PathObject]) {
                     workspace: LapceWorkspace {
                         kind: self.workspace.kind.clone(),
                         path: Some(folder.path.clone()),
-                        last_open: 0,
+                        ..Default::default()
                     },
                     end: false,
                 },
@@ -2826,6 +3524,27 @@ pub fn open_paths(&self, paths: &[PathObject]) {
         }
 
         for file in files {
+            if self.workspace.kind.is_local()
+                && LapceWorkspace::is_workspace_file(&file.path)
+            {
+                match LapceWorkspace::from_file(&file.path) {
+                    Ok(workspace) => {
+                        self.common.window_common.window_command.send(
+                            WindowCommand::NewWorkspaceTab {
+                                workspace,
+                                end: false,
+                            },
+                        );
+                    }
+                    Err(err) => {
+                        tracing::error!(
+                            "Failed to read workspace file {:?}: {err}",
+                            file.path
+                        );
+                    }
+                }
+                continue;
+            }
             let position = file.linecol.map(|pos| {
                 EditorPosition::Position(lsp_types::Position {
                     line: pos.line.saturating_sub(1) as u32,
@@ -2856,6 +3575,48 @@ pub fn show_alert(&self, title: String, msg: String, buttons: Vec<AlertButton>)
         self.alert_data.active.set(true);
     }
 
+    /// Ask the user what the connection to the remote prompts for, like a
+    /// password or whether to trust the key of the host.
+    fn show_remote_prompt(&self, prompt: RemotePrompt) {
+        // Only one prompt is answered at a time
+        self.palette.answer_remote_prompt(None);
+
+        if prompt.kind == RemotePromptKind::Confirm {
+            let internal_command = self.common.internal_command;
+            let id = prompt.id;
+            let button = move |text: &str, answer: &'static str| AlertButton {
+                text: text.to_string(),
+                action: Rc::new(move || {
+                    internal_command.send(InternalCommand::AnswerRemotePrompt {
+                        id,
+                        answer: Some(answer.to_string()),
+                    });
+                    internal_command.send(InternalCommand::HideAlert);
+                }),
+            };
+            self.show_alert(
+                "Connecting to Remote".to_string(),
+                prompt.prompt,
+                vec![button("Yes", "yes"), button("No", "no")],
+            );
+
+            // Closing the alert without answering cancels the prompt, which
+            // does nothing once it's answered
+            let active = self.alert_data.active;
+            let remote_prompts = self.proxy.remote_prompts.clone();
+            self.scope.create_effect(move |done: Option<bool>| {
+                if done == Some(true) || active.get() {
+                    return done == Some(true);
+                }
+                remote_prompts.answer(id, None);
+                true
+            });
+        } else {
+            self.palette.remote_prompt.set(Some(prompt));
+            self.palette.run(PaletteKind::RemotePrompt);
+        }
+    }
+
     fn update_progress(&self, progress: &ProgressParams) {
         let token = progress.token.clone();
         match &progress.value {
@@ -2888,6 +3649,40 @@ fn update_progress(&self, progress: &ProgressParams) {
         }
     }
 
+    /// Move files and directories into `to`, updating the editors of the
+    /// moved files. The move can be undone if `undoable`.
+    fn move_paths(&self, paths: Vec<PathBuf>, to: PathBuf, undoable: bool) {
+        let file_explorer = self.file_explorer.clone();
+        let editors = self.main_split.editors;
+        let messages = self.messages;
+        let send = create_ext_action(
+            self.scope,
+            move |response: Result<ProxyResponse, RpcError>| {
+                match response {
+                    Ok(ProxyResponse::PathsResponse { paths, error }) => {
+                        for (from, to) in &paths {
+                            editors.path_moved(from, to);
+                        }
+                        if undoable && !paths.is_empty() {
+                            file_explorer
+                                .last_operation
+                                .set(Some(FileOperation::Move(paths)));
+                        }
+                        if let Some(error) = error {
+                            file_operation_failed(messages, error);
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(err) => {
+                        file_operation_failed(messages, err.message);
+                    }
+                }
+                file_explorer.reload();
+            },
+        );
+        self.common.proxy.move_paths(paths, to, send);
+    }
+
     fn show_message(&self, title: &str, message: &ShowMessageParams) {
         self.messages.update(|messages| {
             messages.push((title.to_string(), message.clone()));
@@ -2918,11 +3713,13 @@ pub fn show_code_lens(
             .send(InternalCommand::ShowCodeActions {
                 offset,
                 mouse_click,
-                plugin_id,
                 code_actions: lens
                     .into_iter()
                     .filter_map(|lens| {
-                        Some(CodeActionOrCommand::Command(lens.command?))
+                        Some((
+                            plugin_id,
+                            CodeActionOrCommand::Command(lens.command?),
+                        ))
                     })
                     .collect(),
             });
@@ -2982,6 +3779,22 @@ pub fn call_hierarchy_incoming(&self, item_id: ViewId) {
     }
 }
 
+/// Tell about a copy or move of the file explorer that failed.
+fn file_operation_failed(
+    messages: RwSignal<Vec<(String, ShowMessageParams)>>,
+    message: String,
+) {
+    messages.update(|messages| {
+        messages.push((
+            "File Explorer".to_string(),
+            ShowMessageParams {
+                typ: MessageType::ERROR,
+                message,
+            },
+        ));
+    });
+}
+
 /// Open path with the default application without blocking.
 fn open_uri(path: &Path) {
     match open::that(path) {
@@ -2993,3 +3806,22 @@ fn open_uri(path: &Path) {
         }
     }
 }
+
+/// The lines of `editor` that the addresses of a command line range refer to.
+fn ex_line_context(editor: &EditorData) -> LineContext {
+    let cursor = editor.cursor().get_untracked();
+    editor.doc().buffer.with_untracked(|buffer| {
+        let visual = match cursor.mode {
+            CursorMode::Visual { start, end, .. } => Some((
+                buffer.line_of_offset(start.min(end)),
+                buffer.line_of_offset(start.max(end)),
+            )),
+            _ => None,
+        };
+        LineContext {
+            current: buffer.line_of_offset(cursor.offset()),
+            last: buffer.last_line(),
+            visual,
+        }
+    })
+}
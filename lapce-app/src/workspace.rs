@@ -1,8 +1,16 @@
-use std::{collections::HashMap, fmt::Display, path::PathBuf};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+};
 
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
-use crate::{debug::LapceBreakpoint, main_split::SplitInfo, panel::data::PanelInfo};
+use crate::{
+    config::core::RemoteCommand, debug::LapceBreakpoint, main_split::SplitInfo,
+    panel::data::PanelInfo,
+};
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Hash)]
 pub struct SshHost {
@@ -48,6 +56,47 @@ fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     }
 }
 
+/// A host, like a container or a sandbox, reached by running a command.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Hash)]
+pub struct CommandHost {
+    /// The name of the remote command in the settings this host uses
+    pub template: String,
+    /// What `{name}` stands for in the commands, like the name of a container
+    pub name: String,
+    /// The command that runs the arguments after it on the host
+    pub command: Vec<String>,
+    /// The command that copies the file `{local}` to `{remote}` on the host
+    #[serde(default)]
+    pub upload: Vec<String>,
+}
+
+impl CommandHost {
+    /// The host typed as `template:name`, with the commands of the remote
+    /// command of that name in the settings.
+    pub fn from_string(
+        s: &str,
+        remote_commands: &HashMap<String, RemoteCommand>,
+    ) -> Option<Self> {
+        let (template, name) = s.trim().split_once(':')?;
+        let remote_command = remote_commands.get(template)?;
+        if name.is_empty() || remote_command.command.is_empty() {
+            return None;
+        }
+        Some(Self {
+            template: template.to_string(),
+            name: name.to_string(),
+            command: remote_command.command.clone(),
+            upload: remote_command.upload.clone(),
+        })
+    }
+}
+
+impl Display for CommandHost {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.template, self.name)
+    }
+}
+
 #[cfg(windows)]
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Hash)]
 pub struct WslHost {
@@ -66,6 +115,7 @@ fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 pub enum LapceWorkspaceType {
     Local,
     RemoteSSH(SshHost),
+    RemoteCommand(CommandHost),
     #[cfg(windows)]
     RemoteWSL(WslHost),
 }
@@ -79,10 +129,10 @@ pub fn is_remote(&self) -> bool {
         use LapceWorkspaceType::*;
 
         #[cfg(not(windows))]
-        return matches!(self, RemoteSSH(_));
+        return matches!(self, RemoteSSH(_) | RemoteCommand(_));
 
         #[cfg(windows)]
-        return matches!(self, RemoteSSH(_) | RemoteWSL(_));
+        return matches!(self, RemoteSSH(_) | RemoteCommand(_) | RemoteWSL(_));
     }
 }
 
@@ -93,6 +143,9 @@ fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
             LapceWorkspaceType::RemoteSSH(remote) => {
                 write!(f, "ssh://{remote}")
             }
+            LapceWorkspaceType::RemoteCommand(remote) => {
+                write!(f, "{}://{}", remote.template, remote.name)
+            }
             #[cfg(windows)]
             LapceWorkspaceType::RemoteWSL(remote) => {
                 write!(f, "{remote} (WSL)")
@@ -105,22 +158,110 @@ fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 pub struct LapceWorkspace {
     pub kind: LapceWorkspaceType,
     pub path: Option<PathBuf>,
+    /// The root folders opened beside `path`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub folders: Vec<PathBuf>,
+    /// The workspace file the folders were read from, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
     pub last_open: u64,
 }
 
+/// What a workspace file holds, such as
+///
+/// ```toml
+/// folders = ["../api", "../web", "../shared"]
+/// ```
+///
+/// where relative paths are relative to the file.
+#[derive(Default, Serialize, Deserialize)]
+struct WorkspaceFile {
+    folders: Vec<PathBuf>,
+}
+
 impl LapceWorkspace {
+    /// The extension of workspace files, which list the root folders of a
+    /// workspace.
+    pub const FILE_EXTENSION: &'static str = "lapce-workspace";
+
+    pub fn is_workspace_file(path: &Path) -> bool {
+        path.extension()
+            .is_some_and(|ext| ext == Self::FILE_EXTENSION)
+    }
+
+    /// A local workspace with the root folders listed in a workspace file.
+    pub fn from_file(file: &Path) -> Result<Self> {
+        let content: WorkspaceFile =
+            toml::from_str(&std::fs::read_to_string(file)?)?;
+        let base = file.parent().unwrap_or(Path::new(""));
+        let mut folders = content.folders.iter().map(|folder| base.join(folder));
+        Ok(Self {
+            kind: LapceWorkspaceType::Local,
+            path: folders.next(),
+            folders: folders.collect(),
+            file: Some(file.to_path_buf()),
+            last_open: 0,
+        })
+    }
+
+    /// Write the root folders back to the workspace file, if there is one.
+    pub fn save_file(&self) -> Result<()> {
+        let Some(file) = self.file.as_ref() else {
+            return Ok(());
+        };
+        let base = file.parent().unwrap_or(Path::new(""));
+        let folders = self
+            .roots()
+            .into_iter()
+            .map(|folder| {
+                folder
+                    .strip_prefix(base)
+                    .map(|relative| {
+                        if relative.as_os_str().is_empty() {
+                            PathBuf::from(".")
+                        } else {
+                            relative.to_path_buf()
+                        }
+                    })
+                    .unwrap_or(folder)
+            })
+            .collect();
+        std::fs::write(file, toml::to_string(&WorkspaceFile { folders })?)?;
+        Ok(())
+    }
+
+    /// Every root folder of the workspace, `path` being the first.
+    pub fn roots(&self) -> Vec<PathBuf> {
+        self.path.iter().chain(&self.folders).cloned().collect()
+    }
+
+    /// The workspace with other root folders, the first becoming `path`.
+    pub fn with_roots(&self, roots: &[PathBuf]) -> Self {
+        Self {
+            path: roots.first().cloned(),
+            folders: roots.iter().skip(1).cloned().collect(),
+            ..self.clone()
+        }
+    }
+
     pub fn display(&self) -> Option<String> {
-        let path = self.path.as_ref()?;
-        let path = path
-            .file_name()
-            .unwrap_or(path.as_os_str())
-            .to_string_lossy()
-            .to_string();
+        let path = self.file.as_ref().or(self.path.as_ref())?;
+        let path = if self.file.is_some() {
+            path.file_stem()
+        } else {
+            path.file_name()
+        }
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .to_string();
         let remote = match &self.kind {
             LapceWorkspaceType::Local => String::new(),
             LapceWorkspaceType::RemoteSSH(remote) => {
                 format!(" [SSH: {}]", remote.host)
             }
+            LapceWorkspaceType::RemoteCommand(remote) => {
+                format!(" [{}: {}]", remote.template, remote.name)
+            }
             #[cfg(windows)]
             LapceWorkspaceType::RemoteWSL(remote) => {
                 format!(" [WSL: {}]", remote.host)
@@ -135,6 +276,8 @@ fn default() -> Self {
         Self {
             kind: LapceWorkspaceType::Local,
             path: None,
+            folders: Vec::new(),
+            file: None,
             last_open: 0,
         }
     }
@@ -146,14 +289,59 @@ fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
             f,
             "{}:{}",
             self.kind,
-            self.path.as_ref().and_then(|p| p.to_str()).unwrap_or("")
+            self.file
+                .as_ref()
+                .or(self.path.as_ref())
+                .and_then(|p| p.to_str())
+                .unwrap_or("")
         )
     }
 }
 
+/// The path relative to the root folder it's in, starting with the name of
+/// that folder when there are several of them.
+pub fn relative_to_roots(roots: &[PathBuf], path: &Path) -> PathBuf {
+    let Some(root) = roots
+        .iter()
+        .filter(|root| path.starts_with(root))
+        .max_by_key(|root| root.components().count())
+    else {
+        return path.to_path_buf();
+    };
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    match root.file_name() {
+        Some(name) if roots.len() > 1 => Path::new(name).join(relative),
+        _ => relative.to_path_buf(),
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct WorkspaceInfo {
     pub split: SplitInfo,
     pub panel: PanelInfo,
     pub breakpoints: HashMap<PathBuf, Vec<LapceBreakpoint>>,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::relative_to_roots;
+
+    #[test]
+    fn test_relative_to_roots() {
+        let roots = vec![PathBuf::from("/work/api"), PathBuf::from("/work/web")];
+        assert_eq!(
+            relative_to_roots(&roots, Path::new("/work/web/src/main.rs")),
+            Path::new("web/src/main.rs")
+        );
+        assert_eq!(
+            relative_to_roots(&roots[..1], Path::new("/work/api/lib.rs")),
+            Path::new("lib.rs")
+        );
+        assert_eq!(
+            relative_to_roots(&roots, Path::new("/tmp/a.rs")),
+            Path::new("/tmp/a.rs")
+        );
+    }
+}
@@ -0,0 +1,289 @@
+use std::{fmt::Write, ops::Range};
+
+use lapce_xi_rope::Rope;
+
+/// How many bytes a line of a hex dump shows.
+pub const BYTES_PER_LINE: usize = 16;
+
+/// The column of the first character of the ASCII column.
+const ASCII_START: usize = 61;
+
+/// A hex dump of the bytes, with the offset, hex and ASCII columns of
+/// `hexdump -C`. `len` is the size of the whole file, of which the bytes may
+/// only be the start.
+pub fn hex_dump(bytes: &[u8], len: u64) -> String {
+    let mut dump = String::with_capacity(bytes.len() / BYTES_PER_LINE * 78 + 78);
+    for (i, line) in bytes.chunks(BYTES_PER_LINE).enumerate() {
+        let _ = write!(dump, "{:08x} ", i * BYTES_PER_LINE);
+        for (j, byte) in line.iter().enumerate() {
+            if j == BYTES_PER_LINE / 2 {
+                dump.push(' ');
+            }
+            let _ = write!(dump, " {byte:02x}");
+        }
+        for j in line.len()..BYTES_PER_LINE {
+            dump.push_str(if j == BYTES_PER_LINE / 2 {
+                "    "
+            } else {
+                "   "
+            });
+        }
+        dump.push_str("  |");
+        dump.extend(line.iter().map(|&byte| ascii_char(byte)));
+        dump.push_str("|\n");
+    }
+    if (bytes.len() as u64) < len {
+        let _ = writeln!(dump, "... {} more bytes", len - bytes.len() as u64);
+    }
+    dump
+}
+
+/// The bytes shown by a hex dump, read from its hex column. Lines that
+/// aren't lines of bytes, like the one saying the dump was cut short, are
+/// skipped.
+pub fn parse_hex_dump(text: &Rope) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(text.len() / 78 * BYTES_PER_LINE);
+    for line in text.lines(0..text.len()) {
+        if !line
+            .get(..8)
+            .is_some_and(|offset| offset.bytes().all(|b| b.is_ascii_hexdigit()))
+        {
+            continue;
+        }
+        bytes.extend((0..BYTES_PER_LINE).map_while(|i| line_byte(&line, i)));
+    }
+    bytes
+}
+
+/// The column of the first hex digit of the byte at `index` in its line.
+pub fn hex_column(index: usize) -> usize {
+    10 + 3 * index + usize::from(index >= BYTES_PER_LINE / 2)
+}
+
+/// The column of the character of the byte at `index` in its line.
+pub fn ascii_column(index: usize) -> usize {
+    ASCII_START + index
+}
+
+/// How a byte is shown in the ASCII column.
+pub fn ascii_char(byte: u8) -> char {
+    if byte.is_ascii_graphic() || byte == b' ' {
+        byte as char
+    } else {
+        '.'
+    }
+}
+
+/// The offset in the file of the byte at a column of a line of a dump.
+pub fn byte_offset(line: usize, col: usize) -> usize {
+    let index = match column_at(col) {
+        Some(HexColumn::Hex { index, .. } | HexColumn::Ascii { index }) => index,
+        None if col >= ascii_column(BYTES_PER_LINE) => BYTES_PER_LINE - 1,
+        None => (0..BYTES_PER_LINE)
+            .take_while(|i| hex_column(*i) <= col)
+            .last()
+            .unwrap_or(0),
+    };
+    line * BYTES_PER_LINE + index
+}
+
+/// What a column of a line of a dump shows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HexColumn {
+    /// One of the digits of the byte at `index`
+    Hex { index: usize, low: bool },
+    /// The character of the byte at `index`
+    Ascii { index: usize },
+}
+
+/// What is at the column of a line of a dump, if it's part of a byte.
+pub fn column_at(col: usize) -> Option<HexColumn> {
+    if (ascii_column(0)..ascii_column(BYTES_PER_LINE)).contains(&col) {
+        return Some(HexColumn::Ascii {
+            index: col - ASCII_START,
+        });
+    }
+    (0..BYTES_PER_LINE).find_map(|index| {
+        let start = hex_column(index);
+        (start..start + 2).contains(&col).then_some(HexColumn::Hex {
+            index,
+            low: col > start,
+        })
+    })
+}
+
+/// The byte at `index` in a line of a dump, if it has that many.
+fn line_byte(line: &str, index: usize) -> Option<u8> {
+    let start = hex_column(index);
+    u8::from_str_radix(line.get(start..start + 2)?, 16).ok()
+}
+
+/// Overwrite part of the byte at a column of a line of a dump with a typed
+/// character: a hex digit over a digit of the hex column, or an ASCII
+/// character over the ASCII column. Returns the edits to the line, which
+/// keep both columns showing the same byte, and the column to move to
+/// next, on the next line when the line is done.
+pub fn overwrite(
+    line: &str,
+    col: usize,
+    c: char,
+) -> Option<(Vec<(Range<usize>, String)>, usize, bool)> {
+    let column = column_at(col)?;
+    let (HexColumn::Hex { index, .. } | HexColumn::Ascii { index }) = column;
+    let byte = line_byte(line, index)?;
+    let hex = hex_column(index);
+    let ascii = ascii_column(index);
+
+    let (byte, next) = match column {
+        HexColumn::Hex { low, .. } => {
+            let digit = c.to_digit(16)? as u8;
+            let byte = if low {
+                (byte & 0xf0) | digit
+            } else {
+                (byte & 0x0f) | (digit << 4)
+            };
+            let next = if !low {
+                Some(col + 1)
+            } else if index + 1 < BYTES_PER_LINE {
+                Some(hex_column(index + 1))
+            } else {
+                None
+            };
+            (byte, next.ok_or(hex_column(0)))
+        }
+        HexColumn::Ascii { .. } => {
+            if !c.is_ascii() || c.is_ascii_control() {
+                return None;
+            }
+            let next = if index + 1 < BYTES_PER_LINE {
+                Ok(ascii_column(index + 1))
+            } else {
+                Err(ascii_column(0))
+            };
+            (c as u8, next)
+        }
+    };
+
+    let mut edits = vec![(hex..hex + 2, format!("{byte:02x}"))];
+    if line.get(ascii..ascii + 1).is_some() {
+        edits.push((ascii..ascii + 1, ascii_char(byte).to_string()));
+    }
+    Some(match next {
+        Ok(col) => (edits, col, false),
+        Err(col) => (edits, col, true),
+    })
+}
+
+/// Bytes to search for, typed as hex digits with or without spaces, or as
+/// text in quotes.
+pub fn parse_bytes(input: &str) -> Option<Vec<u8>> {
+    let input = input.trim();
+    if let Some(text) = input
+        .strip_prefix('"')
+        .and_then(|input| input.strip_suffix('"'))
+    {
+        return (!text.is_empty()).then(|| text.as_bytes().to_vec());
+    }
+    let digits: String = input
+        .trim_start_matches("0x")
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    if digits.is_empty() || digits.len() % 2 != 0 {
+        return None;
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(digits.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// The first place the needle is found in the bytes after `from`, going
+/// around to the start after the end.
+pub fn find_bytes(bytes: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    if needle.is_empty() {
+        return None;
+    }
+    let find = |start: usize, end: usize| {
+        bytes
+            .get(start..end)?
+            .windows(needle.len())
+            .position(|window| window == needle)
+            .map(|i| start + i)
+    };
+    let from = from.min(bytes.len());
+    find(from, bytes.len())
+        .or_else(|| find(0, (from + needle.len() - 1).min(bytes.len())))
+}
+
+/// The offset a goto is typed as: hex with `0x`, or decimal.
+pub fn parse_offset(input: &str) -> Option<usize> {
+    let input = input.trim();
+    match input.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => input.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex_dump() {
+        let bytes: Vec<u8> = (0x3c..0x50).collect();
+        let dump = hex_dump(&bytes, 30);
+        assert_eq!(
+            dump,
+            "00000000  3c 3d 3e 3f 40 41 42 43  44 45 46 47 48 49 4a 4b  |<=>?@ABCDEFGHIJK|\n\
+             00000010  4c 4d 4e 4f                                       |LMNO|\n\
+             ... 10 more bytes\n"
+        );
+        assert_eq!(parse_hex_dump(&Rope::from(dump)), bytes);
+    }
+
+    #[test]
+    fn test_overwrite() {
+        let line = "00000000  3c 3d 3e 3f 40 41 42 43  44 45 46 47 48 49 4a 4b  |<=>?@ABCDEFGHIJK|";
+
+        // The high digit of the byte 0x3d becomes 0x4d
+        let (edits, next, next_line) = overwrite(line, 13, '4').unwrap();
+        assert_eq!(
+            edits,
+            vec![(13..15, "4d".to_string()), (62..63, "M".to_string())]
+        );
+        assert_eq!((next, next_line), (14, false));
+
+        // Typing over the last character moves to the next line
+        let (edits, next, next_line) = overwrite(line, 76, 'z').unwrap();
+        assert_eq!(
+            edits,
+            vec![(56..58, "7a".to_string()), (76..77, "z".to_string())]
+        );
+        assert_eq!((next, next_line), (ascii_column(0), true));
+
+        assert!(overwrite(line, 13, 'g').is_none());
+        assert!(overwrite(line, 9, '1').is_none());
+    }
+
+    #[test]
+    fn test_find_bytes() {
+        let bytes = b"abcabc";
+        assert_eq!(find_bytes(bytes, b"bc", 0), Some(1));
+        assert_eq!(find_bytes(bytes, b"bc", 2), Some(4));
+        assert_eq!(find_bytes(bytes, b"bc", 5), Some(1));
+        assert_eq!(find_bytes(bytes, b"x", 0), None);
+    }
+
+    #[test]
+    fn test_parse_input() {
+        assert_eq!(
+            parse_bytes("de ad BEEF"),
+            Some(vec![0xde, 0xad, 0xbe, 0xef])
+        );
+        assert_eq!(parse_bytes("\"PK\""), Some(b"PK".to_vec()));
+        assert_eq!(parse_bytes("abc"), None);
+        assert_eq!(parse_offset("0x1f"), Some(31));
+        assert_eq!(parse_offset("31"), Some(31));
+    }
+}
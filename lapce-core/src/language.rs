@@ -3,9 +3,14 @@
     fmt::Write,
     path::Path,
     str::FromStr,
+    sync::RwLock,
 };
 
+use floem_editor_core::buffer::rope_text::{RopeText, RopeTextRef};
+use itertools::Itertools;
+pub use lapce_rpc::language::LanguageDefinition;
 use lapce_rpc::style::{LineStyle, Style};
+use lapce_xi_rope::Rope;
 use once_cell::sync::Lazy;
 use regex::Regex;
 use strum_macros::{AsRefStr, Display, EnumMessage, EnumString, IntoStaticStr};
@@ -167,8 +172,9 @@ struct CommentProperties {
     multi_line_prefix: Option<&'static str>,
 }
 
-/// NOTE: Keep the enum variants "fieldless" so they can cast to usize as array
-/// indices into the LANGUAGES array.  See method `LapceLanguage::properties`.
+/// NOTE: Keep the built-in enum variants "fieldless" and in the same order as
+/// the LANGUAGES array, which is searched by them.  See method
+/// `LapceLanguage::properties`.
 ///
 /// Do not assign values to the variants because the number of variants and
 /// number of elements in the LANGUAGES array change as different features
@@ -444,6 +450,11 @@ pub enum LapceLanguage {
     Yaml,
     #[strum(message = "Zig")]
     Zig,
+
+    /// A language defined in the settings, by its index among them
+    #[remain::unsorted]
+    #[strum(disabled)]
+    Custom(u16),
 }
 
 /// NOTE: Elements in the array must be in the same order as the enum variants of
@@ -1677,35 +1688,74 @@ pub enum LapceLanguage {
 impl LapceLanguage {
     const HIGHLIGHTS_INJECTIONS_FILE_NAME: &'static str = "injections.scm";
     const HIGHLIGHTS_QUERIES_FILE_NAME: &'static str = "highlights.scm";
+    const TEXTOBJECTS_QUERIES_FILE_NAME: &'static str = "textobjects.scm";
+    const FOLDS_QUERIES_FILE_NAME: &'static str = "folds.scm";
+    const TAGS_QUERIES_FILE_NAME: &'static str = "tags.scm";
+    const INDENTS_QUERIES_FILE_NAME: &'static str = "indents.scm";
 
     pub fn from_path(path: &Path) -> LapceLanguage {
         Self::from_path_raw(path).unwrap_or(LapceLanguage::PlainText)
     }
 
+    /// The language of a file from its path and, when it has one, the
+    /// modeline or the shebang line in its text. A modeline wins over the
+    /// path, while a shebang only counts for paths that say nothing.
+    pub fn from_path_and_text(path: &Path, text: &Rope) -> LapceLanguage {
+        let text = RopeTextRef::new(text);
+        let last_line = text.last_line();
+        // Modelines go in the first or the last few lines
+        let lines = (0..=last_line.min(MODELINE_LINES - 1))
+            .chain(
+                MODELINE_LINES.max(last_line.saturating_sub(MODELINE_LINES - 1))
+                    ..=last_line,
+            )
+            .map(|line| text.line_content(line))
+            .collect::<Vec<_>>();
+
+        from_modeline(&lines)
+            .or_else(|| Self::from_path_raw(path))
+            .or_else(|| from_shebang(&lines[0]))
+            .unwrap_or(LapceLanguage::PlainText)
+    }
+
     pub fn from_path_raw(path: &Path) -> Option<LapceLanguage> {
+        if let Some(language) = Self::from_user_settings(path) {
+            return Some(language);
+        }
+
         let filename = path.file_name().and_then(|s| s.to_str());
         let extension = path
             .extension()
             .and_then(|s| s.to_str().map(|s| s.to_lowercase()));
+
         // NOTE: This is a linear search.  It is assumed that this function
         // isn't called in any tight loop.
-        for properties in LANGUAGES {
-            if properties.files.iter().any(|f| Some(*f) == filename) {
-                return Some(properties.id);
-            }
-            if properties
-                .extensions
-                .iter()
-                .any(|e| Some(*e) == extension.as_deref())
-            {
-                return Some(properties.id);
-            }
-        }
+        LANGUAGES
+            .iter()
+            .find(|properties| {
+                properties.matches_path(filename, extension.as_deref())
+            })
+            .map(|properties| properties.id)
+    }
 
-        None
+    /// The language that the file associations or the languages defined in
+    /// the settings pick for the path.
+    pub fn from_user_settings(path: &Path) -> Option<LapceLanguage> {
+        let filename = path.file_name().and_then(|s| s.to_str());
+        let extension = path
+            .extension()
+            .and_then(|s| s.to_str().map(|s| s.to_lowercase()));
+        USER_LANGUAGES.read().unwrap().path_language(
+            path,
+            filename,
+            extension.as_deref(),
+        )
     }
 
     pub fn from_name(name: &str) -> Option<LapceLanguage> {
+        if let Some(language) = USER_LANGUAGES.read().unwrap().find(name) {
+            return Some(language);
+        }
         match LapceLanguage::from_str(name.to_lowercase().as_str()) {
             Ok(v) => Some(v),
             Err(e) => {
@@ -1723,14 +1773,26 @@ pub fn languages() -> Vec<&'static str> {
                 langs.push(lang)
             }
         }
+        let user = USER_LANGUAGES.read().unwrap();
+        langs.extend(user.active().map(|language| language.name));
         langs
     }
 
-    // NOTE: Instead of using `&LANGUAGES[*self as usize]` directly, the
-    // `debug_assertion` gives better feedback should something has gone wrong
-    // badly.
-    fn properties(&self) -> &SyntaxProperties {
-        let i = *self as usize;
+    // NOTE: The built-in languages are found with a binary search of the
+    // LANGUAGES array, which is sorted like the enum variants, and the
+    // `debug_assertion` gives better feedback should something has gone
+    // wrong badly.
+    fn properties(&self) -> &'static SyntaxProperties {
+        if let LapceLanguage::Custom(_) = self {
+            let user = USER_LANGUAGES.read().unwrap();
+            return user
+                .get(*self)
+                .map(|language| language.properties)
+                .unwrap_or(&LANGUAGES[0]);
+        }
+        let i = LANGUAGES
+            .partition_point(|l| l.id < *self)
+            .min(LANGUAGES.len() - 1);
         let l = &LANGUAGES[i];
         debug_assert!(
             l.id == *self,
@@ -1742,9 +1804,24 @@ fn properties(&self) -> &SyntaxProperties {
     }
 
     pub fn name(&self) -> &'static str {
+        if let Some(language) = USER_LANGUAGES.read().unwrap().get(*self) {
+            return language.name;
+        }
         strum::EnumMessage::get_message(self).unwrap_or(self.into())
     }
 
+    /// The id of a language defined in the settings for language servers,
+    /// which is its name in lowercase.
+    pub fn user_language_id(&self) -> Option<&'static str> {
+        let user = USER_LANGUAGES.read().unwrap();
+        user.get(*self).map(|language| language.language_id)
+    }
+
+    /// The first of the file name extensions of the language, like `rs`.
+    pub fn extension(&self) -> Option<&'static str> {
+        self.properties().extensions.first().copied()
+    }
+
     pub fn sticky_header_tags(&self) -> &[&'static str] {
         self.properties().tree_sitter.sticky_headers
     }
@@ -1843,6 +1920,45 @@ pub(crate) fn new_highlight_config(
         }
     }
 
+    pub(crate) fn new_textobject_query(&self) -> Option<tree_sitter::Query> {
+        self.new_query(Self::TEXTOBJECTS_QUERIES_FILE_NAME, "text object")
+    }
+
+    pub(crate) fn new_fold_query(&self) -> Option<tree_sitter::Query> {
+        self.new_query(Self::FOLDS_QUERIES_FILE_NAME, "fold")
+    }
+
+    pub(crate) fn new_tags_query(&self) -> Option<tree_sitter::Query> {
+        self.new_query(Self::TAGS_QUERIES_FILE_NAME, "tags")
+    }
+
+    pub(crate) fn new_indent_query(&self) -> Option<tree_sitter::Query> {
+        self.new_query(Self::INDENTS_QUERIES_FILE_NAME, "indent")
+    }
+
+    /// Build the query in the language's `file_name`, which is optional
+    /// unlike the highlight queries.
+    fn new_query(&self, file_name: &str, kind: &str) -> Option<tree_sitter::Query> {
+        let grammar = self.get_grammar()?;
+        let queries_dir = Directory::queries_directory()?;
+        let query = read_grammar_query(&queries_dir, &self.query_name(), file_name);
+        if query.trim().is_empty() {
+            return None;
+        }
+
+        match tree_sitter::Query::new(&grammar, &query) {
+            Ok(query) => Some(query),
+            Err(err) => {
+                event!(
+                    Level::ERROR,
+                    "Encountered {err:?} while trying to construct the {kind} query for {}",
+                    self.name()
+                );
+                None
+            }
+        }
+    }
+
     pub(crate) fn walk_tree(
         &self,
         cursor: &mut TreeCursor,
@@ -1853,6 +1969,359 @@ pub(crate) fn walk_tree(
     }
 }
 
+impl SyntaxProperties {
+    fn matches_path(&self, filename: Option<&str>, extension: Option<&str>) -> bool {
+        self.files.iter().any(|f| Some(*f) == filename)
+            || self.extensions.iter().any(|e| Some(*e) == extension)
+    }
+}
+
+/// How many lines at the start and at the end of a file can hold a modeline
+const MODELINE_LINES: usize = 5;
+
+/// The properties of a language defined in the settings, which live as long
+/// as the built-in ones do.
+fn definition_properties(
+    definition: &LanguageDefinition,
+    id: LapceLanguage,
+    name: &str,
+) -> &'static SyntaxProperties {
+    fn leak(s: &str) -> &'static str {
+        Box::leak(s.to_string().into_boxed_str())
+    }
+    fn leak_all(strings: &[String]) -> &'static [&'static str] {
+        let strings = strings.iter().map(|s| leak(s)).collect::<Vec<_>>();
+        Box::leak(strings.into_boxed_slice())
+    }
+
+    let grammar = leak(definition.grammar.as_deref().unwrap_or(name));
+    let extensions = definition
+        .extensions
+        .iter()
+        .map(|e| e.trim_start_matches('.').to_lowercase())
+        .collect::<Vec<_>>();
+    Box::leak(Box::new(SyntaxProperties {
+        id,
+        comment: CommentProperties {
+            single_line_start: definition.comment.as_deref().map(leak),
+            single_line_end: definition.comment_end.as_deref().map(leak),
+            ..Default::default()
+        },
+        indent: definition
+            .indent
+            .as_deref()
+            .map(leak)
+            .unwrap_or(Indent::space(4)),
+        files: leak_all(&definition.files),
+        extensions: leak_all(&extensions),
+        tree_sitter: TreeSitterProperties {
+            grammar: Some(grammar),
+            grammar_fn: Some(
+                definition
+                    .grammar_fn
+                    .as_deref()
+                    .map(leak)
+                    .unwrap_or(grammar),
+            ),
+            query: Some(leak(definition.query.as_deref().unwrap_or(name))),
+            ..TreeSitterProperties::DEFAULT
+        },
+    }))
+}
+
+struct UserLanguage {
+    name: &'static str,
+    /// The name in lowercase, as the language id sent to language servers
+    language_id: &'static str,
+    definition: LanguageDefinition,
+    properties: &'static SyntaxProperties,
+    /// The properties built for each definition the language had, so that
+    /// going back to one doesn't build them again
+    built: Vec<(LanguageDefinition, &'static SyntaxProperties)>,
+    /// Whether the language is still in the settings. Removed languages are
+    /// kept so that the documents still using them don't change language.
+    active: bool,
+}
+
+impl UserLanguage {
+    fn set_definition(
+        &mut self,
+        definition: &LanguageDefinition,
+        id: LapceLanguage,
+    ) {
+        if self.definition == *definition {
+            return;
+        }
+        let properties = match self.built.iter().find(|(d, _)| d == definition) {
+            Some((_, properties)) => *properties,
+            None => {
+                let properties = definition_properties(definition, id, self.name);
+                self.built.push((definition.clone(), properties));
+                properties
+            }
+        };
+        self.definition = definition.clone();
+        self.properties = properties;
+    }
+}
+
+#[derive(Default)]
+struct UserLanguages {
+    /// Indexed by the id of [`LapceLanguage::Custom`]
+    languages: Vec<UserLanguage>,
+    /// Globs over file paths and the language they pick, ahead of anything
+    /// else
+    associations: Vec<(Regex, LapceLanguage)>,
+}
+
+impl UserLanguages {
+    fn active(&self) -> impl Iterator<Item = &UserLanguage> {
+        self.languages.iter().filter(|language| language.active)
+    }
+
+    fn find(&self, name: &str) -> Option<LapceLanguage> {
+        self.active()
+            .find(|language| language.name.eq_ignore_ascii_case(name))
+            .map(|language| language.properties.id)
+    }
+
+    fn get(&self, language: LapceLanguage) -> Option<&UserLanguage> {
+        match language {
+            LapceLanguage::Custom(id) => self.languages.get(id as usize),
+            _ => None,
+        }
+    }
+
+    /// The language the file associations or the defined languages pick for
+    /// the path.
+    fn path_language(
+        &self,
+        path: &Path,
+        filename: Option<&str>,
+        extension: Option<&str>,
+    ) -> Option<LapceLanguage> {
+        let path = path.to_string_lossy().replace('\\', "/");
+        if let Some((_, language)) = self
+            .associations
+            .iter()
+            .find(|(glob, _)| glob.is_match(&path))
+        {
+            return Some(*language);
+        }
+        self.active()
+            .find(|language| language.properties.matches_path(filename, extension))
+            .map(|language| language.properties.id)
+    }
+
+    fn interpreter_language(&self, interpreter: &str) -> Option<LapceLanguage> {
+        self.active()
+            .find(|language| {
+                language
+                    .definition
+                    .shebangs
+                    .iter()
+                    .any(|s| s == interpreter)
+            })
+            .map(|language| language.properties.id)
+    }
+
+    fn set(
+        &mut self,
+        definitions: &HashMap<String, LanguageDefinition>,
+        associations: &HashMap<String, String>,
+    ) {
+        for language in &mut self.languages {
+            language.active = false;
+        }
+        for (name, definition) in definitions.iter().sorted_by_key(|(name, _)| *name)
+        {
+            let id = LapceLanguage::Custom(self.languages.len() as u16);
+            match self
+                .languages
+                .iter_mut()
+                .enumerate()
+                .find(|(_, language)| language.name.eq_ignore_ascii_case(name))
+            {
+                Some((i, language)) => {
+                    language
+                        .set_definition(definition, LapceLanguage::Custom(i as u16));
+                    language.active = true;
+                }
+                None => {
+                    let properties = definition_properties(definition, id, name);
+                    self.languages.push(UserLanguage {
+                        name: Box::leak(name.clone().into_boxed_str()),
+                        language_id: Box::leak(name.to_lowercase().into_boxed_str()),
+                        definition: definition.clone(),
+                        properties,
+                        built: vec![(definition.clone(), properties)],
+                        active: true,
+                    });
+                }
+            }
+        }
+
+        self.associations = associations
+            .iter()
+            .sorted()
+            .filter_map(|(glob, name)| {
+                let language = self
+                    .find(name)
+                    .or_else(|| LapceLanguage::from_str(name).ok());
+                if language.is_none() {
+                    event!(Level::WARN, "unknown language `{name}` for `{glob}`");
+                }
+                Some((glob_regex(glob)?, language?))
+            })
+            .collect();
+    }
+}
+
+static USER_LANGUAGES: Lazy<RwLock<UserLanguages>> = Lazy::new(Default::default);
+
+/// Set the languages defined in the settings, and the file associations that
+/// map globs like `*.tpl` to the name of a language, built-in or defined.
+pub fn set_user_languages(
+    definitions: &HashMap<String, LanguageDefinition>,
+    associations: &HashMap<String, String>,
+) {
+    USER_LANGUAGES
+        .write()
+        .unwrap()
+        .set(definitions, associations);
+}
+
+/// A regex for a glob like `*.tpl` or `**/templates/*.html`, which matches
+/// the end of a path from the start of a file or folder name.
+fn glob_regex(glob: &str) -> Option<Regex> {
+    let mut regex = String::from("(?:^|/)");
+    let mut chars = glob.chars().peekable();
+    let mut in_group = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.next_if_eq(&'/').is_some() {
+                    regex.push_str("(?:.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '{' => {
+                in_group = true;
+                regex.push_str("(?:");
+            }
+            '}' if in_group => {
+                in_group = false;
+                regex.push(')');
+            }
+            ',' if in_group => regex.push('|'),
+            c => regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    regex.push('$');
+    Regex::new(&regex).ok()
+}
+
+/// The language named by an Emacs modeline, like `-*- mode: python -*-` on
+/// the first line or the one after a shebang, or by a Vim modeline, like
+/// `vim: set ft=python:`, on any of the lines.
+fn from_modeline(lines: &[impl AsRef<str>]) -> Option<LapceLanguage> {
+    static EMACS_REGEX: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"-\*-\s*(.*?)\s*-\*-").unwrap());
+    static VIM_REGEX: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(
+            r"(?:^|\s)(?:vi|vim|ex)(?:[<=>]?\d+)?:.*?\b(?:ft|filetype)=([\w+.-]+)",
+        )
+        .unwrap()
+    });
+
+    let first_lines = match lines.first() {
+        Some(line) if line.as_ref().starts_with("#!") => {
+            &lines[..lines.len().min(2)]
+        }
+        _ => &lines[..lines.len().min(1)],
+    };
+    let emacs = first_lines.iter().find_map(|line| {
+        let mode = EMACS_REGEX.captures(line.as_ref())?.get(1)?.as_str();
+        if !mode.contains(':') {
+            return Some(mode.to_string());
+        }
+        mode.split(';').find_map(|variable| {
+            let (name, value) = variable.split_once(':')?;
+            name.trim()
+                .eq_ignore_ascii_case("mode")
+                .then(|| value.trim().to_string())
+        })
+    });
+    let name = emacs.or_else(|| {
+        lines.iter().find_map(|line| {
+            Some(
+                VIM_REGEX
+                    .captures(line.as_ref())?
+                    .get(1)?
+                    .as_str()
+                    .to_string(),
+            )
+        })
+    })?;
+
+    // Modes are named by the language, its interpreter or its extension
+    LapceLanguage::from_name(&name)
+        .or_else(|| from_interpreter(&name.to_lowercase()))
+        .or_else(|| LapceLanguage::from_path_raw(Path::new(&format!("file.{name}"))))
+}
+
+/// The language of the interpreter a shebang line like `#!/usr/bin/env
+/// python3` runs.
+fn from_shebang(line: &str) -> Option<LapceLanguage> {
+    let mut words = line.strip_prefix("#!")?.split_whitespace();
+    let mut interpreter = words.next()?.rsplit('/').next()?;
+    if interpreter == "env" {
+        // Skip options like `-S` and variables like `A=b`
+        interpreter = words.find(|w| !w.starts_with('-') && !w.contains('='))?;
+    }
+
+    let user = USER_LANGUAGES
+        .read()
+        .unwrap()
+        .interpreter_language(interpreter);
+    user.or_else(|| from_interpreter(interpreter))
+}
+
+/// The language run by an interpreter, whatever its version, like `python3.12`.
+fn from_interpreter(interpreter: &str) -> Option<LapceLanguage> {
+    let interpreter =
+        interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    Some(match interpreter {
+        "bash" => LapceLanguage::Bash,
+        "sh" | "zsh" | "dash" | "ksh" | "ash" => LapceLanguage::ShellScript,
+        "fish" => LapceLanguage::Fish,
+        "nu" => LapceLanguage::Nushell,
+        "pwsh" | "powershell" => LapceLanguage::PowerShell,
+        "python" | "pypy" => LapceLanguage::Python,
+        "node" | "nodejs" | "deno" | "bun" => LapceLanguage::Javascript,
+        "ts-node" => LapceLanguage::Typescript,
+        "ruby" => LapceLanguage::Ruby,
+        "php" => LapceLanguage::Php,
+        "lua" | "luajit" => LapceLanguage::Lua,
+        "rscript" | "Rscript" => LapceLanguage::R,
+        "julia" => LapceLanguage::Julia,
+        "elixir" => LapceLanguage::Elixir,
+        "make" => LapceLanguage::Make,
+        "tclsh" | "wish" => LapceLanguage::Tcl,
+        "scala" => LapceLanguage::Scala,
+        "swift" => LapceLanguage::Swift,
+        "groovy" => LapceLanguage::Groovy,
+        "guile" => LapceLanguage::Scheme,
+        "runghc" | "runhaskell" => LapceLanguage::Haskell,
+        "ocaml" => LapceLanguage::Ocaml,
+        _ => return None,
+    })
+}
+
 fn load_grammar(
     grammar_name: &str,
     grammar_fn_name: &str,
@@ -2044,11 +2513,84 @@ fn read_grammar_query(queries_dir: &Path, name: &str, kind: &str) -> String {
 mod tests {
     use std::path::PathBuf;
 
-    use super::LapceLanguage;
+    use super::*;
 
     #[test]
     fn test_lanaguage_from_path() {
         let l = LapceLanguage::from_path(&PathBuf::new().join("test.rs"));
         assert_eq!(l, LapceLanguage::Rust);
     }
+
+    #[test]
+    fn test_language_from_text() {
+        let path = PathBuf::from("script");
+        let l = |text: &str| LapceLanguage::from_path_and_text(&path, &text.into());
+        assert_eq!(l("#!/usr/bin/env python3\n"), LapceLanguage::Python);
+        assert_eq!(l("#!/bin/sh\necho\n"), LapceLanguage::ShellScript);
+        assert_eq!(l("# -*- mode: ruby; -*-\n"), LapceLanguage::Ruby);
+        assert_eq!(l("a\nb\n// vim: set ft=rust:\n"), LapceLanguage::Rust);
+        assert_eq!(l("no idea\n"), LapceLanguage::PlainText);
+
+        // A modeline wins over the path
+        let text = "# vim: ft=python\n".into();
+        let l = LapceLanguage::from_path_and_text(Path::new("a.sh"), &text);
+        assert_eq!(l, LapceLanguage::Python);
+    }
+
+    #[test]
+    fn test_user_languages() {
+        let definition = LanguageDefinition {
+            extensions: vec!["mylang".to_string()],
+            shebangs: vec!["mylangi".to_string()],
+            comment: Some("%%".to_string()),
+            ..Default::default()
+        };
+        let definitions = HashMap::from([("MyLang".to_string(), definition)]);
+        let associations = HashMap::from([
+            ("*.tpl".to_string(), "html".to_string()),
+            ("**/conf/*.d".to_string(), "mylang".to_string()),
+        ]);
+        let mut user = UserLanguages::default();
+        user.set(&definitions, &associations);
+
+        let mylang = user.find("mylang").unwrap();
+        let language = user.get(mylang).unwrap();
+        assert_eq!(language.name, "MyLang");
+        assert_eq!(language.language_id, "mylang");
+        assert_eq!(language.properties.comment.single_line_start, Some("%%"));
+        assert_eq!(language.properties.indent, "    ");
+
+        let from_path = |path: &str| {
+            let path = Path::new(path);
+            let filename = path.file_name().and_then(|s| s.to_str());
+            let extension = path.extension().and_then(|s| s.to_str());
+            user.path_language(path, filename, extension)
+        };
+        assert_eq!(from_path("a.mylang"), Some(mylang));
+        assert_eq!(from_path("page.tpl"), Some(LapceLanguage::Html));
+        assert_eq!(from_path("etc/conf/a.d"), Some(mylang));
+        assert_eq!(from_path("etc/a.d"), None);
+        assert_eq!(user.interpreter_language("mylangi"), Some(mylang));
+
+        // Going back to a definition reuses what was built for it
+        let properties = user.get(mylang).unwrap().properties;
+        let mut changed = definitions.clone();
+        changed.get_mut("MyLang").unwrap().comment = Some("#".to_string());
+        user.set(&changed, &associations);
+        assert!(!std::ptr::eq(
+            user.get(mylang).unwrap().properties,
+            properties
+        ));
+        user.set(&definitions, &associations);
+        assert!(std::ptr::eq(
+            user.get(mylang).unwrap().properties,
+            properties
+        ));
+        assert_eq!(user.find("mylang"), Some(mylang));
+
+        // Removed languages keep their id but aren't picked anymore
+        user.set(&HashMap::new(), &HashMap::new());
+        assert_eq!(user.find("mylang"), None);
+        assert_eq!(user.get(mylang).unwrap().name, "MyLang");
+    }
 }
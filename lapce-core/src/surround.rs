@@ -0,0 +1,140 @@
+use lapce_xi_rope::Rope;
+
+use crate::{
+    selection::Selection,
+    syntax::{textobject::TextObject, Syntax},
+};
+
+/// What text can be surrounded with: a pair of characters or an HTML/XML tag.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Surround {
+    /// The opening and the closing character
+    Pair(char, char),
+    /// A tag with its attributes, like `div class="x"`
+    Tag(String),
+}
+
+impl Surround {
+    /// The pair named by the character typed after `ys` or `cs` in vim,
+    /// where either bracket names the same pair.
+    pub fn from_char(c: char) -> Option<Self> {
+        let (open, close) = match c {
+            '(' | ')' | 'b' => ('(', ')'),
+            '[' | ']' => ('[', ']'),
+            '{' | '}' | 'B' => ('{', '}'),
+            '<' | '>' => ('<', '>'),
+            c if c.is_ascii_punctuation() => (c, c),
+            _ => return None,
+        };
+        Some(Surround::Pair(open, close))
+    }
+
+    pub fn open(&self) -> String {
+        match self {
+            Surround::Pair(open, _) => open.to_string(),
+            Surround::Tag(tag) => format!("<{tag}>"),
+        }
+    }
+
+    pub fn close(&self) -> String {
+        match self {
+            Surround::Pair(_, close) => close.to_string(),
+            Surround::Tag(tag) => {
+                let name = tag.split_whitespace().next().unwrap_or_default();
+                format!("</{name}>")
+            }
+        }
+    }
+
+    /// The edits that surround the text from `start` to `end`.
+    pub fn add_edits(&self, start: usize, end: usize) -> [(Selection, String); 2] {
+        [
+            (Selection::caret(start), self.open()),
+            (Selection::caret(end), self.close()),
+        ]
+    }
+
+    /// The edits that replace what surrounds some text, as found by
+    /// [`find_surround`], with this.
+    pub fn change_edits(
+        &self,
+        [open, close]: [(usize, usize); 2],
+    ) -> [(Selection, String); 2] {
+        [
+            (Selection::region(open.0, open.1), self.open()),
+            (Selection::region(close.0, close.1), self.close()),
+        ]
+    }
+}
+
+/// The edits that remove what surrounds some text, as found by
+/// [`find_surround`].
+pub fn delete_edits([open, close]: [(usize, usize); 2]) -> [(Selection, String); 2] {
+    [
+        (Selection::region(open.0, open.1), String::new()),
+        (Selection::region(close.0, close.1), String::new()),
+    ]
+}
+
+/// The ranges of the opening and the closing part of the innermost `target`
+/// around the offset, each with an exclusive end. `t` names the enclosing
+/// HTML or XML tag, which needs the syntax tree. `syntax` is `None` when it's
+/// out of date with the text.
+pub fn find_surround(
+    syntax: Option<&Syntax>,
+    text: &Rope,
+    offset: usize,
+    target: char,
+) -> Option<[(usize, usize); 2]> {
+    if target == 't' {
+        return syntax?.find_enclosing_tags(offset);
+    }
+    let Surround::Pair(open, close) = Surround::from_char(target)? else {
+        return None;
+    };
+    let object = if open == close {
+        TextObject::Quote(open)
+    } else {
+        TextObject::Pair(open, close)
+    };
+    let (start, end) = object.range(syntax, text, offset, false)?;
+    Some([(start, start + 1), (end - 1, end)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_surround_pairs() {
+        let paren = Surround::from_char('b').unwrap();
+        assert_eq!(paren, Surround::from_char(')').unwrap());
+        assert_eq!((paren.open(), paren.close()), ("(".into(), ")".into()));
+
+        let quote = Surround::from_char('\'').unwrap();
+        assert_eq!((quote.open(), quote.close()), ("'".into(), "'".into()));
+
+        assert_eq!(Surround::from_char('x'), None);
+    }
+
+    #[test]
+    fn test_surround_tag() {
+        let tag = Surround::Tag("div class=\"x\"".to_string());
+        assert_eq!(tag.open(), "<div class=\"x\">");
+        assert_eq!(tag.close(), "</div>");
+    }
+
+    #[test]
+    fn test_find_surround() {
+        let text = Rope::from("f(a, [b, \"c\"])");
+        let surround =
+            |offset: usize, target: char| find_surround(None, &text, offset, target);
+
+        assert_eq!(surround(6, 'b'), Some([(1, 2), (13, 14)]));
+        assert_eq!(surround(6, ']'), Some([(5, 6), (12, 13)]));
+        assert_eq!(surround(10, '"'), Some([(9, 10), (11, 12)]));
+        assert_eq!(surround(6, '{'), None);
+        // Tags can't be found without the syntax tree
+        assert_eq!(surround(6, 't'), None);
+    }
+}
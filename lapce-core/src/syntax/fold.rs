@@ -0,0 +1,141 @@
+use std::{cell::RefCell, collections::HashMap, sync::Arc};
+
+use lapce_xi_rope::Rope;
+use tree_sitter::{Node, Query, QueryCursor};
+
+use super::{util::RopeProvider, Syntax};
+use crate::language::LapceLanguage;
+
+thread_local! {
+    static FOLD_QUERIES: RefCell<HashMap<LapceLanguage, Option<Arc<Query>>>> = Default::default();
+}
+
+pub fn reset_fold_queries() {
+    FOLD_QUERIES.with_borrow_mut(|queries| {
+        queries.clear();
+    });
+}
+
+fn get_fold_query(lang: LapceLanguage) -> Option<Arc<Query>> {
+    FOLD_QUERIES.with_borrow_mut(|queries| {
+        queries
+            .entry(lang)
+            .or_insert_with(|| lang.new_fold_query().map(Arc::new))
+            .clone()
+    })
+}
+
+/// A range of lines that can be folded, found without a language server.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fold {
+    pub start_line: usize,
+    pub end_line: usize,
+    pub comment: bool,
+}
+
+impl Syntax {
+    /// The folds captured as `@fold` by the language's `folds.scm`, sorted by
+    /// their start. Comments can be captured as `@fold.comment`, otherwise
+    /// they are told apart by the kind of their node.
+    pub fn folds(&self) -> Option<Vec<Fold>> {
+        let query = get_fold_query(self.language)?;
+        let tree = self.layers.as_ref()?.try_tree()?;
+
+        let mut cursor = QueryCursor::new();
+        let mut folds: Vec<Fold> = cursor
+            .matches(&query, tree.root_node(), RopeProvider(&self.text))
+            .flat_map(|mat| mat.captures)
+            .filter_map(|capture| {
+                let name = &query.capture_names()[capture.index as usize];
+                let comment = match *name {
+                    "fold" => capture.node.kind().contains("comment"),
+                    "fold.comment" => true,
+                    _ => return None,
+                };
+                node_fold(capture.node, comment)
+            })
+            .collect();
+        folds.sort_by_key(|fold| (fold.start_line, usize::MAX - fold.end_line));
+        folds.dedup_by_key(|fold| fold.start_line);
+        Some(folds)
+    }
+}
+
+/// The lines a node spans, if it spans more than one. A node that ends at the
+/// start of a line doesn't fold that line.
+fn node_fold(node: Node, comment: bool) -> Option<Fold> {
+    let start_line = node.start_position().row;
+    let end = node.end_position();
+    let end_line = if end.column == 0 {
+        end.row.checked_sub(1)?
+    } else {
+        end.row
+    };
+    (end_line > start_line).then_some(Fold {
+        start_line,
+        end_line,
+        comment,
+    })
+}
+
+/// Folds for every line followed by lines indented deeper than it, for text
+/// without a syntax tree to go by. Blank lines don't end a fold.
+pub fn indent_folds(text: &Rope, tab_width: usize) -> Vec<Fold> {
+    let mut folds = Vec::new();
+    // The indentation and line of each fold that is still open
+    let mut open: Vec<(usize, usize)> = Vec::new();
+    let mut last_line = 0;
+
+    for (line, content) in text.lines(0..text.len()).enumerate() {
+        if content.trim().is_empty() {
+            continue;
+        }
+        let indent = content
+            .chars()
+            .take_while(|c| c.is_whitespace())
+            .map(|c| if c == '\t' { tab_width } else { 1 })
+            .sum::<usize>();
+        while let Some(&(start_indent, start_line)) = open.last() {
+            if start_indent < indent {
+                break;
+            }
+            open.pop();
+            if last_line > start_line {
+                folds.push(indent_fold(start_line, last_line));
+            }
+        }
+        open.push((indent, line));
+        last_line = line;
+    }
+    for (_, start_line) in open.into_iter().rev() {
+        if last_line > start_line {
+            folds.push(indent_fold(start_line, last_line));
+        }
+    }
+
+    folds.sort_by_key(|fold| fold.start_line);
+    folds
+}
+
+fn indent_fold(start_line: usize, end_line: usize) -> Fold {
+    Fold {
+        start_line,
+        end_line,
+        comment: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_indent_folds() {
+        let text = Rope::from("a:\n  b:\n    c\n\n    d\n  e\nf:\n\tg\n");
+        let folds: Vec<_> = indent_folds(&text, 4)
+            .into_iter()
+            .map(|fold| (fold.start_line, fold.end_line))
+            .collect();
+        assert_eq!(folds, vec![(0, 5), (1, 4), (6, 7)]);
+    }
+}
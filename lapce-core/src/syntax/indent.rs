@@ -0,0 +1,339 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use floem_editor_core::buffer::rope_text::{RopeText, RopeTextRef};
+use lapce_xi_rope::Rope;
+use tree_sitter::{Node, Query, QueryCursor, Tree};
+
+use super::{util::RopeProvider, Syntax};
+use crate::language::LapceLanguage;
+
+thread_local! {
+    static INDENT_QUERIES: RefCell<HashMap<LapceLanguage, Option<Arc<Query>>>> = Default::default();
+}
+
+pub fn reset_indent_queries() {
+    INDENT_QUERIES.with_borrow_mut(|queries| {
+        queries.clear();
+    });
+}
+
+fn get_indent_query(lang: LapceLanguage) -> Option<Arc<Query>> {
+    INDENT_QUERIES.with_borrow_mut(|queries| {
+        queries
+            .entry(lang)
+            .or_insert_with(|| lang.new_indent_query().map(Arc::new))
+            .clone()
+    })
+}
+
+/// What the indent query captured a node as.
+#[derive(Default)]
+struct NodeIndent<'a> {
+    /// `@indent`: the lines inside the node are indented one level deeper
+    indent: bool,
+    /// `@outdent`: a line starting with the node goes one level back, like a
+    /// closing bracket
+    outdent: bool,
+    /// `@extend`: the node also takes in the lines after it that are
+    /// indented deeper than its start, like the body of a Python block
+    extend: bool,
+    /// `@align`: the lines inside the node line up with the start of its
+    /// `@anchor`, or of the node itself when there is none
+    align: Option<Node<'a>>,
+}
+
+/// Where a new line is being indented.
+struct NewLine {
+    /// Where the text before the new line ends
+    offset: usize,
+    /// The first non-blank character moved onto the new line, if any
+    rest: Option<usize>,
+    /// The last non-blank character before the new line, if any
+    prev: Option<usize>,
+    /// The indentation width of the line `prev` is on
+    prev_width: usize,
+}
+
+impl NewLine {
+    /// Where the new line's content starts
+    fn split(&self) -> usize {
+        self.rest.unwrap_or(self.offset)
+    }
+}
+
+impl Syntax {
+    /// The indentation for a new line whose content starts at `offset`, with
+    /// the text before `offset` staying on the line above it, as given by the
+    /// `indents.scm` of the languages at that point. The levels of injected
+    /// languages are added on top of the ones of the languages around them.
+    ///
+    /// Returns `None` when none of those languages has indent queries, so
+    /// that the caller can fall back on keeping the previous line's indent.
+    pub fn indent_for_new_line(
+        &self,
+        offset: usize,
+        indent_unit: &str,
+        tab_width: usize,
+    ) -> Option<String> {
+        let text = RopeTextRef::new(&self.text);
+        let new_line = new_line_at(&text, offset, tab_width);
+
+        let mut found = false;
+        let mut base = String::new();
+        let mut levels = 0isize;
+        for layer in self.layers.as_ref()?.layers_at(offset) {
+            let Some(query) = get_indent_query(layer.language) else {
+                continue;
+            };
+            let Some(tree) = layer.try_tree() else {
+                continue;
+            };
+            found = true;
+            let (align, layer_levels) =
+                layer_indent(&query, tree, &self.text, &new_line, tab_width);
+            match align {
+                Some(align) => {
+                    base = align;
+                    levels = layer_levels;
+                }
+                None => levels += layer_levels,
+            }
+        }
+        if !found {
+            return None;
+        }
+
+        Some(base + &indent_unit.repeat(levels.max(0) as usize))
+    }
+}
+
+fn new_line_at(text: &RopeTextRef, offset: usize, tab_width: usize) -> NewLine {
+    let line = text.line_of_offset(offset);
+    let after = text.slice_to_cow(offset..text.offset_of_line(line + 1));
+    let trimmed = after.trim_start_matches([' ', '\t']);
+    let rest = (!trimmed.trim_end_matches(['\r', '\n']).is_empty())
+        .then(|| offset + after.len() - trimmed.len());
+
+    let prev = last_non_blank(text, offset);
+    let prev_width = prev
+        .map(|prev| {
+            let line = text.line_of_offset(prev);
+            indent_width(&text.line_content(line), tab_width)
+        })
+        .unwrap_or(0);
+
+    NewLine {
+        offset,
+        rest,
+        prev,
+        prev_width,
+    }
+}
+
+/// The alignment and the indent levels one language layer gives a new line.
+/// The walk goes from the node the new line is in up to the root, and stops
+/// at the first node whose lines are aligned, which sets the line's base
+/// indentation instead of the start of the line.
+fn layer_indent(
+    query: &Query,
+    tree: &Tree,
+    text: &Rope,
+    new_line: &NewLine,
+    tab_width: usize,
+) -> (Option<String>, isize) {
+    let root = tree.root_node();
+    let start = new_line.prev.unwrap_or(new_line.offset);
+    let end = new_line.split() + 1;
+
+    let mut nodes: HashMap<usize, NodeIndent> = HashMap::new();
+    let mut cursor = QueryCursor::new();
+    cursor.set_byte_range(start..end);
+    for mat in cursor.matches(query, root, RopeProvider(text)) {
+        let anchor = mat
+            .captures
+            .iter()
+            .find(|capture| {
+                query.capture_names()[capture.index as usize] == "anchor"
+            })
+            .map(|capture| capture.node);
+        for capture in mat.captures {
+            let entry = nodes.entry(capture.node.id()).or_default();
+            match query.capture_names()[capture.index as usize] {
+                "indent" => entry.indent = true,
+                "outdent" => entry.outdent = true,
+                "extend" => entry.extend = true,
+                "align" => entry.align = Some(anchor.unwrap_or(capture.node)),
+                _ => {}
+            }
+        }
+    }
+    let captured = |node: Node, f: fn(&NodeIndent) -> bool| {
+        nodes.get(&node.id()).is_some_and(f)
+    };
+
+    let split = new_line.split();
+    let (mut node, closed) = match (new_line.rest, new_line.prev) {
+        (Some(rest), _) => match root.descendant_for_byte_range(rest, rest) {
+            Some(node) => (node, false),
+            None => return (None, 0),
+        },
+        (None, Some(prev)) => match root.descendant_for_byte_range(prev, prev) {
+            // A node that ends with a closing bracket right before the new
+            // line doesn't go on into it
+            Some(node) => (node, captured(node, |n| n.outdent)),
+            None => return (None, 0),
+        },
+        (None, None) => return (None, 0),
+    };
+    let prev_end = new_line.prev.map(|prev| {
+        root.descendant_for_byte_range(prev, prev)
+            .map(|node| node.end_byte())
+            .unwrap_or(prev + 1)
+    });
+
+    let mut levels = 0isize;
+    // Opening several nodes on one line only indents the next one once
+    let mut indented_rows = HashSet::new();
+    let mut outdented = false;
+    loop {
+        let starts_before = node.start_byte() < split;
+        let contains = starts_before
+            && (node.end_byte() > split
+                || (new_line.rest.is_none()
+                    && Some(node.end_byte()) == prev_end
+                    && !closed)
+                || (captured(node, |n| n.extend)
+                    && new_line.prev_width
+                        > indent_width(
+                            &line_before(text, node.start_byte()),
+                            tab_width,
+                        )));
+
+        if !outdented && node.start_byte() == split && captured(node, |n| n.outdent)
+        {
+            levels -= 1;
+            outdented = true;
+        }
+        if contains {
+            if let Some(anchor) = nodes.get(&node.id()).and_then(|n| n.align) {
+                if anchor.start_byte() < split {
+                    return (Some(align_to(text, anchor.start_byte())), levels);
+                }
+            }
+            if captured(node, |n| n.indent)
+                && indented_rows.insert(node.start_position().row)
+            {
+                levels += 1;
+            }
+        }
+
+        match node.parent() {
+            Some(parent) => node = parent,
+            None => break,
+        }
+    }
+
+    (None, levels)
+}
+
+/// The last non-blank character before the offset, on any line.
+fn last_non_blank(text: &RopeTextRef, offset: usize) -> Option<usize> {
+    let mut line = text.line_of_offset(offset);
+    let mut end = offset;
+    loop {
+        let start = text.offset_of_line(line);
+        let content = text.slice_to_cow(start..end);
+        if let Some((i, _)) = content
+            .char_indices()
+            .rev()
+            .find(|(_, c)| !c.is_whitespace())
+        {
+            return Some(start + i);
+        }
+        if line == 0 {
+            return None;
+        }
+        line -= 1;
+        end = start;
+    }
+}
+
+/// The text of the line before the offset.
+fn line_before(text: &Rope, offset: usize) -> String {
+    let text = RopeTextRef::new(text);
+    let line_start = text.offset_of_line(text.line_of_offset(offset));
+    text.slice_to_cow(line_start..offset).into_owned()
+}
+
+/// Whitespace that reaches the column of the offset, keeping the tabs of the
+/// line it is on.
+fn align_to(text: &Rope, offset: usize) -> String {
+    line_before(text, offset)
+        .chars()
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect()
+}
+
+/// The width of the indentation at the start of the line, with tabs going to
+/// the next tab stop.
+pub fn indent_width(line: &str, tab_width: usize) -> usize {
+    line.chars().take_while(|c| *c == ' ' || *c == '\t').fold(
+        0,
+        |width, c| match c {
+            '\t' => (width / tab_width + 1) * tab_width,
+            _ => width + 1,
+        },
+    )
+}
+
+/// The indentation of the given width, made of tabs when the indent unit is
+/// one.
+pub fn indent_string(width: usize, indent_unit: &str, tab_width: usize) -> String {
+    if indent_unit == "\t" {
+        "\t".repeat(width / tab_width) + &" ".repeat(width % tab_width)
+    } else {
+        " ".repeat(width)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_indent_width() {
+        assert_eq!(indent_width("    a", 4), 4);
+        assert_eq!(indent_width("\ta", 4), 4);
+        assert_eq!(indent_width("  \ta", 4), 4);
+        assert_eq!(indent_width("\t  a", 4), 6);
+        assert_eq!(indent_width("a  ", 4), 0);
+    }
+
+    #[test]
+    fn test_indent_string() {
+        assert_eq!(indent_string(6, "\t", 4), "\t  ");
+        assert_eq!(indent_string(6, "  ", 4), "      ");
+    }
+
+    #[test]
+    fn test_new_line_at() {
+        let text = Rope::from("fn a() {\n    b\n}\n");
+        let text = RopeTextRef::new(&text);
+
+        // Splitting right before the closing bracket moves it down
+        let new_line = new_line_at(&text, 15, 4);
+        assert_eq!(new_line.rest, Some(15));
+        assert_eq!(new_line.prev, Some(13));
+        assert_eq!(new_line.prev_width, 4);
+
+        // Nothing moves down at the end of a line
+        let new_line = new_line_at(&text, 8, 4);
+        assert_eq!(new_line.rest, None);
+        assert_eq!(new_line.prev, Some(7));
+        assert_eq!(new_line.prev_width, 0);
+    }
+}
@@ -45,7 +45,11 @@
     syntax::highlight::InjectionLanguageMarker,
 };
 pub mod edit;
+pub mod fold;
 pub mod highlight;
+pub mod indent;
+pub mod symbol;
+pub mod textobject;
 pub mod util;
 
 const TREE_SITTER_MATCH_LIMIT: u32 = 256;
@@ -384,6 +388,7 @@ fn highlight_pos(
 pub struct LanguageLayer {
     // mode
     // grammar
+    pub language: LapceLanguage,
     pub config: Arc<HighlightConfiguration>,
     pub(crate) tree: Option<Tree>,
     pub ranges: Vec<tree_sitter::Range>,
@@ -462,16 +467,21 @@ pub struct SyntaxLayers {
     root: LayerId,
 }
 impl SyntaxLayers {
-    pub fn new_empty(config: Arc<HighlightConfiguration>) -> SyntaxLayers {
-        Self::new(None, config)
+    pub fn new_empty(
+        language: LapceLanguage,
+        config: Arc<HighlightConfiguration>,
+    ) -> SyntaxLayers {
+        Self::new(None, language, config)
     }
 
     pub fn new(
         source: Option<&Rope>,
+        language: LapceLanguage,
         config: Arc<HighlightConfiguration>,
     ) -> SyntaxLayers {
         let root_layer = LanguageLayer {
             tree: None,
+            language,
             config,
             depth: 0,
             ranges: vec![tree_sitter::Range {
@@ -520,9 +530,9 @@ pub fn update(
                 }
                 InjectionLanguageMarker::Shebang(id) => LapceLanguage::from_name(id),
             };
-            language
-                .map(get_highlight_config)
-                .unwrap_or(Err(highlight::HighlightIssue::NotAvailable))
+            let language =
+                language.ok_or(highlight::HighlightIssue::NotAvailable)?;
+            get_highlight_config(language).map(|config| (language, config))
         };
 
         let mut edits = Vec::new();
@@ -726,7 +736,7 @@ fn point_sub(a: Point, b: Point) -> Point {
                             (injection_capture, content_node)
                         {
                             match (injection_callback)(&injection_capture) {
-                                Ok(config) => {
+                                Ok((language, config)) => {
                                     let ranges = intersect_ranges(
                                         &layer.ranges,
                                         &[content_node],
@@ -740,7 +750,7 @@ fn point_sub(a: Point, b: Point) -> Point {
                                             continue;
                                         }
                                         last_injection_end = content_node.end_byte();
-                                        injections.push((config, ranges));
+                                        injections.push((language, config, ranges));
                                     }
                                 }
                                 Err(err) => {
@@ -757,14 +767,14 @@ fn point_sub(a: Point, b: Point) -> Point {
                             (lang_name, content_nodes.is_empty())
                         {
                             match (injection_callback)(&lang_name) {
-                                Ok(config) => {
+                                Ok((language, config)) => {
                                     let ranges = intersect_ranges(
                                         &layer.ranges,
                                         &content_nodes,
                                         included_children,
                                     );
                                     if !ranges.is_empty() {
-                                        injections.push((config, ranges));
+                                        injections.push((language, config, ranges));
                                     }
                                 }
                                 Err(err) => {
@@ -776,9 +786,10 @@ fn point_sub(a: Point, b: Point) -> Point {
 
                     let depth = layer.depth + 1;
                     // TODO: can't inline this since matches borrows self.layers
-                    for (config, ranges) in injections {
+                    for (language, config, ranges) in injections {
                         let new_layer = LanguageLayer {
                             tree: None,
+                            language,
                             config,
                             depth,
                             ranges,
@@ -819,6 +830,22 @@ pub fn try_tree(&self) -> Option<&Tree> {
         self.layers[self.root].try_tree()
     }
 
+    /// The layers whose ranges contain the offset, from the root to the most
+    /// deeply injected one.
+    pub fn layers_at(&self, offset: usize) -> Vec<&LanguageLayer> {
+        let mut layers: Vec<&LanguageLayer> = self
+            .layers
+            .values()
+            .filter(|layer| {
+                layer.ranges.iter().any(|range| {
+                    range.start_byte <= offset && offset <= range.end_byte
+                })
+            })
+            .collect();
+        layers.sort_by_key(|layer| layer.depth);
+        layers
+    }
+
     /// Iterate over the highlighted regions for a given slice of source code.
     pub fn highlight_iter<'a>(
         &'a self,
@@ -939,7 +966,8 @@ pub fn from_language(language: LapceLanguage) -> Syntax {
             rev: 0,
             language,
             text: Rope::from(""),
-            layers: highlight.map(SyntaxLayers::new_empty),
+            layers: highlight
+                .map(|config| SyntaxLayers::new_empty(language, config)),
             lens: Self::lens_from_normal_lines(0, 0, 0, &Vec::new()),
             line_height: 0,
             lens_height: 0,
@@ -1117,6 +1145,13 @@ pub fn find_tag(
         None
     }
 
+    /// The ranges of the start and end tags of the HTML or XML element around
+    /// the offset, each with an exclusive end.
+    pub fn find_enclosing_tags(&self, offset: usize) -> Option<[(usize, usize); 2]> {
+        let tree = self.layers.as_ref()?.try_tree()?;
+        find_element_tags(tree.root_node(), offset)
+    }
+
     fn find_tag_in_siblings(
         &self,
         node: Node,
@@ -1150,6 +1185,50 @@ fn find_tag_in_children(&self, node: Node, tag: &str) -> Option<usize> {
         None
     }
 
+    /// Find the names of the opening and closing tag of the markup element at the offset, for
+    /// editing them together. This is used when no language server provides the linked editing
+    /// ranges.
+    pub fn linked_tag_ranges(&self, offset: usize) -> Option<Vec<(usize, usize)>> {
+        const TAG_KINDS: &[&str] = &[
+            "start_tag",
+            "end_tag",
+            "STag",
+            "ETag",
+            "jsx_opening_element",
+            "jsx_closing_element",
+        ];
+
+        let tree = self.layers.as_ref()?.try_tree()?;
+        let mut tag = tree.root_node().descendant_for_byte_range(offset, offset)?;
+        while !TAG_KINDS.contains(&tag.kind()) {
+            tag = tag.parent()?;
+        }
+        let element = tag.parent()?;
+        let opening = element.child(0)?;
+        let closing = element.child(element.child_count().checked_sub(1)?)?;
+        if opening.id() == closing.id()
+            || !TAG_KINDS.contains(&opening.kind())
+            || !TAG_KINDS.contains(&closing.kind())
+        {
+            return None;
+        }
+
+        let opening = tag_name_range(opening)?;
+        let closing = tag_name_range(closing)?;
+        let in_range =
+            |(start, end): (usize, usize)| start <= offset && offset <= end;
+        if !in_range(opening) && !in_range(closing) {
+            return None;
+        }
+        if self.text.slice_to_cow(opening.0..opening.1)
+            != self.text.slice_to_cow(closing.0..closing.1)
+        {
+            return None;
+        }
+
+        Some(vec![opening, closing])
+    }
+
     pub fn sticky_headers(&self, offset: usize) -> Option<Vec<usize>> {
         let tree = self.layers.as_ref()?.try_tree()?;
         let mut node = tree.root_node().descendant_for_byte_range(offset, offset)?;
@@ -1241,6 +1320,47 @@ pub fn find_enclosing_pair(&self, offset: usize) -> Option<(usize, usize)> {
     }
 }
 
+/// The ranges of the start and end tags of the innermost element around the
+/// offset, both taken from the same element node so that they always match.
+pub fn find_element_tags(root: Node, offset: usize) -> Option<[(usize, usize); 2]> {
+    // The HTML grammars and the XML one name their tags differently
+    const TAGS: &[(&str, &str)] = &[("start_tag", "end_tag"), ("STag", "ETag")];
+
+    let mut node = root.descendant_for_byte_range(offset, offset + 1)?;
+    loop {
+        let count = node.child_count();
+        if count >= 2 {
+            let start = node.child(0)?;
+            let end = node.child(count - 1)?;
+            if TAGS.contains(&(start.kind(), end.kind())) {
+                return Some([
+                    (start.start_byte(), start.end_byte()),
+                    (end.start_byte(), end.end_byte()),
+                ]);
+            }
+        }
+        node = node.parent()?;
+    }
+}
+
+/// The byte range of the name of a markup tag node.
+fn tag_name_range(tag: Node) -> Option<(usize, usize)> {
+    const TAG_NAME_KINDS: &[&str] = &[
+        "tag_name",
+        "Name",
+        "identifier",
+        "member_expression",
+        "nested_identifier",
+    ];
+
+    let name = tag.child_by_field_name("name").or_else(|| {
+        (0..tag.named_child_count())
+            .filter_map(|i| tag.named_child(i))
+            .find(|child| TAG_NAME_KINDS.contains(&child.kind()))
+    })?;
+    Some((name.start_byte(), name.end_byte()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1277,4 +1397,28 @@ fn test_lens_iter() {
         assert_eq!(Some((90, 25)), iter.next());
         assert_eq!(None, iter.next());
     }
+
+    #[test]
+    fn test_element_tags() {
+        let text = "<div><p>a <b>b</b></p><img/><p>c</p></div>";
+        let language = tree_sitter_html::language();
+        let mut parser = tree_sitter::Parser::new();
+        parser.set_language(&language).unwrap();
+        let tree = parser.parse(text, None).unwrap();
+        let tags = |s: &str| {
+            let offset = text.find(s).unwrap();
+            find_element_tags(tree.root_node(), offset)
+                .map(|[start, end]| (&text[start.0..start.1], &text[end.0..end.1]))
+        };
+
+        assert_eq!(tags("a <b>"), Some(("<p>", "</p>")));
+        assert_eq!(tags("b</b>"), Some(("<b>", "</b>")));
+        assert_eq!(tags("</p><img"), Some(("<p>", "</p>")));
+        // The start and end tags belong to the same element, not to the
+        // closest ones around the offset
+        assert_eq!(tags("<img"), Some(("<div>", "</div>")));
+        let [start, end] =
+            find_element_tags(tree.root_node(), text.find("c<").unwrap()).unwrap();
+        assert_eq!((start.0, end.0), (28, 32));
+    }
 }
@@ -0,0 +1,178 @@
+use std::{cell::RefCell, cmp::Reverse, collections::HashMap, sync::Arc};
+
+use floem_editor_core::buffer::rope_text::RopeTextRef;
+use lsp_types::{DocumentSymbol, Range, SymbolKind};
+use tree_sitter::{Node, Query, QueryCursor};
+
+use super::{util::RopeProvider, Syntax};
+use crate::{language::LapceLanguage, rope_text_pos::RopeTextPosition};
+
+thread_local! {
+    static TAGS_QUERIES: RefCell<HashMap<LapceLanguage, Option<Arc<Query>>>> = Default::default();
+}
+
+pub fn reset_tags_queries() {
+    TAGS_QUERIES.with_borrow_mut(|queries| {
+        queries.clear();
+    });
+}
+
+fn get_tags_query(lang: LapceLanguage) -> Option<Arc<Query>> {
+    TAGS_QUERIES.with_borrow_mut(|queries| {
+        queries
+            .entry(lang)
+            .or_insert_with(|| lang.new_tags_query().map(Arc::new))
+            .clone()
+    })
+}
+
+impl Syntax {
+    /// The symbols defined in the document, for when no language server
+    /// provides them. They come from the `@definition.*` captures of the
+    /// language's `tags.scm`, named by their `@name` capture, and are nested
+    /// the way the definitions are.
+    pub fn document_symbols(&self) -> Option<Vec<DocumentSymbol>> {
+        let query = get_tags_query(self.language)?;
+        let name_index = query.capture_index_for_name("name")?;
+        let tree = self.layers.as_ref()?.try_tree()?;
+        let text = RopeTextRef::new(&self.text);
+
+        let mut cursor = QueryCursor::new();
+        let mut symbols: Vec<(usize, usize, DocumentSymbol)> = cursor
+            .matches(&query, tree.root_node(), RopeProvider(&self.text))
+            .filter_map(|mat| {
+                let (kind, node) = mat.captures.iter().find_map(|capture| {
+                    let name = query.capture_names()[capture.index as usize];
+                    Some((name.strip_prefix("definition.")?, capture.node))
+                })?;
+                let name_node = mat
+                    .captures
+                    .iter()
+                    .find(|capture| capture.index == name_index)?
+                    .node;
+                let name = self
+                    .text
+                    .slice_to_cow(name_node.start_byte()..name_node.end_byte())
+                    .into_owned();
+
+                #[allow(deprecated)]
+                let symbol = DocumentSymbol {
+                    name,
+                    detail: None,
+                    kind: symbol_kind(kind),
+                    tags: None,
+                    deprecated: None,
+                    range: node_range(&text, node),
+                    selection_range: node_range(&text, name_node),
+                    children: None,
+                };
+                Some((node.start_byte(), node.end_byte(), symbol))
+            })
+            .collect();
+        symbols.sort_by_key(|(start, end, _)| (*start, Reverse(*end)));
+        symbols.dedup_by_key(|(start, end, _)| (*start, *end));
+
+        Some(nest_symbols(symbols))
+    }
+}
+
+fn node_range(text: &RopeTextRef, node: Node) -> Range {
+    Range {
+        start: text.offset_to_position(node.start_byte()),
+        end: text.offset_to_position(node.end_byte()),
+    }
+}
+
+/// The symbol kind for the `definition.<kind>` capture names used by the
+/// `tags.scm` of the tree-sitter grammars.
+fn symbol_kind(kind: &str) -> SymbolKind {
+    match kind {
+        "function" | "macro" => SymbolKind::FUNCTION,
+        "method" => SymbolKind::METHOD,
+        "constructor" => SymbolKind::CONSTRUCTOR,
+        "class" | "type" => SymbolKind::CLASS,
+        "struct" => SymbolKind::STRUCT,
+        "enum" => SymbolKind::ENUM,
+        "interface" | "trait" => SymbolKind::INTERFACE,
+        "module" | "namespace" => SymbolKind::MODULE,
+        "implementation" => SymbolKind::OBJECT,
+        "constant" => SymbolKind::CONSTANT,
+        "field" | "property" => SymbolKind::FIELD,
+        _ => SymbolKind::VARIABLE,
+    }
+}
+
+/// Nest symbols sorted by their start, and outermost first when they start
+/// at the same place, into the ones whose range contains them.
+fn nest_symbols(
+    symbols: Vec<(usize, usize, DocumentSymbol)>,
+) -> Vec<DocumentSymbol> {
+    let mut roots = Vec::new();
+    // The symbols that may still contain the next ones, with their end
+    let mut open: Vec<(usize, DocumentSymbol)> = Vec::new();
+    for (start, end, symbol) in symbols {
+        while open
+            .last()
+            .is_some_and(|(parent_end, _)| start >= *parent_end)
+        {
+            let (_, done) = open.pop().unwrap();
+            add_symbol(&mut open, &mut roots, done);
+        }
+        open.push((end, symbol));
+    }
+    while let Some((_, done)) = open.pop() {
+        add_symbol(&mut open, &mut roots, done);
+    }
+    roots
+}
+
+fn add_symbol(
+    open: &mut [(usize, DocumentSymbol)],
+    roots: &mut Vec<DocumentSymbol>,
+    symbol: DocumentSymbol,
+) {
+    match open.last_mut() {
+        Some((_, parent)) => {
+            parent.children.get_or_insert_with(Vec::new).push(symbol);
+        }
+        None => roots.push(symbol),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[allow(deprecated)]
+    fn symbol(name: &str) -> DocumentSymbol {
+        DocumentSymbol {
+            name: name.to_string(),
+            detail: None,
+            kind: SymbolKind::FUNCTION,
+            tags: None,
+            deprecated: None,
+            range: Range::default(),
+            selection_range: Range::default(),
+            children: None,
+        }
+    }
+
+    #[test]
+    fn test_nest_symbols() {
+        let symbols = nest_symbols(vec![
+            (0, 50, symbol("a")),
+            (5, 20, symbol("b")),
+            (10, 15, symbol("c")),
+            (20, 40, symbol("d")),
+            (60, 70, symbol("e")),
+        ]);
+        let names = |symbols: &[DocumentSymbol]| {
+            symbols.iter().map(|s| s.name.clone()).collect::<Vec<_>>()
+        };
+        assert_eq!(names(&symbols), vec!["a", "e"]);
+        let a = symbols[0].children.as_deref().unwrap();
+        assert_eq!(names(a), vec!["b", "d"]);
+        assert_eq!(names(a[0].children.as_deref().unwrap()), vec!["c"]);
+        assert!(symbols[1].children.is_none());
+    }
+}
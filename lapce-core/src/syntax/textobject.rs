@@ -0,0 +1,417 @@
+use std::{cell::RefCell, collections::HashMap, sync::Arc};
+
+use lapce_xi_rope::Rope;
+use tree_sitter::{Node, Query, QueryCursor};
+
+use super::{util::RopeProvider, Syntax};
+use crate::language::LapceLanguage;
+
+thread_local! {
+    static TEXTOBJECT_QUERIES: RefCell<HashMap<LapceLanguage, Option<Arc<Query>>>> = Default::default();
+}
+
+pub fn reset_textobject_queries() {
+    TEXTOBJECT_QUERIES.with_borrow_mut(|queries| {
+        queries.clear();
+    });
+}
+
+fn get_textobject_query(lang: LapceLanguage) -> Option<Arc<Query>> {
+    TEXTOBJECT_QUERIES.with_borrow_mut(|queries| {
+        queries
+            .entry(lang)
+            .or_insert_with(|| lang.new_textobject_query().map(Arc::new))
+            .clone()
+    })
+}
+
+/// A structural piece of text that can be selected or operated on, like the
+/// `af` or `i(` objects of vim.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextObject {
+    Function,
+    Class,
+    Parameter,
+    /// A pair of brackets, with the opening and the closing bracket
+    Pair(char, char),
+    /// A pair of quotes on a single line
+    Quote(char),
+}
+
+impl TextObject {
+    /// The object named by the character typed after `i` or `a` in vim.
+    pub fn from_char(c: char) -> Option<Self> {
+        let object = match c {
+            'f' => TextObject::Function,
+            'c' => TextObject::Class,
+            'a' => TextObject::Parameter,
+            '(' | ')' | 'b' => TextObject::Pair('(', ')'),
+            '[' | ']' => TextObject::Pair('[', ']'),
+            '{' | '}' | 'B' => TextObject::Pair('{', '}'),
+            '<' | '>' => TextObject::Pair('<', '>'),
+            '"' | '\'' | '`' => TextObject::Quote(c),
+            _ => return None,
+        };
+        Some(object)
+    }
+
+    /// The name of the `textobjects.scm` capture for the object, which
+    /// follows the `function.inside`/`function.around` convention of helix.
+    fn capture_name(&self, inside: bool) -> Option<String> {
+        let name = match self {
+            TextObject::Function => "function",
+            TextObject::Class => "class",
+            TextObject::Parameter => "parameter",
+            _ => return None,
+        };
+        let kind = if inside { "inside" } else { "around" };
+        Some(format!("{name}.{kind}"))
+    }
+
+    /// The range of the object around the offset, with an exclusive end.
+    /// `syntax` is `None` when it's out of date with the text. Pairs are
+    /// the nodes of the syntax tree delimited by them, so that brackets and
+    /// quotes in strings and comments are left out, and are found in the
+    /// text itself without a syntax tree.
+    pub fn range(
+        &self,
+        syntax: Option<&Syntax>,
+        text: &Rope,
+        offset: usize,
+        inside: bool,
+    ) -> Option<(usize, usize)> {
+        let root = syntax
+            .and_then(|syntax| syntax.layers.as_ref()?.try_tree())
+            .map(|tree| tree.root_node());
+        let (start, end) = match (*self, root) {
+            (TextObject::Pair(open, close), Some(root)) => {
+                find_enclosing_node_pair(root, offset, open, close)?
+            }
+            (TextObject::Quote(quote), Some(root)) => {
+                find_enclosing_node_pair(root, offset, quote, quote)?
+            }
+            (TextObject::Pair(open, close), None) => {
+                find_enclosing_bracket(text, offset, open, close)?
+            }
+            (TextObject::Quote(quote), None) => {
+                find_quote_pair(text, offset, quote)?
+            }
+            _ => return syntax?.textobject_range(offset, *self, inside),
+        };
+        if inside {
+            Some((start + 1, end))
+        } else {
+            Some((start, end + 1))
+        }
+    }
+}
+
+impl Syntax {
+    /// The ranges captured as `name` by the language's `textobjects.scm`.
+    fn textobject_captures(&self, name: &str) -> Option<Vec<(usize, usize)>> {
+        let query = get_textobject_query(self.language)?;
+        let tree = self.layers.as_ref()?.try_tree()?;
+        query_captures(&query, tree.root_node(), &self.text, name)
+    }
+
+    /// The innermost syntax text object containing the offset.
+    pub fn textobject_range(
+        &self,
+        offset: usize,
+        object: TextObject,
+        inside: bool,
+    ) -> Option<(usize, usize)> {
+        let name = object.capture_name(inside)?;
+        innermost_range(self.textobject_captures(&name)?, offset)
+    }
+
+    /// The start of the closest syntax text object that starts after the
+    /// offset, or before it if not `forward`.
+    pub fn textobject_offset(
+        &self,
+        offset: usize,
+        object: TextObject,
+        forward: bool,
+    ) -> Option<usize> {
+        let name = object.capture_name(false)?;
+        closest_start(self.textobject_captures(&name)?, offset, forward)
+    }
+}
+
+/// The ranges captured as `name` by the query under `root`. Captures of
+/// several nodes in the same match are joined into one range.
+fn query_captures(
+    query: &Query,
+    root: Node,
+    text: &Rope,
+    name: &str,
+) -> Option<Vec<(usize, usize)>> {
+    let index = query.capture_index_for_name(name)?;
+    let mut cursor = QueryCursor::new();
+    let ranges = cursor
+        .matches(query, root, RopeProvider(text))
+        .filter_map(|mat| {
+            mat.captures
+                .iter()
+                .filter(|capture| capture.index == index)
+                .map(|capture| (capture.node.start_byte(), capture.node.end_byte()))
+                .reduce(|(start, end), (s, e)| (start.min(s), end.max(e)))
+        })
+        .collect();
+    Some(ranges)
+}
+
+/// The smallest of the ranges that contains the offset.
+fn innermost_range(
+    ranges: Vec<(usize, usize)>,
+    offset: usize,
+) -> Option<(usize, usize)> {
+    ranges
+        .into_iter()
+        .filter(|(start, end)| *start <= offset && offset < *end)
+        .min_by_key(|(start, end)| end - start)
+}
+
+/// The closest start of the ranges after the offset, or before it if not
+/// `forward`.
+fn closest_start(
+    ranges: Vec<(usize, usize)>,
+    offset: usize,
+    forward: bool,
+) -> Option<usize> {
+    let starts = ranges.into_iter().map(|(start, _)| start);
+    if forward {
+        starts.filter(|start| *start > offset).min()
+    } else {
+        starts.filter(|start| *start < offset).max()
+    }
+}
+
+/// The offsets of the `open` and `close` tokens of the innermost node around
+/// the offset that starts and ends with them, where being on either token
+/// counts as being inside the node. Only the node's own tokens are looked
+/// at, so brackets in strings and comments are never matched.
+pub fn find_enclosing_node_pair(
+    root: Node,
+    offset: usize,
+    open: char,
+    close: char,
+) -> Option<(usize, usize)> {
+    let (mut open_buf, mut close_buf) = ([0; 4], [0; 4]);
+    let open: &str = open.encode_utf8(&mut open_buf);
+    let close: &str = close.encode_utf8(&mut close_buf);
+
+    let mut node = root.descendant_for_byte_range(offset, offset + 1)?;
+    loop {
+        let count = node.child_count();
+        if count >= 2 {
+            let first = node.child(0)?;
+            let last = node.child(count - 1)?;
+            if first.kind() == open && last.kind() == close {
+                return Some((first.start_byte(), last.start_byte()));
+            }
+        }
+        node = node.parent()?;
+    }
+}
+
+/// The offsets of the innermost `open` and `close` brackets around the offset,
+/// where being on either bracket counts as being inside it.
+pub fn find_enclosing_bracket(
+    text: &Rope,
+    offset: usize,
+    open: char,
+    close: char,
+) -> Option<(usize, usize)> {
+    let (open, close) = (open as u8, close as u8);
+    if offset >= text.len() {
+        return None;
+    }
+
+    let start = if text.byte_at(offset) == open {
+        offset
+    } else {
+        let mut depth = 0;
+        let mut start = None;
+        for i in (0..offset).rev() {
+            let b = text.byte_at(i);
+            if b == close {
+                depth += 1;
+            } else if b == open {
+                if depth == 0 {
+                    start = Some(i);
+                    break;
+                }
+                depth -= 1;
+            }
+        }
+        start?
+    };
+
+    let mut depth = 0;
+    for i in start + 1..text.len() {
+        let b = text.byte_at(i);
+        if b == open {
+            depth += 1;
+        } else if b == close {
+            if depth == 0 {
+                return (i >= offset).then_some((start, i));
+            }
+            depth -= 1;
+        }
+    }
+    None
+}
+
+/// The offsets of the quotes around the offset on its line, or of the next
+/// quoted string on the line if the offset isn't inside one.
+pub fn find_quote_pair(
+    text: &Rope,
+    offset: usize,
+    quote: char,
+) -> Option<(usize, usize)> {
+    let quote = quote as u8;
+    if offset >= text.len() {
+        return None;
+    }
+    let line_start = (0..offset)
+        .rev()
+        .find(|i| text.byte_at(*i) == b'\n')
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    let line_end = (offset..text.len())
+        .find(|i| text.byte_at(*i) == b'\n')
+        .unwrap_or(text.len());
+
+    let mut quotes = Vec::new();
+    let mut escaped = false;
+    for i in line_start..line_end {
+        let b = text.byte_at(i);
+        if escaped {
+            escaped = false;
+        } else if b == b'\\' {
+            escaped = true;
+        } else if b == quote {
+            quotes.push(i);
+        }
+    }
+
+    quotes
+        .chunks_exact(2)
+        .map(|pair| (pair[0], pair[1]))
+        .find(|(_, end)| offset <= *end)
+}
+
+#[cfg(test)]
+mod tests {
+    use tree_sitter::{Parser, Tree};
+
+    use super::*;
+
+    const TEXT: &str = "\
+struct Point {
+    x: i32,
+}
+
+impl Point {
+    fn add(&self, other: Point, scale: i32) -> i32 {
+        let open = \"(\";
+        (self.x + other.x) * scale
+    }
+}
+";
+
+    const QUERY: &str = "
+(function_item body: (_) @function.inside) @function.around
+(struct_item body: (_) @class.inside) @class.around
+(impl_item body: (_) @class.inside) @class.around
+(parameters (_) @parameter.inside @parameter.around)
+";
+
+    fn parse() -> (Tree, Query) {
+        let language = tree_sitter_rust::language();
+        let mut parser = Parser::new();
+        parser.set_language(&language).unwrap();
+        let tree = parser.parse(TEXT, None).unwrap();
+        let query = Query::new(&language, QUERY).unwrap();
+        (tree, query)
+    }
+
+    fn at(s: &str) -> usize {
+        TEXT.find(s).unwrap()
+    }
+
+    fn text_of(range: Option<(usize, usize)>) -> Option<&'static str> {
+        range.map(|(start, end)| &TEXT[start..end])
+    }
+
+    #[test]
+    fn test_syntax_objects() {
+        let (tree, query) = parse();
+        let text = Rope::from(TEXT);
+        let object = |name: &str, offset: usize| {
+            let ranges =
+                query_captures(&query, tree.root_node(), &text, name).unwrap();
+            text_of(innermost_range(ranges, offset))
+        };
+
+        let function = object("function.around", at("let open")).unwrap();
+        assert!(function.starts_with("fn add("));
+        assert!(function.ends_with("* scale\n    }"));
+        let body = object("function.inside", at("let open")).unwrap();
+        assert!(body.starts_with("{\n        let open"));
+
+        // The innermost class is the one around the offset
+        assert!(object("class.around", at("let open"))
+            .unwrap()
+            .starts_with("impl Point {"));
+        assert_eq!(
+            object("class.inside", at("x: i32")),
+            Some("{\n    x: i32,\n}")
+        );
+        assert_eq!(object("class.around", at("\n\nimpl")), None);
+
+        assert_eq!(
+            object("parameter.inside", at("other:") + 2),
+            Some("other: Point")
+        );
+        assert_eq!(object("parameter.inside", at("scale:")), Some("scale: i32"));
+
+        let functions =
+            query_captures(&query, tree.root_node(), &text, "function.around")
+                .unwrap();
+        assert_eq!(
+            closest_start(functions.clone(), 0, true),
+            Some(at("fn add"))
+        );
+        assert_eq!(closest_start(functions, at("fn add"), true), None);
+    }
+
+    #[test]
+    fn test_node_pairs() {
+        let (tree, _) = parse();
+        let root = tree.root_node();
+        let pair = |offset: usize, open: char, close: char| {
+            find_enclosing_node_pair(root, offset, open, close)
+                .map(|(start, end)| &TEXT[start..=end])
+        };
+
+        assert_eq!(pair(at("self.x"), '(', ')'), Some("(self.x + other.x)"));
+        // Being on a bracket is being inside it
+        assert_eq!(pair(at("(self.x"), '(', ')'), Some("(self.x + other.x)"));
+        assert!(pair(at("let open"), '{', '}')
+            .unwrap()
+            .starts_with("{\n        let open"));
+        assert_eq!(pair(at("self, other"), '(', ')').map(str::len), Some(33));
+
+        // The bracket in the string isn't paired with the one after it,
+        // unlike when only the text is looked at
+        let string_bracket = at("\"(\"") + 1;
+        assert_eq!(pair(string_bracket, '(', ')'), None);
+        assert!(
+            find_enclosing_bracket(&Rope::from(TEXT), string_bracket, '(', ')')
+                .is_some()
+        );
+        assert_eq!(pair(string_bracket, '"', '"'), Some("\"(\""));
+    }
+}
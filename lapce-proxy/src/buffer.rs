@@ -9,10 +9,18 @@
 };
 
 use anyhow::{anyhow, Result};
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
 use floem_editor_core::buffer::rope_text::CharIndicesJoin;
-use lapce_core::encoding::offset_utf8_to_utf16;
+use lapce_core::{
+    encoding::offset_utf8_to_utf16, hex::hex_dump, language::LapceLanguage,
+};
 use lapce_rpc::buffer::BufferId;
-use lapce_xi_rope::{interval::IntervalBounds, rope::Rope, RopeDelta};
+use lapce_xi_rope::{
+    interval::IntervalBounds,
+    rope::{Rope, RopeInfo},
+    tree::TreeBuilder,
+    RopeDelta,
+};
 use lsp_types::*;
 
 #[derive(Clone)]
@@ -24,24 +32,55 @@ pub struct Buffer {
     pub path: PathBuf,
     pub rev: u64,
     pub mod_time: Option<SystemTime>,
+    pub encoding: FileEncoding,
+    /// Whether the file is larger than the threshold it was opened with, and
+    /// so isn't synced with language servers. For binary files, whether the
+    /// hex dump was cut short.
+    pub large: bool,
+    /// Whether the file is binary, in which case the buffer holds a hex dump
+    /// of it rather than its content
+    pub binary: bool,
+    /// Whether the file is large and had bytes that aren't valid in the
+    /// encoding guessed from its start. They were read as replacement
+    /// characters, so the file isn't saved, as that would lose them.
+    pub lossy: bool,
 }
 
 impl Buffer {
     pub fn new(id: BufferId, path: PathBuf) -> Buffer {
-        let (s, read_only) = match load_file(&path) {
-            Ok(s) => (s, false),
+        Self::with_large_file_size(id, path, DEFAULT_LARGE_FILE_SIZE)
+    }
+
+    /// Open the file, treating it as large when it has more than
+    /// `large_file_size` bytes.
+    pub fn with_large_file_size(
+        id: BufferId,
+        path: PathBuf,
+        large_file_size: u64,
+    ) -> Buffer {
+        let mut encoding = FileEncoding::UTF8;
+        let mut large = false;
+        let mut binary = false;
+        let mut lossy = false;
+        let (rope, read_only) = match open_file(&path, large_file_size) {
+            Ok(file) => {
+                encoding = file.encoding;
+                large = file.large;
+                binary = file.binary;
+                lossy = file.lossy;
+                (file.content, false)
+            }
             Err(err) => match err.downcast_ref::<std::io::Error>() {
                 Some(err) => match err.kind() {
                     std::io::ErrorKind::PermissionDenied => {
-                        ("Permission Denied".to_string(), true)
+                        (Rope::from("Permission Denied"), true)
                     }
-                    std::io::ErrorKind::NotFound => ("".to_string(), false),
-                    _ => ("Not Supported".to_string(), true),
+                    std::io::ErrorKind::NotFound => (Rope::from(""), false),
+                    _ => (Rope::from("Not Supported"), true),
                 },
-                None => ("Not Supported".to_string(), true),
+                None => (Rope::from("Not Supported"), true),
             },
         };
-        let rope = Rope::from(s);
         let rev = u64::from(!rope.is_empty());
         let language_id = language_id_from_path(&path).unwrap_or("");
         let mod_time = get_mod_time(&path);
@@ -53,10 +92,59 @@ pub fn new(id: BufferId, path: PathBuf) -> Buffer {
             language_id,
             rev,
             mod_time,
+            encoding,
+            large,
+            binary,
+            lossy,
+        }
+    }
+
+    /// Whether language servers and plugins are told about the buffer, which
+    /// they aren't for large and binary files.
+    pub fn is_synced(&self) -> bool {
+        !self.large && !self.binary
+    }
+
+    /// Read the file again after it changed on disk, the way it was opened.
+    pub fn read_again(&self) -> Result<String> {
+        if self.binary {
+            let mut file = File::open(&self.path)?;
+            let len = file.metadata()?.len();
+            let mut bytes = Vec::new();
+            (&mut file).take(HEX_VIEW_LIMIT).read_to_end(&mut bytes)?;
+            Ok(hex_dump(&bytes, len))
+        } else {
+            load_file_with_encoding(&self.path, self.encoding)
         }
     }
 
     pub fn save(&mut self, rev: u64, create_parents: bool) -> Result<()> {
+        if self.binary {
+            return Err(anyhow!("binary files are saved from their bytes"));
+        }
+        if self.lossy {
+            return Err(anyhow!(
+                "the file has bytes that aren't valid in {}, reopen it with \
+                 its encoding to save it",
+                self.encoding.label()
+            ));
+        }
+        let bytes = self.encoding.encode(&self.rope)?;
+        self.write(rev, &bytes, create_parents)
+    }
+
+    /// Save the bytes of a binary file, as edited in its hex dump.
+    pub fn save_bytes(&mut self, rev: u64, bytes: &[u8]) -> Result<()> {
+        if !self.binary {
+            return Err(anyhow!("only binary files are saved from their bytes"));
+        }
+        if self.large {
+            return Err(anyhow!("the hex dump doesn't show the whole file"));
+        }
+        self.write(rev, bytes, false)
+    }
+
+    fn write(&mut self, rev: u64, bytes: &[u8], create_parents: bool) -> Result<()> {
         if self.read_only {
             return Err(anyhow!("can't save to read only file"));
         }
@@ -95,9 +183,7 @@ pub fn save(&mut self, rev: u64, create_parents: bool) -> Result<()> {
             .write(true)
             .truncate(true)
             .open(&path)?;
-        for chunk in self.rope.iter_chunks(..self.rope.len()) {
-            f.write_all(chunk.as_bytes())?;
-        }
+        f.write_all(bytes)?;
 
         self.mod_time = get_mod_time(&path);
         if !new_file {
@@ -131,6 +217,20 @@ pub fn get_document(&self) -> String {
         self.rope.to_string()
     }
 
+    /// The text from `offset` on, about a chunk of it, and the offset of the
+    /// text after it when there is more. Large files are sent a chunk at a
+    /// time, so that their text is never all in one message.
+    pub fn content_chunk(&self, offset: usize) -> (String, Option<usize>) {
+        let len = self.rope.len();
+        let offset = offset.min(len);
+        let end = self
+            .rope
+            .at_or_next_codepoint_boundary((offset + CHUNK_LEN).min(len))
+            .unwrap_or(len);
+        let content = self.rope.slice_to_cow(offset..end).into_owned();
+        (content, (end < len).then_some(end))
+    }
+
     pub fn offset_of_line(&self, line: usize) -> usize {
         self.rope.offset_of_line(line)
     }
@@ -186,25 +286,277 @@ pub fn is_empty(&self) -> bool {
     }
 }
 
-pub fn load_file(path: &Path) -> Result<String> {
-    read_path_to_string(path)
+/// Read a file, decoding it in the encoding it is detected to be in.
+pub fn load_file(path: &Path) -> Result<(String, FileEncoding)> {
+    let bytes = read_path_to_bytes(path)?;
+    let encoding = FileEncoding::detect(&bytes);
+    Ok((encoding.decode(&bytes), encoding))
+}
+
+/// Read a file, decoding it in the given encoding.
+pub fn load_file_with_encoding(
+    path: &Path,
+    encoding: FileEncoding,
+) -> Result<String> {
+    Ok(load_file_with_encoding_checked(path, encoding)?.0)
+}
+
+/// Read a file, decoding it in the given encoding, and tell whether any of
+/// its bytes weren't valid in it.
+pub fn load_file_with_encoding_checked(
+    path: &Path,
+    encoding: FileEncoding,
+) -> Result<(String, bool)> {
+    let bytes = read_path_to_bytes(path)?;
+    Ok(encoding.decode_checked(&bytes))
 }
 
 pub fn read_path_to_string<P: AsRef<Path>>(path: P) -> Result<String> {
-    let path = path.as_ref();
+    Ok(load_file(path.as_ref())?.0)
+}
 
+fn read_path_to_bytes(path: &Path) -> Result<Vec<u8>> {
     let mut file = File::open(path)?;
-    // Read the file in as bytes
     let mut buffer = Vec::new();
     file.read_to_end(&mut buffer)?;
+    Ok(buffer)
+}
+
+/// The size above which files are opened as large files when the editor
+/// doesn't say otherwise.
+pub const DEFAULT_LARGE_FILE_SIZE: u64 = 20 * 1024 * 1024;
+
+/// How many bytes at the start of a file are looked at to tell whether it is
+/// binary, like git does.
+const SNIFF_LEN: usize = 8000;
+
+/// How many bytes of a binary file the hex dump shows. Larger files can only
+/// be looked at, as their dump doesn't have all of their bytes.
+const HEX_VIEW_LIMIT: u64 = 4 * 1024 * 1024;
+
+/// The size of the chunks large files are read and decoded in.
+const CHUNK_LEN: usize = 1024 * 1024;
+
+/// A file as read into a buffer.
+pub struct OpenedFile {
+    pub content: Rope,
+    pub encoding: FileEncoding,
+    pub large: bool,
+    pub binary: bool,
+    /// Whether bytes that aren't valid in the encoding were replaced
+    pub lossy: bool,
+}
+
+/// Read a file into a rope. Binary files are read as a hex dump. Files larger
+/// than `large_file_size` have their encoding detected from their start only,
+/// and are decoded a chunk at a time so that their bytes are never all held
+/// in memory next to their text.
+pub fn open_file(path: &Path, large_file_size: u64) -> Result<OpenedFile> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+
+    let mut head = Vec::with_capacity(SNIFF_LEN);
+    (&mut file).take(SNIFF_LEN as u64).read_to_end(&mut head)?;
+    if is_binary(&head) {
+        let mut bytes = head;
+        (&mut file)
+            .take(HEX_VIEW_LIMIT - bytes.len() as u64)
+            .read_to_end(&mut bytes)?;
+        return Ok(OpenedFile {
+            content: Rope::from(hex_dump(&bytes, len)),
+            encoding: FileEncoding::UTF8,
+            large: len > HEX_VIEW_LIMIT,
+            binary: true,
+            lossy: false,
+        });
+    }
+
+    if len <= large_file_size {
+        let mut bytes = head;
+        file.read_to_end(&mut bytes)?;
+        let encoding = FileEncoding::detect(&bytes);
+        return Ok(OpenedFile {
+            content: Rope::from(encoding.decode(&bytes)),
+            encoding,
+            large: false,
+            binary: false,
+            lossy: false,
+        });
+    }
+
+    let encoding = FileEncoding::detect_from_start(&head);
+    let mut decoder = encoding.encoding.new_decoder_with_bom_removal();
+    let mut builder = TreeBuilder::<RopeInfo>::new();
+    let mut chunk = head;
+    chunk.reserve(CHUNK_LEN.saturating_sub(chunk.len()));
+    let mut text = String::new();
+    let mut lossy = false;
+    loop {
+        let last = chunk.is_empty();
+        text.clear();
+        text.reserve(
+            decoder
+                .max_utf8_buffer_length(chunk.len())
+                .unwrap_or(chunk.len() * 3),
+        );
+        let (_, _, replaced) = decoder.decode_to_string(&chunk, &mut text, last);
+        lossy |= replaced;
+        builder.push_str(&text);
+        if last {
+            break;
+        }
+        chunk.clear();
+        (&mut file).take(CHUNK_LEN as u64).read_to_end(&mut chunk)?;
+    }
+
+    Ok(OpenedFile {
+        content: builder.build(),
+        encoding,
+        large: true,
+        binary: false,
+        lossy,
+    })
+}
+
+/// Whether the start of a file looks binary, which is when it has NUL bytes
+/// and isn't UTF-16.
+fn is_binary(head: &[u8]) -> bool {
+    Encoding::for_bom(head).is_none() && head.contains(&0)
+}
+
+/// The character encoding a file is read and written in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FileEncoding {
+    pub encoding: &'static Encoding,
+    /// Whether the file starts with a byte order mark. UTF-16 files always
+    /// do, as they can't be told apart from other files without one.
+    pub bom: bool,
+}
+
+impl FileEncoding {
+    pub const UTF8: FileEncoding = FileEncoding {
+        encoding: UTF_8,
+        bom: false,
+    };
+
+    const BOM_SUFFIX: &'static str = " with BOM";
+
+    /// The encoding for a label as given by [`FileEncoding::label`], or any
+    /// label of the WHATWG Encoding Standard, like `latin1` or `sjis`.
+    pub fn from_label(label: &str) -> Option<FileEncoding> {
+        let (label, bom) = match label.strip_suffix(Self::BOM_SUFFIX) {
+            Some(label) => (label, true),
+            None => (label, false),
+        };
+        let encoding = Encoding::for_label(label.trim().as_bytes())?;
+        Some(FileEncoding {
+            encoding,
+            bom: bom || encoding == UTF_16LE || encoding == UTF_16BE,
+        })
+    }
+
+    /// The name of the encoding, like `UTF-8`, `Shift_JIS` or
+    /// `UTF-8 with BOM`.
+    pub fn label(&self) -> String {
+        if self.bom && self.encoding == UTF_8 {
+            format!("{}{}", self.encoding.name(), Self::BOM_SUFFIX)
+        } else {
+            self.encoding.name().to_string()
+        }
+    }
 
-    // Parse the file contents as utf8
-    let contents = String::from_utf8(buffer)?;
+    /// Detect the encoding of a file's content from its byte order mark, and
+    /// otherwise guess it from the bytes, going for UTF-8 whenever they are
+    /// valid UTF-8.
+    pub fn detect(bytes: &[u8]) -> FileEncoding {
+        if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+            return FileEncoding {
+                encoding,
+                bom: true,
+            };
+        }
+        if std::str::from_utf8(bytes).is_ok() {
+            return FileEncoding::UTF8;
+        }
+        let mut detector = chardetng::EncodingDetector::new();
+        detector.feed(bytes, true);
+        FileEncoding {
+            encoding: detector.guess(None, true),
+            bom: false,
+        }
+    }
+
+    /// Detect the encoding of a file from only its first bytes, which may end
+    /// in the middle of a character.
+    pub fn detect_from_start(head: &[u8]) -> FileEncoding {
+        match std::str::from_utf8(head) {
+            Err(err) if err.error_len().is_none() => {
+                Self::detect(&head[..err.valid_up_to()])
+            }
+            _ => Self::detect(head),
+        }
+    }
+
+    /// Decode the content of a file, dropping its byte order mark. Bytes that
+    /// aren't valid in the encoding become replacement characters.
+    pub fn decode(&self, bytes: &[u8]) -> String {
+        self.decode_checked(bytes).0
+    }
+
+    /// Like [`FileEncoding::decode`], also telling whether any bytes were
+    /// replaced.
+    pub fn decode_checked(&self, bytes: &[u8]) -> (String, bool) {
+        let (content, replaced) = self.encoding.decode_with_bom_removal(bytes);
+        (content.into_owned(), replaced)
+    }
 
-    Ok(contents.to_string())
+    /// Encode the text to be written to the file, failing when it has
+    /// characters the encoding can't represent rather than losing them.
+    pub fn encode(&self, rope: &Rope) -> Result<Vec<u8>> {
+        let content = rope.slice_to_cow(..);
+        let mut bytes = Vec::with_capacity(content.len() + 3);
+        if self.encoding == UTF_16LE || self.encoding == UTF_16BE {
+            let le = self.encoding == UTF_16LE;
+            for unit in std::iter::once(0xFEFF).chain(content.encode_utf16()) {
+                bytes.extend_from_slice(&if le {
+                    unit.to_le_bytes()
+                } else {
+                    unit.to_be_bytes()
+                });
+            }
+            return Ok(bytes);
+        }
+
+        if self.bom && self.encoding == UTF_8 {
+            bytes.extend_from_slice(b"\xEF\xBB\xBF");
+        }
+        let (encoded, _, unmappable) = self.encoding.encode(&content);
+        if unmappable {
+            return Err(anyhow!(
+                "the file has characters that can't be saved as {}",
+                self.label()
+            ));
+        }
+        bytes.extend_from_slice(&encoded);
+        Ok(bytes)
+    }
 }
 
 pub fn language_id_from_path(path: &Path) -> Option<&'static str> {
+    // The languages the settings pick go first, with the id of a built-in
+    // one being the one of its file name extension
+    if let Some(language) = LapceLanguage::from_user_settings(path) {
+        return language.user_language_id().or_else(|| {
+            builtin_language_id(Path::new(&format!(
+                "file.{}",
+                language.extension()?
+            )))
+        });
+    }
+    builtin_language_id(path)
+}
+
+fn builtin_language_id(path: &Path) -> Option<&'static str> {
     // recommended language_id values
     // https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/#textDocumentItem
     Some(match path.extension() {
@@ -341,3 +693,56 @@ pub fn get_mod_time<P: AsRef<Path>>(path: P) -> Option<SystemTime> {
         .and_then(|meta| meta.modified())
         .ok()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_encoding() {
+        let detect = |bytes: &[u8]| FileEncoding::detect(bytes).label();
+        assert_eq!(detect(b"plain"), "UTF-8");
+        assert_eq!(detect(b"\xEF\xBB\xBFbom"), "UTF-8 with BOM");
+        assert_eq!(detect(b"\xFF\xFEa\x00"), "UTF-16LE");
+        assert_eq!(detect(b"caf\xE9 cr\xE8me br\xFBl\xE9e"), "windows-1252");
+    }
+
+    #[test]
+    fn test_is_binary() {
+        assert!(is_binary(b"ELF\x00\x01"));
+        assert!(!is_binary(b"\xFF\xFEa\x00"));
+    }
+
+    #[test]
+    fn test_encoding_round_trip() {
+        let rope = Rope::from("caf\u{e9}\n");
+        for label in ["UTF-8 with BOM", "UTF-16BE", "latin1"] {
+            let encoding = FileEncoding::from_label(label).unwrap();
+            let bytes = encoding.encode(&rope).unwrap();
+            if encoding.bom {
+                assert_eq!(FileEncoding::detect(&bytes), encoding);
+            }
+            assert_eq!(encoding.decode(&bytes), rope.to_string());
+        }
+
+        // Latin-1 has no kana
+        let latin1 = FileEncoding::from_label("latin1").unwrap();
+        assert!(latin1.encode(&Rope::from("\u{3042}")).is_err());
+    }
+
+    #[test]
+    fn test_content_chunk() {
+        let mut buffer = Buffer::new(BufferId::next(), PathBuf::new());
+        // Chunks don't end in the middle of a character
+        buffer.rope = Rope::from("\u{e9}".repeat(CHUNK_LEN));
+        let mut content = String::new();
+        let mut offset = Some(0);
+        while let Some(start) = offset {
+            let (chunk, next) = buffer.content_chunk(start);
+            assert!(chunk.len() <= CHUNK_LEN + 1);
+            content.push_str(&chunk);
+            offset = next;
+        }
+        assert_eq!(content, buffer.get_document());
+    }
+}
@@ -1,5 +1,6 @@
 use std::{
     collections::{HashMap, HashSet},
+    ffi::OsStr,
     fs, io,
     path::{Path, PathBuf},
     sync::{
@@ -20,6 +21,7 @@
 use grep_regex::RegexMatcherBuilder;
 use grep_searcher::{sinks::UTF8, SearcherBuilder};
 use indexmap::IndexMap;
+use lapce_core::language::set_user_languages;
 use lapce_rpc::{
     buffer::BufferId,
     core::{CoreNotification, CoreRpcHandler, FileChanged},
@@ -29,7 +31,7 @@
         ProxyHandler, ProxyNotification, ProxyRequest, ProxyResponse,
         ProxyRpcHandler, SearchMatch,
     },
-    source_control::{DiffInfo, FileDiff},
+    source_control::{DiffInfo, FileDiff, GitFileCommit},
     style::{LineStyle, SemanticStyles},
     terminal::TermId,
     RequestId, RpcError,
@@ -43,7 +45,7 @@
 use parking_lot::Mutex;
 
 use crate::{
-    buffer::{get_mod_time, load_file, Buffer},
+    buffer::{get_mod_time, load_file_with_encoding_checked, Buffer, FileEncoding},
     plugin::{catalog::PluginCatalog, PluginCatalogRpcHandler},
     terminal::{Terminal, TerminalSender},
     watcher::{FileWatcher, Notify, WatchToken},
@@ -54,6 +56,11 @@
 
 pub struct Dispatcher {
     workspace: Option<PathBuf>,
+    /// Every root folder of the workspace, `workspace` being the first
+    workspace_folders: Vec<PathBuf>,
+    /// The root folders as the file watcher sees them, to find the git
+    /// changes of each
+    watched_folders: Arc<Mutex<Vec<PathBuf>>>,
     pub proxy_rpc: ProxyRpcHandler,
     core_rpc: CoreRpcHandler,
     catalog_rpc: PluginCatalogRpcHandler,
@@ -70,33 +77,44 @@ fn handle_notification(&mut self, rpc: ProxyNotification) {
         match rpc {
             Initialize {
                 workspace,
+                workspace_folders,
                 disabled_volts,
                 extra_plugin_paths,
                 plugin_configurations,
+                language_server_priority,
                 window_id,
                 tab_id,
             } => {
                 self.window_id = window_id;
                 self.tab_id = tab_id;
                 self.workspace = workspace;
+                self.workspace_folders = workspace_folders;
+                if self.workspace_folders.is_empty() {
+                    self.workspace_folders.extend(self.workspace.clone());
+                }
+                self.watched_folders
+                    .lock()
+                    .clone_from(&self.workspace_folders);
                 self.file_watcher.notify(FileWatchNotifier::new(
-                    self.workspace.clone(),
+                    self.watched_folders.clone(),
                     self.core_rpc.clone(),
                     self.proxy_rpc.clone(),
                 ));
-                if let Some(workspace) = self.workspace.as_ref() {
-                    self.file_watcher
-                        .watch(workspace, true, WORKSPACE_EVENT_TOKEN);
+                for folder in &self.workspace_folders {
+                    self.file_watcher.watch(folder, true, WORKSPACE_EVENT_TOKEN);
                 }
 
                 let plugin_rpc = self.catalog_rpc.clone();
                 let workspace = self.workspace.clone();
+                let workspace_folders = self.workspace_folders.clone();
                 thread::spawn(move || {
                     let mut plugin = PluginCatalog::new(
                         workspace,
+                        workspace_folders,
                         disabled_volts,
                         extra_plugin_paths,
                         plugin_configurations,
+                        language_server_priority,
                         plugin_rpc.clone(),
                     );
                     plugin_rpc.mainloop(&mut plugin);
@@ -116,13 +134,33 @@ fn handle_notification(&mut self, rpc: ProxyNotification) {
                 self.core_rpc
                     .notification(CoreNotification::OpenPaths { paths });
             }
+            SetWorkspaceFolders { folders } => {
+                for folder in &self.workspace_folders {
+                    if !folders.contains(folder) {
+                        self.file_watcher.unwatch(folder, WORKSPACE_EVENT_TOKEN);
+                    }
+                }
+                for folder in &folders {
+                    if !self.workspace_folders.contains(folder) {
+                        self.file_watcher.watch(folder, true, WORKSPACE_EVENT_TOKEN);
+                    }
+                }
+                // The first root stands for the workspace where only one is
+                // looked at
+                self.workspace = folders.first().cloned();
+                self.workspace_folders = folders.clone();
+                self.watched_folders.lock().clone_from(&folders);
+                if let Err(err) = self.catalog_rpc.set_workspace_folders(folders) {
+                    tracing::error!("{:?}", err);
+                }
+            }
             OpenFileChanged { path } => {
                 if path.exists() {
                     if let Some(buffer) = self.buffers.get(&path) {
                         if get_mod_time(&buffer.path) == buffer.mod_time {
                             return;
                         }
-                        match load_file(&buffer.path) {
+                        match buffer.read_again() {
                             Ok(content) => {
                                 self.core_rpc.open_file_changed(
                                     path,
@@ -166,19 +204,34 @@ fn handle_notification(&mut self, rpc: ProxyNotification) {
                 let buffer = self.buffers.get_mut(&path).unwrap();
                 let old_text = buffer.rope.clone();
                 buffer.update(&delta, rev);
-                self.catalog_rpc.did_change_text_document(
-                    &path,
-                    rev,
-                    delta,
-                    old_text,
-                    buffer.rope.clone(),
-                );
+                if buffer.is_synced() {
+                    self.catalog_rpc.did_change_text_document(
+                        &path,
+                        rev,
+                        delta,
+                        old_text,
+                        buffer.rope.clone(),
+                    );
+                }
             }
             UpdatePluginConfigs { configs } => {
                 if let Err(err) = self.catalog_rpc.update_plugin_configs(configs) {
                     tracing::error!("{:?}", err);
                 }
             }
+            UpdateLanguageServerPriority { priority } => {
+                if let Err(err) =
+                    self.catalog_rpc.update_language_server_priority(priority)
+                {
+                    tracing::error!("{:?}", err);
+                }
+            }
+            UpdateUserLanguages {
+                languages,
+                file_associations,
+            } => {
+                set_user_languages(&languages, &file_associations);
+            }
             NewTerminal { term_id, profile } => {
                 let mut terminal = match Terminal::new(term_id, profile, 50, 10) {
                     Ok(terminal) => terminal,
@@ -333,33 +386,40 @@ fn handle_notification(&mut self, rpc: ProxyNotification) {
                 }
             }
             GitCommit { message, diffs } => {
-                if let Some(workspace) = self.workspace.as_ref() {
-                    match git_commit(workspace, &message, diffs) {
-                        Ok(()) => (),
-                        Err(e) => {
-                            self.core_rpc.show_message(
-                                "Git Commit failure".to_owned(),
-                                ShowMessageParams {
-                                    typ: MessageType::ERROR,
-                                    message: e.to_string(),
-                                },
-                            );
-                        }
+                // Each repository of the workspace gets a commit of its files
+                let groups =
+                    group_by_repository(diffs, |diff| diff.path().as_path());
+                for (repo, diffs) in groups {
+                    if let Err(e) = git_commit(&repo, &message, diffs) {
+                        self.core_rpc.show_message(
+                            "Git Commit failure".to_owned(),
+                            ShowMessageParams {
+                                typ: MessageType::ERROR,
+                                message: e.to_string(),
+                            },
+                        );
                     }
                 }
             }
             GitCheckout { reference } => {
-                if let Some(workspace) = self.workspace.as_ref() {
-                    match git_checkout(workspace, &reference) {
+                // The branches shown are the ones of the first repository
+                let repo = self
+                    .workspace_folders
+                    .iter()
+                    .find_map(|folder| Repository::discover(folder).ok());
+                if let Some(repo) = repo {
+                    match git_checkout(&repo, &reference) {
                         Ok(()) => (),
                         Err(e) => eprintln!("{e:?}"),
                     }
                 }
             }
             GitDiscardFilesChanges { files } => {
-                if let Some(workspace) = self.workspace.as_ref() {
+                for (repo, files) in
+                    group_by_repository(files, |file| file.as_path())
+                {
                     match git_discard_files_changes(
-                        workspace,
+                        &repo,
                         files.iter().map(AsRef::as_ref),
                     ) {
                         Ok(()) => (),
@@ -368,15 +428,18 @@ fn handle_notification(&mut self, rpc: ProxyNotification) {
                 }
             }
             GitDiscardWorkspaceChanges {} => {
-                if let Some(workspace) = self.workspace.as_ref() {
-                    match git_discard_workspace_changes(workspace) {
+                let folders = self.workspace_folders.clone();
+                for (repo, _) in
+                    group_by_repository(folders, |folder| folder.as_path())
+                {
+                    match git_discard_workspace_changes(&repo) {
                         Ok(()) => (),
                         Err(e) => eprintln!("{e:?}"),
                     }
                 }
             }
             GitInit {} => {
-                if let Some(workspace) = self.workspace.as_ref() {
+                if let Some(workspace) = self.workspace_folders.first() {
                     match git_init(workspace) {
                         Ok(()) => (),
                         Err(e) => eprintln!("{e:?}"),
@@ -395,47 +458,85 @@ fn handle_notification(&mut self, rpc: ProxyNotification) {
                     false,
                 );
             }
+            PullWorkspaceDiagnostics {} => {
+                if let Err(err) = self.catalog_rpc.pull_workspace_diagnostics(None) {
+                    tracing::error!("{:?}", err);
+                }
+            }
         }
     }
 
     fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
         use ProxyRequest::*;
         match rpc {
-            NewBuffer { buffer_id, path } => {
-                let buffer = Buffer::new(buffer_id, path.clone());
-                let content = buffer.rope.to_string();
-                let read_only = buffer.read_only;
-                self.catalog_rpc.did_open_document(
-                    &path,
-                    buffer.language_id.to_string(),
-                    buffer.rev as i32,
-                    content.clone(),
+            NewBuffer {
+                buffer_id,
+                path,
+                large_file_size,
+            } => {
+                let buffer = Buffer::with_large_file_size(
+                    buffer_id,
+                    path.clone(),
+                    large_file_size,
                 );
+                let (content, next_offset) = if buffer.large {
+                    buffer.content_chunk(0)
+                } else {
+                    (buffer.rope.to_string(), None)
+                };
+                let read_only = buffer.read_only;
+                let encoding = buffer.encoding.label();
+                let large = buffer.large;
+                let binary = buffer.binary;
+                if buffer.is_synced() {
+                    self.catalog_rpc.did_open_document(
+                        &path,
+                        buffer.language_id.to_string(),
+                        buffer.rev as i32,
+                        content.clone(),
+                    );
+                }
                 self.file_watcher.watch(&path, false, OPEN_FILE_EVENT_TOKEN);
                 self.buffers.insert(path, buffer);
                 self.respond_rpc(
                     id,
-                    Ok(ProxyResponse::NewBufferResponse { content, read_only }),
+                    Ok(ProxyResponse::NewBufferResponse {
+                        content,
+                        read_only,
+                        encoding,
+                        large,
+                        binary,
+                        next_offset,
+                    }),
                 );
             }
-            BufferHead { path } => {
-                let result = if let Some(workspace) = self.workspace.as_ref() {
-                    let result = file_get_head(workspace, &path);
-                    if let Ok((_blob_id, content)) = result {
-                        Ok(ProxyResponse::BufferHeadResponse {
-                            version: "head".to_string(),
+            BufferChunk { path, offset } => {
+                let result = self
+                    .buffers
+                    .get(&path)
+                    .map(|buffer| {
+                        let (content, next_offset) = buffer.content_chunk(offset);
+                        ProxyResponse::BufferChunkResponse {
                             content,
-                        })
-                    } else {
-                        Err(RpcError {
-                            code: 0,
-                            message: "can't get file head".to_string(),
-                        })
-                    }
+                            next_offset,
+                        }
+                    })
+                    .ok_or_else(|| RpcError {
+                        code: 0,
+                        message: "buffer not found".to_string(),
+                    });
+                self.respond_rpc(id, result);
+            }
+            BufferHead { path } => {
+                let result = if let Ok((_blob_id, content)) = file_get_head(&path) {
+                    Ok(ProxyResponse::BufferHeadResponse {
+                        version: "head".to_string(),
+                        content,
+                    })
                 } else {
                     Err(RpcError {
                         code: 0,
-                        message: "no workspace set".to_string(),
+                        message: "can't get file head".to_string(),
                     })
                 };
                 self.respond_rpc(id, result);
@@ -449,7 +550,7 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                 static WORKER_ID: AtomicU64 = AtomicU64::new(0);
                 let our_id = WORKER_ID.fetch_add(1, Ordering::SeqCst) + 1;
 
-                let workspace = self.workspace.clone();
+                let workspace_folders = self.workspace_folders.clone();
                 let buffers = self
                     .buffers
                     .iter()
@@ -465,7 +566,7 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                         search_in_path(
                             our_id,
                             &WORKER_ID,
-                            workspace
+                            workspace_folders
                                 .iter()
                                 .flat_map(|w| ignore::Walk::new(w).flatten())
                                 .chain(
@@ -506,11 +607,16 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                 position,
             } => {
                 let proxy_rpc = self.proxy_rpc.clone();
-                self.catalog_rpc.hover(&path, position, move |_, result| {
-                    let result = result.map(|hover| ProxyResponse::HoverResponse {
-                        request_id,
-                        hover,
-                    });
+                self.catalog_rpc.hover(&path, position, move |hover| {
+                    let result = hover
+                        .map(|hover| ProxyResponse::HoverResponse {
+                            request_id,
+                            hover,
+                        })
+                        .ok_or_else(|| RpcError {
+                            code: 0,
+                            message: "no hover".to_string(),
+                        });
                     proxy_rpc.handle_response(id, result);
                 });
             }
@@ -539,6 +645,33 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                     }
                 }
             }
+            GitFileHistory { path } => {
+                let proxy_rpc = self.proxy_rpc.clone();
+                // Walking the history can take a while in large repositories
+                thread::spawn(move || {
+                    let result = git_file_history(&path)
+                        .map(|commits| ProxyResponse::GitFileHistoryResponse {
+                            commits,
+                        })
+                        .map_err(|e| RpcError {
+                            code: 0,
+                            message: e.to_string(),
+                        });
+                    proxy_rpc.handle_response(id, result);
+                });
+            }
+            GitFileAtCommit { path, commit } => {
+                let result = git_file_at_commit(&path, &commit)
+                    .map(|content| ProxyResponse::BufferHeadResponse {
+                        version: commit,
+                        content,
+                    })
+                    .map_err(|e| RpcError {
+                        code: 0,
+                        message: e.to_string(),
+                    });
+                self.respond_rpc(id, result);
+            }
             GetDefinition {
                 request_id,
                 path,
@@ -697,11 +830,11 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                     &path,
                     position,
                     diagnostics,
-                    move |plugin_id, result| {
-                        let result = result.map(|resp| {
-                            ProxyResponse::GetCodeActionsResponse { plugin_id, resp }
-                        });
-                        proxy_rpc.handle_response(id, result);
+                    move |actions| {
+                        proxy_rpc.handle_response(
+                            id,
+                            Ok(ProxyResponse::GetCodeActionsResponse { actions }),
+                        );
                     },
                 );
             }
@@ -714,6 +847,54 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                         proxy_rpc.handle_response(id, result);
                     });
             }
+            GetDocumentLinks { path } => {
+                let proxy_rpc = self.proxy_rpc.clone();
+                self.catalog_rpc
+                    .get_document_links(&path, move |_, result| {
+                        let result =
+                            result.map(|links| ProxyResponse::GetDocumentLinks {
+                                links: links.unwrap_or_default(),
+                            });
+                        proxy_rpc.handle_response(id, result);
+                    });
+            }
+            GetLinkedEditingRanges { path, position } => {
+                let proxy_rpc = self.proxy_rpc.clone();
+                self.catalog_rpc.get_linked_editing_ranges(
+                    &path,
+                    position,
+                    move |_, result| {
+                        let result = result.map(|ranges| {
+                            ProxyResponse::GetLinkedEditingRanges { ranges }
+                        });
+                        proxy_rpc.handle_response(id, result);
+                    },
+                );
+            }
+            GetDocumentColors { path } => {
+                let proxy_rpc = self.proxy_rpc.clone();
+                self.catalog_rpc
+                    .get_document_colors(&path, move |_, result| {
+                        let result = result.map(|colors| {
+                            ProxyResponse::GetDocumentColors { colors }
+                        });
+                        proxy_rpc.handle_response(id, result);
+                    });
+            }
+            GetColorPresentations { path, color, range } => {
+                let proxy_rpc = self.proxy_rpc.clone();
+                self.catalog_rpc.get_color_presentations(
+                    &path,
+                    color,
+                    range,
+                    move |_, result| {
+                        let result = result.map(|presentations| {
+                            ProxyResponse::GetColorPresentations { presentations }
+                        });
+                        proxy_rpc.handle_response(id, result);
+                    },
+                );
+            }
             GetWorkspaceSymbols { query } => {
                 let proxy_rpc = self.proxy_rpc.clone();
                 self.catalog_rpc
@@ -764,10 +945,11 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                 );
             }
             GetFiles { .. } => {
-                let workspace = self.workspace.clone();
+                let workspace_folders = self.workspace_folders.clone();
                 let proxy_rpc = self.proxy_rpc.clone();
                 thread::spawn(move || {
-                    let result = if let Some(workspace) = workspace {
+                    let mut items = Vec::new();
+                    for workspace in workspace_folders {
                         let git_folder =
                             ignore::overrides::OverrideBuilder::new(&workspace)
                                 .add("!.git/")
@@ -788,7 +970,6 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                                 .build(),
                         };
 
-                        let mut items = Vec::new();
                         for path in walker.flatten() {
                             if let Some(file_type) = path.file_type() {
                                 if file_type.is_file() {
@@ -796,11 +977,11 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                                 }
                             }
                         }
-                        Ok(ProxyResponse::GetFilesResponse { items })
-                    } else {
-                        Ok(ProxyResponse::GetFilesResponse { items: Vec::new() })
-                    };
-                    proxy_rpc.handle_response(id, result);
+                    }
+                    proxy_rpc.handle_response(
+                        id,
+                        Ok(ProxyResponse::GetFilesResponse { items }),
+                    );
                 });
             }
             GetOpenFilesContent {} => {
@@ -858,8 +1039,10 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                 let result = buffer
                     .save(rev, create_parents)
                     .map(|_r| {
-                        self.catalog_rpc
-                            .did_save_text_document(&path, buffer.rope.clone());
+                        if buffer.is_synced() {
+                            self.catalog_rpc
+                                .did_save_text_document(&path, buffer.rope.clone());
+                        }
                         ProxyResponse::SaveResponse {}
                     })
                     .map_err(|e| RpcError {
@@ -868,6 +1051,69 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                     });
                 self.respond_rpc(id, result);
             }
+            SaveWithEncoding {
+                rev,
+                path,
+                encoding,
+            } => {
+                let result = FileEncoding::from_label(&encoding)
+                    .ok_or_else(|| anyhow!("unknown encoding {encoding}"))
+                    .and_then(|encoding| {
+                        let buffer = self
+                            .buffers
+                            .get_mut(&path)
+                            .ok_or_else(|| anyhow!("buffer not found"))?;
+                        let old_encoding = buffer.encoding;
+                        buffer.encoding = encoding;
+                        if let Err(err) = buffer.save(rev, false) {
+                            buffer.encoding = old_encoding;
+                            return Err(err);
+                        }
+                        if buffer.is_synced() {
+                            self.catalog_rpc
+                                .did_save_text_document(&path, buffer.rope.clone());
+                        }
+                        Ok(ProxyResponse::SaveResponse {})
+                    })
+                    .map_err(|e| RpcError {
+                        code: 0,
+                        message: e.to_string(),
+                    });
+                self.respond_rpc(id, result);
+            }
+            SaveBytes { rev, path, bytes } => {
+                let result = self
+                    .buffers
+                    .get_mut(&path)
+                    .ok_or_else(|| anyhow!("buffer not found"))
+                    .and_then(|buffer| buffer.save_bytes(rev, &bytes))
+                    .map(|_| ProxyResponse::SaveResponse {})
+                    .map_err(|e| RpcError {
+                        code: 0,
+                        message: e.to_string(),
+                    });
+                self.respond_rpc(id, result);
+            }
+            ReopenWithEncoding { path, encoding } => {
+                let result = FileEncoding::from_label(&encoding)
+                    .ok_or_else(|| anyhow!("unknown encoding {encoding}"))
+                    .and_then(|encoding| {
+                        let buffer = self
+                            .buffers
+                            .get_mut(&path)
+                            .ok_or_else(|| anyhow!("buffer not found"))?;
+                        let (content, lossy) =
+                            load_file_with_encoding_checked(&path, encoding)?;
+                        buffer.encoding = encoding;
+                        buffer.lossy = buffer.large && lossy;
+                        Ok(ProxyResponse::ReopenWithEncodingResponse { content })
+                    })
+                    .map_err(|e| RpcError {
+                        code: 0,
+                        message: e.to_string(),
+                    });
+                self.respond_rpc(id, result);
+            }
             SaveBufferAs {
                 buffer_id,
                 path,
@@ -896,9 +1142,12 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                         std::fs::OpenOptions::new()
                             .write(true)
                             .create_new(true)
-                            .open(path)
+                            .open(&path)
+                    })
+                    .map(|_| {
+                        self.catalog_rpc.did_create_files(&path);
+                        ProxyResponse::Success {}
                     })
-                    .map(|_| ProxyResponse::Success {})
                     .map_err(|e| RpcError {
                         code: 0,
                         message: e.to_string(),
@@ -906,8 +1155,11 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                 self.respond_rpc(id, result);
             }
             CreateDirectory { path } => {
-                let result = std::fs::create_dir_all(path)
-                    .map(|_| ProxyResponse::Success {})
+                let result = std::fs::create_dir_all(&path)
+                    .map(|_| {
+                        self.catalog_rpc.did_create_files(&path);
+                        ProxyResponse::Success {}
+                    })
                     .map_err(|e| RpcError {
                         code: 0,
                         message: e.to_string(),
@@ -915,8 +1167,11 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                 self.respond_rpc(id, result);
             }
             TrashPath { path } => {
-                let result = trash::delete(path)
-                    .map(|_| ProxyResponse::Success {})
+                let result = trash::delete(&path)
+                    .map(|_| {
+                        self.catalog_rpc.did_delete_files(&path);
+                        ProxyResponse::Success {}
+                    })
                     .map_err(|e| RpcError {
                         code: 0,
                         message: e.to_string(),
@@ -945,8 +1200,11 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                             return;
                         }
                     }
-                    std::fs::copy(existing_path, new_path)
-                        .map(|_| ProxyResponse::Success {})
+                    std::fs::copy(existing_path, &new_path)
+                        .map(|_| {
+                            self.catalog_rpc.did_create_files(&new_path);
+                            ProxyResponse::Success {}
+                        })
                         .map_err(|e| RpcError {
                             code: 0,
                             message: e.to_string(),
@@ -986,6 +1244,7 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                 let result = result
                     .map(|_| {
                         let to = to.canonicalize().unwrap_or(to);
+                        self.catalog_rpc.did_rename_files(&from, &to);
 
                         let (is_dir, is_file) = to
                             .metadata()
@@ -1033,6 +1292,84 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
 
                 self.respond_rpc(id, result);
             }
+            CopyPaths { paths, to } => {
+                let mut copied = Vec::new();
+                let mut error = None;
+                for from in paths {
+                    let Some(name) = from.file_name() else {
+                        continue;
+                    };
+                    if is_real_dir(&from) && to.starts_with(&from) {
+                        error = Some(format!(
+                            "Can't copy {} into itself",
+                            from.display()
+                        ));
+                        break;
+                    }
+                    let dest = free_copy_path(&to, name);
+                    if let Err(e) = copy_path(&from, &dest) {
+                        error = Some(e.to_string());
+                        break;
+                    }
+                    self.catalog_rpc.did_create_files(&dest);
+                    copied.push((from, dest));
+                }
+
+                // The paths copied before an error are still reported, so
+                // that they can be undone.
+                self.respond_rpc(
+                    id,
+                    Ok(ProxyResponse::PathsResponse {
+                        paths: copied,
+                        error,
+                    }),
+                );
+            }
+            MovePaths { paths, to } => {
+                let moves: Vec<(PathBuf, PathBuf)> = paths
+                    .into_iter()
+                    .filter_map(|from| {
+                        let dest = to.join(from.file_name()?);
+                        (dest != from).then_some((from, dest))
+                    })
+                    .collect();
+
+                // Check every move first, so that a conflict doesn't leave the
+                // paths half moved.
+                if let Err(message) = check_moves(&moves, &to) {
+                    self.respond_rpc(id, Err(RpcError { code: 0, message }));
+                    return;
+                }
+
+                let mut moved = Vec::new();
+                let mut error = None;
+                for (from, dest) in moves {
+                    if let Err(e) = move_path(&from, &dest) {
+                        error = Some(e.to_string());
+                        break;
+                    }
+                    self.catalog_rpc.did_rename_files(&from, &dest);
+                    self.move_buffers(&from, &dest);
+                    moved.push((from, dest));
+                }
+
+                self.respond_rpc(
+                    id,
+                    Ok(ProxyResponse::PathsResponse {
+                        paths: moved,
+                        error,
+                    }),
+                );
+            }
+            WillRenamePath { from, to } => {
+                let proxy_rpc = self.proxy_rpc.clone();
+                self.catalog_rpc.will_rename_files(&from, &to, move |edit| {
+                    proxy_rpc.handle_response(
+                        id,
+                        Ok(ProxyResponse::WillRenamePath { edit }),
+                    );
+                });
+            }
             TestCreateAtPath { path } => {
                 // This performs a best effort test to see if an attempt to create an item at
                 // `path` or rename an item to `path` will succeed.
@@ -1214,6 +1551,8 @@ pub fn new(core_rpc: CoreRpcHandler, proxy_rpc: ProxyRpcHandler) -> Self {
 
         Self {
             workspace: None,
+            workspace_folders: Vec::new(),
+            watched_folders: Arc::new(Mutex::new(Vec::new())),
             proxy_rpc,
             core_rpc,
             catalog_rpc: plugin_rpc,
@@ -1234,12 +1573,32 @@ fn get_buffer_or_insert(&mut self, path: PathBuf) -> &mut Buffer {
             .entry(path.clone())
             .or_insert(Buffer::new(BufferId::next(), path))
     }
+
+    /// Point the open buffers of `from`, or of the files in it when it is a
+    /// directory, at their paths under `to`.
+    fn move_buffers(&mut self, from: &Path, to: &Path) {
+        let moved: Vec<PathBuf> = self
+            .buffers
+            .keys()
+            .filter(|path| path.starts_with(from))
+            .cloned()
+            .collect();
+        for path in moved {
+            if let Some(mut buffer) = self.buffers.remove(&path) {
+                buffer.path = match path.strip_prefix(from) {
+                    Ok(suffix) if !suffix.as_os_str().is_empty() => to.join(suffix),
+                    _ => to.to_path_buf(),
+                };
+                self.buffers.insert(buffer.path.clone(), buffer);
+            }
+        }
+    }
 }
 
 struct FileWatchNotifier {
     core_rpc: CoreRpcHandler,
     proxy_rpc: ProxyRpcHandler,
-    workspace: Option<PathBuf>,
+    workspace_folders: Arc<Mutex<Vec<PathBuf>>>,
     workspace_fs_change_handler: Arc<Mutex<Option<Sender<bool>>>>,
     last_diff: Arc<Mutex<DiffInfo>>,
 }
@@ -1252,23 +1611,24 @@ fn notify(&self, events: Vec<(WatchToken, notify::Event)>) {
 
 impl FileWatchNotifier {
     fn new(
-        workspace: Option<PathBuf>,
+        workspace_folders: Arc<Mutex<Vec<PathBuf>>>,
         core_rpc: CoreRpcHandler,
         proxy_rpc: ProxyRpcHandler,
     ) -> Self {
         let notifier = Self {
-            workspace,
+            workspace_folders,
             core_rpc,
             proxy_rpc,
             workspace_fs_change_handler: Arc::new(Mutex::new(None)),
             last_diff: Arc::new(Mutex::new(DiffInfo::default())),
         };
 
-        if let Some(workspace) = notifier.workspace.clone() {
+        let workspace_folders = notifier.workspace_folders.lock().clone();
+        if !workspace_folders.is_empty() {
             let core_rpc = notifier.core_rpc.clone();
             let last_diff = notifier.last_diff.clone();
             thread::spawn(move || {
-                if let Some(diff) = git_diff_new(&workspace) {
+                if let Some(diff) = git_diff_folders(&workspace_folders) {
                     core_rpc.diff_info(diff.clone());
                     *last_diff.lock() = diff;
                 }
@@ -1337,7 +1697,7 @@ fn handle_workspace_fs_event(&self, event: notify::Event) {
 
         let local_handler = self.workspace_fs_change_handler.clone();
         let core_rpc = self.core_rpc.clone();
-        let workspace = self.workspace.clone().unwrap();
+        let workspace_folders = self.workspace_folders.clone();
         let last_diff = self.last_diff.clone();
         thread::spawn(move || {
             thread::sleep(Duration::from_millis(500));
@@ -1356,7 +1716,8 @@ fn handle_workspace_fs_event(&self, event: notify::Event) {
             if explorer_change {
                 core_rpc.workspace_file_change();
             }
-            if let Some(diff) = git_diff_new(&workspace) {
+            let workspace_folders = workspace_folders.lock().clone();
+            if let Some(diff) = git_diff_folders(&workspace_folders) {
                 let mut last_diff = last_diff.lock();
                 if diff != *last_diff {
                     core_rpc.diff_info(diff.clone());
@@ -1377,6 +1738,150 @@ pub struct DiffHunk {
     pub header: String,
 }
 
+/// Whether the path is a directory itself, rather than a file or a symlink,
+/// even one to a directory.
+fn is_real_dir(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|metadata| metadata.is_dir())
+}
+
+/// Copy a file, or a directory with everything in it. Symlinks are copied as
+/// symlinks to the same target, rather than what they point to.
+fn copy_path(from: &Path, to: &Path) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(from)?;
+    if metadata.is_symlink() {
+        copy_symlink(from, to)
+    } else if metadata.is_dir() {
+        fs::create_dir_all(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_path(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else {
+        fs::copy(from, to).map(|_| ())
+    }
+}
+
+fn copy_symlink(from: &Path, to: &Path) -> io::Result<()> {
+    let target = fs::read_link(from)?;
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(target, to)
+    }
+    #[cfg(windows)]
+    {
+        if from.is_dir() {
+            std::os::windows::fs::symlink_dir(target, to)
+        } else {
+            std::os::windows::fs::symlink_file(target, to)
+        }
+    }
+}
+
+/// Check that the paths can be moved to their destination in `to`, so that
+/// none is moved when one of them can't be.
+fn check_moves(moves: &[(PathBuf, PathBuf)], to: &Path) -> Result<(), String> {
+    let mut dests = HashSet::new();
+    moves.iter().try_for_each(|(from, dest)| {
+        if to.starts_with(from) {
+            Err(format!("Can't move {} into itself", from.display()))
+        } else if dest.symlink_metadata().is_ok() || !dests.insert(dest) {
+            Err(format!("{} already exists", dest.display()))
+        } else {
+            Ok(())
+        }
+    })
+}
+
+/// Move a file or directory, copying it when it can't be renamed because
+/// it's moved to another file system.
+fn move_path(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Ok(()) => return Ok(()),
+        Err(e) if is_cross_device_error(&e) => {}
+        Err(e) => return Err(e),
+    }
+    copy_path(from, to)?;
+    if is_real_dir(from) {
+        fs::remove_dir_all(from)
+    } else {
+        fs::remove_file(from)
+    }
+}
+
+/// Whether renaming failed because the paths are on different file systems.
+fn is_cross_device_error(e: &io::Error) -> bool {
+    #[cfg(unix)]
+    {
+        e.raw_os_error() == Some(libc::EXDEV)
+    }
+    #[cfg(windows)]
+    {
+        // ERROR_NOT_SAME_DEVICE
+        e.raw_os_error() == Some(17)
+    }
+}
+
+/// The path in `dir` for a copy of `name` that isn't taken yet, like
+/// `main.rs`, `main copy.rs` or `main copy 2.rs`.
+fn free_copy_path(dir: &Path, name: &OsStr) -> PathBuf {
+    let path = dir.join(name);
+    if path.symlink_metadata().is_err() {
+        return path;
+    }
+
+    let name = Path::new(name);
+    let stem = name.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = name
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+    (1..)
+        .map(|i| {
+            if i == 1 {
+                dir.join(format!("{stem} copy{extension}"))
+            } else {
+                dir.join(format!("{stem} copy {i}{extension}"))
+            }
+        })
+        .find(|path| path.symlink_metadata().is_err())
+        .unwrap()
+}
+
+/// The repository a file or a folder is in, found from the closest folder of
+/// it that still exists, so that the one of a deleted file is found too.
+fn discover_repository(path: &Path) -> Result<Repository> {
+    let folder = path.ancestors().find(|p| p.is_dir()).unwrap_or(path);
+    Ok(Repository::discover(folder)?)
+}
+
+fn repository_workdir(repo: &Repository) -> Result<&Path> {
+    repo.workdir()
+        .ok_or_else(|| anyhow!("no working directory"))
+}
+
+/// The items grouped by the repository their path is in, leaving out the
+/// ones that aren't in any.
+fn group_by_repository<T>(
+    items: Vec<T>,
+    path: impl Fn(&T) -> &Path,
+) -> Vec<(Repository, Vec<T>)> {
+    let mut groups: Vec<(Repository, Vec<T>)> = Vec::new();
+    for item in items {
+        let Ok(repo) = discover_repository(path(&item)) else {
+            continue;
+        };
+        match groups
+            .iter_mut()
+            .find(|(group, _)| group.workdir() == repo.workdir())
+        {
+            Some((_, items)) => items.push(item),
+            None => groups.push((repo, vec![item])),
+        }
+    }
+    groups
+}
+
 fn git_init(workspace_path: &Path) -> Result<()> {
     if Repository::discover(workspace_path).is_err() {
         Repository::init(workspace_path)?;
@@ -1384,12 +1889,8 @@ fn git_init(workspace_path: &Path) -> Result<()> {
     Ok(())
 }
 
-fn git_commit(
-    workspace_path: &Path,
-    message: &str,
-    diffs: Vec<FileDiff>,
-) -> Result<()> {
-    let repo = Repository::discover(workspace_path)?;
+fn git_commit(repo: &Repository, message: &str, diffs: Vec<FileDiff>) -> Result<()> {
+    let workspace_path = repository_workdir(repo)?;
     let mut index = repo.index()?;
     for diff in diffs {
         match diff {
@@ -1439,8 +1940,7 @@ fn git_commit(
     }
 }
 
-fn git_checkout(workspace_path: &Path, reference: &str) -> Result<()> {
-    let repo = Repository::discover(workspace_path)?;
+fn git_checkout(repo: &Repository, reference: &str) -> Result<()> {
     let (object, reference) = repo.revparse_ext(reference)?;
     repo.checkout_tree(&object, None)?;
     repo.set_head(reference.unwrap().name().unwrap())?;
@@ -1448,10 +1948,10 @@ fn git_checkout(workspace_path: &Path, reference: &str) -> Result<()> {
 }
 
 fn git_discard_files_changes<'a>(
-    workspace_path: &Path,
+    repo: &Repository,
     files: impl Iterator<Item = &'a Path>,
 ) -> Result<()> {
-    let repo = Repository::discover(workspace_path)?;
+    let workspace_path = repository_workdir(repo)?;
 
     let mut checkout_b = CheckoutBuilder::new();
     checkout_b.update_only(false).force();
@@ -1477,8 +1977,7 @@ fn git_discard_files_changes<'a>(
     Ok(())
 }
 
-fn git_discard_workspace_changes(workspace_path: &Path) -> Result<()> {
-    let repo = Repository::discover(workspace_path)?;
+fn git_discard_workspace_changes(repo: &Repository) -> Result<()> {
     let mut checkout_b = CheckoutBuilder::new();
     checkout_b.force();
 
@@ -1511,8 +2010,32 @@ fn git_delta_format(
     }
 }
 
+/// The git changes of every root folder, along with the branches and tags
+/// of the first one that is in a repository.
+fn git_diff_folders(folders: &[PathBuf]) -> Option<DiffInfo> {
+    let mut info: Option<DiffInfo> = None;
+    for diff in folders.iter().filter_map(|folder| git_diff_new(folder)) {
+        match info.as_mut() {
+            Some(info) => {
+                // Folders in the same repository have the same changes
+                for file_diff in diff.diffs {
+                    if !info.diffs.contains(&file_diff) {
+                        info.diffs.push(file_diff);
+                    }
+                }
+            }
+            None => info = Some(diff),
+        }
+    }
+    info
+}
+
 fn git_diff_new(workspace_path: &Path) -> Option<DiffInfo> {
     let repo = Repository::discover(workspace_path).ok()?;
+    // The paths of the changes are relative to the top of the repository,
+    // which the folder can be in
+    let workspace_path = repo.workdir()?.to_path_buf();
+    let workspace_path = workspace_path.as_path();
     let name = match repo.head() {
         Ok(head) => head.shorthand()?.to_string(),
         _ => "(No branch)".to_owned(),
@@ -1613,11 +2136,12 @@ fn git_diff_new(workspace_path: &Path) -> Option<DiffInfo> {
     })
 }
 
-fn file_get_head(workspace_path: &Path, path: &Path) -> Result<(String, String)> {
-    let repo = Repository::discover(workspace_path)?;
+fn file_get_head(path: &Path) -> Result<(String, String)> {
+    let repo = discover_repository(path)?;
     let head = repo.head()?;
     let tree = head.peel_to_tree()?;
-    let tree_entry = tree.get_path(path.strip_prefix(workspace_path)?)?;
+    let tree_entry =
+        tree.get_path(path.strip_prefix(repository_workdir(&repo)?)?)?;
     let blob = repo.find_blob(tree_entry.id())?;
     let id = blob.id().to_string();
     let content = std::str::from_utf8(blob.content())
@@ -1626,6 +2150,64 @@ fn file_get_head(workspace_path: &Path, path: &Path) -> Result<(String, String)>
     Ok((id, content))
 }
 
+/// The most commits listed in the history of a file
+const GIT_FILE_HISTORY_LIMIT: usize = 100;
+
+/// The commits reachable from `HEAD` that changed the file, newest first.
+fn git_file_history(path: &Path) -> Result<Vec<GitFileCommit>> {
+    let repo = Repository::discover(path.parent().unwrap_or(path))?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("no working directory"))?;
+    let relative = path.strip_prefix(workdir)?;
+    let entry_id = |commit: &git2::Commit| {
+        commit
+            .tree()
+            .ok()
+            .and_then(|tree| tree.get_path(relative).ok())
+            .map(|entry| entry.id())
+    };
+
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push_head()?;
+    revwalk.set_sorting(git2::Sort::TIME)?;
+
+    let mut commits = Vec::new();
+    for oid in revwalk {
+        let commit = repo.find_commit(oid?)?;
+        let Some(id) = entry_id(&commit) else {
+            continue;
+        };
+        // The file changed in the commit when it's different in every parent
+        if commit.parents().all(|parent| entry_id(&parent) != Some(id)) {
+            commits.push(GitFileCommit {
+                id: commit.id().to_string(),
+                summary: commit.summary().unwrap_or_default().to_string(),
+                author: commit.author().name().unwrap_or_default().to_string(),
+                time: commit.time().seconds(),
+            });
+            if commits.len() >= GIT_FILE_HISTORY_LIMIT {
+                break;
+            }
+        }
+    }
+    Ok(commits)
+}
+
+fn git_file_at_commit(path: &Path, commit: &str) -> Result<String> {
+    let repo = Repository::discover(path.parent().unwrap_or(path))?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("no working directory"))?;
+    let commit = repo.revparse_single(commit)?.peel_to_commit()?;
+    let tree_entry = commit.tree()?.get_path(path.strip_prefix(workdir)?)?;
+    let blob = repo.find_blob(tree_entry.id())?;
+    let content = std::str::from_utf8(blob.content())
+        .with_context(|| "content bytes to string")?
+        .to_string();
+    Ok(content)
+}
+
 fn git_get_remote_file_url(workspace_path: &Path, file: &Path) -> Result<String> {
     let repo = Repository::discover(workspace_path)?;
     let head = repo.head()?;
@@ -1756,3 +2338,89 @@ fn search_in_path(
 
     Ok(ProxyResponse::GlobalSearchResponse { matches })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_free_copy_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        let name = OsStr::new("main.rs");
+        assert_eq!(free_copy_path(dir, name), dir.join("main.rs"));
+
+        fs::write(dir.join("main.rs"), "").unwrap();
+        assert_eq!(free_copy_path(dir, name), dir.join("main copy.rs"));
+        fs::write(dir.join("main copy.rs"), "").unwrap();
+        assert_eq!(free_copy_path(dir, name), dir.join("main copy 2.rs"));
+
+        fs::create_dir(dir.join("src")).unwrap();
+        assert_eq!(free_copy_path(dir, OsStr::new("src")), dir.join("src copy"));
+    }
+
+    #[test]
+    fn test_move_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        fs::create_dir_all(dir.join("src/bin")).unwrap();
+        fs::write(dir.join("src/bin/main.rs"), "fn main() {}").unwrap();
+        fs::create_dir(dir.join("dest")).unwrap();
+
+        move_path(&dir.join("src"), &dir.join("dest/src")).unwrap();
+        assert!(!dir.join("src").exists());
+        assert_eq!(
+            fs::read_to_string(dir.join("dest/src/bin/main.rs")).unwrap(),
+            "fn main() {}"
+        );
+    }
+
+    #[test]
+    fn test_check_moves() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        fs::create_dir_all(dir.join("a/b")).unwrap();
+        fs::create_dir(dir.join("dest")).unwrap();
+        fs::write(dir.join("dest/taken.rs"), "").unwrap();
+        let to = dir.join("dest");
+
+        let moves = [(dir.join("lib.rs"), to.join("lib.rs"))];
+        assert_eq!(check_moves(&moves, &to), Ok(()));
+
+        let moves = [
+            (dir.join("lib.rs"), to.join("lib.rs")),
+            (dir.join("taken.rs"), to.join("taken.rs")),
+        ];
+        assert!(check_moves(&moves, &to).is_err());
+
+        let moves = [
+            (dir.join("a/lib.rs"), to.join("lib.rs")),
+            (dir.join("b/lib.rs"), to.join("lib.rs")),
+        ];
+        assert!(check_moves(&moves, &to).is_err());
+
+        let to = dir.join("a/b");
+        let moves = [(dir.join("a"), to.join("a"))];
+        assert!(check_moves(&moves, &to).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_copy_symlink() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        fs::create_dir_all(dir.join("src/target")).unwrap();
+        fs::write(dir.join("src/target/main.rs"), "").unwrap();
+        std::os::unix::fs::symlink("target", dir.join("src/link")).unwrap();
+
+        copy_path(&dir.join("src"), &dir.join("copy")).unwrap();
+        assert!(fs::symlink_metadata(dir.join("copy/link"))
+            .unwrap()
+            .is_symlink());
+        assert_eq!(
+            fs::read_link(dir.join("copy/link")).unwrap(),
+            Path::new("target")
+        );
+        assert!(dir.join("copy/target/main.rs").exists());
+    }
+}
@@ -18,9 +18,15 @@
 };
 use lapce_xi_rope::{Rope, RopeDelta};
 use lsp_types::{
-    notification::DidOpenTextDocument, request::Request, DidOpenTextDocumentParams,
-    MessageType, SemanticTokens, ShowMessageParams, TextDocumentIdentifier,
-    TextDocumentItem, VersionedTextDocumentIdentifier,
+    notification::{DidChangeWorkspaceFolders, DidOpenTextDocument},
+    request::{DocumentDiagnosticRequest, Request, WorkspaceDiagnosticRequest},
+    DidChangeWorkspaceFoldersParams, DidOpenTextDocumentParams,
+    DocumentDiagnosticParams, DocumentDiagnosticReport,
+    DocumentDiagnosticReportKind, DocumentDiagnosticReportResult, MessageType,
+    PreviousResultId, PublishDiagnosticsParams, SemanticTokens, ShowMessageParams,
+    TextDocumentIdentifier, TextDocumentItem, Url, VersionedTextDocumentIdentifier,
+    WorkspaceDiagnosticParams, WorkspaceDiagnosticReportResult,
+    WorkspaceDocumentDiagnosticReport, WorkspaceFoldersChangeEvent,
 };
 use parking_lot::Mutex;
 use psp_types::Notification;
@@ -30,7 +36,7 @@
     dap::{DapClient, DapRpcHandler, DebuggerData},
     psp::{ClonableCallback, PluginServerRpc, PluginServerRpcHandler, RpcCallback},
     wasi::{load_all_volts, start_volt},
-    PluginCatalogNotification, PluginCatalogRpcHandler,
+    workspace_folder, PluginCatalogNotification, PluginCatalogRpcHandler,
 };
 use crate::plugin::{
     install_volt, psp::PluginHandlerNotification, wasi::enable_volt,
@@ -38,6 +44,8 @@
 
 pub struct PluginCatalog {
     workspace: Option<PathBuf>,
+    /// Every root folder of the workspace, `workspace` being the first
+    workspace_folders: Vec<PathBuf>,
     plugin_rpc: PluginCatalogRpcHandler,
     plugins: HashMap<PluginId, PluginServerRpcHandler>,
     daps: HashMap<DapId, DapRpcHandler>,
@@ -45,18 +53,30 @@ pub struct PluginCatalog {
     plugin_configurations: HashMap<String, HashMap<String, serde_json::Value>>,
     unactivated_volts: HashMap<VoltID, VoltMetadata>,
     open_files: HashMap<PathBuf, String>,
+    /// Language id -> the volts whose language servers are preferred for requests
+    /// with a single answer
+    language_server_priority: HashMap<String, Vec<String>>,
+    /// The result ids of the last pulled diagnostic reports, per plugin and document
+    diagnostic_result_ids: Arc<Mutex<HashMap<(PluginId, Url), String>>>,
 }
 
 impl PluginCatalog {
     pub fn new(
         workspace: Option<PathBuf>,
+        workspace_folders: Vec<PathBuf>,
         disabled_volts: Vec<VoltID>,
         extra_plugin_paths: Vec<PathBuf>,
         plugin_configurations: HashMap<String, HashMap<String, serde_json::Value>>,
+        language_server_priority: HashMap<String, Vec<String>>,
         plugin_rpc: PluginCatalogRpcHandler,
     ) -> Self {
+        plugin_rpc
+            .workspace_folders
+            .lock()
+            .clone_from(&workspace_folders);
         let plugin = Self {
             workspace,
+            workspace_folders,
             plugin_rpc: plugin_rpc.clone(),
             plugin_configurations,
             plugins: HashMap::new(),
@@ -64,6 +84,8 @@ pub fn new(
             debuggers: HashMap::new(),
             unactivated_volts: HashMap::new(),
             open_files: HashMap::new(),
+            language_server_priority,
+            diagnostic_result_ids: Arc::new(Mutex::new(HashMap::new())),
         };
 
         thread::spawn(move || {
@@ -145,6 +167,45 @@ pub fn handle_server_request(
         }
     }
 
+    pub fn handle_server_request_by_priority(
+        &mut self,
+        method: Cow<'static, str>,
+        params: Value,
+        language_id: Option<String>,
+        path: Option<PathBuf>,
+        f: Box<dyn ClonableCallback<Value, RpcError>>,
+    ) {
+        let priority = language_id
+            .as_ref()
+            .and_then(|language_id| self.language_server_priority.get(language_id));
+        let rank = |plugin: &PluginServerRpcHandler| {
+            priority
+                .and_then(|priority| {
+                    priority.iter().position(|volt| {
+                        *volt == plugin.volt_id.name
+                            || *volt == plugin.volt_id.to_string()
+                    })
+                })
+                .unwrap_or(usize::MAX)
+        };
+
+        let mut plugins: Vec<PluginServerRpcHandler> =
+            self.plugins.values().cloned().collect();
+        if plugins.is_empty() {
+            f(
+                lapce_rpc::plugin::PluginId(0),
+                Err(RpcError {
+                    code: 0,
+                    message: "no available plugin could make a callback, because the plugins list is empty".to_string(),
+                }),
+            );
+            return;
+        }
+        plugins.sort_by_key(|plugin| (rank(plugin), plugin.plugin_id.0));
+
+        server_request_in_order(plugins, method, params, language_id, path, f);
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub fn handle_server_notification(
         &mut self,
@@ -239,7 +300,7 @@ fn check_unactivated_volts(&mut self) {
                     return Some(id.clone());
                 }
 
-                if let Some(workspace) = self.workspace.as_ref() {
+                for workspace in &self.workspace_folders {
                     if let Some(globs) = meta
                         .activation
                         .as_ref()
@@ -322,6 +383,7 @@ pub fn handle_did_open_text_document(&mut self, document: TextDocumentItem) {
                 true,
             );
         }
+        self.pull_document_diagnostics(None, document.uri, document.language_id);
     }
 
     pub fn handle_did_save_text_document(
@@ -360,6 +422,196 @@ pub fn handle_did_change_text_document(
                 change: change.clone(),
             });
         }
+        self.pull_document_diagnostics(None, document.uri, language_id);
+    }
+
+    /// Pull the diagnostics of a document from the plugins which support
+    /// `textDocument/diagnostic`, passing along the result id of their last report.
+    fn pull_document_diagnostics(
+        &self,
+        plugin_id: Option<PluginId>,
+        uri: Url,
+        language_id: String,
+    ) {
+        let path = uri.to_file_path().ok();
+        for (id, plugin) in self.plugins.iter() {
+            if plugin_id.is_some_and(|plugin_id| plugin_id != *id) {
+                continue;
+            }
+
+            let plugin_id = *id;
+            let params = DocumentDiagnosticParams {
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+                identifier: None,
+                previous_result_id: self
+                    .diagnostic_result_ids
+                    .lock()
+                    .get(&(plugin_id, uri.clone()))
+                    .cloned(),
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            };
+            let uri = uri.clone();
+            let catalog_rpc = self.plugin_rpc.clone();
+            let result_ids = self.diagnostic_result_ids.clone();
+            plugin.server_request_async(
+                DocumentDiagnosticRequest::METHOD,
+                params,
+                Some(language_id.clone()),
+                path.clone(),
+                true,
+                move |result: Result<Value, RpcError>| {
+                    let Ok(value) = result else {
+                        return;
+                    };
+                    let (report, related_documents) =
+                        match serde_json::from_value(value) {
+                            Ok(DocumentDiagnosticReportResult::Report(
+                                DocumentDiagnosticReport::Full(report),
+                            )) => (
+                                Some(DocumentDiagnosticReportKind::Full(
+                                    report.full_document_diagnostic_report,
+                                )),
+                                report.related_documents,
+                            ),
+                            Ok(DocumentDiagnosticReportResult::Report(
+                                DocumentDiagnosticReport::Unchanged(report),
+                            )) => (
+                                Some(DocumentDiagnosticReportKind::Unchanged(
+                                    report.unchanged_document_diagnostic_report,
+                                )),
+                                report.related_documents,
+                            ),
+                            Ok(DocumentDiagnosticReportResult::Partial(partial)) => {
+                                (None, partial.related_documents)
+                            }
+                            Err(err) => {
+                                tracing::error!("{:?}", err);
+                                return;
+                            }
+                        };
+
+                    if let Some(report) = report {
+                        publish_diagnostic_report(
+                            &catalog_rpc,
+                            &result_ids,
+                            plugin_id,
+                            uri,
+                            None,
+                            report,
+                        );
+                    }
+                    for (uri, report) in related_documents.into_iter().flatten() {
+                        publish_diagnostic_report(
+                            &catalog_rpc,
+                            &result_ids,
+                            plugin_id,
+                            uri,
+                            None,
+                            report,
+                        );
+                    }
+                },
+            );
+        }
+    }
+
+    /// Pull the diagnostics of every open document and of the workspace again
+    /// from the given plugin.
+    fn refresh_diagnostics(&self, plugin_id: PluginId) {
+        for (path, language_id) in self.open_files.iter() {
+            if let Ok(uri) = Url::from_file_path(path) {
+                self.pull_document_diagnostics(
+                    Some(plugin_id),
+                    uri,
+                    language_id.clone(),
+                );
+            }
+        }
+        self.pull_workspace_diagnostics(Some(plugin_id));
+    }
+
+    /// Pull the diagnostics of the whole workspace from the plugins which support
+    /// `workspace/diagnostic`, so that files which aren't open show up as problems too.
+    fn pull_workspace_diagnostics(&self, plugin_id: Option<PluginId>) {
+        for (id, plugin) in self.plugins.iter() {
+            if plugin_id.is_some_and(|plugin_id| plugin_id != *id) {
+                continue;
+            }
+
+            let plugin_id = *id;
+            let previous_result_ids = self
+                .diagnostic_result_ids
+                .lock()
+                .iter()
+                .filter(|((id, _), _)| *id == plugin_id)
+                .map(|((_, uri), value)| PreviousResultId {
+                    uri: uri.clone(),
+                    value: value.clone(),
+                })
+                .collect();
+            let params = WorkspaceDiagnosticParams {
+                identifier: None,
+                previous_result_ids,
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            };
+            let catalog_rpc = self.plugin_rpc.clone();
+            let result_ids = self.diagnostic_result_ids.clone();
+            plugin.server_request_async(
+                WorkspaceDiagnosticRequest::METHOD,
+                params,
+                None,
+                None,
+                true,
+                move |result: Result<Value, RpcError>| {
+                    let Ok(value) = result else {
+                        return;
+                    };
+                    let items = match serde_json::from_value(value) {
+                        Ok(WorkspaceDiagnosticReportResult::Report(report)) => {
+                            report.items
+                        }
+                        Ok(WorkspaceDiagnosticReportResult::Partial(partial)) => {
+                            partial.items
+                        }
+                        Err(err) => {
+                            tracing::error!("{:?}", err);
+                            return;
+                        }
+                    };
+
+                    for item in items {
+                        let (uri, version, report) = match item {
+                            WorkspaceDocumentDiagnosticReport::Full(report) => (
+                                report.uri,
+                                report.version,
+                                DocumentDiagnosticReportKind::Full(
+                                    report.full_document_diagnostic_report,
+                                ),
+                            ),
+                            WorkspaceDocumentDiagnosticReport::Unchanged(report) => {
+                                (
+                                    report.uri,
+                                    report.version,
+                                    DocumentDiagnosticReportKind::Unchanged(
+                                        report.unchanged_document_diagnostic_report,
+                                    ),
+                                )
+                            }
+                        };
+                        publish_diagnostic_report(
+                            &catalog_rpc,
+                            &result_ids,
+                            plugin_id,
+                            uri,
+                            version,
+                            report,
+                        );
+                    }
+                },
+            );
+        }
     }
 
     pub fn format_semantic_tokens(
@@ -492,6 +744,34 @@ pub fn handle_notification(&mut self, notification: PluginCatalogNotification) {
                 tracing::debug!("UpdatePluginConfigs {:?}", configs);
                 self.plugin_configurations = configs;
             }
+            WorkspaceFolders(folders) => {
+                let added: Vec<PathBuf> = folders
+                    .iter()
+                    .filter(|folder| !self.workspace_folders.contains(folder))
+                    .cloned()
+                    .collect();
+                let removed: Vec<PathBuf> = self
+                    .workspace_folders
+                    .iter()
+                    .filter(|folder| !folders.contains(folder))
+                    .cloned()
+                    .collect();
+                self.workspace = folders.first().cloned();
+                self.workspace_folders = folders;
+                if added.is_empty() && removed.is_empty() {
+                    return;
+                }
+                for plugin in self.plugins.values() {
+                    plugin.server_notification(
+                        DidChangeWorkspaceFolders::METHOD,
+                        workspace_folders_change(&added, &removed),
+                        None,
+                        None,
+                        true,
+                    );
+                }
+                self.check_unactivated_volts();
+            }
             PluginServerLoaded(plugin) => {
                 // TODO: check if the server has did open registered
                 match self.plugin_rpc.proxy_rpc.get_open_files_content() {
@@ -520,6 +800,7 @@ pub fn handle_notification(&mut self, notification: PluginCatalogNotification) {
                 let spawned_by = plugin.spawned_by;
 
                 self.plugins.insert(plugin.plugin_id, plugin);
+                self.refresh_diagnostics(plugin_id);
 
                 if let Some(spawned_by) = spawned_by {
                     if let Some(plugin) = self.plugins.get(&spawned_by) {
@@ -750,6 +1031,15 @@ pub fn handle_notification(&mut self, notification: PluginCatalogNotification) {
                     },
                 );
             }
+            PullWorkspaceDiagnostics(plugin_id) => {
+                self.pull_workspace_diagnostics(plugin_id);
+            }
+            UpdateLanguageServerPriority(priority) => {
+                self.language_server_priority = priority;
+            }
+            RefreshDiagnostics(plugin_id) => {
+                self.refresh_diagnostics(plugin_id);
+            }
             Shutdown => {
                 for (_, plugin) in self.plugins.iter() {
                     plugin.shutdown();
@@ -758,3 +1048,94 @@ pub fn handle_notification(&mut self, notification: PluginCatalogNotification) {
         }
     }
 }
+
+/// Publish a pulled diagnostic report to the editor, and remember its result id
+/// for the next pull.
+fn publish_diagnostic_report(
+    catalog_rpc: &PluginCatalogRpcHandler,
+    result_ids: &Mutex<HashMap<(PluginId, Url), String>>,
+    plugin_id: PluginId,
+    uri: Url,
+    version: Option<i64>,
+    report: DocumentDiagnosticReportKind,
+) {
+    match report {
+        DocumentDiagnosticReportKind::Full(report) => {
+            {
+                let mut result_ids = result_ids.lock();
+                match report.result_id {
+                    Some(result_id) => {
+                        result_ids.insert((plugin_id, uri.clone()), result_id);
+                    }
+                    None => {
+                        result_ids.remove(&(plugin_id, uri.clone()));
+                    }
+                }
+            }
+            catalog_rpc.publish_diagnostics(
+                plugin_id,
+                PublishDiagnosticsParams {
+                    uri,
+                    diagnostics: report.items,
+                    version: version.map(|v| v as i32),
+                },
+            );
+        }
+        DocumentDiagnosticReportKind::Unchanged(report) => {
+            result_ids.lock().insert((plugin_id, uri), report.result_id);
+        }
+    }
+}
+
+/// Send the request to the first plugin, moving on to the next one when it isn't
+/// capable of answering it or gives no answer.
+fn server_request_in_order(
+    mut plugins: Vec<PluginServerRpcHandler>,
+    method: Cow<'static, str>,
+    params: Value,
+    language_id: Option<String>,
+    path: Option<PathBuf>,
+    f: Box<dyn ClonableCallback<Value, RpcError>>,
+) {
+    let plugin = plugins.remove(0);
+    let plugin_id = plugin.plugin_id;
+    plugin.server_request_async(
+        method.clone(),
+        params.clone(),
+        language_id.clone(),
+        path.clone(),
+        true,
+        move |result: Result<Value, RpcError>| match result {
+            Ok(value) if !value.is_null() => f(plugin_id, Ok(value)),
+            result if plugins.is_empty() => f(plugin_id, result),
+            _ => server_request_in_order(
+                plugins,
+                method,
+                params,
+                language_id,
+                path,
+                f,
+            ),
+        },
+    );
+}
+
+/// The notification telling language servers which root folders of the
+/// workspace were added and removed.
+fn workspace_folders_change(
+    added: &[PathBuf],
+    removed: &[PathBuf],
+) -> DidChangeWorkspaceFoldersParams {
+    let folders = |paths: &[PathBuf]| {
+        paths
+            .iter()
+            .filter_map(|path| workspace_folder(path))
+            .collect()
+    };
+    DidChangeWorkspaceFoldersParams {
+        event: WorkspaceFoldersChangeEvent {
+            added: folders(added),
+            removed: folders(removed),
+        },
+    }
+}
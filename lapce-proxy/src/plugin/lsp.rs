@@ -71,6 +71,14 @@ fn method_registered(&mut self, method: &str) -> bool {
         self.host.method_registered(method)
     }
 
+    fn filter_file_operation(
+        &mut self,
+        method: &str,
+        params: Params,
+    ) -> Option<Params> {
+        self.host.filter_file_operation(method, params)
+    }
+
     fn document_supported(
         &mut self,
         lanaguage_id: Option<&str>,
@@ -366,16 +374,12 @@ fn initialize(&mut self) {
         #[allow(deprecated)]
         let params = InitializeParams {
             process_id: Some(process::id()),
-            root_uri: root_uri.clone(),
+            root_uri,
             initialization_options: self.options.clone(),
             capabilities: client_capabilities(),
             trace: Some(TraceValue::Verbose),
-            workspace_folders: root_uri.map(|uri| {
-                vec![WorkspaceFolder {
-                    name: uri.as_str().to_string(),
-                    uri,
-                }]
-            }),
+            workspace_folders: Some(self.plugin_rpc.workspace_folders())
+                .filter(|folders| !folders.is_empty()),
             client_info: Some(ClientInfo {
                 name: meta::NAME.to_owned(),
                 version: Some(meta::VERSION.to_owned()),
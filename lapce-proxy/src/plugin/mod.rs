@@ -32,35 +32,47 @@
 };
 use lapce_xi_rope::{Rope, RopeDelta};
 use lsp_types::{
+    notification::{DidCreateFiles, DidDeleteFiles, DidRenameFiles, Notification},
     request::{
         CallHierarchyIncomingCalls, CallHierarchyPrepare, CodeActionRequest,
-        CodeActionResolveRequest, CodeLensRequest, CodeLensResolve, Completion,
+        CodeActionResolveRequest, CodeLensRequest, CodeLensResolve,
+        ColorPresentationRequest, Completion, DocumentColor, DocumentLinkRequest,
         DocumentSymbolRequest, FoldingRangeRequest, Formatting, GotoDefinition,
         GotoImplementation, GotoImplementationResponse, GotoTypeDefinition,
         GotoTypeDefinitionParams, GotoTypeDefinitionResponse, HoverRequest,
-        InlayHintRequest, InlineCompletionRequest, PrepareRenameRequest, References,
-        Rename, Request, ResolveCompletionItem, SelectionRangeRequest,
-        SemanticTokensFullRequest, SignatureHelpRequest, WorkspaceSymbolRequest,
+        InlayHintRequest, InlineCompletionRequest, LinkedEditingRange,
+        PrepareRenameRequest, References, Rename, Request, ResolveCompletionItem,
+        SelectionRangeRequest, SemanticTokensFullRequest, SignatureHelpRequest,
+        WillRenameFiles, WorkspaceSymbolRequest,
     },
     CallHierarchyClientCapabilities, CallHierarchyIncomingCall,
     CallHierarchyIncomingCallsParams, CallHierarchyItem, CallHierarchyPrepareParams,
     ClientCapabilities, CodeAction, CodeActionCapabilityResolveSupport,
     CodeActionClientCapabilities, CodeActionContext, CodeActionKind,
     CodeActionKindLiteralSupport, CodeActionLiteralSupport, CodeActionParams,
-    CodeActionResponse, CodeLens, CodeLensParams, CompletionClientCapabilities,
+    CodeActionResponse, CodeLens, CodeLensParams, Color, ColorInformation,
+    ColorPresentation, ColorPresentationParams, CompletionClientCapabilities,
     CompletionItem, CompletionItemCapability,
     CompletionItemCapabilityResolveSupport, CompletionParams, CompletionResponse,
-    Diagnostic, DocumentFormattingParams, DocumentSymbolClientCapabilities,
-    DocumentSymbolParams, DocumentSymbolResponse, FoldingRange,
+    CreateFilesParams, DeleteFilesParams, Diagnostic, DiagnosticClientCapabilities,
+    DiagnosticWorkspaceClientCapabilities, DocumentChangeOperation, DocumentChanges,
+    DocumentColorClientCapabilities, DocumentColorParams, DocumentFormattingParams,
+    DocumentLink, DocumentLinkClientCapabilities, DocumentLinkParams,
+    DocumentSymbolClientCapabilities, DocumentSymbolParams, DocumentSymbolResponse,
+    FileCreate, FileDelete, FileRename, FoldingRange,
     FoldingRangeClientCapabilities, FoldingRangeParams, FormattingOptions,
     GotoCapability, GotoDefinitionParams, GotoDefinitionResponse, Hover,
-    HoverClientCapabilities, HoverParams, InlayHint, InlayHintClientCapabilities,
-    InlayHintParams, InlineCompletionClientCapabilities, InlineCompletionParams,
-    InlineCompletionResponse, InlineCompletionTriggerKind, Location, MarkupKind,
+    HoverClientCapabilities, HoverContents, HoverParams, InlayHint,
+    InlayHintClientCapabilities, InlayHintParams,
+    InlineCompletionClientCapabilities, InlineCompletionParams,
+    InlineCompletionResponse, InlineCompletionTriggerKind, LanguageString,
+    LinkedEditingRangeClientCapabilities, LinkedEditingRangeParams,
+    LinkedEditingRanges, Location, MarkedString, MarkupKind,
     MessageActionItemCapabilities, ParameterInformationSettings,
     PartialResultParams, Position, PrepareRenameResponse,
-    PublishDiagnosticsClientCapabilities, Range, ReferenceContext, ReferenceParams,
-    RenameParams, SelectionRange, SelectionRangeParams, SemanticTokens,
+    PublishDiagnosticsClientCapabilities, PublishDiagnosticsParams, Range,
+    ReferenceContext, ReferenceParams, RenameFilesParams, RenameParams,
+    SelectionRange, SelectionRangeParams, SemanticTokens,
     SemanticTokensClientCapabilities, SemanticTokensParams,
     ShowMessageRequestClientCapabilities, SignatureHelp,
     SignatureHelpClientCapabilities, SignatureHelpParams,
@@ -69,6 +81,7 @@
     TextDocumentSyncClientCapabilities, TextEdit, Url,
     VersionedTextDocumentIdentifier, WindowClientCapabilities,
     WorkDoneProgressParams, WorkspaceClientCapabilities, WorkspaceEdit,
+    WorkspaceFileOperationsClientCapabilities, WorkspaceFolder,
     WorkspaceSymbolClientCapabilities, WorkspaceSymbolParams,
 };
 use parking_lot::Mutex;
@@ -99,6 +112,15 @@ pub enum PluginCatalogRpc {
         check: bool,
         f: Box<dyn ClonableCallback<Value, RpcError>>,
     },
+    /// Send a request to the plugins one at a time in the order of the language server
+    /// priority, until one of them answers
+    ServerRequestByPriority {
+        method: Cow<'static, str>,
+        params: Value,
+        language_id: Option<String>,
+        path: Option<PathBuf>,
+        f: Box<dyn ClonableCallback<Value, RpcError>>,
+    },
     ServerNotification {
         plugin_id: Option<PluginId>,
         method: Cow<'static, str>,
@@ -155,6 +177,8 @@ pub enum PluginCatalogRpc {
 #[allow(clippy::large_enum_variant)]
 pub enum PluginCatalogNotification {
     UpdatePluginConfigs(HashMap<String, HashMap<String, serde_json::Value>>),
+    /// The root folders of the workspace were added to or removed
+    WorkspaceFolders(Vec<PathBuf>),
     UnactivatedVolts(Vec<VoltMetadata>),
     PluginServerLoaded(PluginServerRpcHandler),
     InstallVolt(VoltInfo),
@@ -212,6 +236,13 @@ pub enum PluginCatalogNotification {
         program: String,
         args: Option<Vec<String>>,
     },
+    /// Pull the diagnostics of the workspace, from one plugin or all of them
+    PullWorkspaceDiagnostics(Option<PluginId>),
+    /// The plugin asked for its pulled diagnostics to be refreshed
+    RefreshDiagnostics(PluginId),
+    /// Language id -> the volts whose language servers are preferred for requests
+    /// with a single answer, such as formatting and rename
+    UpdateLanguageServerPriority(HashMap<String, Vec<String>>),
     Shutdown,
 }
 
@@ -225,6 +256,13 @@ pub struct PluginCatalogRpcHandler {
     id: Arc<AtomicU64>,
     #[allow(dead_code, clippy::type_complexity)]
     pending: Arc<Mutex<HashMap<u64, Sender<Result<Value, RpcError>>>>>,
+    /// The diagnostics each plugin reported for a document, so that the ones from
+    /// several language servers can be merged
+    #[allow(clippy::type_complexity)]
+    diagnostics: Arc<Mutex<HashMap<Url, HashMap<PluginId, Vec<Diagnostic>>>>>,
+    /// Every root folder of the workspace, which plugins and language servers
+    /// are told of when they're initialized
+    workspace_folders: Arc<Mutex<Vec<PathBuf>>>,
 }
 
 impl PluginCatalogRpcHandler {
@@ -237,6 +275,8 @@ pub fn new(core_rpc: CoreRpcHandler, proxy_rpc: ProxyRpcHandler) -> Self {
             plugin_rx: Arc::new(Mutex::new(Some(plugin_rx))),
             id: Arc::new(AtomicU64::new(0)),
             pending: Arc::new(Mutex::new(HashMap::new())),
+            diagnostics: Arc::new(Mutex::new(HashMap::new())),
+            workspace_folders: Arc::new(Mutex::new(Vec::new())),
         }
     }
 
@@ -274,6 +314,21 @@ pub fn mainloop(&self, plugin: &mut PluginCatalog) {
                         f,
                     );
                 }
+                PluginCatalogRpc::ServerRequestByPriority {
+                    method,
+                    params,
+                    language_id,
+                    path,
+                    f,
+                } => {
+                    plugin.handle_server_request_by_priority(
+                        method,
+                        params,
+                        language_id,
+                        path,
+                        f,
+                    );
+                }
                 PluginCatalogRpc::ServerNotification {
                     plugin_id,
                     method,
@@ -378,6 +433,85 @@ fn catalog_notification(
         Ok(())
     }
 
+    /// Send a request to every plugin, and call `cb` with all the successful
+    /// responses once each of them has answered.
+    fn collect_request_from_all_plugins<P, Resp>(
+        &self,
+        method: &'static str,
+        params: P,
+        language_id: Option<String>,
+        path: Option<PathBuf>,
+        cb: impl FnOnce(Vec<(PluginId, Resp)>) + Send + 'static,
+    ) where
+        P: Serialize,
+        Resp: DeserializeOwned + Send + 'static,
+    {
+        let request_sent = Arc::new(AtomicUsize::new(0));
+        let received = Arc::new(AtomicUsize::new(0));
+        let results = Arc::new(Mutex::new(Vec::new()));
+        let cb = Arc::new(Mutex::new(Some(cb)));
+        self.send_request(
+            None,
+            Some(request_sent.clone()),
+            method,
+            params,
+            language_id,
+            path,
+            true,
+            move |plugin_id, result| {
+                if let Ok(item) = result.and_then(|value| {
+                    serde_json::from_value::<Resp>(value).map_err(|_| RpcError {
+                        code: 0,
+                        message: "deserialize error".to_string(),
+                    })
+                }) {
+                    results.lock().push((plugin_id, item));
+                }
+                let rx = received.fetch_add(1, Ordering::AcqRel) + 1;
+                if request_sent.load(Ordering::Acquire) == rx {
+                    if let Some(cb) = cb.lock().take() {
+                        cb(std::mem::take(&mut *results.lock()));
+                    }
+                }
+            },
+        );
+    }
+
+    /// Send a request to the plugins in the order of the language server priority
+    /// configured for the document's language, and call `cb` with the first answer.
+    fn send_request_by_priority<P, Resp>(
+        &self,
+        method: &'static str,
+        params: P,
+        language_id: Option<String>,
+        path: Option<PathBuf>,
+        cb: impl FnOnce(PluginId, Result<Resp, RpcError>) + Clone + Send + 'static,
+    ) where
+        P: Serialize,
+        Resp: DeserializeOwned,
+    {
+        let params = serde_json::to_value(params).unwrap();
+        let f = move |plugin_id: PluginId, result: Result<Value, RpcError>| {
+            let result = result.and_then(|value| {
+                serde_json::from_value::<Resp>(value).map_err(|_| RpcError {
+                    code: 0,
+                    message: "deserialize error".to_string(),
+                })
+            });
+            cb(plugin_id, result);
+        };
+        let rpc = PluginCatalogRpc::ServerRequestByPriority {
+            method: method.into(),
+            params,
+            language_id,
+            path,
+            f: Box::new(f),
+        };
+        if let Err(err) = self.plugin_tx.send(rpc) {
+            tracing::error!("{:?}", err);
+        }
+    }
+
     fn send_request_to_all_plugins<P, Resp>(
         &self,
         method: &'static str,
@@ -764,10 +898,7 @@ pub fn get_code_actions(
         path: &Path,
         position: Position,
         diagnostics: Vec<Diagnostic>,
-        cb: impl FnOnce(PluginId, Result<CodeActionResponse, RpcError>)
-            + Clone
-            + Send
-            + 'static,
+        cb: impl FnOnce(Vec<(PluginId, CodeActionResponse)>) + Send + 'static,
     ) {
         let uri = Url::from_file_path(path).unwrap();
         let method = CodeActionRequest::METHOD;
@@ -787,7 +918,7 @@ pub fn get_code_actions(
         };
         let language_id =
             Some(language_id_from_path(path).unwrap_or("").to_string());
-        self.send_request_to_all_plugins(
+        self.collect_request_from_all_plugins(
             method,
             params,
             language_id,
@@ -930,6 +1061,117 @@ pub fn get_document_symbols(
         );
     }
 
+    pub fn get_document_links(
+        &self,
+        path: &Path,
+        cb: impl FnOnce(PluginId, Result<Option<Vec<DocumentLink>>, RpcError>)
+            + Clone
+            + Send
+            + 'static,
+    ) {
+        let uri = Url::from_file_path(path).unwrap();
+        let method = DocumentLinkRequest::METHOD;
+        let params = DocumentLinkParams {
+            text_document: TextDocumentIdentifier { uri },
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+        };
+        let language_id =
+            Some(language_id_from_path(path).unwrap_or("").to_string());
+        self.send_request_to_all_plugins(
+            method,
+            params,
+            language_id,
+            Some(path.to_path_buf()),
+            cb,
+        );
+    }
+
+    pub fn get_linked_editing_ranges(
+        &self,
+        path: &Path,
+        position: Position,
+        cb: impl FnOnce(PluginId, Result<Option<LinkedEditingRanges>, RpcError>)
+            + Clone
+            + Send
+            + 'static,
+    ) {
+        let uri = Url::from_file_path(path).unwrap();
+        let method = LinkedEditingRange::METHOD;
+        let params = LinkedEditingRangeParams {
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri },
+                position,
+            },
+            work_done_progress_params: WorkDoneProgressParams::default(),
+        };
+        let language_id =
+            Some(language_id_from_path(path).unwrap_or("").to_string());
+        self.send_request_to_all_plugins(
+            method,
+            params,
+            language_id,
+            Some(path.to_path_buf()),
+            cb,
+        );
+    }
+
+    pub fn get_document_colors(
+        &self,
+        path: &Path,
+        cb: impl FnOnce(PluginId, Result<Vec<ColorInformation>, RpcError>)
+            + Clone
+            + Send
+            + 'static,
+    ) {
+        let uri = Url::from_file_path(path).unwrap();
+        let method = DocumentColor::METHOD;
+        let params = DocumentColorParams {
+            text_document: TextDocumentIdentifier { uri },
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+        };
+        let language_id =
+            Some(language_id_from_path(path).unwrap_or("").to_string());
+        self.send_request_to_all_plugins(
+            method,
+            params,
+            language_id,
+            Some(path.to_path_buf()),
+            cb,
+        );
+    }
+
+    pub fn get_color_presentations(
+        &self,
+        path: &Path,
+        color: Color,
+        range: Range,
+        cb: impl FnOnce(PluginId, Result<Vec<ColorPresentation>, RpcError>)
+            + Clone
+            + Send
+            + 'static,
+    ) {
+        let uri = Url::from_file_path(path).unwrap();
+        let method = ColorPresentationRequest::METHOD;
+        let params = ColorPresentationParams {
+            text_document: TextDocumentIdentifier { uri },
+            color,
+            range,
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+        };
+        let language_id =
+            Some(language_id_from_path(path).unwrap_or("").to_string());
+        self.send_request_to_all_plugins(
+            method,
+            params,
+            language_id,
+            Some(path.to_path_buf()),
+            cb,
+        );
+    }
+
     pub fn get_workspace_symbols(
         &self,
         query: String,
@@ -968,7 +1210,7 @@ pub fn get_document_formatting(
         };
         let language_id =
             Some(language_id_from_path(path).unwrap_or("").to_string());
-        self.send_request_to_all_plugins(
+        self.send_request_by_priority(
             method,
             params,
             language_id,
@@ -994,7 +1236,7 @@ pub fn prepare_rename(
         };
         let language_id =
             Some(language_id_from_path(path).unwrap_or("").to_string());
-        self.send_request_to_all_plugins(
+        self.send_request_by_priority(
             method,
             params,
             language_id,
@@ -1025,7 +1267,7 @@ pub fn rename(
         };
         let language_id =
             Some(language_id_from_path(path).unwrap_or("").to_string());
-        self.send_request_to_all_plugins(
+        self.send_request_by_priority(
             method,
             params,
             language_id,
@@ -1034,6 +1276,99 @@ pub fn rename(
         );
     }
 
+    /// Ask the plugins for the edits to apply before `from` is renamed to `to`,
+    /// merging them into a single workspace edit.
+    pub fn will_rename_files(
+        &self,
+        from: &Path,
+        to: &Path,
+        cb: impl FnOnce(Option<WorkspaceEdit>) + Send + 'static,
+    ) {
+        let (Ok(old_uri), Ok(new_uri)) =
+            (Url::from_file_path(from), Url::from_file_path(to))
+        else {
+            cb(None);
+            return;
+        };
+        let params = RenameFilesParams {
+            files: vec![FileRename {
+                old_uri: old_uri.to_string(),
+                new_uri: new_uri.to_string(),
+            }],
+        };
+        self.collect_request_from_all_plugins(
+            WillRenameFiles::METHOD,
+            params,
+            None,
+            None,
+            move |edits: Vec<(PluginId, WorkspaceEdit)>| {
+                cb(merge_workspace_edits(
+                    edits.into_iter().map(|(_, edit)| edit),
+                ));
+            },
+        );
+    }
+
+    pub fn did_rename_files(&self, from: &Path, to: &Path) {
+        let (Ok(old_uri), Ok(new_uri)) =
+            (Url::from_file_path(from), Url::from_file_path(to))
+        else {
+            return;
+        };
+        let params = RenameFilesParams {
+            files: vec![FileRename {
+                old_uri: old_uri.to_string(),
+                new_uri: new_uri.to_string(),
+            }],
+        };
+        self.send_notification(
+            None,
+            DidRenameFiles::METHOD,
+            params,
+            None,
+            None,
+            true,
+        );
+    }
+
+    pub fn did_create_files(&self, path: &Path) {
+        let Ok(uri) = Url::from_file_path(path) else {
+            return;
+        };
+        let params = CreateFilesParams {
+            files: vec![FileCreate {
+                uri: uri.to_string(),
+            }],
+        };
+        self.send_notification(
+            None,
+            DidCreateFiles::METHOD,
+            params,
+            None,
+            None,
+            true,
+        );
+    }
+
+    pub fn did_delete_files(&self, path: &Path) {
+        let Ok(uri) = Url::from_file_path(path) else {
+            return;
+        };
+        let params = DeleteFilesParams {
+            files: vec![FileDelete {
+                uri: uri.to_string(),
+            }],
+        };
+        self.send_notification(
+            None,
+            DidDeleteFiles::METHOD,
+            params,
+            None,
+            None,
+            true,
+        );
+    }
+
     pub fn get_semantic_tokens(
         &self,
         path: &Path,
@@ -1088,11 +1423,12 @@ pub fn get_selection_range(
         );
     }
 
+    /// Ask every plugin for the hover at `position`, merging their contents.
     pub fn hover(
         &self,
         path: &Path,
         position: Position,
-        cb: impl FnOnce(PluginId, Result<Hover, RpcError>) + Clone + Send + 'static,
+        cb: impl FnOnce(Option<Hover>) + Send + 'static,
     ) {
         let uri = Url::from_file_path(path).unwrap();
         let method = HoverRequest::METHOD;
@@ -1106,12 +1442,14 @@ pub fn hover(
         let language_id =
             Some(language_id_from_path(path).unwrap_or("").to_string());
 
-        self.send_request_to_all_plugins(
+        self.collect_request_from_all_plugins(
             method,
             params,
             language_id,
             Some(path.to_path_buf()),
-            cb,
+            move |hovers: Vec<(PluginId, Hover)>| {
+                cb(merge_hovers(hovers.into_iter().map(|(_, hover)| hover)));
+            },
         );
     }
 
@@ -1138,11 +1476,15 @@ pub fn completion(
         let language_id =
             Some(language_id_from_path(path).unwrap_or("").to_string());
 
-        self.send_request_to_all_plugins(
+        // Every plugin answers separately, and the completion merges their items.
+        self.send_request(
+            None,
+            None,
             method,
             params,
             language_id,
             Some(path.to_path_buf()),
+            true,
             move |plugin_id, result| match result {
                 Ok(value) => {
                     if let Ok(resp) =
@@ -1153,7 +1495,7 @@ pub fn completion(
                     }
                 }
                 Err(err) => {
-                    tracing::error!("{:?}", err);
+                    tracing::debug!("{:?}", err);
                 }
             },
         );
@@ -1326,6 +1668,22 @@ pub fn update_plugin_configs(
         ))
     }
 
+    pub fn set_workspace_folders(&self, folders: Vec<PathBuf>) -> Result<()> {
+        self.workspace_folders.lock().clone_from(&folders);
+        self.catalog_notification(PluginCatalogNotification::WorkspaceFolders(
+            folders,
+        ))
+    }
+
+    /// The root folders of the workspace as they're given to language servers.
+    pub fn workspace_folders(&self) -> Vec<WorkspaceFolder> {
+        self.workspace_folders
+            .lock()
+            .iter()
+            .filter_map(|path| workspace_folder(path))
+            .collect()
+    }
+
     pub fn install_volt(&self, volt: VoltInfo) -> Result<()> {
         self.catalog_notification(PluginCatalogNotification::InstallVolt(volt))
     }
@@ -1364,6 +1722,58 @@ pub fn remove_volt(&self, volt: VoltMetadata) {
         }
     }
 
+    pub fn pull_workspace_diagnostics(
+        &self,
+        plugin_id: Option<PluginId>,
+    ) -> Result<()> {
+        self.catalog_notification(
+            PluginCatalogNotification::PullWorkspaceDiagnostics(plugin_id),
+        )
+    }
+
+    pub fn update_language_server_priority(
+        &self,
+        priority: HashMap<String, Vec<String>>,
+    ) -> Result<()> {
+        self.catalog_notification(
+            PluginCatalogNotification::UpdateLanguageServerPriority(priority),
+        )
+    }
+
+    /// Publish the diagnostics a plugin reported for a document, merged with the
+    /// ones the other plugins reported for it.
+    pub fn publish_diagnostics(
+        &self,
+        plugin_id: PluginId,
+        params: PublishDiagnosticsParams,
+    ) {
+        let diagnostics = {
+            let mut all_diagnostics = self.diagnostics.lock();
+            let document = all_diagnostics.entry(params.uri.clone()).or_default();
+            if params.diagnostics.is_empty() {
+                document.remove(&plugin_id);
+            } else {
+                document.insert(plugin_id, params.diagnostics);
+            }
+            let diagnostics = document.values().flatten().cloned().collect();
+            if document.is_empty() {
+                all_diagnostics.remove(&params.uri);
+            }
+            diagnostics
+        };
+        self.core_rpc.publish_diagnostics(PublishDiagnosticsParams {
+            uri: params.uri,
+            diagnostics,
+            version: params.version,
+        });
+    }
+
+    pub fn refresh_diagnostics(&self, plugin_id: PluginId) -> Result<()> {
+        self.catalog_notification(PluginCatalogNotification::RefreshDiagnostics(
+            plugin_id,
+        ))
+    }
+
     pub fn reload_volt(&self, volt: VoltMetadata) -> Result<()> {
         self.catalog_notification(PluginCatalogNotification::ReloadVolt(volt))
     }
@@ -1663,6 +2073,106 @@ pub fn remove_volt(
     Ok(())
 }
 
+/// Merge the hovers from several plugins into one, keeping the range of the first
+fn merge_hovers(mut hovers: impl Iterator<Item = Hover>) -> Option<Hover> {
+    let first = hovers.next()?;
+    let mut rest = hovers.peekable();
+    if rest.peek().is_none() {
+        return Some(first);
+    }
+
+    let range = first.range;
+    let contents = std::iter::once(first)
+        .chain(rest)
+        .flat_map(|hover| match hover.contents {
+            HoverContents::Scalar(text) => vec![text],
+            HoverContents::Array(texts) => texts,
+            HoverContents::Markup(content) => match content.kind {
+                MarkupKind::Markdown => vec![MarkedString::String(content.value)],
+                MarkupKind::PlainText => {
+                    vec![MarkedString::LanguageString(LanguageString {
+                        language: "text".to_string(),
+                        value: content.value,
+                    })]
+                }
+            },
+        })
+        .collect();
+    Some(Hover {
+        contents: HoverContents::Array(contents),
+        range,
+    })
+}
+
+/// Merge the workspace edits from several plugins into one
+fn merge_workspace_edits(
+    edits: impl Iterator<Item = WorkspaceEdit>,
+) -> Option<WorkspaceEdit> {
+    let mut merged: Option<WorkspaceEdit> = None;
+    for edit in edits {
+        let Some(merged) = merged.as_mut() else {
+            merged = Some(edit);
+            continue;
+        };
+
+        if let Some(changes) = edit.changes {
+            let merged_changes = merged.changes.get_or_insert_with(HashMap::new);
+            for (uri, edits) in changes {
+                merged_changes.entry(uri).or_default().extend(edits);
+            }
+        }
+
+        if let Some(document_changes) = edit.document_changes {
+            merged.document_changes =
+                Some(match (merged.document_changes.take(), document_changes) {
+                    (None, document_changes) => document_changes,
+                    (
+                        Some(DocumentChanges::Edits(mut edits)),
+                        DocumentChanges::Edits(new_edits),
+                    ) => {
+                        edits.extend(new_edits);
+                        DocumentChanges::Edits(edits)
+                    }
+                    (Some(existing), new) => {
+                        let mut operations = document_change_operations(existing);
+                        operations.extend(document_change_operations(new));
+                        DocumentChanges::Operations(operations)
+                    }
+                });
+        }
+
+        if let Some(change_annotations) = edit.change_annotations {
+            merged
+                .change_annotations
+                .get_or_insert_with(HashMap::new)
+                .extend(change_annotations);
+        }
+    }
+    merged
+}
+
+fn document_change_operations(
+    changes: DocumentChanges,
+) -> Vec<DocumentChangeOperation> {
+    match changes {
+        DocumentChanges::Edits(edits) => edits
+            .into_iter()
+            .map(DocumentChangeOperation::Edit)
+            .collect(),
+        DocumentChanges::Operations(operations) => operations,
+    }
+}
+
+/// The root folder of the workspace at `path`, named after its directory.
+fn workspace_folder(path: &Path) -> Option<WorkspaceFolder> {
+    let uri = Url::from_directory_path(path).ok()?;
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| uri.to_string());
+    Some(WorkspaceFolder { uri, name })
+}
+
 fn client_capabilities() -> ClientCapabilities {
     // https://github.com/rust-lang/rust-analyzer/blob/master/docs/dev/lsp-extensions.md#server-status
     let mut experimental = Map::new();
@@ -1772,6 +2282,20 @@ fn client_capabilities() -> ClientCapabilities {
                 folding_range_kind: None,
                 folding_range: None,
             }),
+            document_link: Some(DocumentLinkClientCapabilities {
+                dynamic_registration: Some(false),
+                tooltip_support: Some(true),
+            }),
+            color_provider: Some(DocumentColorClientCapabilities {
+                dynamic_registration: Some(false),
+            }),
+            linked_editing_range: Some(LinkedEditingRangeClientCapabilities {
+                dynamic_registration: Some(false),
+            }),
+            diagnostic: Some(DiagnosticClientCapabilities {
+                dynamic_registration: Some(false),
+                related_document_support: Some(true),
+            }),
             ..Default::default()
         }),
         window: Some(WindowClientCapabilities {
@@ -1789,6 +2313,18 @@ fn client_capabilities() -> ClientCapabilities {
             }),
             configuration: Some(false),
             workspace_folders: Some(true),
+            diagnostic: Some(DiagnosticWorkspaceClientCapabilities {
+                refresh_support: Some(true),
+            }),
+            file_operations: Some(WorkspaceFileOperationsClientCapabilities {
+                dynamic_registration: Some(false),
+                did_create: Some(true),
+                will_create: Some(false),
+                did_rename: Some(true),
+                will_rename: Some(true),
+                did_delete: Some(true),
+                will_delete: Some(false),
+            }),
             ..Default::default()
         }),
         experimental: Some(experimental.into()),
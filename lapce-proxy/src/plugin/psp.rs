@@ -24,30 +24,38 @@
 use lapce_xi_rope::{Rope, RopeDelta};
 use lsp_types::{
     notification::{
-        Cancel, DidChangeTextDocument, DidOpenTextDocument, DidSaveTextDocument,
+        Cancel, DidChangeTextDocument, DidChangeWorkspaceFolders, DidCreateFiles,
+        DidDeleteFiles, DidOpenTextDocument, DidRenameFiles, DidSaveTextDocument,
         Initialized, LogMessage, Notification, Progress, PublishDiagnostics,
         ShowMessage,
     },
     request::{
         CallHierarchyIncomingCalls, CallHierarchyPrepare, CodeActionRequest,
-        CodeActionResolveRequest, CodeLensRequest, CodeLensResolve, Completion,
-        DocumentSymbolRequest, FoldingRangeRequest, Formatting, GotoDefinition,
-        GotoImplementation, GotoTypeDefinition, HoverRequest, Initialize,
-        InlayHintRequest, InlineCompletionRequest, PrepareRenameRequest, References,
-        RegisterCapability, Rename, ResolveCompletionItem, SelectionRangeRequest,
-        SemanticTokensFullRequest, SignatureHelpRequest, WorkDoneProgressCreate,
-        WorkspaceSymbolRequest,
+        CodeActionResolveRequest, CodeLensRequest, CodeLensResolve,
+        ColorPresentationRequest, Completion, DocumentColor,
+        DocumentDiagnosticRequest, DocumentLinkRequest, DocumentSymbolRequest,
+        FoldingRangeRequest, Formatting, GotoDefinition, GotoImplementation,
+        GotoTypeDefinition, HoverRequest, Initialize, InlayHintRequest,
+        InlineCompletionRequest, LinkedEditingRange, PrepareRenameRequest,
+        References, RegisterCapability, Rename, ResolveCompletionItem,
+        SelectionRangeRequest, SemanticTokensFullRequest, SignatureHelpRequest,
+        WillRenameFiles, WorkDoneProgressCreate, WorkspaceDiagnosticRefresh,
+        WorkspaceDiagnosticRequest, WorkspaceSymbolRequest,
     },
-    CancelParams, CodeActionProviderCapability, DidChangeTextDocumentParams,
-    DidSaveTextDocumentParams, DocumentSelector, FoldingRangeProviderCapability,
-    HoverProviderCapability, ImplementationProviderCapability, InitializeResult,
-    LogMessageParams, MessageType, OneOf, ProgressParams, PublishDiagnosticsParams,
-    Range, Registration, RegistrationParams, SemanticTokens, SemanticTokensLegend,
+    CancelParams, CodeActionProviderCapability, ColorProviderCapability,
+    DiagnosticServerCapabilities, DidChangeTextDocumentParams,
+    DidSaveTextDocumentParams, DocumentSelector, FileOperationFilter,
+    FileOperationPatternKind, FileOperationRegistrationOptions,
+    FoldingRangeProviderCapability, HoverProviderCapability,
+    ImplementationProviderCapability, InitializeResult,
+    LinkedEditingRangeServerCapabilities, LogMessageParams, MessageType, OneOf,
+    ProgressParams, PublishDiagnosticsParams, Range, Registration,
+    RegistrationParams, SemanticTokens, SemanticTokensLegend,
     SemanticTokensServerCapabilities, ServerCapabilities, ShowMessageParams,
     TextDocumentContentChangeEvent, TextDocumentIdentifier,
     TextDocumentSaveRegistrationOptions, TextDocumentSyncCapability,
-    TextDocumentSyncKind, TextDocumentSyncSaveOptions,
-    VersionedTextDocumentIdentifier,
+    TextDocumentSyncKind, TextDocumentSyncSaveOptions, Url,
+    VersionedTextDocumentIdentifier, WorkspaceFolder,
 };
 use parking_lot::Mutex;
 use psp_types::{
@@ -221,6 +229,11 @@ fn document_supported(
         path: Option<&Path>,
     ) -> bool;
     fn method_registered(&mut self, method: &str) -> bool;
+    fn filter_file_operation(
+        &mut self,
+        method: &str,
+        params: Params,
+    ) -> Option<Params>;
     fn handle_host_notification(
         &mut self,
         method: String,
@@ -466,7 +479,12 @@ pub fn mainloop<H>(&self, handler: &mut H)
                         .document_supported(language_id.as_deref(), path.as_deref())
                         && handler.method_registered(&method)
                     {
-                        self.send_server_request(id, &method, params, rh);
+                        match handler.filter_file_operation(&method, params) {
+                            Some(params) => {
+                                self.send_server_request(id, &method, params, rh);
+                            }
+                            None => rh.invoke(Ok(Value::Null)),
+                        }
                     } else {
                         rh.invoke(Err(RpcError {
                             code: 0,
@@ -484,7 +502,11 @@ pub fn mainloop<H>(&self, handler: &mut H)
                         .document_supported(language_id.as_deref(), path.as_deref())
                         && handler.method_registered(&method)
                     {
-                        self.send_server_notification(&method, params);
+                        if let Some(params) =
+                            handler.filter_file_operation(&method, params)
+                        {
+                            self.send_server_notification(&method, params);
+                        }
                     }
                 }
                 PluginServerRpc::HostRequest {
@@ -693,6 +715,12 @@ pub fn document_supported(
         }
     }
 
+    /// The root folders of the workspace, which the plugin is told of when
+    /// it's initialized.
+    pub fn workspace_folders(&self) -> Vec<WorkspaceFolder> {
+        self.catalog_rpc.workspace_folders()
+    }
+
     pub fn method_registered(&mut self, method: &str) -> bool {
         match method {
             Initialize::METHOD => true,
@@ -856,10 +884,110 @@ pub fn method_registered(&mut self, method: &str) -> bool {
             CallHierarchyIncomingCalls::METHOD => {
                 self.server_capabilities.call_hierarchy_provider.is_some()
             }
+            DocumentLinkRequest::METHOD => {
+                self.server_capabilities.document_link_provider.is_some()
+            }
+            LinkedEditingRange::METHOD => {
+                match &self.server_capabilities.linked_editing_range_provider {
+                    Some(LinkedEditingRangeServerCapabilities::Simple(
+                        is_capable,
+                    )) => *is_capable,
+                    Some(LinkedEditingRangeServerCapabilities::Options(_))
+                    | Some(
+                        LinkedEditingRangeServerCapabilities::RegistrationOptions(_),
+                    ) => true,
+                    None => false,
+                }
+            }
+            DocumentDiagnosticRequest::METHOD => {
+                self.server_capabilities.diagnostic_provider.is_some()
+            }
+            WorkspaceDiagnosticRequest::METHOD => {
+                match &self.server_capabilities.diagnostic_provider {
+                    Some(DiagnosticServerCapabilities::Options(options)) => {
+                        options.workspace_diagnostics
+                    }
+                    Some(DiagnosticServerCapabilities::RegistrationOptions(
+                        options,
+                    )) => options.diagnostic_options.workspace_diagnostics,
+                    None => false,
+                }
+            }
+            DocumentColor::METHOD | ColorPresentationRequest::METHOD => self
+                .server_capabilities
+                .color_provider
+                .as_ref()
+                .map(|c| match c {
+                    ColorProviderCapability::Simple(is_capable) => *is_capable,
+                    ColorProviderCapability::ColorProvider(_)
+                    | ColorProviderCapability::Options(_) => true,
+                })
+                .unwrap_or(false),
+            WillRenameFiles::METHOD
+            | DidRenameFiles::METHOD
+            | DidCreateFiles::METHOD
+            | DidDeleteFiles::METHOD => {
+                self.file_operation_registration(method).is_some()
+            }
+            DidChangeWorkspaceFolders::METHOD => self
+                .server_capabilities
+                .workspace
+                .as_ref()
+                .and_then(|w| w.workspace_folders.as_ref())
+                .and_then(|f| f.change_notifications.as_ref())
+                .is_some_and(|c| match c {
+                    OneOf::Left(is_capable) => *is_capable,
+                    OneOf::Right(_) => true,
+                }),
             _ => false,
         }
     }
 
+    fn file_operation_registration(
+        &self,
+        method: &str,
+    ) -> Option<&FileOperationRegistrationOptions> {
+        let file_operations = self
+            .server_capabilities
+            .workspace
+            .as_ref()?
+            .file_operations
+            .as_ref()?;
+        match method {
+            WillRenameFiles::METHOD => file_operations.will_rename.as_ref(),
+            DidRenameFiles::METHOD => file_operations.did_rename.as_ref(),
+            DidCreateFiles::METHOD => file_operations.did_create.as_ref(),
+            DidDeleteFiles::METHOD => file_operations.did_delete.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Only keep the files of a file operation which match the filters the server
+    /// registered for it, returning `None` if no file is left.
+    /// Params of any other method are returned as is.
+    pub fn filter_file_operation(
+        &self,
+        method: &str,
+        params: Params,
+    ) -> Option<Params> {
+        let Some(registration) = self.file_operation_registration(method) else {
+            return Some(params);
+        };
+
+        let mut value = serde_json::to_value(params).ok()?;
+        let files = value.get_mut("files")?.as_array_mut()?;
+        files.retain(|file| {
+            ["uri", "oldUri", "newUri"]
+                .iter()
+                .filter_map(|key| file.get(key)?.as_str())
+                .any(|uri| file_operation_matches(&registration.filters, uri))
+        });
+        if files.is_empty() {
+            return None;
+        }
+        Some(Params::from(value))
+    }
+
     fn check_save_capability(&self, language_id: &str, path: &Path) -> (bool, bool) {
         if self.document_supported(Some(language_id), Some(path)) {
             let (should_send, include_text) = self
@@ -959,6 +1087,15 @@ pub fn process_request(
             WorkDoneProgressCreate::METHOD => {
                 resp.send_null();
             }
+            WorkspaceDiagnosticRefresh::METHOD => {
+                resp.send_null();
+                if let Err(err) = self
+                    .catalog_rpc
+                    .refresh_diagnostics(self.server_rpc.plugin_id)
+                {
+                    tracing::error!("{:?}", err);
+                }
+            }
             RegisterCapability::METHOD => {
                 let params: RegistrationParams =
                     serde_json::from_value(serde_json::to_value(params)?)?;
@@ -1131,7 +1268,8 @@ pub fn handle_notification(
             PublishDiagnostics::METHOD => {
                 let diagnostics: PublishDiagnosticsParams =
                     serde_json::from_value(serde_json::to_value(params)?)?;
-                self.catalog_rpc.core_rpc.publish_diagnostics(diagnostics);
+                self.catalog_rpc
+                    .publish_diagnostics(self.server_rpc.plugin_id, diagnostics);
             }
             Progress::METHOD => {
                 let progress: ProgressParams =
@@ -1436,3 +1574,47 @@ fn semantic_tokens_legend(
         ) => &options.semantic_tokens_options.legend,
     }
 }
+
+fn file_operation_matches(filters: &[FileOperationFilter], uri: &str) -> bool {
+    let Ok(uri) = Url::parse(uri) else {
+        return false;
+    };
+    filters.iter().any(|filter| {
+        if filter
+            .scheme
+            .as_deref()
+            .is_some_and(|scheme| scheme != uri.scheme())
+        {
+            return false;
+        }
+        let Ok(path) = uri.to_file_path() else {
+            return false;
+        };
+
+        // The path no longer exists after a deletion, in which case we can't tell
+        // whether it was a file or a folder.
+        if let Ok(metadata) = path.metadata() {
+            match filter.pattern.matches {
+                Some(FileOperationPatternKind::File) if !metadata.is_file() => {
+                    return false;
+                }
+                Some(FileOperationPatternKind::Folder) if !metadata.is_dir() => {
+                    return false;
+                }
+                _ => {}
+            }
+        }
+
+        let ignore_case = filter
+            .pattern
+            .options
+            .as_ref()
+            .and_then(|options| options.ignore_case)
+            .unwrap_or(false);
+        globset::GlobBuilder::new(&filter.pattern.glob)
+            .case_insensitive(ignore_case)
+            .build()
+            .map(|glob| glob.compile_matcher().is_match(&path))
+            .unwrap_or(false)
+    })
+}
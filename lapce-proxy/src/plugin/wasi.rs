@@ -24,7 +24,7 @@
     notification::Initialized, request::Initialize, DocumentFilter,
     InitializeParams, InitializedParams, TextDocumentContentChangeEvent,
     TextDocumentIdentifier, Url, VersionedTextDocumentIdentifier,
-    WorkDoneProgressParams, WorkspaceFolder,
+    WorkDoneProgressParams,
 };
 use parking_lot::Mutex;
 use psp_types::{Notification, Request};
@@ -94,6 +94,14 @@ fn method_registered(&mut self, method: &str) -> bool {
         self.host.method_registered(method)
     }
 
+    fn filter_file_operation(
+        &mut self,
+        method: &str,
+        params: Params,
+    ) -> Option<Params> {
+        self.host.filter_file_operation(method, params)
+    }
+
     fn document_supported(
         &mut self,
         language_id: Option<&str>,
@@ -205,18 +213,14 @@ fn initialize(&mut self) {
             InitializeParams {
                 process_id: Some(process::id()),
                 root_path: None,
-                root_uri: root_uri.clone(),
+                root_uri,
                 capabilities: client_capabilities(),
                 trace: None,
                 client_info: None,
                 locale: None,
                 initialization_options: configurations,
-                workspace_folders: root_uri.map(|uri| {
-                    vec![WorkspaceFolder {
-                        name: uri.as_str().to_string(),
-                        uri,
-                    }]
-                }),
+                workspace_folders: Some(self.host.workspace_folders())
+                    .filter(|folders| !folders.is_empty()),
                 work_done_progress_params: WorkDoneProgressParams::default(),
             },
             None,
@@ -12,6 +12,37 @@ pub fn next() -> Self {
     }
 }
 
+/// The encodings files can be reopened or saved in, by their label and the
+/// scripts they are for.
+pub const FILE_ENCODINGS: &[(&str, &str)] = &[
+    ("UTF-8", "Unicode"),
+    ("UTF-8 with BOM", "Unicode"),
+    ("UTF-16LE", "Unicode"),
+    ("UTF-16BE", "Unicode"),
+    ("windows-1252", "Western"),
+    ("ISO-8859-2", "Central European"),
+    ("windows-1250", "Central European"),
+    ("ISO-8859-5", "Cyrillic"),
+    ("windows-1251", "Cyrillic"),
+    ("KOI8-R", "Cyrillic"),
+    ("ISO-8859-7", "Greek"),
+    ("windows-1253", "Greek"),
+    ("windows-1254", "Turkish"),
+    ("ISO-8859-8", "Hebrew"),
+    ("windows-1255", "Hebrew"),
+    ("windows-1256", "Arabic"),
+    ("windows-1257", "Baltic"),
+    ("windows-1258", "Vietnamese"),
+    ("windows-874", "Thai"),
+    ("Shift_JIS", "Japanese"),
+    ("EUC-JP", "Japanese"),
+    ("ISO-2022-JP", "Japanese"),
+    ("GBK", "Simplified Chinese"),
+    ("gb18030", "Simplified Chinese"),
+    ("Big5", "Traditional Chinese"),
+    ("EUC-KR", "Korean"),
+];
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NewBufferResponse {
     pub content: String,
@@ -21,7 +21,7 @@
     },
     file::PathObject,
     plugin::{PluginId, VoltInfo, VoltMetadata},
-    proxy::ProxyStatus,
+    proxy::{ProxyStatus, RemotePrompt},
     source_control::DiffInfo,
     terminal::TermId,
     RequestId, RpcError, RpcMessage,
@@ -47,6 +47,9 @@ pub enum CoreNotification {
     ProxyStatus {
         status: ProxyStatus,
     },
+    RemotePrompt {
+        prompt: RemotePrompt,
+    },
     OpenFileChanged {
         path: PathBuf,
         content: FileChanged,
@@ -1,7 +1,9 @@
 use std::{
     cmp::{Ord, Ordering, PartialOrd},
-    collections::HashMap,
+    collections::{BTreeMap, HashMap, HashSet},
+    ops::{Bound, Range},
     path::{Path, PathBuf},
+    sync::Arc,
 };
 
 use serde::{Deserialize, Serialize};
@@ -235,6 +237,8 @@ pub fn extra_node(
                 is_root: false,
                 open: false,
                 level: level + 1,
+                label: None,
+                has_nested: false,
             }),
             Naming::Duplicating(d) if d.path == path => Some(FileNodeViewData {
                 kind: FileNodeViewKind::Duplicating {
@@ -245,19 +249,97 @@ pub fn extra_node(
                 is_root: false,
                 open: false,
                 level: level + 1,
+                label: None,
+                has_nested: false,
             }),
             _ => None,
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct FileNodeViewData {
     pub kind: FileNodeViewKind,
     pub is_dir: bool,
     pub is_root: bool,
     pub open: bool,
     pub level: usize,
+    /// The text shown instead of the file name, like `src/foo/bar` for a
+    /// chain of compacted folders
+    pub label: Option<String>,
+    /// Whether there are files nested under this file
+    pub has_nested: bool,
+}
+
+/// A rule for showing files under another file in the same folder, like
+/// `Cargo.lock` under `Cargo.toml`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileNestingRule {
+    /// The name of the file that others are nested under, where a `*`
+    /// matches any text
+    pub parent: String,
+    /// The names of the files nested under it, where a `*` matches any
+    /// text and `${capture}` is what the `*` in `parent` matched
+    pub children: Vec<String>,
+}
+
+impl FileNestingRule {
+    /// The patterns of the files nested under the file named `parent`, with
+    /// `${capture}` filled in, if the rule applies to it.
+    fn children_of(&self, parent: &str) -> Option<Vec<String>> {
+        let capture = wildcard_match(&self.parent, parent)?;
+        Some(
+            self.children
+                .iter()
+                .map(|child| child.replace("${capture}", capture))
+                .collect(),
+        )
+    }
+
+    /// Whether the file named `name` is nested under the one named `parent`.
+    pub fn nests(&self, parent: &str, name: &str) -> bool {
+        parent != name
+            && self.children_of(parent).map_or(false, |children| {
+                children
+                    .iter()
+                    .any(|child| wildcard_match(child, name).is_some())
+            })
+    }
+}
+
+/// Match `text` against `pattern`, where the first `*` matches any text,
+/// returning what it matched.
+fn wildcard_match<'a>(pattern: &str, text: &'a str) -> Option<&'a str> {
+    let Some((prefix, suffix)) = pattern.split_once('*') else {
+        return (pattern == text).then_some("");
+    };
+    if text.len() < prefix.len() + suffix.len()
+        || !text.starts_with(prefix)
+        || !text.ends_with(suffix)
+    {
+        return None;
+    }
+    text.get(prefix.len()..text.len() - suffix.len())
+}
+
+/// How the rows of the file explorer are laid out.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileNodeViewOptions {
+    /// Show a folder that only has a single folder in it in one row with it
+    pub compact_folders: bool,
+    /// The rules for nesting files under other files
+    pub nesting: Arc<Vec<FileNestingRule>>,
+    /// When filtering, the paths that are shown, which are the matches along
+    /// with their ancestors. The folders are all shown open.
+    pub filter: Option<Arc<HashSet<PathBuf>>>,
+}
+
+impl FileNodeViewOptions {
+    fn is_shown(&self, path: &Path) -> bool {
+        self.filter
+            .as_ref()
+            .map_or(true, |filter| filter.contains(path))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -270,7 +352,9 @@ pub struct FileNodeItem {
     /// Whether the directory is open in the explorer view.
     pub open: bool,
     pub children: HashMap<PathBuf, FileNodeItem>,
-    /// The number of child (directories) that are open themselves  
+    /// The number of rows shown under the directory's own row, which are
+    /// those of its open children, the files nested under them and the input
+    /// for a new node.  
     /// Used for sizing of the explorer list
     pub children_open_count: usize,
 }
@@ -370,19 +454,18 @@ pub fn get_file_node_mut(&mut self, path: &Path) -> Option<&mut FileNodeItem> {
     }
 
     /// Remove a specific child from the node.  
-    /// The path is recursive and will remove the child from parent indicated by the path.
+    /// The path is recursive and will remove the child from parent indicated by the path.  
+    /// The row counts of its ancestors need updating after, with
+    /// [`Self::update_node_count_recursive`].
     pub fn remove_child(&mut self, path: &Path) -> Option<FileNodeItem> {
         let parent = path.parent()?;
         let node = self.get_file_node_mut(parent)?;
-        let node = node.children.remove(path)?;
-        for p in path.ancestors() {
-            self.update_node_count(p);
-        }
-
-        Some(node)
+        node.children.remove(path)
     }
 
-    /// Add a new (unread & unopened) child to the node.
+    /// Add a new (unread & unopened) child to the node.  
+    /// The row counts of its ancestors need updating after, with
+    /// [`Self::update_node_count_recursive`].
     pub fn add_child(&mut self, path: &Path, is_dir: bool) -> Option<()> {
         let parent = path.parent()?;
         let node = self.get_file_node_mut(parent)?;
@@ -397,15 +480,13 @@ pub fn add_child(&mut self, path: &Path, is_dir: bool) -> Option<()> {
                 children_open_count: 0,
             },
         );
-        for p in path.ancestors() {
-            self.update_node_count(p);
-        }
 
         Some(())
     }
 
     /// Set the children of the node.  
-    /// Note: this opens the node.
+    /// Note: this opens the node, so the row counts of it and its ancestors
+    /// need updating after, with [`Self::update_node_count_recursive`].
     pub fn set_item_children(
         &mut self,
         path: &Path,
@@ -416,176 +497,675 @@ pub fn set_item_children(
             node.read = true;
             node.children = children;
         }
+    }
 
-        for p in path.ancestors() {
-            self.update_node_count(p);
+    /// The children sorted by name like [`Self::sorted_children`], each with
+    /// the files that `rules` nest under it, which aren't in the list
+    /// themselves.
+    pub fn nested_children(
+        &self,
+        rules: &[FileNestingRule],
+    ) -> Vec<(&FileNodeItem, Vec<&FileNodeItem>)> {
+        let children = self.sorted_children();
+        if rules.is_empty() {
+            return children.into_iter().map(|c| (c, Vec::new())).collect();
         }
+
+        let names = children
+            .iter()
+            .map(|child| {
+                child
+                    .path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect::<Vec<String>>();
+        let files = names
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !children[*i].is_dir)
+            .map(|(i, name)| (name.as_str(), i))
+            .collect::<BTreeMap<&str, usize>>();
+
+        // The index of the file that each nested file is under
+        let mut nested_under = HashMap::new();
+        for (parent, parent_name) in names.iter().enumerate() {
+            if children[parent].is_dir {
+                continue;
+            }
+            for rule in rules {
+                let Some(patterns) = rule.children_of(parent_name) else {
+                    continue;
+                };
+                for pattern in patterns {
+                    if let Some((prefix, _)) = pattern.split_once('*') {
+                        // Only the names that start with what's before the
+                        // `*` can match
+                        let candidates = files
+                            .range::<str, _>((
+                                Bound::Included(prefix),
+                                Bound::Unbounded,
+                            ))
+                            .take_while(|(name, _)| name.starts_with(prefix));
+                        for (name, &child) in candidates {
+                            if child != parent
+                                && wildcard_match(&pattern, name).is_some()
+                            {
+                                nested_under.entry(child).or_insert(parent);
+                            }
+                        }
+                    } else if let Some(&child) = files.get(pattern.as_str()) {
+                        if child != parent {
+                            nested_under.entry(child).or_insert(parent);
+                        }
+                    }
+                }
+            }
+        }
+        // Files are only nested one level deep, so ones under a nested file
+        // stay where they are
+        let nested_under = nested_under
+            .iter()
+            .filter(|(_, parent)| !nested_under.contains_key(*parent))
+            .map(|(child, parent)| (*child, *parent))
+            .collect::<HashMap<usize, usize>>();
+
+        let mut nested = HashMap::<usize, Vec<&FileNodeItem>>::new();
+        for (i, child) in children.iter().enumerate() {
+            if let Some(parent) = nested_under.get(&i) {
+                nested.entry(*parent).or_default().push(*child);
+            }
+        }
+        children
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !nested_under.contains_key(i))
+            .map(|(i, child)| (*child, nested.remove(&i).unwrap_or_default()))
+            .collect()
+    }
+
+    /// Whether the children of the directory are shown, which they all are
+    /// while filtering.
+    fn is_shown_open(&self, options: &FileNodeViewOptions) -> bool {
+        self.is_dir && (self.open || options.filter.is_some())
     }
 
-    pub fn update_node_count_recursive(&mut self, path: &Path) {
+    /// Whether the files nested under this file are shown.
+    fn is_nested_open(
+        &self,
+        nested: &[&FileNodeItem],
+        options: &FileNodeViewOptions,
+    ) -> bool {
+        !nested.is_empty() && (self.open || options.filter.is_some())
+    }
+
+    /// The only child of this directory when it's a directory too, so the
+    /// two can be shown in one row.
+    fn compact_child(
+        &self,
+        naming: &Naming,
+        options: &FileNodeViewOptions,
+    ) -> Option<&FileNodeItem> {
+        if !self.is_shown_open(options)
+            || naming.extra_node(true, 0, &self.path).is_some()
+        {
+            return None;
+        }
+        let mut children = self
+            .children
+            .values()
+            .filter(|child| options.is_shown(&child.path));
+        let child = children.next()?;
+        if children.next().is_some() || !child.is_dir {
+            return None;
+        }
+        // A folder being renamed keeps its own row for the input
+        if matches!(naming, Naming::Renaming(r) if r.path == self.path || r.path == child.path)
+        {
+            return None;
+        }
+        Some(child)
+    }
+
+    /// The last folder of the chain of single folders shown in one row with
+    /// this one, which is this one when there's none.
+    fn compacted(
+        &self,
+        naming: &Naming,
+        options: &FileNodeViewOptions,
+        level: usize,
+    ) -> &FileNodeItem {
+        let mut node = self;
+        if options.compact_folders && level > 1 {
+            while let Some(child) = node.compact_child(naming, options) {
+                node = child;
+            }
+        }
+        node
+    }
+
+    /// What is shown under this directory, in order, which is nothing but
+    /// the input for a new node when it's closed.
+    fn child_rows(
+        &self,
+        naming: &Naming,
+        options: &FileNodeViewOptions,
+        level: usize,
+    ) -> Vec<ChildRow<'_>> {
+        let mut naming_extra = naming.extra_node(self.is_dir, level, &self.path);
+        if !self.is_shown_open(options) {
+            // If the folder isn't open, then we just put it right at the top
+            return naming_extra.map(ChildRow::Naming).into_iter().collect();
+        }
+
+        let mut rows = Vec::new();
+        // Immediately put the naming entry first if it's a directory
+        if naming_extra.as_ref().is_some_and(|n| n.is_dir) {
+            rows.extend(naming_extra.take().map(ChildRow::Naming));
+        }
+        for (item, nested) in self.nested_children(&options.nesting) {
+            let nested = nested
+                .into_iter()
+                .filter(|nested| options.is_shown(&nested.path))
+                .collect::<Vec<_>>();
+            if !options.is_shown(&item.path) && nested.is_empty() {
+                continue;
+            }
+            // If we're creating a new file node, then we show it after the
+            // directories
+            if !item.is_dir {
+                rows.extend(naming_extra.take().map(ChildRow::Naming));
+            }
+            rows.push(ChildRow::Item(item, nested));
+        }
+        // If it has not been added yet, add it now.
+        rows.extend(naming_extra.map(ChildRow::Naming));
+        rows
+    }
+
+    /// The number of rows the item is shown in, its own along with those
+    /// under it and the ones of the files nested under it.
+    fn row_count(
+        &self,
+        nested: &[&FileNodeItem],
+        naming: &Naming,
+        options: &FileNodeViewOptions,
+        level: usize,
+    ) -> usize {
+        let mut count =
+            1 + self.compacted(naming, options, level).children_open_count;
+        if self.is_nested_open(nested, options) {
+            count += nested
+                .iter()
+                .map(|nested| nested.row_count(&[], naming, options, level + 1))
+                .sum::<usize>();
+        }
+        count
+    }
+
+    /// Recalculate `children_open_count` of the node at `path` from the
+    /// counts of its children.
+    pub fn update_node_count(
+        &mut self,
+        path: &Path,
+        naming: &Naming,
+        options: &FileNodeViewOptions,
+    ) -> Option<()> {
+        let count = self
+            .get_file_node(path)?
+            .child_rows(naming, options, 0)
+            .iter()
+            .map(|row| match row {
+                ChildRow::Naming(_) => 1,
+                ChildRow::Item(item, nested) => {
+                    item.row_count(nested, naming, options, 2)
+                }
+            })
+            .sum();
+        self.get_file_node_mut(path)?.children_open_count = count;
+        Some(())
+    }
+
+    /// Recalculate `children_open_count` of the node at `path` and of its
+    /// ancestors, after the node changed.
+    pub fn update_node_count_recursive(
+        &mut self,
+        path: &Path,
+        naming: &Naming,
+        options: &FileNodeViewOptions,
+    ) {
         for current_path in path.ancestors() {
-            self.update_node_count(current_path);
+            self.update_node_count(current_path, naming, options);
         }
     }
 
-    pub fn update_node_count(&mut self, path: &Path) -> Option<()> {
-        let node = self.get_file_node_mut(path)?;
-        if node.is_dir {
-            node.children_open_count = if node.open {
-                node.children
-                    .values()
-                    .map(|item| item.children_open_count + 1)
-                    .sum::<usize>()
-            } else {
-                0
-            };
+    /// Recalculate `children_open_count` of every node, after the options
+    /// the rows are laid out with changed.
+    pub fn update_all_node_counts(
+        &mut self,
+        naming: &Naming,
+        options: &FileNodeViewOptions,
+    ) {
+        for child in self.children.values_mut() {
+            child.update_all_node_counts(naming, options);
         }
-        None
+        let path = self.path.clone();
+        self.update_node_count(&path, naming, options);
     }
 
+    /// Append the rows of this item and everything shown under it that are
+    /// in `range`, where `row` is the index of the item's own row.  
+    /// Returns the index of the row after them, or of where it stopped once
+    /// past the range.
     pub fn append_view_slice(
         &self,
         view_items: &mut Vec<FileNodeViewData>,
         naming: &Naming,
-        min: usize,
-        max: usize,
-        current: usize,
+        options: &FileNodeViewOptions,
+        range: &Range<usize>,
+        row: usize,
         level: usize,
     ) -> usize {
-        if current > max {
-            return current;
-        }
-        if current + self.children_open_count < min {
-            return current + self.children_open_count;
+        self.append_item_view_slice(
+            &[],
+            view_items,
+            naming,
+            options,
+            range,
+            row,
+            level,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn append_item_view_slice(
+        &self,
+        nested: &[&FileNodeItem],
+        view_items: &mut Vec<FileNodeViewData>,
+        naming: &Naming,
+        options: &FileNodeViewOptions,
+        range: &Range<usize>,
+        row: usize,
+        level: usize,
+    ) -> usize {
+        let end = row + self.row_count(nested, naming, options, level);
+        if row >= range.end || end <= range.start {
+            return end;
         }
 
-        if current >= min {
-            let kind = if let Naming::Renaming(r) = &naming {
-                if r.path == self.path {
+        let node = self.compacted(naming, options, level);
+        let nested_open = self.is_nested_open(nested, options);
+        if range.contains(&row) {
+            let label = (node.path != self.path).then(|| {
+                let base = self.path.parent().unwrap_or(&self.path);
+                node.path
+                    .strip_prefix(base)
+                    .unwrap_or(&node.path)
+                    .to_string_lossy()
+                    .into_owned()
+            });
+            let kind = match naming {
+                Naming::Renaming(r) if r.path == node.path => {
                     FileNodeViewKind::Renaming {
-                        path: self.path.clone(),
+                        path: node.path.clone(),
                         err: r.state.err().map(ToString::to_string),
                     }
-                } else {
-                    FileNodeViewKind::Path(self.path.clone())
                 }
-            } else {
-                FileNodeViewKind::Path(self.path.clone())
+                _ => FileNodeViewKind::Path(node.path.clone()),
             };
             view_items.push(FileNodeViewData {
                 kind,
-                is_dir: self.is_dir,
+                is_dir: node.is_dir,
                 is_root: level == 1,
-                open: self.open,
+                open: if nested.is_empty() {
+                    node.is_shown_open(options)
+                } else {
+                    nested_open
+                },
                 level,
+                label,
+                has_nested: !nested.is_empty(),
             });
         }
 
-        self.append_children_view_slice(view_items, naming, min, max, current, level)
+        let mut row = node.append_children_view_slice(
+            view_items,
+            naming,
+            options,
+            range,
+            row + 1,
+            level,
+        );
+        if nested_open {
+            for nested in nested {
+                if row >= range.end {
+                    break;
+                }
+                row = nested.append_view_slice(
+                    view_items,
+                    naming,
+                    options,
+                    range,
+                    row,
+                    level + 1,
+                );
+            }
+        }
+        row
     }
 
-    /// Calculate the row where the file resides
-    pub fn find_file_at_line(&self, file_path: &Path) -> (bool, f64) {
-        let mut line = 0.0;
-        if !self.open {
-            return (false, line);
-        }
-        for item in self.sorted_children() {
-            line += 1.0;
-            match (item.is_dir, item.open, item.path == file_path) {
-                (_, _, true) => {
-                    return (true, line);
-                }
-                (true, true, _) => {
-                    let (found, item_position) = item.find_file_at_line(file_path);
-                    line += item_position;
-                    if found {
-                        return (true, line);
+    /// Append the rows of the children of this item with the given level
+    /// that are in `range`, starting at `row`.
+    fn append_children_view_slice(
+        &self,
+        view_items: &mut Vec<FileNodeViewData>,
+        naming: &Naming,
+        options: &FileNodeViewOptions,
+        range: &Range<usize>,
+        mut row: usize,
+        level: usize,
+    ) -> usize {
+        for child in self.child_rows(naming, options, level) {
+            if row >= range.end {
+                break;
+            }
+            match child {
+                ChildRow::Naming(node) => {
+                    if range.contains(&row) {
+                        view_items.push(node);
                     }
+                    row += 1;
+                }
+                ChildRow::Item(item, nested) => {
+                    row = item.append_item_view_slice(
+                        &nested,
+                        view_items,
+                        naming,
+                        options,
+                        range,
+                        row,
+                        level + 1,
+                    );
                 }
-                _ => {}
             }
         }
-        (false, line)
+        row
     }
 
-    /// Append the children of this item with the given level
-    pub fn append_children_view_slice(
+    /// The index of the row that `path` is shown in, where `row` is the index
+    /// of this root's own row, if it's shown.
+    pub fn find_view_row(
         &self,
-        view_items: &mut Vec<FileNodeViewData>,
+        path: &Path,
         naming: &Naming,
-        min: usize,
-        max: usize,
-        mut i: usize,
+        options: &FileNodeViewOptions,
+        row: usize,
+    ) -> Option<usize> {
+        self.find_item_view_row(&[], path, naming, options, row, 1)
+    }
+
+    fn find_item_view_row(
+        &self,
+        nested: &[&FileNodeItem],
+        path: &Path,
+        naming: &Naming,
+        options: &FileNodeViewOptions,
+        row: usize,
         level: usize,
-    ) -> usize {
-        let mut naming_extra = naming.extra_node(self.is_dir, level, &self.path);
+    ) -> Option<usize> {
+        let node = self.compacted(naming, options, level);
+        // The path is this item or one of the folders compacted with it
+        if path.starts_with(&self.path) && node.path.starts_with(path) {
+            return Some(row);
+        }
+        if path.starts_with(&node.path) {
+            return node.find_children_view_row(
+                path,
+                naming,
+                options,
+                row + 1,
+                level,
+            );
+        }
 
-        if !self.open {
-            // If the folder isn't open, then we just put it right at the top
-            if i >= min {
-                if let Some(naming_extra) = naming_extra {
-                    view_items.push(naming_extra);
-                    i += 1;
-                }
+        if !self.is_nested_open(nested, options) {
+            return None;
+        }
+        let mut row = row + 1 + node.children_open_count;
+        for nested in nested {
+            if nested.path == path {
+                return Some(row);
             }
-            return i;
+            row += nested.row_count(&[], naming, options, level + 1);
         }
+        None
+    }
 
-        let naming_is_dir = naming_extra.as_ref().map(|n| n.is_dir).unwrap_or(false);
-        // Immediately put the naming entry first if it's a directory
-        if naming_is_dir {
-            if let Some(node) = naming_extra.take() {
-                // Actually add the node if it's within the range
-                if i >= min {
-                    view_items.push(node);
-                    i += 1;
+    fn find_children_view_row(
+        &self,
+        path: &Path,
+        naming: &Naming,
+        options: &FileNodeViewOptions,
+        mut row: usize,
+        level: usize,
+    ) -> Option<usize> {
+        for child in self.child_rows(naming, options, level) {
+            match child {
+                ChildRow::Naming(_) => row += 1,
+                ChildRow::Item(item, nested) => {
+                    if path.starts_with(&item.path)
+                        || nested.iter().any(|nested| nested.path == path)
+                    {
+                        return item.find_item_view_row(
+                            &nested,
+                            path,
+                            naming,
+                            options,
+                            row,
+                            level + 1,
+                        );
+                    }
+                    row += item.row_count(&nested, naming, options, level + 1);
                 }
             }
         }
+        None
+    }
+}
 
-        let mut after_dirs = false;
+/// A row shown under an open directory.
+enum ChildRow<'a> {
+    /// The input for a new node
+    Naming(FileNodeViewData),
+    /// A child, with the files nested under it
+    Item(&'a FileNodeItem, Vec<&'a FileNodeItem>),
+}
 
-        for item in self.sorted_children() {
-            // If we're naming a file at the root, then wait until we've added the directories
-            // before adding the input node
-            if naming_extra.is_some()
-                && !naming_is_dir
-                && !item.is_dir
-                && !after_dirs
-            {
-                after_dirs = true;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-                // If we're creating a new file node, then we show it after the directories
-                // TODO(minor): should this be i >= min or i + 1 >= min?
-                if i >= min {
-                    if let Some(node) = naming_extra.take() {
-                        view_items.push(node);
-                        i += 1;
-                    }
-                }
-            }
-            i = item.append_view_slice(
-                view_items,
-                naming,
-                min,
-                max,
-                i + 1,
-                level + 1,
-            );
-            if i > max {
-                return i;
-            }
+    fn node(path: &str, is_dir: bool, children: Vec<FileNodeItem>) -> FileNodeItem {
+        FileNodeItem {
+            path: PathBuf::from(path),
+            is_dir,
+            read: is_dir,
+            open: is_dir,
+            children: children
+                .into_iter()
+                .map(|child| (child.path.clone(), child))
+                .collect(),
+            children_open_count: 0,
         }
+    }
 
-        // If it has not been added yet, add it now.
-        if i >= min {
-            if let Some(node) = naming_extra {
-                view_items.push(node);
-                i += 1;
-            }
+    fn slice(
+        root: &FileNodeItem,
+        options: &FileNodeViewOptions,
+        range: Range<usize>,
+    ) -> Vec<FileNodeViewData> {
+        let mut rows = Vec::new();
+        root.append_view_slice(&mut rows, &Naming::None, options, &range, 0, 1);
+        rows
+    }
+
+    /// The rows of the tree, checking that each can be sliced on its own and
+    /// found by its path too.
+    fn rows(root: &FileNodeItem, options: &FileNodeViewOptions) -> Vec<String> {
+        let mut root = root.clone();
+        root.update_all_node_counts(&Naming::None, options);
+        let rows = slice(&root, options, 0..usize::MAX);
+        assert_eq!(rows.len(), root.children_open_count + 1);
+        for (i, row) in rows.iter().enumerate() {
+            assert_eq!(&slice(&root, options, i..i + 1), std::slice::from_ref(row));
+            let path = row.kind.path().unwrap();
+            assert_eq!(root.find_view_row(path, &Naming::None, options, 0), Some(i));
         }
+        rows.iter()
+            .map(|row| {
+                let name = row.label.clone().unwrap_or_else(|| {
+                    let path = row.kind.path().unwrap();
+                    path.file_name().unwrap().to_string_lossy().into_owned()
+                });
+                format!("{}{name}", "  ".repeat(row.level - 1))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_nesting_rule() {
+        let rule = FileNestingRule {
+            parent: "*.rs".to_string(),
+            children: vec!["${capture}.rs.bak".to_string()],
+        };
+        assert!(rule.nests("main.rs", "main.rs.bak"));
+        assert!(!rule.nests("main.rs", "lib.rs.bak"));
+        assert!(!rule.nests("main.rs.bak", "main.rs.bak.bak"));
+
+        let rule = FileNestingRule {
+            parent: ".env".to_string(),
+            children: vec![".env.*".to_string()],
+        };
+        assert!(rule.nests(".env", ".env.local"));
+        assert!(!rule.nests(".env", ".env"));
+        assert!(!rule.nests(".envrc", ".env.local"));
+    }
+
+    #[test]
+    fn test_view_rows() {
+        let root = node(
+            "/root",
+            true,
+            vec![
+                node(
+                    "/root/src",
+                    true,
+                    vec![node(
+                        "/root/src/foo",
+                        true,
+                        vec![node("/root/src/foo/lib.rs", false, vec![])],
+                    )],
+                ),
+                node("/root/Cargo.toml", false, vec![]),
+                node("/root/Cargo.lock", false, vec![]),
+            ],
+        );
+        let nesting = Arc::new(vec![FileNestingRule {
+            parent: "Cargo.toml".to_string(),
+            children: vec!["Cargo.lock".to_string()],
+        }]);
+
+        assert_eq!(
+            rows(&root, &FileNodeViewOptions::default()),
+            [
+                "root",
+                "  src",
+                "    foo",
+                "      lib.rs",
+                "  Cargo.lock",
+                "  Cargo.toml"
+            ]
+        );
+        assert_eq!(
+            rows(
+                &root,
+                &FileNodeViewOptions {
+                    compact_folders: true,
+                    nesting: nesting.clone(),
+                    filter: None,
+                }
+            ),
+            ["root", "  src/foo", "    lib.rs", "  Cargo.toml"]
+        );
+
+        let filter = ["/", "/root", "/root/Cargo.toml", "/root/Cargo.lock"]
+            .into_iter()
+            .map(PathBuf::from)
+            .collect::<HashSet<_>>();
+        assert_eq!(
+            rows(
+                &root,
+                &FileNodeViewOptions {
+                    compact_folders: true,
+                    nesting,
+                    filter: Some(Arc::new(filter)),
+                }
+            ),
+            ["root", "  Cargo.toml", "    Cargo.lock"]
+        );
+    }
+
+    #[test]
+    fn test_update_node_count() {
+        let mut root = node(
+            "/root",
+            true,
+            vec![
+                node(
+                    "/root/a",
+                    true,
+                    vec![node(
+                        "/root/a/b",
+                        true,
+                        vec![
+                            node("/root/a/b/c.rs", false, vec![]),
+                            node("/root/a/b/d.rs", false, vec![]),
+                        ],
+                    )],
+                ),
+                node("/root/e.rs", false, vec![]),
+            ],
+        );
+        let options = FileNodeViewOptions {
+            compact_folders: true,
+            ..Default::default()
+        };
+        root.update_all_node_counts(&Naming::None, &options);
+        // `a/b`, `c.rs`, `d.rs` and `e.rs`
+        assert_eq!(root.children_open_count, 4);
+
+        let path = Path::new("/root/a/b");
+        root.get_file_node_mut(path).unwrap().open = false;
+        root.update_node_count_recursive(path, &Naming::None, &options);
+        assert_eq!(root.children_open_count, 2);
 
-        i
+        let naming = Naming::NewNode(NewNode {
+            state: NamingState::Naming,
+            base_path: path.to_path_buf(),
+            is_dir: false,
+            editor_needs_reset: false,
+        });
+        root.update_node_count_recursive(path, &naming, &options);
+        let mut counted = root.clone();
+        counted.update_all_node_counts(&naming, &options);
+        assert_eq!(root, counted);
+        // The input for the new file is shown in the closed folder
+        let mut rows = Vec::new();
+        root.append_view_slice(&mut rows, &naming, &options, &(0..10), 0, 1);
+        assert_eq!(rows.len(), root.children_open_count + 1);
+        assert_eq!(rows.len(), 4);
+        assert!(matches!(rows[2].kind, FileNodeViewKind::Naming { .. }));
     }
 }
@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+
+/// A language defined in the settings, for files that none of the built-in
+/// languages cover.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct LanguageDefinition {
+    /// File name extensions, like `["tpl"]`
+    pub extensions: Vec<String>,
+    /// Whole file names, like `["Tiltfile"]`
+    pub files: Vec<String>,
+    /// Interpreters named by shebang lines, like `["tclsh"]`
+    pub shebangs: Vec<String>,
+    /// The token that starts a line comment
+    pub comment: Option<String>,
+    /// The token that ends a comment, for comments like `<!-- -->`
+    pub comment_end: Option<String>,
+    /// The indent unit, four spaces when not set
+    pub indent: Option<String>,
+    /// The `libtree-sitter-<grammar>` library in the grammars directory, the
+    /// language name when not set
+    pub grammar: Option<String>,
+    /// The name in the grammar's `tree_sitter_<name>` function, the grammar
+    /// when not set
+    pub grammar_fn: Option<String>,
+    /// The folder of the language's queries in the queries directory, the
+    /// language name when not set
+    pub query: Option<String>,
+}
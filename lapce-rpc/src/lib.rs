@@ -6,6 +6,7 @@
 pub mod dap_types;
 pub mod file;
 pub mod file_line;
+pub mod language;
 mod parse;
 pub mod plugin;
 pub mod proxy;
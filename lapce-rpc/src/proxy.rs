@@ -13,10 +13,12 @@
 use lsp_types::{
     request::{GotoImplementationResponse, GotoTypeDefinitionResponse},
     CallHierarchyIncomingCall, CallHierarchyItem, CodeAction, CodeActionResponse,
-    CodeLens, CompletionItem, Diagnostic, DocumentSymbolResponse, FoldingRange,
+    CodeLens, Color, ColorInformation, ColorPresentation, CompletionItem,
+    Diagnostic, DocumentLink, DocumentSymbolResponse, FoldingRange,
     GotoDefinitionResponse, Hover, InlayHint, InlineCompletionResponse,
-    InlineCompletionTriggerKind, Location, Position, PrepareRenameResponse,
-    SelectionRange, SymbolInformation, TextDocumentItem, TextEdit, WorkspaceEdit,
+    InlineCompletionTriggerKind, LinkedEditingRanges, Location, Position,
+    PrepareRenameResponse, Range, SelectionRange, SymbolInformation,
+    TextDocumentItem, TextEdit, WorkspaceEdit,
 };
 use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
@@ -27,8 +29,9 @@
     dap_types::{self, DapId, RunDebugConfig, SourceBreakpoint, ThreadId},
     file::{FileNodeItem, PathObject},
     file_line::FileLine,
+    language::LanguageDefinition,
     plugin::{PluginId, VoltInfo, VoltMetadata},
-    source_control::FileDiff,
+    source_control::{FileDiff, GitFileCommit},
     style::SemanticStyles,
     terminal::{TermId, TerminalProfile},
     RequestId, RpcError, RpcMessage,
@@ -41,11 +44,38 @@ pub enum ProxyRpc {
     Shutdown,
 }
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum ProxyStatus {
     Connecting,
+    /// Still connecting, and at the described step
+    Progress {
+        message: String,
+    },
     Connected,
     Disconnected,
+    /// The connection failed, for the described reason
+    Error {
+        message: String,
+    },
+}
+
+/// What a prompt from the connection to a remote asks for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RemotePromptKind {
+    /// A yes or no question, like whether to trust the key of a host
+    Confirm,
+    /// Something that shouldn't be shown while typed, like a password
+    Secret,
+    Text,
+}
+
+/// A question that the connection to a remote asks the user, whose answer
+/// is given back with the `id`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemotePrompt {
+    pub id: u64,
+    pub prompt: String,
+    pub kind: RemotePromptKind,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -63,6 +93,13 @@ pub enum ProxyRequest {
     NewBuffer {
         buffer_id: BufferId,
         path: PathBuf,
+        /// The size in bytes above which the file is opened as a large file
+        large_file_size: u64,
+    },
+    /// The next chunk of the content of a large file, from `offset` on
+    BufferChunk {
+        path: PathBuf,
+        offset: usize,
     },
     BufferHead {
         path: PathBuf,
@@ -97,6 +134,15 @@ pub enum ProxyRequest {
     GitGetRemoteFileUrl {
         file: PathBuf,
     },
+    /// The commits that changed the file, newest first
+    GitFileHistory {
+        path: PathBuf,
+    },
+    /// The content of the file as of a commit
+    GitFileAtCommit {
+        path: PathBuf,
+        commit: String,
+    },
     GetReferences {
         path: PathBuf,
         position: Position,
@@ -161,6 +207,21 @@ pub enum ProxyRequest {
     GetDocumentSymbols {
         path: PathBuf,
     },
+    GetDocumentLinks {
+        path: PathBuf,
+    },
+    GetLinkedEditingRanges {
+        path: PathBuf,
+        position: Position,
+    },
+    GetDocumentColors {
+        path: PathBuf,
+    },
+    GetColorPresentations {
+        path: PathBuf,
+        color: Color,
+        range: Range,
+    },
     GetWorkspaceSymbols {
         /// The search query
         query: String,
@@ -181,6 +242,24 @@ pub enum ProxyRequest {
         /// Whether to create the parent directories if they do not exist.
         create_parents: bool,
     },
+    /// Save the buffer in another encoding, which it keeps for later saves.
+    SaveWithEncoding {
+        rev: u64,
+        path: PathBuf,
+        /// The label of the encoding, as in [`crate::buffer::FILE_ENCODINGS`]
+        encoding: String,
+    },
+    /// Save a binary file edited as a hex dump, writing its bytes as they are.
+    SaveBytes {
+        rev: u64,
+        path: PathBuf,
+        bytes: Vec<u8>,
+    },
+    /// Read the file of an open buffer again in another encoding.
+    ReopenWithEncoding {
+        path: PathBuf,
+        encoding: String,
+    },
     SaveBufferAs {
         buffer_id: BufferId,
         path: PathBuf,
@@ -206,6 +285,24 @@ pub enum ProxyRequest {
         from: PathBuf,
         to: PathBuf,
     },
+    /// Copy files and directories into the directory `to`, giving a copy a
+    /// new name when one with the same name is already there.
+    CopyPaths {
+        paths: Vec<PathBuf>,
+        to: PathBuf,
+    },
+    /// Move files and directories into the directory `to`, failing before
+    /// anything is moved when one with the same name is already there.
+    MovePaths {
+        paths: Vec<PathBuf>,
+        to: PathBuf,
+    },
+    /// Ask the language servers for the edits to apply before `from` is renamed to `to`,
+    /// such as updating imports and module paths.
+    WillRenamePath {
+        from: PathBuf,
+        to: PathBuf,
+    },
     TestCreateAtPath {
         path: PathBuf,
     },
@@ -228,10 +325,15 @@ pub enum ProxyRequest {
 pub enum ProxyNotification {
     Initialize {
         workspace: Option<PathBuf>,
+        /// Every root folder of the workspace, `workspace` being the first
+        workspace_folders: Vec<PathBuf>,
         disabled_volts: Vec<VoltID>,
         /// Paths to extra plugins that should be loaded
         extra_plugin_paths: Vec<PathBuf>,
         plugin_configurations: HashMap<String, HashMap<String, serde_json::Value>>,
+        /// Language id -> the volts whose language servers are preferred for requests
+        /// with a single answer, such as formatting and rename
+        language_server_priority: HashMap<String, Vec<String>>,
         window_id: usize,
         tab_id: usize,
     },
@@ -241,6 +343,10 @@ pub enum ProxyNotification {
     OpenPaths {
         paths: Vec<PathObject>,
     },
+    /// The root folders of the workspace were added to or removed
+    SetWorkspaceFolders {
+        folders: Vec<PathBuf>,
+    },
     Shutdown {},
     Completion {
         request_id: usize,
@@ -261,6 +367,15 @@ pub enum ProxyNotification {
     UpdatePluginConfigs {
         configs: HashMap<String, HashMap<String, serde_json::Value>>,
     },
+    UpdateLanguageServerPriority {
+        priority: HashMap<String, Vec<String>>,
+    },
+    /// The languages defined in the settings and the file associations, for
+    /// the language ids sent to language servers
+    UpdateUserLanguages {
+        languages: HashMap<String, LanguageDefinition>,
+        file_associations: HashMap<String, String>,
+    },
     NewTerminal {
         term_id: TermId,
         profile: TerminalProfile,
@@ -295,6 +410,8 @@ pub enum ProxyNotification {
     LspCancel {
         id: i32,
     },
+    /// Pull the diagnostics of the whole workspace from the language servers which support it
+    PullWorkspaceDiagnostics {},
     TerminalWrite {
         term_id: TermId,
         content: String,
@@ -360,9 +477,30 @@ pub enum ProxyResponse {
     GitGetRemoteFileUrl {
         file_url: String,
     },
+    GitFileHistoryResponse {
+        commits: Vec<GitFileCommit>,
+    },
     NewBufferResponse {
         content: String,
         read_only: bool,
+        /// The label of the encoding the file was read in
+        encoding: String,
+        /// Whether the file is too large to be highlighted or synced with
+        /// language servers
+        large: bool,
+        /// Whether the file is binary, and the content a hex dump of it
+        binary: bool,
+        /// Where the rest of the content starts, when only the first chunk of
+        /// a large file is sent
+        next_offset: Option<usize>,
+    },
+    BufferChunkResponse {
+        content: String,
+        /// Where the rest of the content starts, if there is more
+        next_offset: Option<usize>,
+    },
+    ReopenWithEncodingResponse {
+        content: String,
     },
     BufferHeadResponse {
         version: String,
@@ -399,8 +537,8 @@ pub enum ProxyResponse {
         references: Vec<Location>,
     },
     GetCodeActionsResponse {
-        plugin_id: PluginId,
-        resp: CodeActionResponse,
+        /// The code actions of every plugin which answered
+        actions: Vec<(PluginId, CodeActionResponse)>,
     },
     LspFoldingRangeResponse {
         plugin_id: PluginId,
@@ -427,6 +565,18 @@ pub enum ProxyResponse {
     GetDocumentSymbols {
         resp: DocumentSymbolResponse,
     },
+    GetDocumentLinks {
+        links: Vec<DocumentLink>,
+    },
+    GetLinkedEditingRanges {
+        ranges: Option<LinkedEditingRanges>,
+    },
+    GetDocumentColors {
+        colors: Vec<ColorInformation>,
+    },
+    GetColorPresentations {
+        presentations: Vec<ColorPresentation>,
+    },
     GetWorkspaceSymbols {
         symbols: Vec<SymbolInformation>,
     },
@@ -448,6 +598,9 @@ pub enum ProxyResponse {
     Rename {
         edit: WorkspaceEdit,
     },
+    WillRenamePath {
+        edit: Option<WorkspaceEdit>,
+    },
     GetOpenFilesContentResponse {
         items: Vec<TextDocumentItem>,
     },
@@ -463,6 +616,12 @@ pub enum ProxyResponse {
     CreatePathResponse {
         path: PathBuf,
     },
+    /// The paths that were copied or moved, and where they are now, with why
+    /// the rest weren't if it stopped partway
+    PathsResponse {
+        paths: Vec<(PathBuf, PathBuf)>,
+        error: Option<String>,
+    },
     Success {},
     SaveResponse {},
     ReferencesResolveResponse {
@@ -597,6 +756,10 @@ pub fn lsp_cancel(&self, id: i32) {
         self.notification(ProxyNotification::LspCancel { id });
     }
 
+    pub fn pull_workspace_diagnostics(&self) {
+        self.notification(ProxyNotification::PullWorkspaceDiagnostics {});
+    }
+
     pub fn git_init(&self) {
         self.notification(ProxyNotification::GitInit {});
     }
@@ -636,20 +799,25 @@ pub fn shutdown(&self) {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn initialize(
         &self,
         workspace: Option<PathBuf>,
+        workspace_folders: Vec<PathBuf>,
         disabled_volts: Vec<VoltID>,
         extra_plugin_paths: Vec<PathBuf>,
         plugin_configurations: HashMap<String, HashMap<String, serde_json::Value>>,
+        language_server_priority: HashMap<String, Vec<String>>,
         window_id: usize,
         tab_id: usize,
     ) {
         self.notification(ProxyNotification::Initialize {
             workspace,
+            workspace_folders,
             disabled_volts,
             extra_plugin_paths,
             plugin_configurations,
+            language_server_priority,
             window_id,
             tab_id,
         });
@@ -707,9 +875,26 @@ pub fn new_buffer(
         &self,
         buffer_id: BufferId,
         path: PathBuf,
+        large_file_size: u64,
+        f: impl ProxyCallback + 'static,
+    ) {
+        self.request_async(
+            ProxyRequest::NewBuffer {
+                buffer_id,
+                path,
+                large_file_size,
+            },
+            f,
+        );
+    }
+
+    pub fn get_buffer_chunk(
+        &self,
+        path: PathBuf,
+        offset: usize,
         f: impl ProxyCallback + 'static,
     ) {
-        self.request_async(ProxyRequest::NewBuffer { buffer_id, path }, f);
+        self.request_async(ProxyRequest::BufferChunk { path, offset }, f);
     }
 
     pub fn get_buffer_head(&self, path: PathBuf, f: impl ProxyCallback + 'static) {
@@ -752,6 +937,33 @@ pub fn rename_path(
         self.request_async(ProxyRequest::RenamePath { from, to }, f);
     }
 
+    pub fn copy_paths(
+        &self,
+        paths: Vec<PathBuf>,
+        to: PathBuf,
+        f: impl ProxyCallback + 'static,
+    ) {
+        self.request_async(ProxyRequest::CopyPaths { paths, to }, f);
+    }
+
+    pub fn move_paths(
+        &self,
+        paths: Vec<PathBuf>,
+        to: PathBuf,
+        f: impl ProxyCallback + 'static,
+    ) {
+        self.request_async(ProxyRequest::MovePaths { paths, to }, f);
+    }
+
+    pub fn will_rename_path(
+        &self,
+        from: PathBuf,
+        to: PathBuf,
+        f: impl ProxyCallback + 'static,
+    ) {
+        self.request_async(ProxyRequest::WillRenamePath { from, to }, f);
+    }
+
     pub fn test_create_at_path(
         &self,
         path: PathBuf,
@@ -817,6 +1029,42 @@ pub fn save(
         );
     }
 
+    pub fn save_with_encoding(
+        &self,
+        rev: u64,
+        path: PathBuf,
+        encoding: String,
+        f: impl ProxyCallback + 'static,
+    ) {
+        self.request_async(
+            ProxyRequest::SaveWithEncoding {
+                rev,
+                path,
+                encoding,
+            },
+            f,
+        );
+    }
+
+    pub fn save_bytes(
+        &self,
+        rev: u64,
+        path: PathBuf,
+        bytes: Vec<u8>,
+        f: impl ProxyCallback + 'static,
+    ) {
+        self.request_async(ProxyRequest::SaveBytes { rev, path, bytes }, f);
+    }
+
+    pub fn reopen_with_encoding(
+        &self,
+        path: PathBuf,
+        encoding: String,
+        f: impl ProxyCallback + 'static,
+    ) {
+        self.request_async(ProxyRequest::ReopenWithEncoding { path, encoding }, f);
+    }
+
     pub fn get_files(&self, f: impl ProxyCallback + 'static) {
         self.request_async(
             ProxyRequest::GetFiles {
@@ -1027,6 +1275,47 @@ pub fn get_document_symbols(
         self.request_async(ProxyRequest::GetDocumentSymbols { path }, f);
     }
 
+    pub fn get_document_links(
+        &self,
+        path: PathBuf,
+        f: impl ProxyCallback + 'static,
+    ) {
+        self.request_async(ProxyRequest::GetDocumentLinks { path }, f);
+    }
+
+    pub fn get_linked_editing_ranges(
+        &self,
+        path: PathBuf,
+        position: Position,
+        f: impl ProxyCallback + 'static,
+    ) {
+        self.request_async(
+            ProxyRequest::GetLinkedEditingRanges { path, position },
+            f,
+        );
+    }
+
+    pub fn get_document_colors(
+        &self,
+        path: PathBuf,
+        f: impl ProxyCallback + 'static,
+    ) {
+        self.request_async(ProxyRequest::GetDocumentColors { path }, f);
+    }
+
+    pub fn get_color_presentations(
+        &self,
+        path: PathBuf,
+        color: Color,
+        range: Range,
+        f: impl ProxyCallback + 'static,
+    ) {
+        self.request_async(
+            ProxyRequest::GetColorPresentations { path, color, range },
+            f,
+        );
+    }
+
     pub fn get_workspace_symbols(
         &self,
         query: String,
@@ -1052,6 +1341,19 @@ pub fn git_get_remote_file_url(
         self.request_async(ProxyRequest::GitGetRemoteFileUrl { file }, f);
     }
 
+    pub fn git_file_history(&self, path: PathBuf, f: impl ProxyCallback + 'static) {
+        self.request_async(ProxyRequest::GitFileHistory { path }, f);
+    }
+
+    pub fn git_file_at_commit(
+        &self,
+        path: PathBuf,
+        commit: String,
+        f: impl ProxyCallback + 'static,
+    ) {
+        self.request_async(ProxyRequest::GitFileAtCommit { path, commit }, f);
+    }
+
     pub fn rename(
         &self,
         path: PathBuf,
@@ -1094,6 +1396,10 @@ pub fn update(&self, path: PathBuf, delta: RopeDelta, rev: u64) {
         self.notification(ProxyNotification::Update { path, delta, rev });
     }
 
+    pub fn set_workspace_folders(&self, folders: Vec<PathBuf>) {
+        self.notification(ProxyNotification::SetWorkspaceFolders { folders });
+    }
+
     pub fn update_plugin_configs(
         &self,
         configs: HashMap<String, HashMap<String, serde_json::Value>>,
@@ -1101,6 +1407,26 @@ pub fn update_plugin_configs(
         self.notification(ProxyNotification::UpdatePluginConfigs { configs });
     }
 
+    pub fn update_language_server_priority(
+        &self,
+        priority: HashMap<String, Vec<String>>,
+    ) {
+        self.notification(ProxyNotification::UpdateLanguageServerPriority {
+            priority,
+        });
+    }
+
+    pub fn update_user_languages(
+        &self,
+        languages: HashMap<String, LanguageDefinition>,
+        file_associations: HashMap<String, String>,
+    ) {
+        self.notification(ProxyNotification::UpdateUserLanguages {
+            languages,
+            file_associations,
+        });
+    }
+
     pub fn git_discard_files_changes(&self, files: Vec<PathBuf>) {
         self.notification(ProxyNotification::GitDiscardFilesChanges { files });
     }
@@ -10,6 +10,16 @@ pub struct DiffInfo {
     pub diffs: Vec<FileDiff>,
 }
 
+/// A commit that changed a file
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GitFileCommit {
+    pub id: String,
+    pub summary: String,
+    pub author: String,
+    /// When it was committed, in seconds since the Unix epoch
+    pub time: i64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum FileDiff {
     Modified(PathBuf),